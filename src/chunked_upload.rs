@@ -0,0 +1,231 @@
+//! Chunked upload module
+//!
+//! Lets clients send large panoramas in pieces so a dropped connection only
+//! costs the chunk in flight:
+//!
+//! 1. `start` registers the upload (name, destination folder, total size) and returns an id.
+//! 2. `write_chunk` stores one byte range; ranges may arrive in any order and may be re-sent.
+//! 3. `complete` checks that every byte arrived and hands the upload over; `assemble`
+//!    then reads the chunks back into one file, away from the registry's lock.
+//!
+//! Chunks are kept as `<dir>/<upload id>/<offset>.part` files until the upload
+//! completes or is expired for inactivity.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Why a chunk or completion request was refused
+#[derive(Debug)]
+pub enum ChunkError {
+    /// No such upload (never started, completed or expired)
+    UnknownUpload,
+    /// The range falls outside the declared file size
+    OutOfRange,
+    /// Not every byte has arrived yet
+    Incomplete { received: u64, total: u64 },
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkError::UnknownUpload => write!(f, "unknown or expired upload"),
+            ChunkError::OutOfRange => write!(f, "chunk outside the declared file size"),
+            ChunkError::Incomplete { received, total } => write!(f, "upload incomplete: {} of {} bytes received", received, total),
+            ChunkError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ChunkError {
+    fn from(e: std::io::Error) -> Self {
+        ChunkError::Io(e)
+    }
+}
+
+/// What the client declared when starting the upload
+#[derive(Debug, Clone)]
+pub struct UploadInfo {
+    pub filename: String,
+    /// Destination folder under `assets/` (insta360, closeups, ...)
+    pub subdir: String,
+    pub total_size: u64,
+    pub username: Option<String>,
+}
+
+#[derive(Debug)]
+struct PendingUpload {
+    info: UploadInfo,
+    /// Chunk offset -> length
+    chunks: BTreeMap<u64, u64>,
+    last_activity: Instant,
+}
+
+impl PendingUpload {
+    /// Bytes covered by the received chunks (overlaps counted once)
+    fn received(&self) -> u64 {
+        let mut covered = 0;
+        let mut end = 0;
+        for (&offset, &len) in &self.chunks {
+            let chunk_end = offset.saturating_add(len);
+            if chunk_end > end {
+                covered += chunk_end - offset.max(end);
+                end = chunk_end;
+            }
+        }
+        covered
+    }
+}
+
+/// In-progress chunked uploads
+#[derive(Debug)]
+pub struct ChunkedUploads {
+    dir: PathBuf,
+    uploads: HashMap<String, PendingUpload>,
+}
+
+impl ChunkedUploads {
+    /// Creates a registry keeping partial chunks under `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), uploads: HashMap::new() }
+    }
+
+    /// Number of uploads in progress
+    pub fn len(&self) -> usize {
+        self.uploads.len()
+    }
+
+    /// Registers a new upload and returns its id.
+    pub fn start(&mut self, info: UploadInfo) -> std::io::Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        std::fs::create_dir_all(self.dir.join(&id))?;
+        self.uploads.insert(id.clone(), PendingUpload {
+            info,
+            chunks: BTreeMap::new(),
+            last_activity: Instant::now(),
+        });
+        Ok(id)
+    }
+
+    /// Stores the bytes at `offset` and returns how many bytes of the file have arrived so far.
+    pub fn write_chunk(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<u64, ChunkError> {
+        let upload = self.uploads.get_mut(id).ok_or(ChunkError::UnknownUpload)?;
+        let end = offset.checked_add(data.len() as u64).ok_or(ChunkError::OutOfRange)?;
+        if data.is_empty() || end > upload.info.total_size {
+            return Err(ChunkError::OutOfRange);
+        }
+        std::fs::write(self.dir.join(id).join(format!("{}.part", offset)), data)?;
+        upload.chunks.insert(offset, data.len() as u64);
+        upload.last_activity = Instant::now();
+        Ok(upload.received())
+    }
+
+    /// Bytes received so far, for clients resuming an upload.
+    pub fn received(&self, id: &str) -> Option<u64> {
+        self.uploads.get(id).map(|upload| upload.received())
+    }
+
+    /// Takes a fully received upload out of the registry, ready to `assemble`.
+    ///
+    /// An incomplete upload is kept so the client can send the missing ranges.
+    pub fn complete(&mut self, id: &str) -> Result<CompletedUpload, ChunkError> {
+        let upload = self.uploads.get(id).ok_or(ChunkError::UnknownUpload)?;
+        let received = upload.received();
+        if received != upload.info.total_size {
+            return Err(ChunkError::Incomplete { received, total: upload.info.total_size });
+        }
+
+        let upload = self.uploads.remove(id).ok_or(ChunkError::UnknownUpload)?;
+        Ok(CompletedUpload { info: upload.info, chunk_dir: self.dir.join(id), offsets: upload.chunks.into_keys().collect() })
+    }
+
+    /// Drops uploads idle for longer than `max_idle` along with their chunks.
+    pub fn expire(&mut self, max_idle: Duration) -> usize {
+        let stale: Vec<String> = self.uploads
+            .iter()
+            .filter(|(_, upload)| upload.last_activity.elapsed() > max_idle)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            self.uploads.remove(id);
+            let _ = std::fs::remove_dir_all(self.dir.join(id));
+        }
+        stale.len()
+    }
+}
+
+/// A fully received upload whose chunks are still on disk
+#[derive(Debug)]
+pub struct CompletedUpload {
+    info: UploadInfo,
+    chunk_dir: PathBuf,
+    offsets: Vec<u64>,
+}
+
+impl CompletedUpload {
+    /// Reads the chunks back into one file and removes them. This is blocking file IO,
+    /// so async callers should run it on a blocking thread.
+    pub fn assemble(self) -> Result<(UploadInfo, Vec<u8>), ChunkError> {
+        let result = self.read_chunks();
+        let _ = std::fs::remove_dir_all(&self.chunk_dir);
+        result.map(|data| (self.info, data))
+    }
+
+    fn read_chunks(&self) -> Result<Vec<u8>, ChunkError> {
+        let mut data = vec![0u8; self.info.total_size as usize];
+        for &offset in &self.offsets {
+            let chunk = std::fs::read(self.chunk_dir.join(format!("{}.part", offset)))?;
+            let range = offset as usize..offset as usize + chunk.len();
+            data.get_mut(range).ok_or(ChunkError::OutOfRange)?.copy_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_three_chunks_assemble_to_original() {
+        let dir = PathBuf::from("target/test_chunked_uploads").join(uuid::Uuid::new_v4().to_string());
+        let mut uploads = ChunkedUploads::new(&dir);
+        let original: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let id = uploads.start(UploadInfo {
+            filename: "pano.jpg".to_string(),
+            subdir: "insta360".to_string(),
+            total_size: original.len() as u64,
+            username: None,
+        }).unwrap();
+
+        // Out of order, with the middle chunk re-sent after a "dropped connection"
+        assert_eq!(uploads.write_chunk(&id, 8000, &original[8000..]).unwrap(), 2000);
+        assert_eq!(uploads.write_chunk(&id, 0, &original[..4000]).unwrap(), 6000);
+        assert!(matches!(uploads.complete(&id), Err(ChunkError::Incomplete { received: 6000, total: 10_000 })));
+        uploads.write_chunk(&id, 4000, &original[4000..8000]).unwrap();
+        assert_eq!(uploads.write_chunk(&id, 4000, &original[4000..8000]).unwrap(), 10_000);
+        assert!(matches!(uploads.write_chunk(&id, 9000, &original[..2000]), Err(ChunkError::OutOfRange)));
+        assert!(matches!(uploads.write_chunk(&id, u64::MAX, b"xy"), Err(ChunkError::OutOfRange)));
+
+        let completed = uploads.complete(&id).unwrap();
+        assert_eq!(uploads.len(), 0);
+        let (info, assembled) = completed.assemble().unwrap();
+        assert_eq!(info.filename, "pano.jpg");
+        assert_eq!(assembled, original);
+        assert!(!dir.join(&id).exists(), "chunks removed after assembly");
+
+        // Idle uploads expire
+        let stale = uploads.start(UploadInfo {
+            filename: "stale.jpg".to_string(),
+            subdir: "insta360".to_string(),
+            total_size: 10,
+            username: None,
+        }).unwrap();
+        assert_eq!(uploads.expire(Duration::ZERO), 1);
+        assert!(matches!(uploads.write_chunk(&stale, 0, b"x"), Err(ChunkError::UnknownUpload)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -7,30 +7,277 @@ pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub app: AppConfig,
+    #[serde(default)]
+    pub sharing: SharingConfig,
+    #[serde(default)]
+    pub editor: EditorConfig,
+    #[serde(default)]
+    pub uploads: UploadsConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    /// Serve over HTTPS when present; plain HTTP otherwise
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Cache-Control max-age for /static (0 disables caching)
+    #[serde(default = "default_static_cache_secs")]
+    pub static_cache_secs: u64,
+    /// Cache-Control max-age for /assets (0 disables caching)
+    #[serde(default = "default_assets_cache_secs")]
+    pub assets_cache_secs: u64,
+    /// Max queued outbound messages per WebSocket before a slow client is dropped
+    #[serde(default = "default_ws_outbound_capacity")]
+    pub ws_outbound_capacity: usize,
+    /// Open WebSocket connections beyond which new ones are refused with a 503 (0 disables)
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
 }
 
+fn default_static_cache_secs() -> u64 { 86400 }
+fn default_assets_cache_secs() -> u64 { 3600 }
+fn default_ws_outbound_capacity() -> usize { 256 }
+fn default_max_connections() -> usize { 1000 }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DatabaseConfig {
     pub url: String,
+    /// Instrumented database calls taking at least this long are logged, in ms (0 disables)
+    #[serde(default = "default_slow_query_ms")]
+    pub slow_query_ms: u64,
 }
 
+fn default_slow_query_ms() -> u64 { 100 }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
     pub name: String,
     pub version: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EditorConfig {
+    /// Report the EXIF camera heading of uploaded scenes so the client can pre-fill north
+    #[serde(default)]
+    pub infer_north: bool,
+    /// How often deferred editor writes are flushed, in seconds (0 disables autosave)
+    #[serde(default = "default_autosave_secs")]
+    pub autosave_secs: u64,
+    /// Snapshots kept per tour; the oldest is dropped when a new one exceeds this
+    #[serde(default = "default_max_snapshots")]
+    pub max_snapshots: usize,
+    /// Field of view (degrees) newly added scenes open with
+    #[serde(default = "default_fov")]
+    pub default_fov: f32,
+    /// Initial yaw (degrees) of newly added scenes
+    #[serde(default)]
+    pub default_view_yaw: f32,
+    /// Initial pitch (degrees) of newly added scenes
+    #[serde(default)]
+    pub default_view_pitch: f32,
+    /// What happens when a scene is added or renamed to a name already used in the tour
+    #[serde(default)]
+    pub scene_name_collision: SceneNameCollision,
+    /// Scenes a tour may hold; adding more fails with `limit_reached`
+    #[serde(default = "default_max_scenes_per_tour")]
+    pub max_scenes_per_tour: usize,
+    /// Hotspots (transitions and closeups) one scene may hold
+    #[serde(default = "default_max_connections_per_scene")]
+    pub max_connections_per_scene: usize,
+    /// Minimum time between two tour creations by one user, in milliseconds (0 disables)
+    #[serde(default = "default_tour_creation_interval_ms")]
+    pub tour_creation_interval_ms: u64,
+    /// Tours one user may create within an hour (0 = unlimited)
+    #[serde(default = "default_max_tours_per_hour")]
+    pub max_tours_per_hour: usize,
+}
+
+/// Handling of duplicate scene names within a tour
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SceneNameCollision {
+    /// Duplicates are accepted as-is
+    #[default]
+    Allow,
+    /// The add or rename fails with an error
+    Reject,
+    /// The name gets the first free ` (2)`, ` (3)`, ... suffix
+    Suffix,
+}
+
+fn default_autosave_secs() -> u64 { 30 }
+fn default_max_snapshots() -> usize { 20 }
+fn default_fov() -> f32 { 75.0 }
+fn default_max_scenes_per_tour() -> usize { 1000 }
+fn default_max_connections_per_scene() -> usize { 200 }
+fn default_tour_creation_interval_ms() -> u64 { 2000 }
+fn default_max_tours_per_hour() -> usize { 60 }
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            infer_north: false,
+            autosave_secs: default_autosave_secs(),
+            max_snapshots: default_max_snapshots(),
+            default_fov: default_fov(),
+            default_view_yaw: 0.0,
+            default_view_pitch: 0.0,
+            scene_name_collision: SceneNameCollision::default(),
+            max_scenes_per_tour: default_max_scenes_per_tour(),
+            max_connections_per_scene: default_max_connections_per_scene(),
+            tour_creation_interval_ms: default_tour_creation_interval_ms(),
+            max_tours_per_hour: default_max_tours_per_hour(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UploadsConfig {
+    /// Where partial chunked uploads are kept until they are assembled
+    #[serde(default = "default_chunk_dir")]
+    pub chunk_dir: String,
+    /// Largest accepted chunk in bytes
+    #[serde(default = "default_max_chunk_bytes")]
+    pub max_chunk_bytes: usize,
+    /// Largest file accepted through a chunked upload, in bytes
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Chunked uploads that may be in progress at once (further starts get 429)
+    #[serde(default = "default_max_pending_uploads")]
+    pub max_pending: usize,
+    /// Idle time after which a partial upload is discarded, in seconds
+    #[serde(default = "default_upload_expiry_secs")]
+    pub expiry_secs: u64,
+    /// Largest video scene (mp4/webm) accepted, in bytes
+    #[serde(default = "default_max_video_bytes")]
+    pub max_video_bytes: u64,
+    /// Largest hotspot sound (mp3/ogg/wav/m4a) accepted, in bytes
+    #[serde(default = "default_max_audio_bytes")]
+    pub max_audio_bytes: u64,
+    /// How often every asset file is checked for existence, in seconds (0 disables; `GET /api/assets/audit` still works)
+    #[serde(default)]
+    pub audit_interval_secs: u64,
+    /// Most multipart fields read from one `/upload-asset` request before it is rejected
+    #[serde(default = "default_max_form_fields")]
+    pub max_form_fields: usize,
+    /// Most bytes read across all fields of one `/upload-asset` request before it is rejected
+    #[serde(default = "default_max_form_bytes")]
+    pub max_form_bytes: u64,
+    /// Refuse scene images that aren't 2:1 panoramas instead of accepting them with a warning
+    #[serde(default)]
+    pub reject_flat_scenes: bool,
+    /// Closeup images larger than this many megapixels are downscaled on upload (0 disables; scenes are never resized)
+    #[serde(default = "default_max_closeup_megapixels")]
+    pub max_closeup_megapixels: f64,
+}
+
+fn default_chunk_dir() -> String { "tmp_uploads".to_string() }
+fn default_max_chunk_bytes() -> usize { 8 * 1024 * 1024 }
+fn default_max_file_bytes() -> u64 { 512 * 1024 * 1024 }
+fn default_max_pending_uploads() -> usize { 32 }
+fn default_upload_expiry_secs() -> u64 { 3600 }
+fn default_max_video_bytes() -> u64 { 100 * 1024 * 1024 }
+fn default_max_audio_bytes() -> u64 { 20 * 1024 * 1024 }
+fn default_max_form_fields() -> usize { 8 }
+fn default_max_form_bytes() -> u64 { 120 * 1024 * 1024 }
+fn default_max_closeup_megapixels() -> f64 { 24.0 }
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            chunk_dir: default_chunk_dir(),
+            max_chunk_bytes: default_max_chunk_bytes(),
+            max_file_bytes: default_max_file_bytes(),
+            max_pending: default_max_pending_uploads(),
+            expiry_secs: default_upload_expiry_secs(),
+            max_video_bytes: default_max_video_bytes(),
+            max_audio_bytes: default_max_audio_bytes(),
+            audit_interval_secs: 0,
+            max_form_fields: default_max_form_fields(),
+            max_form_bytes: default_max_form_bytes(),
+            reject_flat_scenes: false,
+            max_closeup_megapixels: default_max_closeup_megapixels(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExportConfig {
+    /// Folder holding the viewer's `engine.min.js` and `three.min.js` copied into exports
+    #[serde(default = "default_viewer_js_dir")]
+    pub viewer_js_dir: String,
+    /// The app's own icons/sprites, packaged under `assets/` in every export
+    #[serde(default = "default_static_assets_dir")]
+    pub static_assets_dir: String,
+    /// Icon exported for hotspots without an `icon_index`; must be one of `exporter::ICON_INDEXES`
+    #[serde(default = "default_icon_index")]
+    pub default_icon_index: i32,
+}
+
+fn default_viewer_js_dir() -> String { "static/export-viewer/js".to_string() }
+fn default_static_assets_dir() -> String { "static/assets".to_string() }
+fn default_icon_index() -> i32 { 1 }
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            viewer_js_dir: default_viewer_js_dir(),
+            static_assets_dir: default_static_assets_dir(),
+            default_icon_index: default_icon_index(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: String,
+    /// PEM private key
+    pub key_path: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SharingConfig {
+    /// Lifetime of newly created share tokens in seconds (0 = never expire)
+    #[serde(default = "default_share_token_lifetime_secs")]
+    pub token_lifetime_secs: u64,
+    /// How often expired share tokens are pruned, in seconds
+    #[serde(default = "default_share_prune_interval_secs")]
+    pub prune_interval_secs: u64,
+    /// Public base URL share links are built on, e.g. `https://tours.example.com`.
+    /// Tours can override it; without either, share links are relative paths. `/sitemap.xml`
+    /// is only served when this is set.
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+fn default_share_token_lifetime_secs() -> u64 { 7 * 24 * 3600 }
+fn default_share_prune_interval_secs() -> u64 { 3600 }
+
+impl Default for SharingConfig {
+    fn default() -> Self {
+        Self {
+            token_lifetime_secs: default_share_token_lifetime_secs(),
+            prune_interval_secs: default_share_prune_interval_secs(),
+            base_url: None,
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from a TOML file
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let config_str = fs::read_to_string(path)?;
         let config: Config = toml::from_str(&config_str)?;
+        let icons = crate::exporter::ICON_INDEXES;
+        if !icons.contains(&config.export.default_icon_index) {
+            return Err(From::from(format!("export.default_icon_index {} is not a defined icon ({} to {})",
+                                          config.export.default_icon_index, icons.start(), icons.end())));
+        }
         Ok(config)
     }
 
@@ -47,6 +294,8 @@ impl Config {
             // Fallback to current dir if APPDATA missing
             return std::path::PathBuf::from("config.toml");
         }
+        #[allow(unreachable_code)]
+        std::path::PathBuf::from("config.toml")
     }
 
     /// Load configuration solely from the system configuration path.
@@ -63,6 +312,15 @@ impl Config {
     pub fn server_address(&self) -> String {
         format!("{}:{}", self.server.host, self.server.port)
     }
+
+    /// Build a Cache-Control header value for the given max-age; zero disables caching
+    pub fn cache_control(max_age_secs: u64) -> String {
+        if max_age_secs == 0 {
+            "no-store".to_string()
+        } else {
+            format!("public, max-age={}", max_age_secs)
+        }
+    }
 }
 
 impl Default for Config {
@@ -71,14 +329,24 @@ impl Default for Config {
             server: ServerConfig {
                 host: "0.0.0.0".to_string(),
                 port: 1112,
+                static_cache_secs: default_static_cache_secs(),
+                assets_cache_secs: default_assets_cache_secs(),
+                ws_outbound_capacity: default_ws_outbound_capacity(),
+                max_connections: default_max_connections(),
             },
             database: DatabaseConfig {
                 url: "sqlite:./virtual_tour_editor.db".to_string(),
+                slow_query_ms: default_slow_query_ms(),
             },
             app: AppConfig {
                 name: "Virtual Tour Editor".to_string(),
                 version: "2.1.0".to_string(),
             },
+            sharing: SharingConfig::default(),
+            editor: EditorConfig::default(),
+            uploads: UploadsConfig::default(),
+            export: ExportConfig::default(),
+            tls: None,
         }
     }
 }
@@ -95,6 +363,58 @@ mod tests {
         assert_eq!(config.app.name, "Virtual Tour Editor");
     }
 
+    #[test]
+    fn test_cache_settings_default_when_omitted() {
+        let config: Config = toml::from_str(r#"
+            [server]
+            host = "127.0.0.1"
+            port = 8080
+            [database]
+            url = "sqlite::memory:"
+            [app]
+            name = "Test"
+            version = "0.0.0"
+        "#).unwrap();
+        assert_eq!(config.server.static_cache_secs, 86400);
+        assert_eq!(config.server.assets_cache_secs, 3600);
+        assert_eq!(config.server.ws_outbound_capacity, 256);
+        assert_eq!(config.server.max_connections, 1000);
+        assert_eq!(Config::cache_control(0), "no-store");
+        assert_eq!(Config::cache_control(60), "public, max-age=60");
+        assert_eq!(config.sharing.token_lifetime_secs, 7 * 24 * 3600);
+        assert_eq!(config.sharing.prune_interval_secs, 3600);
+        assert!(config.tls.is_none());
+        assert!(!config.editor.infer_north);
+        assert_eq!(config.editor.autosave_secs, 30);
+        assert_eq!(config.editor.max_snapshots, 20);
+        assert_eq!(config.uploads.max_chunk_bytes, 8 * 1024 * 1024);
+        assert_eq!(config.uploads.expiry_secs, 3600);
+    }
+
+    #[test]
+    fn test_default_icon_must_be_a_defined_icon() {
+        let path = std::env::temp_dir().join(format!("vte_icon_config_{}.toml", std::process::id()));
+        let write = |icon: i32| std::fs::write(&path, format!(r#"
+            [server]
+            host = "127.0.0.1"
+            port = 8080
+            [database]
+            url = "sqlite::memory:"
+            [app]
+            name = "Test"
+            version = "0.0.0"
+            [export]
+            default_icon_index = {}
+        "#, icon)).unwrap();
+
+        write(3);
+        assert_eq!(Config::load_from_file(&path).unwrap().export.default_icon_index, 3);
+        write(7);
+        let error = Config::load_from_file(&path).unwrap_err().to_string();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(error, "export.default_icon_index 7 is not a defined icon (1 to 3)");
+    }
+
     #[test]
     fn test_server_address() {
         let config = Config::default();
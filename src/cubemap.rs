@@ -0,0 +1,127 @@
+//! Cubemap module
+//!
+//! Re-projects an equirectangular scene image onto the six faces of a cube for
+//! viewers that can't consume a single panorama. Faces follow the usual
+//! `px/nx/py/ny/pz/nz` naming (positive/negative x, y, z; +y is up, +z is the
+//! centre of the panorama).
+//!
+//! Faces are rendered on demand and cached as JPEGs next to the other assets;
+//! a cached face is re-rendered once the source image is newer than it.
+
+use image::{codecs::jpeg::JpegEncoder, RgbImage};
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+/// One face of the cube
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubeFace {
+    Px,
+    Nx,
+    Py,
+    Ny,
+    Pz,
+    Nz,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [CubeFace::Px, CubeFace::Nx, CubeFace::Py, CubeFace::Ny, CubeFace::Pz, CubeFace::Nz];
+
+    pub fn parse(face: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == face)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CubeFace::Px => "px",
+            CubeFace::Nx => "nx",
+            CubeFace::Py => "py",
+            CubeFace::Ny => "ny",
+            CubeFace::Pz => "pz",
+            CubeFace::Nz => "nz",
+        }
+    }
+
+    /// Direction through face coordinates `a` (right) and `b` (down), both in [-1, 1]
+    fn direction(self, a: f64, b: f64) -> (f64, f64, f64) {
+        match self {
+            CubeFace::Px => (1.0, -b, -a),
+            CubeFace::Nx => (-1.0, -b, a),
+            CubeFace::Py => (a, 1.0, b),
+            CubeFace::Ny => (a, -1.0, -b),
+            CubeFace::Pz => (a, -b, 1.0),
+            CubeFace::Nz => (-a, -b, -1.0),
+        }
+    }
+}
+
+/// Projects an equirectangular image onto one square cube face of `size` pixels.
+pub fn project_face(equirect: &RgbImage, face: CubeFace, size: u32) -> RgbImage {
+    let (width, height) = equirect.dimensions();
+    RgbImage::from_fn(size, size, |x, y| {
+        let a = 2.0 * (x as f64 + 0.5) / size as f64 - 1.0;
+        let b = 2.0 * (y as f64 + 0.5) / size as f64 - 1.0;
+        let (dx, dy, dz) = face.direction(a, b);
+
+        let lon = dx.atan2(dz);
+        let lat = (dy / (dx * dx + dy * dy + dz * dz).sqrt()).asin();
+        let u = ((lon / (2.0 * PI) + 0.5) * width as f64) as u32;
+        let v = ((0.5 - lat / PI) * height as f64) as u32;
+        *equirect.get_pixel(u.min(width - 1), v.min(height - 1))
+    })
+}
+
+/// Face edge length for a panorama: a quarter of its width keeps roughly the source resolution.
+fn face_size(equirect_width: u32) -> u32 {
+    (equirect_width / 4).clamp(1, 2048)
+}
+
+/// Returns the JPEG bytes of one face, rendering and caching it under `cache_dir` when needed.
+///
+/// Decoding and projection are CPU-bound, so callers on the async runtime should run
+/// this through `spawn_blocking`.
+pub fn face_jpeg(source: &Path, cache_dir: &Path, asset_id: i64, face: CubeFace) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let cached: PathBuf = cache_dir.join(format!("{}_{}.jpg", asset_id, face.as_str()));
+    let source_modified = std::fs::metadata(source)?.modified()?;
+    if let Ok(meta) = std::fs::metadata(&cached) {
+        if meta.modified()? >= source_modified {
+            return Ok(std::fs::read(&cached)?);
+        }
+    }
+
+    let equirect = image::open(source)?.to_rgb8();
+    let face_image = project_face(&equirect, face, face_size(equirect.width()));
+
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, 90).encode_image(&face_image)?;
+
+    std::fs::create_dir_all(cache_dir)?;
+    if let Err(e) = std::fs::write(&cached, &bytes) {
+        eprintln!("Failed to cache cubemap face {:?}: {}", cached, e);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_faces_are_square_jpegs() {
+        let dir = PathBuf::from("target/test_cubemap").join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("pano.jpg");
+        let pano = RgbImage::from_fn(64, 32, |x, y| image::Rgb([(x * 4) as u8, (y * 8) as u8, 128]));
+        pano.save(&source).unwrap();
+
+        for face in CubeFace::ALL {
+            let bytes = face_jpeg(&source, &dir.join("cubemaps"), 1, face).unwrap();
+            assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::Jpeg);
+            let decoded = image::load_from_memory(&bytes).unwrap();
+            assert_eq!(decoded.width(), decoded.height(), "face {} not square", face.as_str());
+            assert_eq!(decoded.width(), 16);
+            assert!(dir.join("cubemaps").join(format!("1_{}.jpg", face.as_str())).exists());
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -9,18 +9,502 @@
 //! It uses `sqlx` for asynchronous database interactions and `uuid` for unique player IDs.
 
 use sqlx::{SqlitePool, Row};
+use sqlx::sqlite::{SqliteConnection, SqliteRow};
 use std::sync::Arc;
+use futures::future::BoxFuture;
+use std::collections::{HashMap, HashSet};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use crate::tour::Tour;
+use crate::editor::ConnectionType;
 use uuid::Uuid;
 use tokio::fs;
+use serde::{Deserialize, Serialize};
+use slow_query::QueryTimer;
+
+mod slow_query;
  
+/// Columns selected for scene assets when building tour JSON
+const SCENE_COLUMNS: &str = "id, name, file_path, created_at, modified_at, initial_view_x, initial_view_y, north_dir, pov, group_id, media_type, hidden, captured_at, notes, min_fov, max_fov, created_by, default_icon_index";
+
+/// Kind of asset row to list for a tour
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Scene,
+    Closeup,
+}
+
+/// Ordering for tour listings
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TourOrder {
+    #[default]
+    CreatedAt,
+    ModifiedAt,
+    Name,
+    Views,
+}
+
+impl TourOrder {
+    /// Fixed column name for ORDER BY; never built from user input
+    fn column(self) -> &'static str {
+        match self {
+            TourOrder::CreatedAt => "created_at",
+            TourOrder::ModifiedAt => "modified_at",
+            TourOrder::Name => "tour_name COLLATE NOCASE",
+            TourOrder::Views => "views",
+        }
+    }
+}
+
+/// Direction of a listing order
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn sql(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// A problem that keeps a tour from being export-ready
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CompletenessIssue {
+    NoScenes,
+    NoInitialScene,
+    MissingInitialView { scene_id: i64 },
+    MissingNorthDirection { scene_id: i64 },
+    NoConnections { scene_id: i64 },
+}
+
+/// How an update treats one nullable column
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FieldUpdate<T> {
+    /// Leave the stored value as it is
+    #[default]
+    Keep,
+    Set(T),
+    /// Store NULL (or the column default where the column is NOT NULL)
+    Clear,
+}
+
+impl<T> FieldUpdate<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> FieldUpdate<U> {
+        match self {
+            FieldUpdate::Keep => FieldUpdate::Keep,
+            FieldUpdate::Set(value) => FieldUpdate::Set(f(value)),
+            FieldUpdate::Clear => FieldUpdate::Clear,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for FieldUpdate<T> {
+    /// `None` keeps the column, `Some` sets it
+    fn from(value: Option<T>) -> Self {
+        value.map_or(FieldUpdate::Keep, FieldUpdate::Set)
+    }
+}
+
+/// Changes to one connection row; `None` fields are left unchanged
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConnectionUpdate {
+    pub id: i64,
+    pub end_id: Option<i64>,
+    pub world_lon: Option<f32>,
+    pub world_lat: Option<f32>,
+    pub name: Option<String>,
+    pub icon_type: Option<i32>,
+    pub file_path: Option<String>,
+    pub transition_style: Option<String>,
+    pub icon_color: Option<String>,
+    pub icon_scale: Option<f32>,
+    pub url_target: Option<String>,
+    pub connection_type: Option<ConnectionType>,
+    pub z_index: Option<i32>,
+    pub audio_path: Option<String>,
+}
+
+/// Changes to one scene (or closeup) asset row; `None`/`Keep` fields are left unchanged.
+/// `initial_view_x/y` are NOT NULL, so clearing them stores 0.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SceneUpdate {
+    pub id: i64,
+    pub name: Option<String>,
+    pub file_path: Option<String>,
+    pub initial_view_x: FieldUpdate<f32>,
+    pub initial_view_y: FieldUpdate<f32>,
+    pub north_direction: FieldUpdate<f32>,
+    pub pov: FieldUpdate<f32>,
+}
+
+/// A row update held back by an editor session in deferred mode
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingWrite {
+    Connection(ConnectionUpdate),
+    Scene(SceneUpdate),
+}
+
+/// Tables holding a tour's scene graph, captured by snapshots (deleted in this order on restore)
+const SNAPSHOT_TABLES: &[&str] = &["connections", "tour_path", "assets", "scene_groups"];
+
+/// `tours` columns a copied tour (duplicate or from a template) gets fresh values for
+const COPIED_TOUR_OWN_COLUMNS: &[&str] = &["id", "owner", "tour_name", "slug", "created_at", "modified_at", "views", "share_base_url", "is_template", "requires_auth", "version"];
+
+/// A saved copy of a tour's scene graph
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub id: i64,
+    pub label: String,
+    pub created_at: String,
+}
+
+/// Outcome of copying one scene's connections onto another scene
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CopyConnectionsReport {
+    /// Tour of the destination scene
+    pub tour_id: i64,
+    pub copied: usize,
+    /// Connections whose target has no counterpart in the destination tour
+    pub dropped: usize,
+}
+
+/// Outcome of merging one scene into another
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MergeScenesReport {
+    pub tour_id: i64,
+    /// Outgoing connections moved from the removed scene to the kept one
+    pub moved: u64,
+    /// Connections (and floorplan markers) that pointed at the removed scene
+    pub repointed: u64,
+    /// Duplicates and kept-scene-to-itself transitions dropped after the move
+    pub duplicates_removed: u64,
+}
+
+/// An asset row whose file is no longer on disk
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MissingAsset {
+    pub id: i64,
+    pub tour_id: i64,
+    pub name: String,
+    pub file_path: String,
+    pub is_scene: bool,
+}
+
+/// Agency branding shown by the exported viewer (all fields optional)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Branding {
+    /// Public `/assets/...` path of the logo image
+    pub logo_path: Option<String>,
+    /// `#RRGGBB`
+    pub primary_color: Option<String>,
+    pub welcome_text: Option<String>,
+}
+
+impl Branding {
+    pub fn is_empty(&self) -> bool {
+        self.logo_path.is_none() && self.primary_color.is_none() && self.welcome_text.is_none()
+    }
+}
+
+/// A scene-to-scene or scene-to-closeup link as stored in the `connections` table
+#[derive(Debug, Clone, Serialize)]
+pub struct Connection {
+    pub id: i64,
+    /// Scene the hotspot is placed in
+    pub start_id: i64,
+    pub start_scene_name: Option<String>,
+    pub end_id: Option<i64>,
+    pub name: Option<String>,
+    pub world_lon: f32,
+    pub world_lat: f32,
+    pub connection_type: ConnectionType,
+}
+
+/// A tour reachable through an active share link (listed in the sitemap)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PublishedTour {
+    pub tour_id: i64,
+    /// Newest active share token of the tour
+    pub share_token: String,
+    pub share_base_url: Option<String>,
+    pub modified_at: String,
+}
+
+/// Export-readiness summary of a tour
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletenessReport {
+    /// Percentage (0-100) of checks passed
+    pub score: u8,
+    pub issues: Vec<CompletenessIssue>,
+}
+
+/// An asset row as exposed to the asset browser
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetRecord {
+    pub id: i64,
+    pub name: String,
+    pub file_path: Option<String>,
+    pub is_scene: bool,
+}
+
+/// Columns added to existing tables after their first release, as `(table, column, definition)`.
+/// `schema.sql` only creates missing tables, so older database files get these via `ALTER TABLE`.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("tours", "views", "INTEGER NOT NULL DEFAULT 0"),
+    ("assets", "group_id", "INTEGER"),
+    ("connections", "transition_style", "TEXT"),
+    ("connections", "icon_color", "TEXT"),
+    ("connections", "icon_scale", "FLOAT"),
+    ("tours", "brand_logo_path", "TEXT"),
+    ("tours", "brand_primary_color", "TEXT"),
+    ("tours", "brand_welcome_text", "TEXT"),
+    ("assets", "media_type", "TEXT NOT NULL DEFAULT 'image'"),
+    ("connections", "url_target", "TEXT"),
+    ("tours", "share_base_url", "TEXT"),
+    ("connections", "connection_type", "TEXT"),
+    ("assets", "thumbnail_path", "TEXT"),
+    ("assets", "hidden", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("assets", "captured_at", "TEXT"),
+    ("tours", "is_template", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("tours", "requires_auth", "BOOLEAN NOT NULL DEFAULT 0"),
+    ("connections", "z_index", "INTEGER NOT NULL DEFAULT 0"),
+    ("tours", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("connections", "audio_path", "TEXT"),
+    ("assets", "notes", "TEXT"),
+    ("assets", "min_fov", "REAL"),
+    ("assets", "max_fov", "REAL"),
+    ("tours", "slug", "TEXT"),
+    ("assets", "created_by", "TEXT"),
+    ("connections", "created_by", "TEXT"),
+    ("assets", "default_icon_index", "INTEGER"),
+];
+
+/// Created after `ADDED_COLUMNS`, since older database files only gain `tours.slug` there
+/// How many times a new tour picks its slug again after another tour took the picked one first
+const SLUG_ATTEMPTS: usize = 5;
+
+const TOUR_SLUG_INDEX: &str = "CREATE UNIQUE INDEX IF NOT EXISTS idx_tours_slug ON tours(slug)";
+
+/// Fills `connections.connection_type` for rows written before the column existed (or restored
+/// from older snapshots), from the legacy `is_transition` flag. Floorplan markers are left NULL.
+const BACKFILL_CONNECTION_TYPES: &str = "UPDATE connections
+    SET connection_type = CASE WHEN is_transition = 1 THEN 'transition' ELSE 'closeup' END
+    WHERE connection_type IS NULL AND is_floorplan = 0";
+
+/// Reads a row's `connection_type`; unknown or missing values load as closeups
+fn connection_type_of(row: &SqliteRow) -> ConnectionType {
+    row.get::<Option<String>, _>("connection_type")
+        .and_then(|value| ConnectionType::parse(&value))
+        .unwrap_or(ConnectionType::Closeup)
+}
+
+/// Brings a database up to the current schema: creates missing tables, then adds
+/// any columns an older database file is missing.
+///
+/// # Returns
+/// * `Ok(())` - The schema is current.
+/// * `Err(sqlx::Error)` - If the schema or a migration fails to apply.
+pub async fn migrate(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::raw_sql(include_str!("../schema.sql")).execute(pool).await?;
+
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists: bool = sqlx::query_scalar(&format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table))
+            .bind(column)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+            println!("Migrated: added {}.{}", table, column);
+        }
+    }
+
+    let backfilled = sqlx::query(BACKFILL_CONNECTION_TYPES).execute(pool).await?.rows_affected();
+    if backfilled > 0 {
+        println!("Migrated: set connection_type on {} connections", backfilled);
+    }
+
+    // Tours created before slugs existed get one, oldest first so they keep the plain names
+    let unslugged: Vec<(i64, String)> = sqlx::query_as("SELECT id, tour_name FROM tours WHERE slug IS NULL ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    let mut conn = pool.acquire().await?;
+    for (tour_id, name) in &unslugged {
+        let slug = Database::unique_tour_slug(&mut conn, name).await?;
+        sqlx::query("UPDATE tours SET slug = ?1 WHERE id = ?2")
+            .bind(slug)
+            .bind(tour_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    if !unslugged.is_empty() {
+        println!("Migrated: set slug on {} tours", unslugged.len());
+    }
+    sqlx::query(TOUR_SLUG_INDEX).execute(&mut *conn).await?;
+    Ok(())
+}
+
+/// URL form of a tour name: lowercase ASCII letters and digits, with every other run of
+/// characters turned into a single hyphen (`"My Tour!"` becomes `my-tour`)
+pub fn slugify(name: &str) -> String {
+    let slug = name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "tour".to_string() } else { slug }
+}
+
+/// True if the error is a UNIQUE / PRIMARY KEY constraint violation (e.g. a taken username).
+pub fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error.as_database_error().is_some_and(|e| e.is_unique_violation())
+}
+
+/// True if the error is the `tours_owner_exists_*` triggers refusing a tour for someone who
+/// isn't a registered user.
+pub fn is_missing_owner(error: &sqlx::Error) -> bool {
+    error.as_database_error().is_some_and(|e| e.message().contains("tour owner does not exist"))
+}
+
+/// Removes what only the editor should see from tour JSON bound for anyone else: the author's
+/// private scene `notes` and who created each scene and hotspot
+pub fn strip_private_fields(tour: &mut serde_json::Value) {
+    if let Some(scenes) = tour["scenes"].as_array_mut() {
+        for scene in scenes.iter_mut().filter_map(|s| s.as_object_mut()) {
+            scene.remove("notes");
+            scene.remove("created_by");
+            if let Some(connections) = scene.get_mut("connections").and_then(|c| c.as_array_mut()) {
+                for connection in connections.iter_mut().filter_map(|c| c.as_object_mut()) {
+                    connection.remove("created_by");
+                }
+            }
+        }
+    }
+}
+
+/// Removes hidden scenes and anything that would lead the viewer to one
+pub fn drop_hidden_scenes(tour: &mut serde_json::Value) {
+    let hidden: HashSet<i64> = tour["scenes"]
+        .as_array()
+        .map(|scenes| scenes.iter()
+            .filter(|s| s["hidden"].as_bool() == Some(true))
+            .filter_map(|s| s["id"].as_i64())
+            .collect())
+        .unwrap_or_default();
+    if hidden.is_empty() {
+        return;
+    }
+    let is_hidden = |id: &serde_json::Value| id.as_i64().is_some_and(|id| hidden.contains(&id));
+
+    if let Some(scenes) = tour["scenes"].as_array_mut() {
+        scenes.retain(|s| !is_hidden(&s["id"]));
+        for scene in scenes {
+            if let Some(conns) = scene["connections"].as_array_mut() {
+                conns.retain(|c| !is_hidden(&c["target_scene_id"]));
+            }
+        }
+    }
+    if let Some(groups) = tour["scene_groups"].as_array_mut() {
+        for group in groups {
+            if let Some(ids) = group["scene_ids"].as_array_mut() {
+                ids.retain(|id| !is_hidden(id));
+            }
+        }
+    }
+    if let Some(path) = tour["tour_path"].as_array_mut() {
+        path.retain(|id| !is_hidden(id));
+    }
+    if let Some(markers) = tour["floorplan_markers"].as_array_mut() {
+        markers.retain(|m| !is_hidden(&m["scene_id"]));
+    }
+}
+
+/// A connection row to insert; `end_scene_db_id` is the closeup asset for closeups
+#[derive(Debug, Clone, Copy)]
+pub struct NewConnection<'a> {
+    pub tour_id: i64,
+    pub start_scene_db_id: i64,
+    pub end_scene_db_id: Option<i64>,
+    pub world_lon: f32,
+    pub world_lat: f32,
+    pub connection_type: ConnectionType,
+    pub name: Option<&'a str>,
+    pub file_path: Option<&'a str>,
+    pub icon_type: Option<i32>,
+    /// User who added the connection
+    pub created_by: Option<&'a str>,
+}
+
+/// A scene row to insert
+#[derive(Debug, Clone, Copy)]
+pub struct NewScene<'a> {
+    pub tour_id: i64,
+    pub name: &'a str,
+    pub file_path: &'a str,
+    pub initial_view_x: Option<f32>,
+    pub initial_view_y: Option<f32>,
+    pub north_direction: Option<f32>,
+    /// User who added the scene
+    pub created_by: Option<&'a str>,
+}
+
+/// Writes available inside `Database::transaction`
+pub struct DbTransaction {
+    tx: sqlx::Transaction<'static, sqlx::Sqlite>,
+}
+
+impl DbTransaction {
+    /// Transaction-scoped `Database::save_closeup`
+    pub async fn save_closeup(&mut self, tour_id: i64, name: &str, file_path: &str, created_by: Option<&str>) -> Result<i64, sqlx::Error> {
+        Database::save_closeup_on(&mut self.tx, tour_id, name, file_path, created_by).await
+    }
+
+    /// Transaction-scoped `Database::save_connection`
+    pub async fn save_connection(&mut self, connection: &NewConnection<'_>) -> Result<i64, sqlx::Error> {
+        Database::save_connection_on(&mut self.tx, connection).await
+    }
+}
 
+/// How many tour changes a slow subscriber may fall behind before it starts missing some
+const TOUR_CHANGE_CAPACITY: usize = 256;
+
+/// A write touched a tour; sent to `Database::subscribe_tour_changes` listeners
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TourChange {
+    pub tour_id: i64,
+    /// User whose connections should hear about it
+    #[serde(skip)]
+    pub owner: String,
+    /// `None` once the tour is gone
+    pub modified_at: Option<String>,
+}
+
+/// When a tour last changed, for clients polling `GET /api/tours/:id/modified`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TourModified {
+    /// Latest `modified_at` of the tour or any of its assets (UTC, SQLite format)
+    pub modified_at: String,
+    /// Change counter, bumped each time the tour is announced as changed
+    pub version: i64,
+}
 
 /// Database wrapper that provides an interface for player management.
 #[derive(Clone, Debug)]
 pub struct Database {
     pub pool: Arc<SqlitePool>,
+    tour_changes: tokio::sync::broadcast::Sender<TourChange>,
+    /// Calls taking at least this long are logged (see `slow_query`); `None` disables it
+    slow_query_threshold: Option<std::time::Duration>,
 }
 
 impl Database {
@@ -28,9 +512,66 @@ impl Database {
     pub fn new(pool: SqlitePool) -> Self {
         Database {
             pool: Arc::new(pool),
+            tour_changes: tokio::sync::broadcast::channel(TOUR_CHANGE_CAPACITY).0,
+            slow_query_threshold: None,
+        }
+    }
+
+    /// Logs instrumented calls that take at least `threshold` (zero turns logging off)
+    pub fn with_slow_query_threshold(mut self, threshold: std::time::Duration) -> Self {
+        self.slow_query_threshold = (!threshold.is_zero()).then_some(threshold);
+        self
+    }
+
+    fn time_query(&self, name: &'static str) -> QueryTimer {
+        QueryTimer::start(name, self.slow_query_threshold)
+    }
+
+    /// Receives a `TourChange` for every tour-level write made through this database
+    pub fn subscribe_tour_changes(&self) -> tokio::sync::broadcast::Receiver<TourChange> {
+        self.tour_changes.subscribe()
+    }
+
+    /// Bumps the tour's `version` and announces that `tour_id` was modified, with its current owner and `modified_at`.
+    ///
+    /// Best effort: a failed lookup or nobody listening is not an error for the write itself.
+    pub async fn notify_tour_changed(&self, tour_id: i64) {
+        let _ = sqlx::query("UPDATE tours SET version = version + 1 WHERE id = ?1")
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await;
+        let row = sqlx::query("SELECT owner, modified_at FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await;
+        if let Ok(Some(row)) = row {
+            let _ = self.tour_changes.send(TourChange {
+                tour_id,
+                owner: row.get("owner"),
+                modified_at: row.get("modified_at"),
+            });
         }
     }
 
+    /// Announces that `owner` no longer has `tour_id` (deleted or handed to someone else)
+    fn notify_tour_gone(&self, tour_id: i64, owner: &str) {
+        let _ = self.tour_changes.send(TourChange { tour_id, owner: owner.to_string(), modified_at: None });
+    }
+
+    /// Runs `f` inside one transaction: committed if it returns `Ok`, rolled back otherwise.
+    ///
+    /// For editor actions whose writes depend on each other (e.g. a closeup asset and the
+    /// connection pointing at it), so a failure halfway doesn't leave orphaned rows.
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: for<'t> FnOnce(&'t mut DbTransaction) -> BoxFuture<'t, Result<T, sqlx::Error>>,
+    {
+        let mut tx = DbTransaction { tx: self.pool.begin().await? };
+        let value = f(&mut tx).await?;
+        tx.tx.commit().await?;
+        Ok(value)
+    }
+
     /// Authenticates a user with username and password
     /// 
     /// # Arguments
@@ -107,6 +648,7 @@ impl Database {
 
     /// Validates a session token and returns whether it's valid
     pub async fn validate_session(&self, username: &str, session_token: &str) -> Result<bool, sqlx::Error> {
+        let _timer = self.time_query("validate_session");
         // Check if session exists and is active
         let row = sqlx::query("SELECT is_active FROM user_sessions WHERE session_token = ?1 AND username = ?2 AND is_active = 1")
             .bind(session_token)
@@ -207,12 +749,15 @@ impl Database {
     /// 
     /// # Arguments
     /// * `username` - The user's username.
+    /// * `order_by` - Column to order by.
+    /// * `direction` - Ascending or descending; ties fall back to tour id.
     /// 
     /// # Returns
     /// * `Ok(Vec<Tour>)` - A vector of tours created by the user if found.
     /// * `Err(sqlx::Error)` - If the user does not exist or a database error occurs.
-    pub async fn get_tours(&self, username: &str) -> Result<Vec<Tour>, sqlx::Error> {
-    let rows = sqlx::query("SELECT id, 
+    pub async fn get_tours(&self, username: &str, order_by: TourOrder, direction: SortDirection) -> Result<Vec<Tour>, sqlx::Error> {
+        let _timer = self.time_query("get_tours");
+    let query = format!("SELECT id, 
                             tour_name,
                             created_at, 
                             modified_at, 
@@ -220,25 +765,16 @@ impl Database {
                             sort_mode,
                             sort_direction,
                             has_floorplan,
-                            floorplan_id
-                            FROM tours WHERE owner = ?1")
+                            floorplan_id,
+                            views
+                            FROM tours WHERE owner = ?1
+                            ORDER BY {} {}, id {}", order_by.column(), direction.sql(), direction.sql());
+    let rows = sqlx::query(&query)
             .bind(username)
             .fetch_all(&*self.pool)
             .await?;
 
-        let tours = rows.into_iter().map(|row| {
-            Tour::new(
-                row.get("id"),
-                row.get("tour_name"),
-                row.get("created_at"),
-                row.get("modified_at"),
-                row.get("initial_scene_id"),
-                row.get("sort_mode"),
-                row.get("sort_direction"),
-                row.get("has_floorplan"),
-                row.get("floorplan_id"),
-            )
-        }).collect();
+        let tours = rows.iter().map(Tour::from_row).collect();
 
         Ok(tours)
     }
@@ -246,27 +782,42 @@ impl Database {
     /// Creates a new tour for a user.
     /// 
     /// # Arguments
-    /// * `username` - The owner's username.
+    /// * `username` - The owner's username; must be a registered user.
     /// * `tour_name` - The name of the tour.
     /// * `location` - The location of the tour.
     /// 
     /// # Returns
     /// * `Ok(i64)` - The ID of the newly created tour.
-    /// * `Err(sqlx::Error)` - If the creation fails.
+    /// * `Err(sqlx::Error)` - If the creation fails, including when no user is named `username`
+    ///   ("tour owner does not exist", raised by the `tours_owner_exists_insert` trigger).
     pub async fn create_tour(&self, username: &str, tour_name: &str, _location: &str) -> Result<i64, sqlx::Error> {
-    let result = sqlx::query("INSERT INTO tours (tour_name, owner, created_at, modified_at, initial_scene_id, has_floorplan, floorplan_id, sort_mode, sort_direction) 
-                  VALUES (?1, ?2, datetime('now'), datetime('now'), 1, 0, 1, 'created_at', 'asc')")
-            .bind(tour_name)
-            .bind(username)
-            .execute(&*self.pool)
-            .await?;
+        let mut conn = self.pool.acquire().await?;
+        let mut attempt = 1;
+        let result = loop {
+            let slug = Self::unique_tour_slug(&mut conn, tour_name).await?;
+            let inserted = sqlx::query("INSERT INTO tours (tour_name, owner, created_at, modified_at, initial_scene_id, has_floorplan, floorplan_id, sort_mode, sort_direction, slug) 
+                      VALUES (?1, ?2, datetime('now'), datetime('now'), 1, 0, 1, 'created_at', 'asc', ?3)")
+                .bind(tour_name)
+                .bind(username)
+                .bind(slug)
+                .execute(&mut *conn)
+                .await;
+            match inserted {
+                // A concurrent create took the slug after it was picked
+                Err(e) if is_unique_violation(&e) && attempt < SLUG_ATTEMPTS => attempt += 1,
+                inserted => break inserted?,
+            }
+        };
+        drop(conn);
 
-        Ok(result.last_insert_rowid())
+        let tour_id = result.last_insert_rowid();
+        self.notify_tour_changed(tour_id).await;
+        Ok(tour_id)
     }
 
     /// Deletes a tour if it belongs to the specified user.
     /// This cascades to delete all associated scenes and connections.
-    /// Also deletes associated files from the filesystem.
+    /// Also deletes associated files from the filesystem, unless something else still uses them.
     /// 
     /// # Arguments
     /// * `username` - The owner's username.
@@ -276,333 +827,1341 @@ impl Database {
     /// * `Ok(bool)` - True if the tour was deleted, false if it didn't exist or didn't belong to the user.
     /// * `Err(sqlx::Error)` - If the deletion fails.
     pub async fn delete_tour(&self, username: &str, tour_id: i64) -> Result<bool, sqlx::Error> {
+        let _timer = self.time_query("delete_tour");
         // First check if the tour exists and belongs to the user
-        let tour_exists = sqlx::query("SELECT 1 FROM tours WHERE id = ?1 AND owner = ?2")
-            .bind(tour_id)
-            .bind(username)
-            .fetch_optional(&*self.pool)
-            .await?;
-
-        if tour_exists.is_none() {
+        if !self.is_tour_owner(tour_id, username).await? {
             return Ok(false);
         }
 
         // Get all file paths for assets belonging to this tour before deleting
-        let file_paths: Vec<String> = sqlx::query("SELECT file_path FROM assets WHERE tour_id = ?1 AND file_path IS NOT NULL")
+        let file_paths = self.tour_asset_paths(tour_id).await?;
+
+        // Delete connections, assets (scenes and closeups) and finally the tour itself
+        let mut tx = self.pool.begin().await?;
+        let deleted = Self::delete_tour_rows(&mut tx, tour_id).await?;
+        let unused = Self::release_unreferenced_paths(&mut tx, file_paths).await?;
+        tx.commit().await?;
+
+        // Delete files from filesystem
+        Self::remove_asset_files(&unused).await;
+
+        if deleted > 0 {
+            self.notify_tour_gone(tour_id, username);
+        }
+        Ok(deleted > 0)
+    }
+
+    /// Collects the file paths of all assets belonging to a tour
+    async fn tour_asset_paths(&self, tour_id: i64) -> Result<Vec<String>, sqlx::Error> {
+        Ok(sqlx::query("SELECT file_path FROM assets WHERE tour_id = ?1 AND file_path IS NOT NULL
+                        UNION SELECT thumbnail_path FROM assets WHERE tour_id = ?1 AND thumbnail_path IS NOT NULL")
             .bind(tour_id)
             .fetch_all(&*self.pool)
             .await?
             .iter()
             .filter_map(|row| row.get::<Option<String>, _>("file_path"))
-            .collect();
+            .collect())
+    }
 
-        // Delete files from filesystem
+    /// Of `file_paths`, keeps those nothing refers to any more once rows have been deleted on
+    /// `conn`, and forgets their uploads. Copied tours and deduplicated uploads share files,
+    /// so a file stays while any tour, hotspot or upload still uses it.
+    async fn release_unreferenced_paths(conn: &mut SqliteConnection, file_paths: Vec<String>) -> Result<Vec<String>, sqlx::Error> {
+        let mut unused = Vec::new();
+        for file_path in file_paths {
+            let in_use: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM assets WHERE file_path = ?1 OR thumbnail_path = ?1)
+                                                       OR EXISTS(SELECT 1 FROM connections WHERE file_path = ?1 OR audio_path = ?1)
+                                                       OR EXISTS(SELECT 1 FROM tours WHERE brand_logo_path = ?1)")
+                .bind(&file_path)
+                .fetch_one(&mut *conn)
+                .await?;
+            if in_use {
+                continue;
+            }
+            sqlx::query("DELETE FROM uploads WHERE file_path = ?1")
+                .bind(&file_path)
+                .execute(&mut *conn)
+                .await?;
+            unused.push(file_path);
+        }
+        Ok(unused)
+    }
+
+    /// File paths already placed as scenes in a tour (for badging the upload picker)
+    pub async fn scene_file_paths(&self, tour_id: i64) -> Result<std::collections::HashSet<String>, sqlx::Error> {
+        let paths: Vec<String> = sqlx::query_scalar("SELECT file_path FROM assets WHERE tour_id = ?1 AND is_scene = 1 AND file_path IS NOT NULL")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(paths.into_iter().collect())
+    }
+
+    /// Removes asset files from the filesystem, logging (not failing on) missing files
+    async fn remove_asset_files(file_paths: &[String]) {
         for file_path in file_paths {
             // Remove leading slash if present (file paths in DB may have /assets/... format)
-            let clean_path = file_path.strip_prefix("/").unwrap_or(&file_path);
+            let clean_path = file_path.strip_prefix("/").unwrap_or(file_path);
             
             match fs::remove_file(clean_path).await {
                 Ok(_) => println!("Deleted file: {}", clean_path),
                 Err(e) => eprintln!("Failed to delete file {}: {}", clean_path, e),
             }
         }
+    }
 
-        // Delete all connections for this tour
-        sqlx::query("DELETE FROM connections WHERE tour_id = ?1")
-            .bind(tour_id)
-            .execute(&*self.pool)
-            .await?;
+    /// Deletes a tour's connections, assets and the tour row on the given connection
+    /// (a pooled connection or an open transaction). Returns the number of tour rows removed.
+    async fn delete_tour_rows(conn: &mut SqliteConnection, tour_id: i64) -> Result<u64, sqlx::Error> {
+        for table in ["connections", "tour_path", "scene_groups", "share_tokens", "tour_snapshots"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE tour_id = ?1", table))
+                .bind(tour_id)
+                .execute(&mut *conn)
+                .await?;
+        }
 
-        // Delete all assets (scenes and closeups) for this tour
         sqlx::query("DELETE FROM assets WHERE tour_id = ?1")
             .bind(tour_id)
-            .execute(&*self.pool)
-            .await?;
-
-        // Finally delete the tour itself
-        let result = sqlx::query("DELETE FROM tours WHERE id = ?1 AND owner = ?2")
-            .bind(tour_id)
-            .bind(username)
-            .execute(&*self.pool)
+            .execute(&mut *conn)
             .await?;
 
-        Ok(result.rows_affected() > 0)
-    }
-
-    pub async fn get_tour(&self, tour_id: i64, username: &str) -> Result<Tour, sqlx::Error> {
-    let row = sqlx::query("SELECT id, 
-                            tour_name,
-                            created_at, 
-                            modified_at, 
-                            initial_scene_id,
-                            sort_mode,
-                            sort_direction,
-                            has_floorplan,
-                            floorplan_id
-                            FROM tours WHERE id = ?1 AND owner = ?2")
+        let result = sqlx::query("DELETE FROM tours WHERE id = ?1")
             .bind(tour_id)
-            .bind(username)
-            .fetch_one(&*self.pool)
+            .execute(&mut *conn)
             .await?;
 
-        Ok(Tour::new(
-            row.get("id"),
-            row.get("tour_name"),
-            row.get("created_at"),
-            row.get("modified_at"),
-            row.get("initial_scene_id"),
-            row.get("sort_mode"),
-            row.get("sort_direction"),
-            row.get("has_floorplan"),
-            row.get("floorplan_id"),
-        ))
+        Ok(result.rows_affected())
     }
 
-    /// Gets a tour with all its scenes and connections for the editor
+    /// Deletes a user account together with all of their tours, assets, connections, uploads and sessions.
+    /// Rows are removed in a single transaction; asset and upload files nothing else uses are
+    /// removed once it commits.
     /// 
     /// # Arguments
-    /// * `username` - The owner's username.
-    /// * `tour_id` - The ID of the tour to get.
+    /// * `username` - The user to delete.
     /// 
     /// # Returns
-    /// * `Ok(Some(TourData))` - The tour data with scenes and connections.
-    /// * `Ok(None)` - If the tour doesn't exist or doesn't belong to the user.
-    /// * `Err(sqlx::Error)` - If the query fails.
-    pub async fn get_tour_with_scenes(&self, username: &str, tour_id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
-        // First get the tour
-    let tour_row = sqlx::query("SELECT id, tour_name, created_at, modified_at, initial_scene_id, sort_mode, sort_direction, has_floorplan, floorplan_id
-                                   FROM tours WHERE id = ?1 AND owner = ?2")
-            .bind(tour_id)
-            .bind(username)
-            .fetch_optional(&*self.pool)
-            .await?;
-
-        if let Some(tour_row) = tour_row {
-            // Get all scenes for this tour
-            let scene_rows = sqlx::query("SELECT id, name, file_path, created_at, modified_at, initial_view_x, initial_view_y, north_dir, pov
-                                         FROM assets WHERE tour_id = ?1 AND is_scene = 1")
-                .bind(tour_id)
-                .fetch_all(&*self.pool)
-                .await?;
+    /// * `Ok(bool)` - True if the user existed and was deleted.
+    /// * `Err(sqlx::Error)` - If the deletion fails (nothing is removed).
+    pub async fn delete_user(&self, username: &str) -> Result<bool, sqlx::Error> {
+        if !self.user_exists(username).await? {
+            return Ok(false);
+        }
 
-            let mut scenes = Vec::new();
-            for scene_row in scene_rows {
-                let scene_id: i64 = scene_row.get("id");
-                
-                // Get connections for this scene
-                    let connection_rows = sqlx::query("SELECT id, end_id, name, world_lon, world_lat, is_transition, file_path, icon_type
-                                                      FROM connections WHERE tour_id = ?1 AND start_id = ?2")
-                    .bind(tour_id)
-                    .bind(scene_id)
-                    .fetch_all(&*self.pool)
-                    .await?;
+        let tour_ids: Vec<i64> = sqlx::query("SELECT id FROM tours WHERE owner = ?1")
+            .bind(username)
+            .fetch_all(&*self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("id"))
+            .collect();
 
-                let mut connections = Vec::new();
-                for conn_row in connection_rows {
-                    let id: i64 = conn_row.get("id");
-                    let target: Option<i64> = conn_row.get("end_id");
-                    let world_lon: f32 = conn_row.get("world_lon");
-                    let world_lat: f32 = conn_row.get("world_lat");
-                    let name: Option<String> = conn_row.get("name");
-                        let is_transition: bool = conn_row.get("is_transition");
-                        let file_path: Option<String> = conn_row.get("file_path");
-                        let icon_type: Option<i64> = conn_row.get("icon_type");
-                    let json = serde_json::json!({
-                        "id": id,
-                        "target_scene_id": target,
-                        "position": [world_lon, world_lat],
-                        "name": name,
-                        "file_path": file_path,
-                        "connection_type": if is_transition { "Transition" } else { "Closeup" },
-                        "icon_index": icon_type
-                    });
-                    connections.push(json);
+        let mut file_paths: Vec<String> = sqlx::query_scalar("SELECT file_path FROM uploads WHERE username = ?1")
+            .bind(username)
+            .fetch_all(&*self.pool)
+            .await?;
+        for tour_id in &tour_ids {
+            for file_path in self.tour_asset_paths(*tour_id).await? {
+                if !file_paths.contains(&file_path) {
+                    file_paths.push(file_path);
                 }
-
-                scenes.push(serde_json::json!({
-                    "id": scene_id,
-                    "name": scene_row.get::<String, _>("name"),
-                    "file_path": scene_row.get::<Option<String>, _>("file_path"),
-                    "created_at": scene_row.get::<String, _>("created_at"),
-                    "modified_at": scene_row.get::<String, _>("modified_at"),
-                    "initial_view_x": scene_row.get::<f32, _>("initial_view_x"),
-                    "initial_view_y": scene_row.get::<f32, _>("initial_view_y"),
-                    "north_dir": scene_row.get::<Option<f32>, _>("north_dir"),
-                    "initial_fov": scene_row.get::<Option<f32>, _>("pov"),
-                    "connections": connections
-                }));
             }
+        }
 
-            // If tour has a floorplan, fetch its asset record
-            let has_floorplan: bool = tour_row.get::<i64, _>("has_floorplan") != 0; // SQLite booleans
-            let mut floorplan_json = serde_json::Value::Null;
-            if has_floorplan {
-                if let Ok(Some(fp_row)) = sqlx::query("SELECT id, file_path, name, created_at, modified_at FROM assets WHERE tour_id = ?1 AND is_floorplan = 1 AND id = ?2")
-                    .bind(tour_id)
-                    .bind(tour_row.get::<i64, _>("floorplan_id"))
-                    .fetch_optional(&*self.pool)
-                    .await {
-                    floorplan_json = serde_json::json!({
-                        "id": fp_row.get::<i64, _>("id"),
-                        "file_path": fp_row.get::<Option<String>, _>("file_path"),
-                        "name": fp_row.get::<String, _>("name"),
-                        "created_at": fp_row.get::<String, _>("created_at"),
-                        "modified_at": fp_row.get::<String, _>("modified_at")
-                    });
-                }
-            }
+        let mut tx = self.pool.begin().await?;
+        for tour_id in &tour_ids {
+            Self::delete_tour_rows(&mut tx, *tour_id).await?;
+        }
+        let unused = Self::release_unreferenced_paths(&mut tx, file_paths).await?;
+        for table in ["user_sessions", "uploads"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE username = ?1", table))
+                .bind(username)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let result = sqlx::query("DELETE FROM users WHERE name = ?1")
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
 
-            // Collect floorplan markers if floorplan present
-            let mut floorplan_markers = Vec::new();
-            if has_floorplan {
-                if let Ok(rows) = sqlx::query("SELECT id, end_id, world_lon, world_lat FROM connections WHERE tour_id = ?1 AND is_floorplan = 1 AND start_id = ?2")
-                    .bind(tour_id)
-                    .bind(tour_row.get::<i64, _>("floorplan_id"))
-                    .fetch_all(&*self.pool)
-                    .await {
-                    for r in rows {
-                        floorplan_markers.push(serde_json::json!({
-                            "id": r.get::<i64,_>("id"),
-                            "scene_id": r.get::<i64,_>("end_id"),
-                            "position": [r.get::<f32,_>("world_lon"), r.get::<f32,_>("world_lat")] 
-                        }));
-                    }
-                }
-            }
+        Self::remove_asset_files(&unused).await;
 
-            let tour_data = serde_json::json!({
-                "id": tour_row.get::<i64, _>("id"),
-                "name": tour_row.get::<String, _>("tour_name"),
-                "sort_mode": tour_row.get::<Option<String>, _>("sort_mode"),
-                "sort_direction": tour_row.get::<Option<String>, _>("sort_direction"),
-                "created_at": tour_row.get::<String, _>("created_at"),
-                "modified_at": tour_row.get::<String, _>("modified_at"),
-                "initial_scene_id": tour_row.get::<i64, _>("initial_scene_id"),
-                "has_floorplan": has_floorplan,
-                "floorplan_id": tour_row.get::<i64, _>("floorplan_id"),
-                "floorplan": floorplan_json,
-                "floorplan_markers": floorplan_markers,
-                "scenes": scenes
-            });
+        Ok(result.rows_affected() > 0)
+    }
 
-            Ok(Some(tour_data))
-        } else {
-            Ok(None)
-        }
+    /// Checks whether a user with the given username exists
+    pub async fn user_exists(&self, username: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT 1 FROM users WHERE name = ?1")
+            .bind(username)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.is_some())
     }
 
-    /// Gets a tour with all its scenes and connections by tour_id only (no owner filter)
-    pub async fn get_tour_with_scenes_by_id(&self, tour_id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
-    let tour_row = sqlx::query("SELECT id, tour_name, created_at, modified_at, initial_scene_id, sort_mode, sort_direction, has_floorplan, floorplan_id
-                                   FROM tours WHERE id = ?1")
+    /// Checks whether a tour exists and belongs to the given user
+    pub async fn is_tour_owner(&self, tour_id: i64, username: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT 1 FROM tours WHERE id = ?1 AND owner = ?2")
             .bind(tour_id)
+            .bind(username)
             .fetch_optional(&*self.pool)
             .await?;
+        Ok(row.is_some())
+    }
 
-        if let Some(tour_row) = tour_row {
-            let scene_rows = sqlx::query("SELECT id, name, file_path, created_at, modified_at, initial_view_x, initial_view_y, north_dir, pov
-                                         FROM assets WHERE tour_id = ?1 AND is_scene = 1")
-                .bind(tour_id)
-                .fetch_all(&*self.pool)
-                .await?;
+    /// Transfers ownership of a tour from one user to another.
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour to transfer.
+    /// * `from_owner` - The current owner's username.
+    /// * `to_owner` - The username of the new owner (must exist).
+    /// 
+    /// # Returns
+    /// * `Ok(true)` - If ownership was reassigned.
+    /// * `Ok(false)` - If the tour doesn't exist or doesn't belong to `from_owner`.
+    /// * `Err(sqlx::Error::RowNotFound)` - If `to_owner` does not exist.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn transfer_tour(&self, tour_id: i64, from_owner: &str, to_owner: &str) -> Result<bool, sqlx::Error> {
+        if !self.is_tour_owner(tour_id, from_owner).await? {
+            return Ok(false);
+        }
 
-            let mut scenes = Vec::new();
-            for scene_row in scene_rows {
-                let scene_id: i64 = scene_row.get("id");
-                let connection_rows = sqlx::query("SELECT id, end_id, name, world_lon, world_lat, is_transition, file_path, icon_type
-                                                  FROM connections WHERE tour_id = ?1 AND start_id = ?2")
-                    .bind(tour_id)
-                    .bind(scene_id)
-                    .fetch_all(&*self.pool)
-                    .await?;
-                let mut connections = Vec::new();
-                for conn_row in connection_rows {
-                    let id: i64 = conn_row.get("id");
-                    let target: Option<i64> = conn_row.get("end_id");
-                    let world_lon: f32 = conn_row.get("world_lon");
-                    let world_lat: f32 = conn_row.get("world_lat");
-                    let name: Option<String> = conn_row.get("name");
-                    let is_transition: bool = conn_row.get("is_transition");
-                    let file_path: Option<String> = conn_row.get("file_path");
-                    let icon_type: Option<i64> = conn_row.get("icon_type");
-                    connections.push(serde_json::json!({
-                        "id": id,
-                        "target_scene_id": target,
-                        "position": [world_lon, world_lat],
-                        "name": name,
-                        "file_path": file_path,
-                        "connection_type": if is_transition { "Transition" } else { "Closeup" },
-                        "icon_index": icon_type
-                    }));
-                }
+        if !self.user_exists(to_owner).await? {
+            return Err(sqlx::Error::RowNotFound);
+        }
 
-                scenes.push(serde_json::json!({
-                    "id": scene_id,
-                    "name": scene_row.get::<String, _>("name"),
-                    "file_path": scene_row.get::<Option<String>, _>("file_path"),
-                    "created_at": scene_row.get::<String, _>("created_at"),
-                    "modified_at": scene_row.get::<String, _>("modified_at"),
-                    "initial_view_x": scene_row.get::<f32, _>("initial_view_x"),
-                    "initial_view_y": scene_row.get::<f32, _>("initial_view_y"),
-                    "north_dir": scene_row.get::<Option<f32>, _>("north_dir"),
-                    "initial_fov": scene_row.get::<Option<f32>, _>("pov"),
-                    "connections": connections
-                }));
-            }
+        let result = sqlx::query("UPDATE tours SET owner = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND owner = ?3")
+            .bind(to_owner)
+            .bind(tour_id)
+            .bind(from_owner)
+            .execute(&*self.pool)
+            .await?;
+
+        let transferred = result.rows_affected() > 0;
+        if transferred {
+            self.notify_tour_gone(tour_id, from_owner);
+            self.notify_tour_changed(tour_id).await;
+        }
+        Ok(transferred)
+    }
+
+    /// Marks or unmarks a tour as a template other users can start new tours from
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the flag was updated.
+    /// * `Ok(false)` - If the tour doesn't exist or doesn't belong to `owner`.
+    pub async fn set_tour_template(&self, tour_id: i64, owner: &str, is_template: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE tours SET is_template = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND owner = ?3")
+            .bind(is_template)
+            .bind(tour_id)
+            .bind(owner)
+            .execute(&*self.pool)
+            .await?;
+        if result.rows_affected() > 0 {
+            self.notify_tour_changed(tour_id).await;
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Restricts (or reopens) a tour's share links to signed-in users
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the flag was updated.
+    /// * `Ok(false)` - If the tour doesn't exist or doesn't belong to `owner`.
+    pub async fn set_tour_requires_auth(&self, tour_id: i64, owner: &str, requires_auth: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE tours SET requires_auth = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND owner = ?3")
+            .bind(requires_auth)
+            .bind(tour_id)
+            .bind(owner)
+            .execute(&*self.pool)
+            .await?;
+        if result.rows_affected() > 0 {
+            self.notify_tour_changed(tour_id).await;
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// True if only signed-in users may view the tour through its share links
+    pub async fn tour_requires_auth(&self, tour_id: i64) -> Result<bool, sqlx::Error> {
+        let requires_auth: Option<bool> = sqlx::query_scalar("SELECT requires_auth FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(requires_auth.unwrap_or(false))
+    }
+
+    /// Gets when a tour last changed without loading its graph.
+    ///
+    /// Scene and closeup edits stamp their asset rather than the tour, so the newest of the two is used.
+    /// Hotspot writes stamp the tour itself (see the `connections_touch_tour_*` triggers).
+    ///
+    /// # Returns
+    /// * `Ok(Some(TourModified))` - The timestamp and version.
+    /// * `Ok(None)` - If the tour doesn't exist.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn tour_modified(&self, tour_id: i64) -> Result<Option<TourModified>, sqlx::Error> {
+        let row = sqlx::query("SELECT MAX(t.modified_at, COALESCE((SELECT MAX(a.modified_at) FROM assets a WHERE a.tour_id = t.id), t.modified_at))
+                                      AS modified_at, t.version
+                               FROM tours t WHERE t.id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(|row| TourModified { modified_at: row.get("modified_at"), version: row.get("version") }))
+    }
+
+    /// Copies one of `username`'s tours into a new tour of theirs, in one transaction.
+    /// `name` defaults to the original's name with " (copy)" appended.
+    ///
+    /// # Returns
+    /// * `Ok(Some(i64))` - The ID of the copy.
+    /// * `Ok(None)` - If the tour doesn't exist or doesn't belong to `username`.
+    /// * `Err(sqlx::Error)` - If the copy fails; nothing is written.
+    pub async fn duplicate_tour(&self, username: &str, tour_id: i64, name: Option<&str>) -> Result<Option<i64>, sqlx::Error> {
+        let _timer = self.time_query("duplicate_tour");
+        let mut tx = self.pool.begin().await?;
+        let source_name: Option<String> = sqlx::query_scalar("SELECT tour_name FROM tours WHERE id = ?1 AND owner = ?2")
+            .bind(tour_id)
+            .bind(username)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(source_name) = source_name else { return Ok(None) };
+        let name = name.map(str::to_string).unwrap_or_else(|| format!("{} (copy)", source_name));
+
+        let copy_id = Self::copy_tour_on(&mut tx, tour_id, username, &name).await?;
+        tx.commit().await?;
+
+        self.notify_tour_changed(copy_id).await;
+        Ok(Some(copy_id))
+    }
+
+    /// Creates a tour for `username` holding a deep copy of a template's scenes, closeups,
+    /// floorplans, connections, scene groups and path. All in one transaction.
+    ///
+    /// # Returns
+    /// * `Ok(Some(i64))` - The ID of the new tour.
+    /// * `Ok(None)` - If `template_tour_id` doesn't exist or isn't marked as a template.
+    /// * `Err(sqlx::Error)` - If the copy fails; nothing is written.
+    pub async fn create_tour_from_template(&self, username: &str, template_tour_id: i64, name: &str) -> Result<Option<i64>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let is_template: Option<bool> = sqlx::query_scalar("SELECT is_template FROM tours WHERE id = ?1")
+            .bind(template_tour_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if is_template != Some(true) {
+            return Ok(None);
+        }
+
+        let tour_id = Self::copy_tour_on(&mut tx, template_tour_id, username, name).await?;
+        // The template author's notes stay with the template
+        sqlx::query("UPDATE assets SET notes = NULL WHERE tour_id = ?1")
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.notify_tour_changed(tour_id).await;
+        Ok(Some(tour_id))
+    }
+
+    /// Deep-copies a tour (scenes, closeups, floorplans, connections, groups, path) into a new
+    /// tour for `username` on the given connection; returns the new tour's id.
+    ///
+    /// Copies point at the source's uploaded files rather than duplicating them.
+    async fn copy_tour_on(conn: &mut SqliteConnection, source_tour_id: i64, username: &str, name: &str) -> Result<i64, sqlx::Error> {
+        // Viewer settings carry over; identity, counters and sharing don't
+        let settings: Vec<String> = Self::table_columns(&mut *conn, "tours").await?
+            .into_iter()
+            .filter(|c| !COPIED_TOUR_OWN_COLUMNS.contains(&c.as_str()))
+            .collect();
+        let insert = format!("INSERT INTO tours (owner, tour_name, slug, {0}) SELECT ?1, ?2, ?3, {0} FROM tours WHERE id = ?4", settings.join(", "));
+        let mut attempt = 1;
+        let tour_id = loop {
+            let inserted = sqlx::query(&insert)
+                .bind(username)
+                .bind(name)
+                .bind(Self::unique_tour_slug(&mut *conn, name).await?)
+                .bind(source_tour_id)
+                .execute(&mut *conn)
+                .await;
+            match inserted {
+                // As in `create_tour`, the slug may have been taken since it was picked
+                Err(e) if is_unique_violation(&e) && attempt < SLUG_ATTEMPTS => attempt += 1,
+                inserted => break inserted?.last_insert_rowid(),
+            }
+        };
+
+        let groups = Self::copy_tour_rows(&mut *conn, "scene_groups", source_tour_id, tour_id).await?;
+        let assets = Self::copy_tour_rows(&mut *conn, "assets", source_tour_id, tour_id).await?;
+        Self::copy_tour_rows(&mut *conn, "connections", source_tour_id, tour_id).await?;
+        sqlx::query("INSERT INTO tour_path (tour_id, position, scene_id) SELECT ?1, position, scene_id FROM tour_path WHERE tour_id = ?2")
+            .bind(tour_id)
+            .bind(source_tour_id)
+            .execute(&mut *conn)
+            .await?;
+
+        // Point the copied rows at each other instead of at the source's rows
+        // (placeholder ids such as a missing floorplan's are left as they are)
+        let remaps = [
+            ("assets", "group_id", "tour_id", &groups),
+            ("connections", "start_id", "tour_id", &assets),
+            ("connections", "end_id", "tour_id", &assets),
+            ("connections", "floorplan_id", "tour_id", &assets),
+            ("tour_path", "scene_id", "tour_id", &assets),
+            ("tours", "initial_scene_id", "id", &assets),
+            ("tours", "floorplan_id", "id", &assets),
+        ];
+        for (table, column, scope, ids) in remaps {
+            sqlx::query(&format!(
+                "UPDATE {0} SET {1} = (SELECT value FROM json_each(?1) WHERE key = CAST({1} AS TEXT))
+                 WHERE {2} = ?2 AND CAST({1} AS TEXT) IN (SELECT key FROM json_each(?1))",
+                table, column, scope))
+                .bind(ids.to_string())
+                .bind(tour_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(tour_id)
+    }
+
+    /// Slug for a tour named `name` that no other tour has: `slugify(name)`, or on a
+    /// collision the same with the lowest free suffix from 2 up (`my-tour-2`, `my-tour-3`, ...)
+    async fn unique_tour_slug(conn: &mut SqliteConnection, name: &str) -> Result<String, sqlx::Error> {
+        let base = slugify(name);
+        let taken: HashSet<String> = sqlx::query_scalar("SELECT slug FROM tours WHERE slug = ?1 OR slug LIKE ?1 || '-%'")
+            .bind(&base)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect();
+        if !taken.contains(&base) {
+            return Ok(base);
+        }
+        let suffix = (2..).find(|n| !taken.contains(&format!("{}-{}", base, n))).unwrap();
+        Ok(format!("{}-{}", base, suffix))
+    }
+
+    /// Copies every row of `table` from one tour to another, all columns but `id` and `tour_id`.
+    /// Returns a JSON object mapping each old id (as a string key) to its copy's id.
+    async fn copy_tour_rows(conn: &mut SqliteConnection, table: &str, from_tour: i64, to_tour: i64) -> Result<serde_json::Value, sqlx::Error> {
+        let columns: Vec<String> = Self::table_columns(conn, table).await?
+            .into_iter()
+            .filter(|c| c != "id" && c != "tour_id")
+            .collect();
+        let sql = format!("INSERT INTO {0} (tour_id, {1}) SELECT ?1, {1} FROM {0} WHERE id = ?2", table, columns.join(", "));
+        let ids: Vec<i64> = sqlx::query_scalar(&format!("SELECT id FROM {} WHERE tour_id = ?1 ORDER BY id", table))
+            .bind(from_tour)
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let mut copies = serde_json::Map::new();
+        for id in ids {
+            let copy = sqlx::query(&sql)
+                .bind(to_tour)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?
+                .last_insert_rowid();
+            copies.insert(id.to_string(), copy.into());
+        }
+        Ok(serde_json::Value::Object(copies))
+    }
+
+    pub async fn get_tour(&self, tour_id: i64, username: &str) -> Result<Tour, sqlx::Error> {
+    let row = sqlx::query("SELECT id, 
+                            tour_name,
+                            created_at, 
+                            modified_at, 
+                            initial_scene_id,
+                            sort_mode,
+                            sort_direction,
+                            has_floorplan,
+                            floorplan_id,
+                            views
+                            FROM tours WHERE id = ?1 AND owner = ?2")
+            .bind(tour_id)
+            .bind(username)
+            .fetch_one(&*self.pool)
+            .await?;
+
+        Ok(Tour::from_row(&row))
+    }
+
+    /// Gets the IDs of all of a user's tours, oldest first.
+    pub async fn tour_ids_of(&self, username: &str) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT id FROM tours WHERE owner = ?1 ORDER BY id")
+            .bind(username)
+            .fetch_all(&*self.pool)
+            .await
+    }
+
+    /// Gets every tour of a user with its full graph, for a JSON backup.
+    ///
+    /// # Returns
+    /// * `Ok(Value)` - An array of tours as `get_tour_with_scenes` returns them (author notes included), oldest first.
+    /// * `Err(sqlx::Error)` - If a query fails.
+    pub async fn export_all_json(&self, username: &str) -> Result<serde_json::Value, sqlx::Error> {
+        let _timer = self.time_query("export_all_json");
+        let mut tours = Vec::new();
+        for tour_id in self.tour_ids_of(username).await? {
+            if let Some(tour) = self.get_tour_with_scenes(username, tour_id).await? {
+                tours.push(tour);
+            }
+        }
+        Ok(serde_json::Value::Array(tours))
+    }
+
+    /// Gets a tour with all its scenes and connections for the editor
+    /// 
+    /// # Arguments
+    /// * `username` - The owner's username.
+    /// * `tour_id` - The ID of the tour to get.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(TourData))` - The tour data with scenes and connections.
+    /// * `Ok(None)` - If the tour doesn't exist or doesn't belong to the user.
+    /// * `Err(sqlx::Error)` - If the query fails.
+    pub async fn get_tour_with_scenes(&self, username: &str, tour_id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let _timer = self.time_query("get_tour_with_scenes");
+        // First get the tour
+        let tour_row = sqlx::query("SELECT id, tour_name, created_at, modified_at, initial_scene_id, sort_mode, sort_direction, has_floorplan, floorplan_id, slug
+                                   FROM tours WHERE id = ?1 AND owner = ?2")
+            .bind(tour_id)
+            .bind(username)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        match tour_row {
+            Some(tour_row) => Ok(Some(self.build_tour_json(&tour_row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets a tour with all its scenes and connections by tour_id only (no owner filter)
+    pub async fn get_tour_with_scenes_by_id(&self, tour_id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let _timer = self.time_query("get_tour_with_scenes_by_id");
+        let tour_row = sqlx::query("SELECT id, tour_name, created_at, modified_at, initial_scene_id, sort_mode, sort_direction, has_floorplan, floorplan_id, slug
+                                   FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        match tour_row {
+            Some(tour_row) => Ok(Some(self.build_tour_json(&tour_row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates a share token granting read-only access to a tour.
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour to share.
+    /// * `lifetime_secs` - Seconds until the token expires; `0` creates a token that never expires.
+    /// 
+    /// # Returns
+    /// * `Ok(String)` - The new token.
+    /// * `Err(sqlx::Error)` - If the insert fails.
+    pub async fn create_share_token(&self, tour_id: i64, lifetime_secs: u64) -> Result<String, sqlx::Error> {
+        let token = Uuid::new_v4().to_string();
+        let expires_modifier = (lifetime_secs > 0).then(|| format!("+{} seconds", lifetime_secs));
+        sqlx::query("INSERT INTO share_tokens (token, tour_id, expires_at) VALUES (?1, ?2, CASE WHEN ?3 IS NULL THEN NULL ELSE datetime('now', ?3) END)")
+            .bind(&token)
+            .bind(tour_id)
+            .bind(expires_modifier)
+            .execute(&*self.pool)
+            .await?;
+        Ok(token)
+    }
+
+    /// Resolves a share token to its tour's data.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(Value))` - The tour (without hidden scenes or author notes), if the token is active and unexpired.
+    /// * `Ok(None)` - If the token is unknown, deactivated or expired.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn get_tour_by_share_token(&self, token: &str) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let row = sqlx::query("SELECT tour_id FROM share_tokens
+                               WHERE token = ?1 AND is_active = 1 AND (expires_at IS NULL OR expires_at > datetime('now'))")
+            .bind(token)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        let Some(row) = row else { return Ok(None) };
+        let mut tour = self.get_tour_with_scenes_by_id(row.get("tour_id")).await?;
+        if let Some(tour) = tour.as_mut() {
+            drop_hidden_scenes(tour);
+            strip_private_fields(tour);
+        }
+        Ok(tour)
+    }
+
+    /// Resolves a tour slug to its tour's data, for tours that are published (have an active,
+    /// unexpired share link).
+    ///
+    /// # Returns
+    /// * `Ok(Some(Value))` - The tour (without hidden scenes or author notes).
+    /// * `Ok(None)` - If no tour has the slug, or it isn't published.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn get_published_tour_by_slug(&self, slug: &str) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let tour_id: Option<i64> = sqlx::query_scalar("SELECT t.id FROM tours t
+                                WHERE t.slug = ?1 AND EXISTS (SELECT 1 FROM share_tokens s WHERE s.tour_id = t.id
+                                    AND s.is_active = 1 AND (s.expires_at IS NULL OR s.expires_at > datetime('now')))")
+            .bind(slug)
+            .fetch_optional(&*self.pool)
+            .await?;
+
+        let Some(tour_id) = tour_id else { return Ok(None) };
+        let mut tour = self.get_tour_with_scenes_by_id(tour_id).await?;
+        if let Some(tour) = tour.as_mut() {
+            drop_hidden_scenes(tour);
+            strip_private_fields(tour);
+        }
+        Ok(tour)
+    }
+
+    /// Lists the tours that have at least one active, unexpired share link, ordered by tour id.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<PublishedTour>)` - One entry per tour, carrying its newest share token.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn list_published_tours(&self) -> Result<Vec<PublishedTour>, sqlx::Error> {
+        let rows = sqlx::query("SELECT t.id, t.modified_at, t.share_base_url, s.token
+                                FROM share_tokens s JOIN tours t ON t.id = s.tour_id
+                                WHERE s.is_active = 1 AND (s.expires_at IS NULL OR s.expires_at > datetime('now'))
+                                    AND t.requires_auth = 0
+                                ORDER BY t.id, s.created_at DESC, s.rowid DESC")
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut tours: Vec<PublishedTour> = Vec::new();
+        for row in rows {
+            let tour_id: i64 = row.get("id");
+            if tours.last().is_some_and(|t| t.tour_id == tour_id) {
+                continue;
+            }
+            tours.push(PublishedTour {
+                tour_id,
+                share_token: row.get("token"),
+                share_base_url: row.get("share_base_url"),
+                modified_at: row.get("modified_at"),
+            });
+        }
+        Ok(tours)
+    }
+
+    /// Counts a visit to a tour through a share link
+    pub async fn record_tour_view(&self, tour_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE tours SET views = views + 1 WHERE id = ?1")
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Finds a file the user already uploaded with the same content
+    /// 
+    /// # Arguments
+    /// * `username` - The uploading user.
+    /// * `content_hash` - Hex SHA-256 of the file bytes.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(String))` - Public path of the earlier upload.
+    /// * `Ok(None)` - If the user has no upload with this hash.
+    pub async fn find_upload_by_hash(&self, username: &str, content_hash: &str) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT file_path FROM uploads WHERE username = ?1 AND content_hash = ?2 ORDER BY created_at LIMIT 1")
+            .bind(username)
+            .bind(content_hash)
+            .fetch_optional(&*self.pool)
+            .await
+    }
+
+    /// Records an uploaded file and its content hash for later deduplication
+    pub async fn record_upload(&self, username: &str, file_path: &str, content_hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT OR REPLACE INTO uploads (file_path, username, content_hash) VALUES (?1, ?2, ?3)")
+            .bind(file_path)
+            .bind(username)
+            .bind(content_hash)
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Every asset path a user may attach to their tours: their own uploads, plus files their
+    /// tours already use (copied from a template, say). Paths are public (`/assets/...`).
+    pub async fn user_asset_paths(&self, username: &str) -> Result<HashSet<String>, sqlx::Error> {
+        let paths: Vec<String> = sqlx::query_scalar("SELECT file_path FROM uploads WHERE username = ?1
+                                    UNION SELECT a.file_path FROM assets a JOIN tours t ON t.id = a.tour_id
+                                        WHERE t.owner = ?1 AND a.file_path IS NOT NULL
+                                    UNION SELECT c.file_path FROM connections c JOIN tours t ON t.id = c.tour_id
+                                        WHERE t.owner = ?1 AND c.file_path IS NOT NULL")
+            .bind(username)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(paths.into_iter().collect())
+    }
+
+    /// Forgets an upload whose file is no longer on disk
+    pub async fn forget_upload(&self, file_path: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM uploads WHERE file_path = ?1")
+            .bind(file_path)
+            .execute(&*self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Gets a tour's branding
+    /// 
+    /// # Returns
+    /// * `Ok(Some(Branding))` - The branding (fields unset when not configured).
+    /// * `Ok(None)` - If the tour does not exist.
+    pub async fn get_tour_branding(&self, tour_id: i64) -> Result<Option<Branding>, sqlx::Error> {
+        let row = sqlx::query("SELECT brand_logo_path, brand_primary_color, brand_welcome_text FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(|row| Branding {
+            logo_path: row.get("brand_logo_path"),
+            primary_color: row.get("brand_primary_color"),
+            welcome_text: row.get("brand_welcome_text"),
+        }))
+    }
+
+    /// Replaces a tour's branding
+    pub async fn set_tour_branding(&self, tour_id: i64, branding: &Branding) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE tours SET brand_logo_path = ?1, brand_primary_color = ?2, brand_welcome_text = ?3,
+                     modified_at = CURRENT_TIMESTAMP WHERE id = ?4")
+            .bind(&branding.logo_path)
+            .bind(&branding.primary_color)
+            .bind(&branding.welcome_text)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        self.notify_tour_changed(tour_id).await;
+        Ok(())
+    }
+
+    /// Returns the base URL a tour's share links are built on, if the owner set one
+    pub async fn get_share_base_url(&self, tour_id: i64) -> Result<Option<String>, sqlx::Error> {
+        let base: Option<Option<String>> = sqlx::query_scalar("SELECT share_base_url FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(base.flatten())
+    }
+
+    /// Sets or clears (`None`) a tour's share link base URL
+    pub async fn set_share_base_url(&self, tour_id: i64, base_url: Option<&str>) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE tours SET share_base_url = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2")
+            .bind(base_url)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        self.notify_tour_changed(tour_id).await;
+        Ok(())
+    }
+
+    async fn table_columns(conn: &mut SqliteConnection, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT name FROM pragma_table_info(?1)")
+            .bind(table)
+            .fetch_all(&mut *conn)
+            .await
+    }
+
+    /// Saves a snapshot of a tour's scenes, closeups, floorplans, connections, path and groups.
+    /// Only the newest `max_snapshots` snapshots of the tour are kept.
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour.
+    /// * `label` - Name shown when picking a snapshot to restore.
+    /// * `max_snapshots` - How many snapshots to keep for the tour.
+    /// 
+    /// # Returns
+    /// * `Ok(i64)` - The ID of the new snapshot.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn create_snapshot(&self, tour_id: i64, label: &str, max_snapshots: usize) -> Result<i64, sqlx::Error> {
+        let _timer = self.time_query("create_snapshot");
+        let mut tx = self.pool.begin().await?;
+        let mut data = serde_json::Map::new();
+        for table in SNAPSHOT_TABLES {
+            // Every column is captured, so snapshots keep up with schema additions
+            let columns = Self::table_columns(&mut tx, table).await?;
+            let fields: Vec<String> = columns.iter().map(|c| format!("'{}', {}", c, c)).collect();
+            let rows: String = sqlx::query_scalar(&format!(
+                "SELECT json_group_array(json_object({})) FROM {} WHERE tour_id = ?1", fields.join(", "), table))
+                .bind(tour_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            data.insert(table.to_string(), serde_json::from_str(&rows).unwrap_or_default());
+        }
+        let tour_row = sqlx::query("SELECT initial_scene_id, has_floorplan, floorplan_id FROM tours WHERE id = ?1")
+            .bind(tour_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        data.insert("tour".to_string(), serde_json::json!({
+            "initial_scene_id": tour_row.get::<Option<i64>, _>("initial_scene_id"),
+            "has_floorplan": tour_row.get::<bool, _>("has_floorplan"),
+            "floorplan_id": tour_row.get::<Option<i64>, _>("floorplan_id")
+        }));
+
+        let snapshot_id = sqlx::query("INSERT INTO tour_snapshots (tour_id, label, data) VALUES (?1, ?2, ?3)")
+            .bind(tour_id)
+            .bind(label)
+            .bind(serde_json::Value::Object(data).to_string())
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+        sqlx::query("DELETE FROM tour_snapshots WHERE tour_id = ?1 AND id NOT IN
+                     (SELECT id FROM tour_snapshots WHERE tour_id = ?1 ORDER BY id DESC LIMIT ?2)")
+            .bind(tour_id)
+            .bind(max_snapshots.max(1) as i64)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(snapshot_id)
+    }
+
+    /// Lists a tour's snapshots, newest first
+    pub async fn list_snapshots(&self, tour_id: i64) -> Result<Vec<SnapshotInfo>, sqlx::Error> {
+        let rows = sqlx::query("SELECT id, label, created_at FROM tour_snapshots WHERE tour_id = ?1 ORDER BY id DESC")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| SnapshotInfo {
+            id: row.get("id"),
+            label: row.get("label"),
+            created_at: row.get("created_at"),
+        }).collect())
+    }
+
+    /// Replaces a tour's scene graph with the contents of one of its snapshots, in a single transaction.
+    /// Rows keep their original IDs, so references between them stay valid.
+    /// 
+    /// # Returns
+    /// * `Ok(true)` - If the tour was restored.
+    /// * `Ok(false)` - If the snapshot doesn't exist or belongs to another tour.
+    /// * `Err(sqlx::Error)` - If a database error occurs (nothing is changed).
+    pub async fn restore_snapshot(&self, tour_id: i64, snapshot_id: i64) -> Result<bool, sqlx::Error> {
+        let _timer = self.time_query("restore_snapshot");
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM tour_snapshots WHERE id = ?1 AND tour_id = ?2")
+            .bind(snapshot_id)
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        let data: serde_json::Value = match data.and_then(|d| serde_json::from_str(&d).ok()) {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+
+        let mut tx = self.pool.begin().await?;
+        for table in SNAPSHOT_TABLES {
+            sqlx::query(&format!("DELETE FROM {} WHERE tour_id = ?1", table))
+                .bind(tour_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for table in SNAPSHOT_TABLES.iter().rev() {
+            let columns = Self::table_columns(&mut tx, table).await?;
+            let rows = data[*table].as_array().cloned().unwrap_or_default();
+            for row in rows.iter().filter_map(|r| r.as_object()) {
+                // Columns dropped from the schema since the snapshot are skipped
+                let present: Vec<&String> = columns.iter().filter(|c| row.contains_key(c.as_str())).collect();
+                let placeholders: Vec<String> = (1..=present.len()).map(|i| format!("?{}", i)).collect();
+                let sql = format!("INSERT INTO {} ({}) VALUES ({})",
+                    table,
+                    present.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", "),
+                    placeholders.join(", "));
+                let mut query = sqlx::query(&sql);
+                for column in &present {
+                    query = match &row[column.as_str()] {
+                        serde_json::Value::Null => query.bind(None::<String>),
+                        serde_json::Value::Bool(b) => query.bind(*b),
+                        serde_json::Value::Number(n) if n.is_i64() => query.bind(n.as_i64()),
+                        serde_json::Value::Number(n) => query.bind(n.as_f64()),
+                        serde_json::Value::String(text) => query.bind(text.clone()),
+                        other => query.bind(other.to_string()),
+                    };
+                }
+                query.execute(&mut *tx).await?;
+            }
+        }
+        // Snapshots taken before connection_type existed only carry is_transition
+        sqlx::query(BACKFILL_CONNECTION_TYPES).execute(&mut *tx).await?;
+        let tour = &data["tour"];
+        sqlx::query("UPDATE tours SET initial_scene_id = ?1, has_floorplan = ?2, floorplan_id = ?3, modified_at = CURRENT_TIMESTAMP WHERE id = ?4")
+            .bind(tour["initial_scene_id"].as_i64())
+            .bind(tour["has_floorplan"].as_bool().unwrap_or(false))
+            .bind(tour["floorplan_id"].as_i64())
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.notify_tour_changed(tour_id).await;
+        Ok(true)
+    }
+
+    /// Deactivates share tokens past their expiry (called periodically)
+    /// 
+    /// # Returns
+    /// * `Ok(u64)` - Number of tokens deactivated.
+    pub async fn prune_expired_share_tokens(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("UPDATE share_tokens SET is_active = 0 WHERE is_active = 1 AND expires_at IS NOT NULL AND expires_at <= datetime('now')")
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Counts a tour's hotspots per connection type, keyed `transition`, `closeup` and `info`
+    /// (every type is present, zero if unused). Floorplan links are not hotspots and aren't counted.
+    ///
+    /// # Returns
+    /// * `Ok(HashMap)` - Hotspot count per type.
+    /// * `Err(sqlx::Error)` - If the query fails.
+    pub async fn connection_type_counts(&self, tour_id: i64) -> Result<HashMap<String, i64>, sqlx::Error> {
+        let rows = sqlx::query("SELECT connection_type, COUNT(*) AS count FROM connections
+                                WHERE tour_id = ?1 AND is_floorplan = 0 GROUP BY connection_type")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut counts: HashMap<String, i64> = [ConnectionType::Transition, ConnectionType::Closeup, ConnectionType::Info]
+            .iter()
+            .map(|kind| (kind.as_str().to_string(), 0))
+            .collect();
+        // Stored values may differ in case (or predate the column), so they are normalised here
+        for row in &rows {
+            *counts.entry(connection_type_of(row).as_str().to_string()).or_default() += row.get::<i64, _>("count");
+        }
+        Ok(counts)
+    }
+
+    /// Checks whether a tour is export-ready.
+    /// 
+    /// One check covers the initial scene; each scene adds three (initial view set, north
+    /// direction set, at least one outgoing connection). An initial view left at the default
+    /// (0, 0) counts as unset.
+    /// 
+    /// # Returns
+    /// * `Ok(CompletenessReport)` - Score and issues; a tour without scenes scores 0.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn tour_completeness(&self, tour_id: i64) -> Result<CompletenessReport, sqlx::Error> {
+        let _timer = self.time_query("tour_completeness");
+        let scene_rows = sqlx::query("SELECT a.id, a.initial_view_x, a.initial_view_y, a.north_dir,
+                                      (SELECT COUNT(*) FROM connections c WHERE c.start_id = a.id AND c.is_floorplan = 0) AS connection_count
+                                      FROM assets a WHERE a.tour_id = ?1 AND a.is_scene = 1 ORDER BY a.id")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        if scene_rows.is_empty() {
+            return Ok(CompletenessReport { score: 0, issues: vec![CompletenessIssue::NoScenes] });
+        }
+
+        let mut issues = Vec::new();
+        let initial_row = sqlx::query("SELECT 1 FROM tours t JOIN assets a ON a.id = t.initial_scene_id
+                                       WHERE t.id = ?1 AND a.tour_id = t.id AND a.is_scene = 1")
+            .bind(tour_id)
+            .fetch_optional(&*self.pool)
+            .await?;
+        if initial_row.is_none() {
+            issues.push(CompletenessIssue::NoInitialScene);
+        }
+
+        for row in &scene_rows {
+            let scene_id: i64 = row.get("id");
+            let view_x: f32 = row.get("initial_view_x");
+            let view_y: f32 = row.get("initial_view_y");
+            if view_x == 0.0 && view_y == 0.0 {
+                issues.push(CompletenessIssue::MissingInitialView { scene_id });
+            }
+            if row.get::<Option<f32>, _>("north_dir").is_none() {
+                issues.push(CompletenessIssue::MissingNorthDirection { scene_id });
+            }
+            if row.get::<i64, _>("connection_count") == 0 {
+                issues.push(CompletenessIssue::NoConnections { scene_id });
+            }
+        }
+
+        let checks = 1 + 3 * scene_rows.len();
+        let score = ((checks - issues.len()) * 100 / checks) as u8;
+        Ok(CompletenessReport { score, issues })
+    }
+
+    /// Finds the fewest-clicks route between two scenes by breadth-first search over the
+    /// tour's transition connections.
+    ///
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour.
+    /// * `from_scene` - Scene the viewer starts in.
+    /// * `to_scene` - Scene to reach.
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<i64>))` - Scene ids from `from_scene` to `to_scene`, both included.
+    /// * `Ok(None)` - If `to_scene` can't be reached (or either scene isn't in the tour).
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn shortest_path(&self, tour_id: i64, from_scene: i64, to_scene: i64) -> Result<Option<Vec<i64>>, sqlx::Error> {
+        let scene_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM assets WHERE tour_id = ?1 AND is_scene = 1")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        if !scene_ids.contains(&from_scene) || !scene_ids.contains(&to_scene) {
+            return Ok(None);
+        }
+
+        let edges = sqlx::query("SELECT start_id, end_id FROM connections
+                                 WHERE tour_id = ?1 AND connection_type = 'transition' AND is_floorplan = 0 AND end_id IS NOT NULL
+                                 ORDER BY id")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        let mut neighbours: HashMap<i64, Vec<i64>> = HashMap::new();
+        for row in &edges {
+            neighbours.entry(row.get("start_id")).or_default().push(row.get("end_id"));
+        }
 
-            let has_floorplan: bool = tour_row.get::<i64, _>("has_floorplan") != 0;
-            let mut floorplan_json = serde_json::Value::Null;
-            if has_floorplan {
-                if let Ok(Some(fp_row)) = sqlx::query("SELECT id, file_path, name, created_at, modified_at FROM assets WHERE tour_id = ?1 AND is_floorplan = 1 AND id = ?2")
-                    .bind(tour_id)
-                    .bind(tour_row.get::<i64, _>("floorplan_id"))
-                    .fetch_optional(&*self.pool)
-                    .await {
-                    floorplan_json = serde_json::json!({
-                        "id": fp_row.get::<i64, _>("id"),
-                        "file_path": fp_row.get::<Option<String>, _>("file_path"),
-                        "name": fp_row.get::<String, _>("name"),
-                        "created_at": fp_row.get::<String, _>("created_at"),
-                        "modified_at": fp_row.get::<String, _>("modified_at")
-                    });
+        // Each visited scene remembers the scene it was first reached from
+        let mut came_from: HashMap<i64, i64> = HashMap::from([(from_scene, from_scene)]);
+        let mut queue = std::collections::VecDeque::from([from_scene]);
+        while let Some(scene) = queue.pop_front() {
+            if scene == to_scene {
+                let mut path = vec![to_scene];
+                let mut current = to_scene;
+                while current != from_scene {
+                    current = came_from[&current];
+                    path.push(current);
                 }
+                path.reverse();
+                return Ok(Some(path));
             }
-            let mut floorplan_markers = Vec::new();
-            if has_floorplan {
-                if let Ok(rows) = sqlx::query("SELECT id, end_id, world_lon, world_lat FROM connections WHERE tour_id = ?1 AND is_floorplan = 1 AND start_id = ?2")
-                    .bind(tour_id)
-                    .bind(tour_row.get::<i64, _>("floorplan_id"))
-                    .fetch_all(&*self.pool)
-                    .await {
-                    for r in rows {
-                        floorplan_markers.push(serde_json::json!({
-                            "id": r.get::<i64,_>("id"),
-                            "scene_id": r.get::<i64,_>("end_id"),
-                            "position": [r.get::<f32,_>("world_lon"), r.get::<f32,_>("world_lat")] 
-                        }));
-                    }
+            for &next in neighbours.get(&scene).into_iter().flatten() {
+                if let std::collections::hash_map::Entry::Vacant(entry) = came_from.entry(next) {
+                    entry.insert(scene);
+                    queue.push_back(next);
                 }
             }
+        }
+        Ok(None)
+    }
 
-            let tour_data = serde_json::json!({
-                "id": tour_row.get::<i64, _>("id"),
-                "name": tour_row.get::<String, _>("tour_name"),
-                "sort_mode": tour_row.get::<Option<String>, _>("sort_mode"),
-                "sort_direction": tour_row.get::<Option<String>, _>("sort_direction"),
-                "created_at": tour_row.get::<String, _>("created_at"),
-                "modified_at": tour_row.get::<String, _>("modified_at"),
-                "initial_scene_id": tour_row.get::<i64, _>("initial_scene_id"),
-                "has_floorplan": has_floorplan,
-                "floorplan_id": tour_row.get::<i64, _>("floorplan_id"),
-                "floorplan": floorplan_json,
-                "floorplan_markers": floorplan_markers,
-                "scenes": scenes
-            });
+    /// Gets one page of a tour's scenes (with their connections) for lazy loading in the editor.
+    /// The initial scene always sorts first so it lands on the first page; the rest follow by id.
+    ///
+    /// # Arguments
+    /// * `username` - The owner's username.
+    /// * `tour_id` - The ID of the tour.
+    /// * `offset` - Number of scenes to skip.
+    /// * `limit` - Maximum number of scenes to return.
+    ///
+    /// # Returns
+    /// * `Ok(Some(Value))` - `{ tour_id, offset, limit, total, scenes }`.
+    /// * `Ok(None)` - If the tour doesn't exist or doesn't belong to the user.
+    /// * `Err(sqlx::Error)` - If the query fails.
+    pub async fn get_scenes_page(&self, username: &str, tour_id: i64, offset: i64, limit: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let _timer = self.time_query("get_scenes_page");
+        let tour_row = sqlx::query("SELECT initial_scene_id FROM tours WHERE id = ?1 AND owner = ?2")
+            .bind(tour_id)
+            .bind(username)
+            .fetch_optional(&*self.pool)
+            .await?;
+        let initial_scene_id: Option<i64> = match tour_row {
+            Some(row) => row.get("initial_scene_id"),
+            None => return Ok(None),
+        };
 
-            Ok(Some(tour_data))
-        } else {
-            Ok(None)
+        let total: i64 = sqlx::query("SELECT COUNT(*) AS count FROM assets WHERE tour_id = ?1 AND is_scene = 1")
+            .bind(tour_id)
+            .fetch_one(&*self.pool)
+            .await?
+            .try_get("count")?;
+
+        let scene_rows = sqlx::query(&format!("SELECT {} FROM assets WHERE tour_id = ?1 AND is_scene = 1
+                                               ORDER BY (id = ?2) DESC, id ASC LIMIT ?3 OFFSET ?4", SCENE_COLUMNS))
+            .bind(tour_id)
+            .bind(initial_scene_id.unwrap_or(-1))
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut scenes = Vec::new();
+        for scene_row in &scene_rows {
+            scenes.push(self.build_scene_json(tour_id, scene_row).await?);
+        }
+
+        Ok(Some(serde_json::json!({
+            "tour_id": tour_id,
+            "offset": offset,
+            "limit": limit,
+            "total": total,
+            "scenes": scenes
+        })))
+    }
+
+    /// Builds the full tour JSON (scenes, connections, floorplan) from a `tours` row
+    async fn build_tour_json(&self, tour_row: &SqliteRow) -> Result<serde_json::Value, sqlx::Error> {
+        let tour_id: i64 = tour_row.get("id");
+
+        // Get all scenes for this tour
+        let scene_rows = sqlx::query(&format!("SELECT {} FROM assets WHERE tour_id = ?1 AND is_scene = 1", SCENE_COLUMNS))
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut scenes = Vec::new();
+        for scene_row in &scene_rows {
+            scenes.push(self.build_scene_json(tour_id, scene_row).await?);
+        }
+
+        // Scene groups, with ungrouped scenes collected into a default group (id null)
+        let group_rows = sqlx::query("SELECT id, name FROM scene_groups WHERE tour_id = ?1 ORDER BY id")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?;
+        let scene_ids_in = |group_id: Option<i64>| -> Vec<i64> {
+            scene_rows.iter()
+                .filter(|r| r.get::<Option<i64>, _>("group_id") == group_id)
+                .map(|r| r.get::<i64, _>("id"))
+                .collect()
+        };
+        let mut scene_groups: Vec<serde_json::Value> = group_rows.iter().map(|g| {
+            let group_id: i64 = g.get("id");
+            serde_json::json!({
+                "id": group_id,
+                "name": g.get::<String, _>("name"),
+                "scene_ids": scene_ids_in(Some(group_id))
+            })
+        }).collect();
+        let ungrouped = scene_ids_in(None);
+        if !ungrouped.is_empty() {
+            scene_groups.push(serde_json::json!({
+                "id": serde_json::Value::Null,
+                "name": "Ungrouped",
+                "scene_ids": ungrouped
+            }));
+        }
+
+        // Recommended path for guided "next/previous" navigation
+        let tour_path: Vec<i64> = sqlx::query("SELECT scene_id FROM tour_path WHERE tour_id = ?1 ORDER BY position")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("scene_id"))
+            .collect();
+
+        // If tour has a floorplan, fetch its asset record
+        let has_floorplan: bool = tour_row.get::<i64, _>("has_floorplan") != 0; // SQLite booleans
+        let mut floorplan_json = serde_json::Value::Null;
+        if has_floorplan {
+            if let Ok(Some(fp_row)) = sqlx::query("SELECT id, file_path, name, created_at, modified_at FROM assets WHERE tour_id = ?1 AND is_floorplan = 1 AND id = ?2")
+                .bind(tour_id)
+                .bind(tour_row.get::<i64, _>("floorplan_id"))
+                .fetch_optional(&*self.pool)
+                .await {
+                floorplan_json = serde_json::json!({
+                    "id": fp_row.get::<i64, _>("id"),
+                    "file_path": fp_row.get::<Option<String>, _>("file_path"),
+                    "name": fp_row.get::<String, _>("name"),
+                    "created_at": fp_row.get::<String, _>("created_at"),
+                    "modified_at": fp_row.get::<String, _>("modified_at")
+                });
+            }
+        }
+
+        // Collect floorplan markers if floorplan present
+        let mut floorplan_markers = Vec::new();
+        if has_floorplan {
+            if let Ok(rows) = sqlx::query("SELECT id, end_id, world_lon, world_lat FROM connections WHERE tour_id = ?1 AND is_floorplan = 1 AND start_id = ?2")
+                .bind(tour_id)
+                .bind(tour_row.get::<i64, _>("floorplan_id"))
+                .fetch_all(&*self.pool)
+                .await {
+                for r in rows {
+                    floorplan_markers.push(serde_json::json!({
+                        "id": r.get::<i64,_>("id"),
+                        "scene_id": r.get::<i64,_>("end_id"),
+                        "position": [r.get::<f32,_>("world_lon"), r.get::<f32,_>("world_lat")] 
+                    }));
+                }
+            }
+        }
+
+        Ok(serde_json::json!({
+            "id": tour_id,
+            "name": tour_row.get::<String, _>("tour_name"),
+            "slug": tour_row.get::<Option<String>, _>("slug"),
+            "sort_mode": tour_row.get::<Option<String>, _>("sort_mode"),
+            "sort_direction": tour_row.get::<Option<String>, _>("sort_direction"),
+            "created_at": tour_row.get::<String, _>("created_at"),
+            "modified_at": tour_row.get::<String, _>("modified_at"),
+            "initial_scene_id": tour_row.get::<Option<i64>, _>("initial_scene_id"),
+            "has_floorplan": has_floorplan,
+            "floorplan_id": tour_row.get::<i64, _>("floorplan_id"),
+            "floorplan": floorplan_json,
+            "floorplan_markers": floorplan_markers,
+            "scene_groups": scene_groups,
+            "tour_path": tour_path,
+            "scenes": scenes
+        }))
+    }
+
+    /// Builds the JSON for a single scene row (selected with `SCENE_COLUMNS`) including its connections
+    /// Gets the outgoing connections (transitions and closeups) of one scene.
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour.
+    /// * `scene_id` - The ID of the scene the connections start from.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<Value>)` - Connection JSON objects, ordered by `z_index` (bottom first) then ID; `connection_type`
+    ///   is `"Transition"`, `"Closeup"` or `"Info"`, `audio_path` is the sound played from the hotspot (if any), and
+    ///   `thumbnail_path` is the target closeup's picker thumbnail when it has one.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn get_scene_connections(&self, tour_id: i64, scene_id: i64) -> Result<Vec<serde_json::Value>, sqlx::Error> {
+        let _timer = self.time_query("get_scene_connections");
+        let connection_rows = sqlx::query("SELECT c.id, c.end_id, c.name, c.world_lon, c.world_lat, c.connection_type, c.file_path, c.icon_type,
+                                                 c.transition_style, c.icon_color, c.icon_scale, c.url_target, c.z_index, c.audio_path, c.created_by, a.thumbnail_path
+                                          FROM connections c LEFT JOIN assets a ON a.id = c.end_id
+                                          WHERE c.tour_id = ?1 AND c.start_id = ?2 ORDER BY c.z_index, c.id")
+            .bind(tour_id)
+            .bind(scene_id)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut connections = Vec::new();
+        for conn_row in connection_rows {
+            let id: i64 = conn_row.get("id");
+            let target: Option<i64> = conn_row.get("end_id");
+            let world_lon: f32 = conn_row.get("world_lon");
+            let world_lat: f32 = conn_row.get("world_lat");
+            let name: Option<String> = conn_row.get("name");
+            let connection_type = connection_type_of(&conn_row);
+            let file_path: Option<String> = conn_row.get("file_path");
+            let icon_type: Option<i64> = conn_row.get("icon_type");
+            let transition_style: Option<String> = conn_row.get("transition_style");
+            let icon_color: Option<String> = conn_row.get("icon_color");
+            let icon_scale: Option<f32> = conn_row.get("icon_scale");
+            let url_target: Option<String> = conn_row.get("url_target");
+            let z_index: i64 = conn_row.get("z_index");
+            let audio_path: Option<String> = conn_row.get("audio_path");
+            let thumbnail_path: Option<String> = conn_row.get("thumbnail_path");
+            let created_by: Option<String> = conn_row.get("created_by");
+            connections.push(serde_json::json!({
+                "id": id,
+                "target_scene_id": target,
+                "position": [world_lon, world_lat],
+                "name": name,
+                "file_path": file_path,
+                "connection_type": connection_type,
+                "icon_index": icon_type,
+                "transition_style": transition_style,
+                "icon_color": icon_color,
+                "icon_scale": icon_scale,
+                "url_target": url_target,
+                "z_index": z_index,
+                "audio_path": audio_path,
+                "thumbnail_path": thumbnail_path,
+                "created_by": created_by
+            }));
         }
+
+        Ok(connections)
+    }
+
+    /// Gets the connections (from any scene) that lead to the given scene.
+    /// Floorplan markers are not included.
+    /// 
+    /// # Arguments
+    /// * `scene_id` - The ID of the target scene.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<Connection>)` - Incoming connections, ordered by ID.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn get_incoming_connections(&self, scene_id: i64) -> Result<Vec<Connection>, sqlx::Error> {
+        let rows = sqlx::query("SELECT c.id, c.start_id, a.name AS start_scene_name, c.end_id, c.name, c.world_lon, c.world_lat, c.connection_type
+                                FROM connections c LEFT JOIN assets a ON a.id = c.start_id
+                                WHERE c.end_id = ?1 AND c.is_floorplan = 0 ORDER BY c.id")
+            .bind(scene_id)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| Connection {
+            id: row.get("id"),
+            start_id: row.get("start_id"),
+            start_scene_name: row.get("start_scene_name"),
+            end_id: row.get("end_id"),
+            name: row.get("name"),
+            world_lon: row.get("world_lon"),
+            world_lat: row.get("world_lat"),
+            connection_type: connection_type_of(row),
+        }).collect())
+    }
+
+    /// Lists assets whose `file_path` no longer exists on disk (e.g. deleted out-of-band).
+    /// Only reports; nothing is deleted.
+    ///
+    /// # Arguments
+    /// * `owner` - Limit the audit to this user's tours; `None` checks every tour.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MissingAsset>)` - Assets with a missing file, ordered by id.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn audit_assets(&self, owner: Option<&str>) -> Result<Vec<MissingAsset>, sqlx::Error> {
+        let _timer = self.time_query("audit_assets");
+        let rows = sqlx::query("SELECT a.id, a.tour_id, a.name, a.file_path, a.is_scene
+                                FROM assets a JOIN tours t ON t.id = a.tour_id
+                                WHERE a.file_path IS NOT NULL AND a.file_path != '' AND (?1 IS NULL OR t.owner = ?1)
+                                ORDER BY a.id")
+            .bind(owner)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        Ok(rows.iter()
+            .filter(|row| !std::path::Path::new(row.get::<String, _>("file_path").trim_start_matches('/')).exists())
+            .map(|row| MissingAsset {
+                id: row.get("id"),
+                tour_id: row.get("tour_id"),
+                name: row.get("name"),
+                file_path: row.get("file_path"),
+                is_scene: row.get("is_scene"),
+            })
+            .collect())
+    }
+
+    /// Checks whether an asset belongs to a tour owned by the given user
+    pub async fn is_asset_owner(&self, asset_id: i64, username: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT 1 FROM assets a JOIN tours t ON t.id = a.tour_id WHERE a.id = ?1 AND t.owner = ?2")
+            .bind(asset_id)
+            .bind(username)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn build_scene_json(&self, tour_id: i64, scene_row: &SqliteRow) -> Result<serde_json::Value, sqlx::Error> {
+        let scene_id: i64 = scene_row.get("id");
+
+        let connections = self.get_scene_connections(tour_id, scene_id).await?;
+
+        Ok(serde_json::json!({
+            "id": scene_id,
+            "name": scene_row.get::<String, _>("name"),
+            "file_path": scene_row.get::<Option<String>, _>("file_path"),
+            "created_at": scene_row.get::<String, _>("created_at"),
+            "modified_at": scene_row.get::<String, _>("modified_at"),
+            "initial_view_x": scene_row.get::<f32, _>("initial_view_x"),
+            "initial_view_y": scene_row.get::<f32, _>("initial_view_y"),
+            "north_dir": scene_row.get::<Option<f32>, _>("north_dir"),
+            "initial_fov": scene_row.get::<Option<f32>, _>("pov"),
+            "group_id": scene_row.get::<Option<i64>, _>("group_id"),
+            "media_type": scene_row.get::<String, _>("media_type"),
+            "hidden": scene_row.get::<bool, _>("hidden"),
+            "captured_at": scene_row.get::<Option<String>, _>("captured_at"),
+            "notes": scene_row.get::<Option<String>, _>("notes"),
+            "min_fov": scene_row.get::<Option<f64>, _>("min_fov"),
+            "max_fov": scene_row.get::<Option<f64>, _>("max_fov"),
+            "created_by": scene_row.get::<Option<String>, _>("created_by"),
+            "default_icon_index": scene_row.get::<Option<i64>, _>("default_icon_index"),
+            "connections": connections
+        }))
     }
 
     /// Saves a scene to the database
@@ -621,28 +2180,45 @@ impl Database {
     pub async fn save_scene(&self, tour_id: i64, name: &str, file_path: &str, 
                            initial_view_x: Option<f32>, initial_view_y: Option<f32>, 
                            north_direction: Option<f32>) -> Result<i64, sqlx::Error> {
+        self.insert_scene(&NewScene { tour_id, name, file_path, initial_view_x, initial_view_y, north_direction, created_by: None }).await
+    }
+
+    /// `save_scene` that also records who added the scene
+    pub async fn insert_scene(&self, scene: &NewScene<'_>) -> Result<i64, sqlx::Error> {
+        let NewScene { tour_id, name, file_path, initial_view_x, initial_view_y, north_direction, created_by } = *scene;
         println!("Creating new asset entry for tour_id: {}, name: '{}', file_path: '{}'", tour_id, name, file_path);
         
-    let result = sqlx::query("INSERT INTO assets (tour_id, name, file_path, is_scene, initial_view_x, initial_view_y, north_dir) 
-                 VALUES (?1, ?2, ?3, 1, ?4, ?5, ?6)")
+    let result = sqlx::query("INSERT INTO assets (tour_id, name, file_path, is_scene, initial_view_x, initial_view_y, north_dir, media_type, created_by) 
+                 VALUES (?1, ?2, ?3, 1, ?4, ?5, ?6, ?7, ?8)")
             .bind(tour_id)
             .bind(name)
             .bind(file_path)
             .bind(initial_view_x.unwrap_or(0.0))
             .bind(initial_view_y.unwrap_or(0.0))
-            .bind(north_direction.map(|d| d as f32))
+            .bind(north_direction)
+            .bind(crate::editor::MediaType::from_path(file_path).as_str())
+            .bind(created_by)
             .execute(&*self.pool)
             .await?;
 
         let new_id = result.last_insert_rowid();
         println!("New asset created with database ID: {}", new_id);
+        self.notify_tour_changed(tour_id).await;
         Ok(new_id)
     }
 
     /// Updates an existing scene in the database
-    pub async fn update_scene(&self, scene_db_id: i64, name: Option<&str>, file_path: Option<&str>, 
-                             initial_view_x: Option<f32>, initial_view_y: Option<f32>, 
-                             north_direction: Option<f32>, pov: Option<f32>) -> Result<(), sqlx::Error> {
+    ///
+    /// `name` and `file_path` are left alone when `None`; the view fields take a `FieldUpdate`
+    /// so they can also be cleared.
+    pub async fn update_scene(&self, update: &SceneUpdate) -> Result<(), sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        Self::update_scene_on(&mut conn, update).await
+    }
+
+    async fn update_scene_on(conn: &mut SqliteConnection, update: &SceneUpdate) -> Result<(), sqlx::Error> {
+        let SceneUpdate { id: scene_db_id, name, file_path, initial_view_x, initial_view_y, north_direction, pov } = update;
+        let scene_db_id = *scene_db_id;
         let mut query = "UPDATE assets SET modified_at = CURRENT_TIMESTAMP".to_string();
         let mut bindings = Vec::new();
         let mut param_count = 1;
@@ -653,29 +2229,36 @@ impl Database {
             param_count += 1;
         }
         if let Some(file_path) = file_path {
-            query.push_str(&format!(", file_path = ?{}", param_count));
+            query.push_str(&format!(", file_path = ?{}, media_type = ?{}", param_count, param_count + 1));
             bindings.push(file_path.to_string());
-            param_count += 1;
-        }
-        if let Some(x) = initial_view_x {
-            query.push_str(&format!(", initial_view_x = ?{}", param_count));
-            bindings.push(x.to_string());
-            param_count += 1;
-        }
-        if let Some(y) = initial_view_y {
-            query.push_str(&format!(", initial_view_y = ?{}", param_count));
-            bindings.push(y.to_string());
-            param_count += 1;
-        }
-        if let Some(dir) = north_direction {
-            query.push_str(&format!(", north_dir = ?{}", param_count));
-            bindings.push((dir as i64).to_string());
-            param_count += 1;
+            bindings.push(crate::editor::MediaType::from_path(file_path).as_str().to_string());
+            param_count += 2;
+            // A replaced closeup image takes its own thumbnail (or none) along
+            match crate::editor::existing_closeup_thumbnail(file_path) {
+                Some(thumbnail) => {
+                    query.push_str(&format!(", thumbnail_path = ?{}", param_count));
+                    bindings.push(thumbnail);
+                    param_count += 1;
+                }
+                None => query.push_str(", thumbnail_path = NULL"),
+            }
         }
-        if let Some(pov_val) = pov {
-            query.push_str(&format!(", pov = ?{}", param_count));
-            bindings.push(pov_val.to_string());
-            param_count += 1;
+        let view_fields = [
+            ("initial_view_x", initial_view_x.map(|x| x.to_string()), "0"),
+            ("initial_view_y", initial_view_y.map(|y| y.to_string()), "0"),
+            ("north_dir", north_direction.map(|dir| (dir as i64).to_string()), "NULL"),
+            ("pov", pov.map(|pov_val| pov_val.to_string()), "NULL"),
+        ];
+        for (column, update, cleared) in view_fields {
+            match update {
+                FieldUpdate::Keep => {}
+                FieldUpdate::Set(value) => {
+                    query.push_str(&format!(", {} = ?{}", column, param_count));
+                    bindings.push(value);
+                    param_count += 1;
+                }
+                FieldUpdate::Clear => query.push_str(&format!(", {} = {}", column, cleared)),
+            }
         }
 
         query.push_str(&format!(" WHERE id = ?{}", param_count));
@@ -687,10 +2270,11 @@ impl Database {
         }
         sql_query = sql_query.bind(scene_db_id);
 
-        sql_query.execute(&*self.pool).await?;
+        sql_query.execute(&mut *conn).await?;
         Ok(())
     }
 
+
     /// Deletes a scene from the database and filesystem
     pub async fn delete_scene(&self, scene_db_id: i64) -> Result<(), sqlx::Error> {
         // First delete all connections involving this scene
@@ -699,6 +2283,12 @@ impl Database {
             .execute(&*self.pool)
             .await?;
 
+        // Drop it from the recommended path (remaining steps keep their relative order)
+        sqlx::query("DELETE FROM tour_path WHERE scene_id = ?1")
+            .bind(scene_db_id)
+            .execute(&*self.pool)
+            .await?;
+
         // Then delete the scene
         sqlx::query("DELETE FROM assets WHERE id = ?1")
             .bind(scene_db_id)
@@ -708,12 +2298,61 @@ impl Database {
         Ok(())
     }
 
+    /// Replaces a tour's recommended path through its scenes.
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour.
+    /// * `scene_ids` - Scene IDs in visiting order; an empty list clears the path.
+    /// 
+    /// # Returns
+    /// * `Ok(true)` - If the path was stored.
+    /// * `Ok(false)` - If any ID is not a scene of the tour (nothing is changed).
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_tour_path(&self, tour_id: i64, scene_ids: &[i64]) -> Result<bool, sqlx::Error> {
+        let tour_scene_ids: std::collections::HashSet<i64> = sqlx::query("SELECT id FROM assets WHERE tour_id = ?1 AND is_scene = 1")
+            .bind(tour_id)
+            .fetch_all(&*self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("id"))
+            .collect();
+        if !scene_ids.iter().all(|id| tour_scene_ids.contains(id)) {
+            return Ok(false);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM tour_path WHERE tour_id = ?1")
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+        for (position, scene_id) in scene_ids.iter().enumerate() {
+            sqlx::query("INSERT INTO tour_path (tour_id, position, scene_id) VALUES (?1, ?2, ?3)")
+                .bind(tour_id)
+                .bind(position as i64)
+                .bind(scene_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Makes `scene_id` the scene a tour opens on
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the initial scene was set.
+    /// * `Err(sqlx::Error::RowNotFound)` - If `scene_id` is not a scene of this tour (or the tour doesn't exist).
+    /// * `Err(sqlx::Error)` - If the update fails.
     pub async fn set_initial_scene(&self, tour_id: i64, scene_id: i64) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE tours SET initial_scene_id = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2")
+        let result = sqlx::query("UPDATE tours SET initial_scene_id = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2
+                                  AND EXISTS (SELECT 1 FROM assets WHERE id = ?1 AND tour_id = ?2 AND is_scene = 1)")
             .bind(scene_id)
             .bind(tour_id)
             .execute(&*self.pool)
             .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
         Ok(())
     }
 
@@ -726,19 +2365,23 @@ impl Database {
         Ok(())
     }
 
-    /// Gets the file path of the initial scene for a tour
-    pub async fn get_initial_scene_thumbnail(&self, tour_id: i64, initial_scene_id: Option<i64>) -> Result<Option<String>, sqlx::Error> {
-        if let Some(scene_id) = initial_scene_id {
-            let row = sqlx::query("SELECT file_path AS display_path FROM assets WHERE id = ?1 AND tour_id = ?2 AND is_scene = 1")
-                .bind(scene_id)
-                .bind(tour_id)
-                .fetch_optional(&*self.pool)
-                .await?;
-
-            Ok(row.and_then(|r| r.get("display_path")))
-        } else {
-            Ok(None)
+    /// Initial scene image of each of `tour_ids` in one query, for listing many tours.
+    ///
+    /// Every requested tour that exists gets an entry: `None` when it has no initial scene,
+    /// the initial scene is missing or belongs elsewhere, or it is a video.
+    pub async fn get_thumbnails_for_tours(&self, tour_ids: &[i64]) -> Result<HashMap<i64, Option<String>>, sqlx::Error> {
+        if tour_ids.is_empty() {
+            return Ok(HashMap::new());
         }
+        let ids = serde_json::Value::from(tour_ids.to_vec()).to_string();
+        let rows = sqlx::query("SELECT t.id, a.file_path FROM tours t
+                                LEFT JOIN assets a ON a.id = t.initial_scene_id AND a.tour_id = t.id
+                                    AND a.is_scene = 1 AND a.media_type = 'image'
+                                WHERE t.id IN (SELECT value FROM json_each(?1))")
+            .bind(ids)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("file_path"))).collect())
     }
 
     /// Saves a connection to the database
@@ -749,33 +2392,318 @@ impl Database {
     /// * `end_scene_db_id` - The database ID of the target scene (optional for closeups)
     /// * `screen_loc_x` - X coordinate of the connection on screen
     /// * `screen_loc_y` - Y coordinate of the connection on screen
-    /// * `is_transition` - Whether this is a scene transition (true) or closeup (false)
+    /// * `connection_type` - Transition to another scene, closeup or info hotspot
     /// 
     /// # Returns
     /// * `Ok(i64)` - The database ID of the inserted connection
     /// * `Err(sqlx::Error)` - If the insertion fails
     pub async fn save_connection(&self, tour_id: i64, start_scene_db_id: i64, end_scene_db_id: Option<i64>,
-                                world_lon: f32, world_lat: f32, is_transition: bool, name: Option<&str>, file_path: Option<&str>, icon_type: Option<i32>) -> Result<i64, sqlx::Error> {
-        let result = sqlx::query("INSERT INTO connections (tour_id, start_id, end_id, is_transition, name, world_lon, world_lat, file_path, icon_type)
-                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)")
+                                world_lon: f32, world_lat: f32, connection_type: ConnectionType, name: Option<&str>, file_path: Option<&str>, icon_type: Option<i32>) -> Result<i64, sqlx::Error> {
+        self.insert_connection(&NewConnection {
+            tour_id, start_scene_db_id, end_scene_db_id, world_lon, world_lat, connection_type, name, file_path, icon_type, created_by: None,
+        }).await
+    }
+
+    /// `save_connection` taking the whole row, including who added it
+    pub async fn insert_connection(&self, connection: &NewConnection<'_>) -> Result<i64, sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        let id = Self::save_connection_on(&mut conn, connection).await?;
+        drop(conn);
+        self.notify_tour_changed(connection.tour_id).await;
+        Ok(id)
+    }
+
+    async fn save_connection_on(conn: &mut SqliteConnection, connection: &NewConnection<'_>) -> Result<i64, sqlx::Error> {
+        let NewConnection { tour_id, start_scene_db_id, end_scene_db_id, world_lon, world_lat, connection_type, name, file_path, icon_type, created_by } = *connection;
+        let result = sqlx::query("INSERT INTO connections (tour_id, start_id, end_id, connection_type, name, world_lon, world_lat, file_path, icon_type, created_by)
+                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")
             .bind(tour_id)
             .bind(start_scene_db_id)
             .bind(end_scene_db_id)
-            .bind(is_transition)
+            .bind(connection_type.as_str())
             .bind(name)
             .bind(world_lon)
             .bind(world_lat)
             .bind(file_path)
             .bind(icon_type)
-            .execute(&*self.pool)
+            .bind(created_by)
+            .execute(&mut *conn)
             .await?;
 
         Ok(result.last_insert_rowid())
     }
 
+    /// Duplicates the transitions and closeups of one scene onto another scene (possibly in
+    /// another tour of the same owner).
+    /// 
+    /// Transition targets are kept within the same tour and otherwise remapped to the destination
+    /// tour's scene with the same name; links that can't be resolved (or would point at the
+    /// destination scene itself) are dropped. Closeups get their own copy of the closeup asset.
+    /// 
+    /// # Arguments
+    /// * `from_scene_id` - Scene to copy connections from.
+    /// * `to_scene_id` - Scene receiving the copies.
+    /// * `owner` - Must own both scenes' tours.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(CopyConnectionsReport))` - How many connections were copied and dropped.
+    /// * `Ok(None)` - If either scene doesn't exist or isn't owned by `owner`.
+    /// * `Err(sqlx::Error)` - If a database error occurs (nothing is copied).
+    pub async fn copy_connections(&self, from_scene_id: i64, to_scene_id: i64, owner: &str) -> Result<Option<CopyConnectionsReport>, sqlx::Error> {
+        let _timer = self.time_query("copy_connections");
+        let scene_tour = |scene_id: i64| {
+            sqlx::query_scalar::<_, i64>("SELECT a.tour_id FROM assets a JOIN tours t ON t.id = a.tour_id
+                                          WHERE a.id = ?1 AND a.is_scene = 1 AND t.owner = ?2")
+                .bind(scene_id)
+                .bind(owner)
+                .fetch_optional(&*self.pool)
+        };
+        let (from_tour, to_tour) = match (scene_tour(from_scene_id).await?, scene_tour(to_scene_id).await?) {
+            (Some(from_tour), Some(to_tour)) => (from_tour, to_tour),
+            _ => return Ok(None),
+        };
+
+        // Destination scenes by name, for remapping transitions across tours
+        let to_scenes_by_name: HashMap<String, i64> = sqlx::query("SELECT id, name FROM assets WHERE tour_id = ?1 AND is_scene = 1 ORDER BY id DESC")
+            .bind(to_tour)
+            .fetch_all(&*self.pool)
+            .await?
+            .iter()
+            .map(|row| (row.get("name"), row.get("id")))
+            .collect();
+
+        let rows = sqlx::query("SELECT c.end_id, c.name, c.world_lon, c.world_lat, c.connection_type, c.file_path, c.icon_type,
+                                       c.transition_style, c.icon_color, c.icon_scale, c.url_target, c.z_index, c.audio_path,
+                                       a.name AS target_name, a.file_path AS target_file_path
+                                FROM connections c LEFT JOIN assets a ON a.id = c.end_id
+                                WHERE c.start_id = ?1 AND c.is_floorplan = 0 ORDER BY c.id")
+            .bind(from_scene_id)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        let mut report = CopyConnectionsReport { tour_id: to_tour, copied: 0, dropped: 0 };
+        let mut tx = self.pool.begin().await?;
+        for row in rows {
+            let connection_type = connection_type_of(&row);
+            let end_id: Option<i64> = row.get("end_id");
+            let target_name: Option<String> = row.get("target_name");
+
+            let new_end_id = match connection_type {
+                ConnectionType::Transition => {
+                    let mapped = if from_tour == to_tour {
+                        end_id
+                    } else {
+                        target_name.and_then(|name| to_scenes_by_name.get(&name).copied())
+                    };
+                    match mapped {
+                        Some(id) if id != to_scene_id => Some(id),
+                        _ => {
+                            report.dropped += 1;
+                            continue;
+                        }
+                    }
+                }
+                ConnectionType::Closeup => {
+                    let Some(closeup_name) = target_name else {
+                        report.dropped += 1;
+                        continue;
+                    };
+                    let target_file_path: Option<String> = row.get("target_file_path");
+                    Some(sqlx::query("INSERT INTO assets (tour_id, name, file_path, is_scene, created_by) VALUES (?1, ?2, ?3, 0, ?4)")
+                        .bind(to_tour)
+                        .bind(closeup_name)
+                        .bind(target_file_path)
+                        .bind(owner)
+                        .execute(&mut *tx)
+                        .await?
+                        .last_insert_rowid())
+                }
+                // Info hotspots carry their content themselves; a target only makes sense within the tour
+                ConnectionType::Info => end_id.filter(|_| from_tour == to_tour),
+            };
+
+            sqlx::query("INSERT INTO connections (tour_id, start_id, end_id, connection_type, name, world_lon, world_lat, file_path, icon_type,
+                                                  transition_style, icon_color, icon_scale, url_target, z_index, audio_path, created_by)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)")
+                .bind(to_tour)
+                .bind(to_scene_id)
+                .bind(new_end_id)
+                .bind(connection_type.as_str())
+                .bind(row.get::<Option<String>, _>("name"))
+                .bind(row.get::<f32, _>("world_lon"))
+                .bind(row.get::<f32, _>("world_lat"))
+                .bind(row.get::<Option<String>, _>("file_path"))
+                .bind(row.get::<Option<i64>, _>("icon_type"))
+                .bind(row.get::<Option<String>, _>("transition_style"))
+                .bind(row.get::<Option<String>, _>("icon_color"))
+                .bind(row.get::<Option<f32>, _>("icon_scale"))
+                .bind(row.get::<Option<String>, _>("url_target"))
+                .bind(row.get::<i64, _>("z_index"))
+                .bind(row.get::<Option<String>, _>("audio_path"))
+                .bind(owner)
+                .execute(&mut *tx)
+                .await?;
+            report.copied += 1;
+        }
+        sqlx::query("UPDATE assets SET modified_at = CURRENT_TIMESTAMP WHERE id = ?1")
+            .bind(to_scene_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.notify_tour_changed(to_tour).await;
+        Ok(Some(report))
+    }
+
+    /// Merges `remove_id` into `keep_id`: the kept scene takes over the removed scene's hotspots
+    /// and incoming links, its place in the tour path and its initial-scene role, then the
+    /// removed scene is deleted. Runs in one transaction.
+    /// 
+    /// Afterwards at most one connection remains per start, target and type around the kept
+    /// scene, and transitions from the kept scene to itself are dropped.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(MergeScenesReport))` - What was moved and dropped.
+    /// * `Ok(None)` - If the ids are equal, aren't scenes of the same tour, or `owner` doesn't own it.
+    /// * `Err(sqlx::Error)` - If a database error occurs (nothing is changed).
+    pub async fn merge_scenes(&self, keep_id: i64, remove_id: i64, owner: &str) -> Result<Option<MergeScenesReport>, sqlx::Error> {
+        let _timer = self.time_query("merge_scenes");
+        if keep_id == remove_id {
+            return Ok(None);
+        }
+        let tour_ids: Vec<i64> = sqlx::query_scalar("SELECT a.tour_id FROM assets a JOIN tours t ON t.id = a.tour_id
+                                                     WHERE a.id IN (?1, ?2) AND a.is_scene = 1 AND t.owner = ?3")
+            .bind(keep_id)
+            .bind(remove_id)
+            .bind(owner)
+            .fetch_all(&*self.pool)
+            .await?;
+        let tour_id = match tour_ids.as_slice() {
+            [a, b] if a == b => *a,
+            _ => return Ok(None),
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let moved = sqlx::query("UPDATE connections SET start_id = ?1 WHERE start_id = ?2 AND is_floorplan = 0")
+            .bind(keep_id)
+            .bind(remove_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let repointed = sqlx::query("UPDATE connections SET end_id = ?1 WHERE end_id = ?2")
+            .bind(keep_id)
+            .bind(remove_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let self_links = sqlx::query("DELETE FROM connections WHERE start_id = ?1 AND end_id = ?1 AND connection_type = 'transition'")
+            .bind(keep_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let duplicates = sqlx::query("DELETE FROM connections
+                                      WHERE (start_id = ?1 OR end_id = ?1) AND end_id IS NOT NULL
+                                        AND id NOT IN (SELECT MIN(id) FROM connections
+                                                       WHERE (start_id = ?1 OR end_id = ?1) AND end_id IS NOT NULL
+                                                       GROUP BY start_id, end_id, connection_type, is_floorplan)")
+            .bind(keep_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        // The kept scene takes the removed one's path step unless it already has its own
+        sqlx::query("UPDATE tour_path SET scene_id = ?1 WHERE tour_id = ?3 AND scene_id = ?2
+                     AND NOT EXISTS (SELECT 1 FROM tour_path WHERE tour_id = ?3 AND scene_id = ?1)")
+            .bind(keep_id)
+            .bind(remove_id)
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM tour_path WHERE scene_id = ?1")
+            .bind(remove_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE tours SET initial_scene_id = ?1 WHERE id = ?3 AND initial_scene_id = ?2")
+            .bind(keep_id)
+            .bind(remove_id)
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM assets WHERE id = ?1")
+            .bind(remove_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE assets SET modified_at = CURRENT_TIMESTAMP WHERE id = ?1")
+            .bind(keep_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE tours SET modified_at = CURRENT_TIMESTAMP WHERE id = ?1")
+            .bind(tour_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.notify_tour_changed(tour_id).await;
+        Ok(Some(MergeScenesReport { tour_id, moved, repointed, duplicates_removed: self_links + duplicates }))
+    }
+
+    /// Saves several connections in one transaction
+    /// 
+    /// Either every connection is inserted or none are.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<i64>)` - The database IDs of the inserted connections, in `connections` order
+    /// * `Err(sqlx::Error)` - If any insertion fails (the whole batch is rolled back)
+    pub async fn save_connections_bulk(&self, connections: &[NewConnection<'_>]) -> Result<Vec<i64>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut ids = Vec::with_capacity(connections.len());
+        for connection in connections {
+            ids.push(Self::save_connection_on(&mut tx, connection).await?);
+        }
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    /// Copies a connection (target, position, name and styling) onto other scenes in one transaction.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<i64>)` - The database IDs of the copies, in `start_scene_db_ids` order (empty if the source doesn't exist)
+    /// * `Err(sqlx::Error)` - If any insertion fails (the whole batch is rolled back)
+    pub async fn duplicate_connection(&self, connection_db_id: i64, start_scene_db_ids: &[i64], created_by: Option<&str>) -> Result<Vec<i64>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut ids = Vec::with_capacity(start_scene_db_ids.len());
+        for start_id in start_scene_db_ids {
+            let result = sqlx::query("INSERT INTO connections (tour_id, start_id, end_id, connection_type, name, world_lon, world_lat, file_path, icon_type,
+                                                                transition_style, icon_color, icon_scale, url_target, z_index, audio_path, created_by)
+                                      SELECT tour_id, ?2, end_id, connection_type, name, world_lon, world_lat, file_path, icon_type,
+                                             transition_style, icon_color, icon_scale, url_target, z_index, audio_path, ?3
+                                      FROM connections WHERE id = ?1")
+                .bind(connection_db_id)
+                .bind(start_id)
+                .bind(created_by)
+                .execute(&mut *tx)
+                .await?;
+            if result.rows_affected() == 0 {
+                return Ok(Vec::new());
+            }
+            ids.push(result.last_insert_rowid());
+        }
+        tx.commit().await?;
+        Ok(ids)
+    }
+
     /// Updates an existing connection in the database
-    pub async fn update_connection(&self, connection_db_id: i64, end_scene_db_id: Option<i64>,
-                                  world_lon: Option<f32>, world_lat: Option<f32>, name: Option<&str>, icon_type: Option<i32>, file_path: Option<&str>) -> Result<(), sqlx::Error> {
+    pub async fn update_connection(&self, update: &ConnectionUpdate) -> Result<(), sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        Self::update_connection_on(&mut conn, update).await
+    }
+
+    async fn update_connection_on(conn: &mut SqliteConnection, update: &ConnectionUpdate) -> Result<(), sqlx::Error> {
+        let ConnectionUpdate { id: connection_db_id, end_id: end_scene_db_id, world_lon, world_lat, name, icon_type, file_path,
+                               transition_style, icon_color, icon_scale, url_target, connection_type, z_index, audio_path } = update;
+        let connection_db_id = *connection_db_id;
         let mut set_clauses: Vec<String> = Vec::new();
         let mut bindings: Vec<String> = Vec::new();
         let mut param_count = 1;
@@ -805,9 +2733,44 @@ impl Database {
             bindings.push(it.to_string());
             param_count += 1;
         }
-        if let Some(fp) = file_path {
-            set_clauses.push(format!("file_path = ?{}", param_count));
-            bindings.push(fp.to_string());
+        if let Some(fp) = file_path {
+            set_clauses.push(format!("file_path = ?{}", param_count));
+            bindings.push(fp.to_string());
+            param_count += 1;
+        }
+        if let Some(style) = transition_style {
+            set_clauses.push(format!("transition_style = ?{}", param_count));
+            bindings.push(style.to_string());
+            param_count += 1;
+        }
+        if let Some(color) = icon_color {
+            set_clauses.push(format!("icon_color = ?{}", param_count));
+            bindings.push(color.to_string());
+            param_count += 1;
+        }
+        if let Some(scale) = icon_scale {
+            set_clauses.push(format!("icon_scale = ?{}", param_count));
+            bindings.push(scale.to_string());
+            param_count += 1;
+        }
+        if let Some(target) = url_target {
+            set_clauses.push(format!("url_target = ?{}", param_count));
+            bindings.push(target.to_string());
+            param_count += 1;
+        }
+        if let Some(kind) = connection_type {
+            set_clauses.push(format!("connection_type = ?{}", param_count));
+            bindings.push(kind.as_str().to_string());
+            param_count += 1;
+        }
+        if let Some(z) = z_index {
+            set_clauses.push(format!("z_index = ?{}", param_count));
+            bindings.push(z.to_string());
+            param_count += 1;
+        }
+        if let Some(path) = audio_path {
+            set_clauses.push(format!("audio_path = ?{}", param_count));
+            bindings.push(path.to_string());
             param_count += 1;
         }
 
@@ -821,10 +2784,35 @@ impl Database {
         }
         sql_query = sql_query.bind(connection_db_id);
 
-        sql_query.execute(&*self.pool).await?;
+        sql_query.execute(&mut *conn).await?;
         Ok(())
     }
 
+    /// Applies row updates queued by a deferred editor session in a single transaction.
+    /// Connection updates also touch their start scene's `modified_at`.
+    /// 
+    /// # Returns
+    /// * `Ok(usize)` - Number of writes applied.
+    /// * `Err(sqlx::Error)` - If any write fails (none are applied).
+    pub async fn apply_pending_writes(&self, writes: &[PendingWrite]) -> Result<usize, sqlx::Error> {
+        let _timer = self.time_query("apply_pending_writes");
+        let mut tx = self.pool.begin().await?;
+        for write in writes {
+            match write {
+                PendingWrite::Connection(update) => {
+                    Self::update_connection_on(&mut tx, update).await?;
+                    sqlx::query("UPDATE assets SET modified_at = CURRENT_TIMESTAMP WHERE id = (SELECT start_id FROM connections WHERE id = ?1)")
+                        .bind(update.id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                PendingWrite::Scene(update) => Self::update_scene_on(&mut tx, update).await?,
+            }
+        }
+        tx.commit().await?;
+        Ok(writes.len())
+    }
+
     /// Deletes a connection from the database
     pub async fn delete_connection(&self, connection_db_id: i64) -> Result<(), sqlx::Error> {
         sqlx::query("DELETE FROM connections WHERE id = ?1")
@@ -835,15 +2823,35 @@ impl Database {
         Ok(())
     }
 
+    /// Deletes several connections in one transaction (all or none)
+    pub async fn delete_connections(&self, connection_db_ids: &[i64]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for id in connection_db_ids {
+            sqlx::query("DELETE FROM connections WHERE id = ?1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Saves a closeup asset to the database
     pub async fn save_closeup(&self, tour_id: i64, name: &str, file_path: &str, _icon_type: Option<i32>) -> Result<i64, sqlx::Error> {
         // icon_type is stored on connections, not assets. We ignore it here.
-        let result = sqlx::query("INSERT INTO assets (tour_id, name, file_path, is_scene) 
-                                 VALUES (?1, ?2, ?3, 0)")
+        let mut conn = self.pool.acquire().await?;
+        Self::save_closeup_on(&mut conn, tour_id, name, file_path, None).await
+    }
+
+    async fn save_closeup_on(conn: &mut SqliteConnection, tour_id: i64, name: &str, file_path: &str, created_by: Option<&str>) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query("INSERT INTO assets (tour_id, name, file_path, is_scene, thumbnail_path, created_by)
+                                 VALUES (?1, ?2, ?3, 0, ?4, ?5)")
             .bind(tour_id)
             .bind(name)
             .bind(file_path)
-            .execute(&*self.pool)
+            .bind(crate::editor::existing_closeup_thumbnail(file_path))
+            .bind(created_by)
+            .execute(&mut *conn)
             .await?;
 
         Ok(result.last_insert_rowid())
@@ -874,6 +2882,173 @@ impl Database {
         Ok(result.last_insert_rowid())
     }
 
+    /// Lists the asset rows of a tour of the given kind (scenes or closeups; floorplans excluded)
+    /// 
+    /// # Arguments
+    /// * `tour_id` - The ID of the tour.
+    /// * `kind` - Which kind of asset to list.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<AssetRecord>)` - Matching assets ordered by id.
+    /// * `Err(sqlx::Error)` - If the query fails.
+    pub async fn list_assets(&self, tour_id: i64, kind: AssetKind) -> Result<Vec<AssetRecord>, sqlx::Error> {
+        let is_scene = matches!(kind, AssetKind::Scene);
+        let rows = sqlx::query("SELECT id, name, file_path, is_scene FROM assets
+                                WHERE tour_id = ?1 AND is_scene = ?2 AND is_floorplan = 0 ORDER BY id")
+            .bind(tour_id)
+            .bind(is_scene)
+            .fetch_all(&*self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| AssetRecord {
+            id: row.get("id"),
+            name: row.get("name"),
+            file_path: row.get("file_path"),
+            is_scene: row.get("is_scene"),
+        }).collect())
+    }
+
+    /// Gets the stored image path of a scene asset in a tour owned by `owner`, or `None` if there is no such scene
+    pub async fn get_owned_scene_file_path(&self, asset_id: i64, owner: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT a.file_path FROM assets a JOIN tours t ON t.id = a.tour_id WHERE a.id = ?1 AND a.is_scene = 1 AND t.owner = ?2")
+            .bind(asset_id)
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.and_then(|r| r.get::<Option<String>, _>("file_path")))
+    }
+
+    /// File path of any asset (scene, closeup or floorplan) in a tour owned by `owner`
+    pub async fn get_owned_asset_file_path(&self, asset_id: i64, owner: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT a.file_path FROM assets a JOIN tours t ON t.id = a.tour_id WHERE a.id = ?1 AND t.owner = ?2")
+            .bind(asset_id)
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.and_then(|r| r.get::<Option<String>, _>("file_path")))
+    }
+
+    /// Creates a named scene group within a tour and returns its ID
+    pub async fn create_scene_group(&self, tour_id: i64, name: &str) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query("INSERT INTO scene_groups (tour_id, name) VALUES (?1, ?2)")
+            .bind(tour_id)
+            .bind(name)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Assigns a scene to a group of the same tour, or back to the default group with `None`
+    /// 
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene or group doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn assign_scene_to_group(&self, tour_id: i64, scene_id: i64, group_id: Option<i64>) -> Result<bool, sqlx::Error> {
+        if let Some(group_id) = group_id {
+            let group = sqlx::query("SELECT 1 FROM scene_groups WHERE id = ?1 AND tour_id = ?2")
+                .bind(group_id)
+                .bind(tour_id)
+                .fetch_optional(&*self.pool)
+                .await?;
+            if group.is_none() {
+                return Ok(false);
+            }
+        }
+
+        let result = sqlx::query("UPDATE assets SET group_id = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND tour_id = ?3 AND is_scene = 1")
+            .bind(group_id)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sets or clears when a scene was shot.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_scene_captured_at(&self, tour_id: i64, scene_id: i64, captured_at: Option<&str>) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE assets SET captured_at = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND tour_id = ?3 AND is_scene = 1")
+            .bind(captured_at)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sets or clears a scene's author notes. Notes are never published, so the tour's
+    /// `modified_at` is left alone.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_scene_notes(&self, tour_id: i64, scene_id: i64, notes: Option<&str>) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE assets SET notes = ?1 WHERE id = ?2 AND tour_id = ?3 AND is_scene = 1")
+            .bind(notes)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sets how far viewers may zoom a scene, as a field of view range in degrees; `None`
+    /// leaves that end to the viewer's default.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_scene_fov_limits(&self, tour_id: i64, scene_id: i64, min_fov: Option<f32>, max_fov: Option<f32>) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE assets SET min_fov = ?1, max_fov = ?2, modified_at = CURRENT_TIMESTAMP WHERE id = ?3 AND tour_id = ?4 AND is_scene = 1")
+            .bind(min_fov)
+            .bind(max_fov)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sets the icon a scene's hotspots are exported with when they don't pick their own;
+    /// `None` falls back to the configured default.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_scene_default_icon(&self, tour_id: i64, scene_id: i64, icon_index: Option<i32>) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE assets SET default_icon_index = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND tour_id = ?3 AND is_scene = 1")
+            .bind(icon_index)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Marks a scene hidden (kept in the editor, left out of exports) or visible again.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the scene was updated.
+    /// * `Ok(false)` - If the scene doesn't belong to the tour.
+    /// * `Err(sqlx::Error)` - If the update fails.
+    pub async fn set_scene_hidden(&self, tour_id: i64, scene_id: i64, hidden: bool) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE assets SET hidden = ?1, modified_at = CURRENT_TIMESTAMP WHERE id = ?2 AND tour_id = ?3 AND is_scene = 1")
+            .bind(hidden)
+            .bind(scene_id)
+            .bind(tour_id)
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Gets a scene database ID by tour ID and scene UUID
     pub async fn get_scene_db_id(&self, tour_id: i64, scene_name: &str) -> Result<Option<i64>, sqlx::Error> {
         let row = sqlx::query("SELECT id FROM assets WHERE tour_id = ?1 AND name = ?2 AND is_scene = 1")
@@ -937,7 +3112,7 @@ mod tests {
                 Some(closeup_id),
                 10.0,
                 5.0,
-                false,
+                ConnectionType::Closeup,
                 None,
                 Some("/assets/closeup_a.jpg"),
                 Some(3),
@@ -968,7 +3143,7 @@ mod tests {
         assert_eq!(found_type.as_deref(), Some("Closeup"), "expected connection_type=Closeup");
 
         // Update icon_type to 1 and verify
-    db.update_connection(conn_id, None, None, None, None, Some(1), None)
+    db.update_connection(&ConnectionUpdate { id: conn_id, icon_type: Some(1), ..Default::default() })
             .await
             .expect("update connection icon_type");
         let tour_data2 = db
@@ -1015,7 +3190,7 @@ mod tests {
                 Some(closeup_id),
                 12.3,
                 4.5,
-                false,
+                ConnectionType::Closeup,
                 Some("Tag Plate"),
                 Some("/assets/closeup_a.jpg"),
                 Some(2),
@@ -1044,4 +3219,768 @@ mod tests {
         }
         assert_eq!(found_name.as_deref(), Some("Tag Plate"));
     }
+
+    #[tokio::test]
+    async fn test_scenes_page_covers_tour_without_duplicates() {
+        let db = setup_test_db().await;
+
+        db.register_user("testuser", "password").await.expect("register user");
+        let tour_id = db.create_tour("testuser", "Big Tour", "").await.expect("create tour");
+
+        let mut scene_ids = Vec::new();
+        for i in 0..50 {
+            let id = db
+                .save_scene(tour_id, &format!("Scene {}", i), &format!("/assets/scene_{}.jpg", i), None, None, None)
+                .await
+                .expect("save scene");
+            scene_ids.push(id);
+        }
+        // Use a scene from the middle so ordering by id alone wouldn't put it first
+        let initial = scene_ids[30];
+        db.set_initial_scene(tour_id, initial).await.expect("set initial scene");
+
+        let mut seen: Vec<i64> = Vec::new();
+        let mut offset = 0;
+        while offset < 50 {
+            let page = db
+                .get_scenes_page("testuser", tour_id, offset, 20)
+                .await
+                .expect("get page")
+                .expect("tour exists");
+            assert_eq!(page["total"].as_i64(), Some(50));
+            let scenes = page["scenes"].as_array().expect("scenes array");
+            if offset == 0 {
+                assert_eq!(scenes[0]["id"].as_i64(), Some(initial), "initial scene should lead the first page");
+            }
+            seen.extend(scenes.iter().filter_map(|s| s["id"].as_i64()));
+            offset += 20;
+        }
+
+        let mut deduped = seen.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(seen.len(), 50, "pages should cover every scene");
+        assert_eq!(deduped.len(), 50, "pages should not repeat scenes");
+
+        // Someone else's tour is not pageable
+        assert!(db.get_scenes_page("intruder", tour_id, 0, 20).await.expect("query").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_tour_to_existing_user() {
+        let db = setup_test_db().await;
+
+        db.register_user("agency", "password").await.expect("register agency");
+        db.register_user("client", "password").await.expect("register client");
+        let tour_id = db.create_tour("agency", "Finished Tour", "").await.expect("create tour");
+
+        assert!(db.transfer_tour(tour_id, "agency", "client").await.expect("transfer"));
+
+        assert!(db.get_tour_with_scenes("client", tour_id).await.expect("query").is_some());
+        assert!(db.get_tour_with_scenes("agency", tour_id).await.expect("query").is_none());
+
+        // The previous owner can no longer transfer it
+        assert!(!db.transfer_tour(tour_id, "agency", "client").await.expect("second transfer"));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_tour_to_missing_user_fails() {
+        let db = setup_test_db().await;
+
+        db.register_user("agency", "password").await.expect("register agency");
+        let tour_id = db.create_tour("agency", "Finished Tour", "").await.expect("create tour");
+
+        let result = db.transfer_tour(tour_id, "agency", "nobody").await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+
+        // Ownership is untouched
+        assert!(db.get_tour_with_scenes("agency", tour_id).await.expect("query").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_assets_by_kind() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.expect("register user");
+        let tour_id = db.create_tour("testuser", "Mixed Tour", "").await.expect("create tour");
+
+        let scene_a = db.save_scene(tour_id, "Scene A", "/assets/insta360/a.jpg", None, None, None).await.expect("save scene");
+        let scene_b = db.save_scene(tour_id, "Scene B", "/assets/insta360/b.jpg", None, None, None).await.expect("save scene");
+        let closeup = db.save_closeup(tour_id, "Closeup", "/assets/closeups/c.jpg", None).await.expect("save closeup");
+        db.save_floorplan(tour_id, "Floorplan", "/assets/floorplans/f.png").await.expect("save floorplan");
+
+        let scenes = db.list_assets(tour_id, AssetKind::Scene).await.expect("list scenes");
+        assert_eq!(scenes.iter().map(|a| a.id).collect::<Vec<_>>(), vec![scene_a, scene_b]);
+        assert!(scenes.iter().all(|a| a.is_scene));
+
+        let closeups = db.list_assets(tour_id, AssetKind::Closeup).await.expect("list closeups");
+        assert_eq!(closeups.len(), 1);
+        assert_eq!(closeups[0].id, closeup);
+        assert_eq!(closeups[0].file_path.as_deref(), Some("/assets/closeups/c.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_purges_everything() {
+        let db = setup_test_db().await;
+        db.register_user("leaving", "password").await.expect("register user");
+        db.login_user("leaving").await.expect("login");
+
+        // Asset files live under a relative scratch dir, like the real assets/ folder
+        let scratch = format!("target/test_assets/{}", Uuid::new_v4());
+        std::fs::create_dir_all(&scratch).expect("create scratch dir");
+
+        let mut files = Vec::new();
+        for tour_name in ["Tour One", "Tour Two"] {
+            let tour_id = db.create_tour("leaving", tour_name, "").await.expect("create tour");
+            let rel = format!("{}/{}.jpg", scratch, tour_name.replace(' ', "_"));
+            std::fs::write(&rel, b"jpeg").expect("write asset");
+            let a = db.save_scene(tour_id, "A", &format!("/{}", rel), None, None, None).await.expect("save scene");
+            let b = db.save_scene(tour_id, "B", "/assets/insta360/missing.jpg", None, None, None).await.expect("save scene");
+            db.save_connection(tour_id, a, Some(b), 0.0, 0.0, ConnectionType::Transition, None, None, None).await.expect("save connection");
+            files.push(rel);
+        }
+        // An upload never placed in a tour goes too, one another user's tour uses stays
+        let unplaced = format!("{}/unplaced.jpg", scratch);
+        let kept = format!("{}/kept.jpg", scratch);
+        for rel in [&unplaced, &kept] {
+            std::fs::write(rel, b"jpeg").expect("write upload");
+            db.record_upload("leaving", &format!("/{}", rel), "hash").await.expect("record upload");
+        }
+        files.push(unplaced);
+        db.register_user("staying", "password").await.expect("register user");
+        let other = db.create_tour("staying", "Other", "").await.expect("create tour");
+        db.save_scene(other, "Kept", &format!("/{}", kept), None, None, None).await.expect("save scene");
+
+        assert!(db.delete_user("leaving").await.expect("delete user"));
+
+        for (table, column) in [("users", "name"), ("user_sessions", "username"), ("tours", "owner")] {
+            let count: i64 = sqlx::query(&format!("SELECT COUNT(*) AS count FROM {} WHERE {} = ?1", table, column))
+                .bind("leaving")
+                .fetch_one(&*db.pool)
+                .await
+                .expect("count")
+                .get("count");
+            assert_eq!(count, 0, "residual rows in {}", table);
+        }
+        for table in ["assets WHERE tour_id != ?1", "connections", "uploads"] {
+            let count: i64 = sqlx::query(&format!("SELECT COUNT(*) AS count FROM {}", table))
+                .bind(other)
+                .fetch_one(&*db.pool)
+                .await
+                .expect("count")
+                .get("count");
+            assert_eq!(count, 0, "residual rows in {}", table);
+        }
+        for file in &files {
+            assert!(!std::path::Path::new(file).exists(), "file {} should be removed", file);
+        }
+        assert!(std::path::Path::new(&kept).exists());
+        let _ = std::fs::remove_dir_all(&scratch);
+
+        assert!(!db.delete_user("leaving").await.expect("second delete"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_tour_keeps_files_other_tours_share() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let scratch = format!("target/test_assets/{}", Uuid::new_v4());
+        std::fs::create_dir_all(&scratch).unwrap();
+        let rel = format!("{}/shared.jpg", scratch);
+        std::fs::write(&rel, b"jpeg").unwrap();
+        let file_path = format!("/{}", rel);
+        db.record_upload("testuser", &file_path, "hash").await.unwrap();
+
+        // A deduplicated upload placed in two tours
+        let first = db.create_tour("testuser", "First", "").await.unwrap();
+        let second = db.create_tour("testuser", "Second", "").await.unwrap();
+        db.save_scene(first, "A", &file_path, None, None, None).await.unwrap();
+        db.save_scene(second, "B", &file_path, None, None, None).await.unwrap();
+
+        assert!(db.delete_tour("testuser", first).await.unwrap());
+        assert!(std::path::Path::new(&rel).exists());
+        assert_eq!(db.find_upload_by_hash("testuser", "hash").await.unwrap(), Some(file_path.clone()));
+
+        assert!(db.delete_tour("testuser", second).await.unwrap());
+        assert!(!std::path::Path::new(&rel).exists());
+        assert_eq!(db.find_upload_by_hash("testuser", "hash").await.unwrap(), None);
+        let _ = std::fs::remove_dir_all(&scratch);
+    }
+
+    #[tokio::test]
+    async fn test_connection_writes_move_tour_modified() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Loft", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let backdate = || async {
+            for table in ["tours", "assets"] {
+                sqlx::query(&format!("UPDATE {} SET modified_at = '2026-03-01 09:30:00'", table)).execute(&*db.pool).await.unwrap();
+            }
+        };
+        let modified = || async { db.tour_modified(tour_id).await.unwrap().unwrap().modified_at };
+
+        backdate().await;
+        let door = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        assert_ne!(modified().await, "2026-03-01 09:30:00");
+
+        backdate().await;
+        sqlx::query("UPDATE connections SET world_lon = 20 WHERE id = ?1").bind(door).execute(&*db.pool).await.unwrap();
+        assert_ne!(modified().await, "2026-03-01 09:30:00");
+
+        backdate().await;
+        sqlx::query("DELETE FROM connections WHERE id = ?1").bind(door).execute(&*db.pool).await.unwrap();
+        assert_ne!(modified().await, "2026-03-01 09:30:00");
+    }
+
+    #[tokio::test]
+    async fn test_expired_share_token_pruned() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Shared", "").await.unwrap();
+
+        let live = db.create_share_token(tour_id, 3600).await.unwrap();
+        sqlx::query("INSERT INTO share_tokens (token, tour_id, expires_at) VALUES ('stale', ?1, datetime('now', '-1 hour'))")
+            .bind(tour_id)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        // Expired tokens are rejected even before the prune job runs
+        assert!(db.get_tour_by_share_token("stale").await.unwrap().is_none());
+        assert!(db.get_tour_by_share_token(&live).await.unwrap().is_some());
+
+        assert_eq!(db.prune_expired_share_tokens().await.unwrap(), 1);
+        let active: bool = sqlx::query("SELECT is_active FROM share_tokens WHERE token = 'stale'")
+            .fetch_one(&*db.pool)
+            .await
+            .unwrap()
+            .get("is_active");
+        assert!(!active);
+        assert!(db.get_tour_by_share_token("stale").await.unwrap().is_none());
+        assert!(db.get_tour_by_share_token(&live).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_shared_tour_leaves_out_hidden_scenes() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Shared", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let attic = db.save_scene(tour_id, "Attic", "/assets/insta360/attic.jpg", None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(attic), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        assert!(db.set_scene_hidden(tour_id, attic, true).await.unwrap());
+        let token = db.create_share_token(tour_id, 0).await.unwrap();
+        let slug: String = sqlx::query_scalar("SELECT slug FROM tours WHERE id = ?1").bind(tour_id).fetch_one(&*db.pool).await.unwrap();
+
+        for tour in [db.get_tour_by_share_token(&token).await.unwrap().unwrap(), db.get_published_tour_by_slug(&slug).await.unwrap().unwrap()] {
+            let scenes = tour["scenes"].as_array().unwrap();
+            assert_eq!(scenes.len(), 1);
+            assert_eq!(scenes[0]["id"].as_i64(), Some(lobby));
+            assert!(scenes[0]["connections"].as_array().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_tours_ordering() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let beta = db.create_tour("testuser", "beta", "").await.unwrap();
+        let alpha = db.create_tour("testuser", "Alpha", "").await.unwrap();
+        let gamma = db.create_tour("testuser", "gamma", "").await.unwrap();
+        for (id, modified) in [(beta, "2024-01-03 00:00:00"), (alpha, "2024-01-01 00:00:00"), (gamma, "2024-01-02 00:00:00")] {
+            sqlx::query("UPDATE tours SET modified_at = ?1 WHERE id = ?2")
+                .bind(modified)
+                .bind(id)
+                .execute(&*db.pool)
+                .await
+                .unwrap();
+        }
+
+        let recent = db.get_tours("testuser", TourOrder::ModifiedAt, SortDirection::Desc).await.unwrap();
+        let ids: Vec<i64> = recent.iter().map(|t| t.get_id() as i64).collect();
+        assert_eq!(ids, vec![beta, gamma, alpha]);
+
+        let by_name = db.get_tours("testuser", TourOrder::Name, SortDirection::Asc).await.unwrap();
+        let names: Vec<&str> = by_name.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["Alpha", "beta", "gamma"]);
+    }
+
+    #[tokio::test]
+    async fn test_tour_completeness_flags_incomplete_tour() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Draft", "").await.unwrap();
+        assert_eq!(db.tour_completeness(tour_id).await.unwrap().issues, vec![CompletenessIssue::NoScenes]);
+
+        // Fully set up scene, and one with no view, no north and no connections
+        let done = db.save_scene(tour_id, "Done", "/assets/insta360/done.jpg", Some(30.0), Some(5.0), Some(90.0)).await.unwrap();
+        let rough = db.save_scene(tour_id, "Rough", "/assets/insta360/rough.jpg", None, None, None).await.unwrap();
+        db.save_connection(tour_id, done, Some(rough), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        sqlx::query("UPDATE tours SET initial_scene_id = NULL WHERE id = ?1")
+            .bind(tour_id)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        let report = db.tour_completeness(tour_id).await.unwrap();
+        assert_eq!(report.issues, vec![
+            CompletenessIssue::NoInitialScene,
+            CompletenessIssue::MissingInitialView { scene_id: rough },
+            CompletenessIssue::MissingNorthDirection { scene_id: rough },
+            CompletenessIssue::NoConnections { scene_id: rough },
+        ]);
+        // 3 of 7 checks pass
+        assert_eq!(report.score, 42);
+
+        db.set_initial_scene(tour_id, done).await.unwrap();
+        assert_eq!(db.tour_completeness(tour_id).await.unwrap().score, 57);
+    }
+
+    #[tokio::test]
+    async fn test_shortest_path_follows_transitions() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Office", "").await.unwrap();
+        let mut ids = Vec::new();
+        for name in ["entrance", "hall", "kitchen", "office", "attic"] {
+            ids.push(db.save_scene(tour_id, name, &format!("/assets/insta360/{}.jpg", name), None, None, None).await.unwrap());
+        }
+        let [entrance, hall, kitchen, office, attic] = ids[..] else { unreachable!() };
+
+        // entrance -> hall -> office, plus a longer detour through the kitchen
+        for (from, to) in [(entrance, kitchen), (entrance, hall), (kitchen, hall), (hall, office), (office, entrance)] {
+            db.save_connection(tour_id, from, Some(to), 0.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        }
+        // Non-transition hotspots don't count as navigation
+        db.save_connection(tour_id, office, Some(attic), 0.0, 0.0, ConnectionType::Closeup, None, None, None).await.unwrap();
+
+        assert_eq!(db.shortest_path(tour_id, entrance, office).await.unwrap(), Some(vec![entrance, hall, office]));
+        assert_eq!(db.shortest_path(tour_id, kitchen, entrance).await.unwrap(), Some(vec![kitchen, hall, office, entrance]));
+        assert_eq!(db.shortest_path(tour_id, hall, hall).await.unwrap(), Some(vec![hall]));
+        assert_eq!(db.shortest_path(tour_id, entrance, attic).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_connection_type_persists_and_migrates() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+
+        let transition = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let closeup = db.save_connection(tour_id, lobby, Some(plaque), 20.0, 0.0, ConnectionType::Closeup, None, None, None).await.unwrap();
+        let info = db.save_connection(tour_id, lobby, None, 30.0, 0.0, ConnectionType::Info, Some("Built 1920"), None, None).await.unwrap();
+
+        let loaded_types = |db: Database| async move {
+            let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+            let scene = tour["scenes"].as_array().unwrap().iter().find(|s| s["id"].as_i64() == Some(lobby)).unwrap().clone();
+            scene["connections"].as_array().unwrap().iter()
+                .map(|c| (c["id"].as_i64().unwrap(), c["connection_type"].as_str().unwrap().to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(loaded_types(db.clone()).await, vec![
+            (transition, "Transition".to_string()),
+            (closeup, "Closeup".to_string()),
+            (info, "Info".to_string()),
+        ]);
+
+        // Closeups can be switched to info hotspots
+        db.update_connection(&ConnectionUpdate { id: closeup, connection_type: Some(ConnectionType::Info), ..Default::default() }).await.unwrap();
+        assert_eq!(loaded_types(db.clone()).await[1].1, "Info");
+
+        // Rows from before the column existed are converted from is_transition
+        sqlx::query("UPDATE connections SET connection_type = NULL, is_transition = (id = ?1)")
+            .bind(transition)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+        migrate(&db.pool).await.unwrap();
+        let types: Vec<String> = loaded_types(db.clone()).await.into_iter().map(|(_, t)| t).collect();
+        assert_eq!(types, vec!["Transition", "Closeup", "Closeup"]);
+        assert_eq!(db.get_incoming_connections(hall).await.unwrap()[0].connection_type, ConnectionType::Transition);
+    }
+
+    #[tokio::test]
+    async fn test_update_scene_keeps_sets_and_clears_fields() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", Some(12.0), Some(3.0), None).await.unwrap();
+        let stored = |tour: serde_json::Value| tour["scenes"][0].clone();
+
+        db.update_scene(&SceneUpdate { id: lobby, north_direction: FieldUpdate::Set(45.0), pov: FieldUpdate::Set(80.0), ..Default::default() }).await.unwrap();
+        let scene = stored(db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap());
+        assert_eq!(scene["north_dir"].as_f64(), Some(45.0));
+        assert_eq!(scene["initial_fov"].as_f64(), Some(80.0));
+        assert_eq!(scene["initial_view_x"].as_f64(), Some(12.0));
+
+        // Clearing north_dir leaves the other fields alone
+        db.update_scene(&SceneUpdate { id: lobby, north_direction: FieldUpdate::Clear, ..Default::default() }).await.unwrap();
+        let scene = stored(db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap());
+        assert!(scene["north_dir"].is_null());
+        assert_eq!(scene["initial_fov"].as_f64(), Some(80.0));
+
+        // The NOT NULL view columns fall back to 0
+        db.update_scene(&SceneUpdate { id: lobby, initial_view_x: FieldUpdate::Clear, initial_view_y: FieldUpdate::Clear, pov: FieldUpdate::Clear, ..Default::default() }).await.unwrap();
+        let scene = stored(db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap());
+        assert_eq!(scene["initial_view_x"].as_f64(), Some(0.0));
+        assert_eq!(scene["initial_view_y"].as_f64(), Some(0.0));
+        assert!(scene["initial_fov"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_merge_scenes_combines_hotspots() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let hall_again = db.save_scene(tour_id, "Hall (2)", "/assets/insta360/hall2.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(hall_again), 12.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall, Some(lobby), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall, Some(hall_again), 90.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall_again, Some(lobby), 185.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall_again, Some(plaque), 40.0, 5.0, ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), None).await.unwrap();
+        db.set_initial_scene(tour_id, hall_again).await.unwrap();
+        db.set_tour_path(tour_id, &[lobby, hall_again]).await.unwrap();
+
+        db.register_user("other", "password").await.unwrap();
+        assert!(db.merge_scenes(hall, hall_again, "other").await.unwrap().is_none());
+
+        let report = db.merge_scenes(hall, hall_again, "testuser").await.unwrap().expect("merged");
+        assert_eq!(report, MergeScenesReport { tour_id, moved: 2, repointed: 2, duplicates_removed: 3 });
+
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        let scenes = tour["scenes"].as_array().unwrap();
+        assert!(scenes.iter().all(|s| s["id"].as_i64() != Some(hall_again)), "removed scene still present");
+        let targets = |scene_id: i64| -> Vec<(String, i64)> {
+            let scene = scenes.iter().find(|s| s["id"].as_i64() == Some(scene_id)).unwrap();
+            scene["connections"].as_array().unwrap().iter()
+                .map(|c| (c["connection_type"].as_str().unwrap_or("").to_string(), c["target_scene_id"].as_i64().unwrap()))
+                .collect()
+        };
+        assert_eq!(targets(lobby), vec![("Transition".to_string(), hall)]);
+        let mut hall_targets = targets(hall);
+        hall_targets.sort();
+        assert_eq!(hall_targets, vec![("Closeup".to_string(), plaque), ("Transition".to_string(), lobby)]);
+        assert_eq!(tour["initial_scene_id"].as_i64(), Some(hall));
+        assert_eq!(tour["tour_path"], serde_json::json!([lobby, hall]));
+    }
+
+    #[tokio::test]
+    async fn test_tour_from_template_copies_structure() {
+        let db = setup_test_db().await;
+        db.register_user("agency", "password").await.unwrap();
+        db.register_user("agent", "password").await.unwrap();
+        let template = db.create_tour("agency", "Two-bed flat", "").await.unwrap();
+        let lobby = db.save_scene(template, "Lobby", "/assets/insta360/lobby.jpg", Some(30.0), None, Some(90.0)).await.unwrap();
+        let hall = db.save_scene(template, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        db.save_connection(template, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(template, hall, Some(lobby), 190.0, 0.0, ConnectionType::Transition, Some("Back"), None, None).await.unwrap();
+        db.set_initial_scene(template, hall).await.unwrap();
+        db.set_tour_path(template, &[hall, lobby]).await.unwrap();
+
+        // Only tours marked as templates can be copied
+        assert!(db.create_tour_from_template("agent", template, "Copy").await.unwrap().is_none());
+        assert!(!db.set_tour_template(template, "agent", true).await.unwrap());
+        assert!(db.set_tour_template(template, "agency", true).await.unwrap());
+
+        let tour_id = db.create_tour_from_template("agent", template, "12 Oak Street").await.unwrap().expect("copied");
+        assert!(db.is_tour_owner(tour_id, "agent").await.unwrap());
+        let copy = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        assert_eq!(copy["name"], "12 Oak Street");
+        let scenes = copy["scenes"].as_array().unwrap();
+        let id_of = |name: &str| scenes.iter().find(|s| s["name"] == name).unwrap()["id"].as_i64().unwrap();
+        let (new_lobby, new_hall) = (id_of("Lobby"), id_of("Hall"));
+        assert_eq!(scenes.len(), 2);
+        assert!(![lobby, hall].contains(&new_lobby) && ![lobby, hall].contains(&new_hall));
+        let copied_lobby = scenes.iter().find(|s| s["id"] == new_lobby).unwrap();
+        assert_eq!(copied_lobby["file_path"], "/assets/insta360/lobby.jpg");
+        assert_eq!(copied_lobby["north_dir"].as_f64(), Some(90.0));
+        assert_eq!(copied_lobby["connections"][0]["target_scene_id"].as_i64(), Some(new_hall));
+        let copied_hall = scenes.iter().find(|s| s["id"] == new_hall).unwrap();
+        assert_eq!(copied_hall["connections"][0]["target_scene_id"].as_i64(), Some(new_lobby));
+        assert_eq!(copied_hall["connections"][0]["name"], "Back");
+        assert_eq!(copy["initial_scene_id"].as_i64(), Some(new_hall));
+        assert_eq!(copy["tour_path"], serde_json::json!([new_hall, new_lobby]));
+
+        // The template itself is untouched
+        let original = db.get_tour_with_scenes_by_id(template).await.unwrap().unwrap();
+        assert_eq!(original["scenes"].as_array().unwrap().len(), 2);
+        assert_eq!(original["initial_scene_id"].as_i64(), Some(hall));
+    }
+
+    #[tokio::test]
+    async fn test_thumbnails_for_tours_in_one_lookup() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let with_scene = db.create_tour("testuser", "Lobby tour", "").await.unwrap();
+        let lobby = db.save_scene(with_scene, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(with_scene, lobby).await.unwrap();
+        let without_initial = db.create_tour("testuser", "Unset", "").await.unwrap();
+        db.save_scene(without_initial, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        db.clear_initial_scene(without_initial).await.unwrap();
+        // New tours start with a placeholder initial_scene_id that may name another tour's scene
+        let empty = db.create_tour("testuser", "Empty", "").await.unwrap();
+        let video = db.create_tour("testuser", "Video", "").await.unwrap();
+        let clip = db.save_scene(video, "Clip", "/assets/video/clip.mp4", None, None, None).await.unwrap();
+        sqlx::query("UPDATE assets SET media_type = 'video' WHERE id = ?1").bind(clip).execute(&*db.pool).await.unwrap();
+        db.set_initial_scene(video, clip).await.unwrap();
+
+        let thumbnails = db.get_thumbnails_for_tours(&[with_scene, without_initial, empty, video, 9999]).await.unwrap();
+        assert_eq!(thumbnails, HashMap::from([
+            (with_scene, Some("/assets/insta360/lobby.jpg".to_string())),
+            (without_initial, None),
+            (empty, None),
+            (video, None),
+        ]));
+        assert!(db.get_thumbnails_for_tours(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_initial_scene_must_belong_to_the_tour() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Mine", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let other_tour = db.create_tour("testuser", "Other", "").await.unwrap();
+        let foreign = db.save_scene(other_tour, "Foyer", "/assets/insta360/foyer.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, lobby).await.unwrap();
+
+        assert!(matches!(db.set_initial_scene(tour_id, foreign).await, Err(sqlx::Error::RowNotFound)));
+        assert!(matches!(db.set_initial_scene(tour_id, plaque).await, Err(sqlx::Error::RowNotFound)));
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        assert_eq!(tour["initial_scene_id"].as_i64(), Some(lobby));
+    }
+
+    /// Collects the `query` field of every slow-query event
+    struct SlowQueryRecorder(Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl tracing::Subscriber for SlowQueryRecorder {
+        fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+            metadata.target() == slow_query::SLOW_QUERY_TARGET
+        }
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id { tracing::span::Id::from_u64(1) }
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct QueryName(Option<String>);
+            impl tracing::field::Visit for QueryName {
+                fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+                    if field.name() == "query" { self.0 = Some(value.to_string()); }
+                }
+                fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+            }
+            let mut name = QueryName(None);
+            event.record(&mut name);
+            self.0.lock().unwrap().extend(name.0);
+        }
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn test_slow_queries_are_logged() {
+        use tracing::instrument::WithSubscriber;
+
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Campus", "").await.unwrap();
+        // A large tour: loading it runs one connection query per scene
+        sqlx::query("WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000)
+                     INSERT INTO assets (tour_id, name, file_path, is_scene) SELECT ?1, 'Room ' || i, '/assets/insta360/room' || i || '.jpg', 1 FROM n")
+            .bind(tour_id)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let tolerant = db.clone().with_slow_query_threshold(std::time::Duration::from_secs(3600));
+        async { tolerant.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap() }
+            .with_subscriber(SlowQueryRecorder(events.clone()))
+            .await;
+        assert!(events.lock().unwrap().is_empty(), "nothing is slow against an hour-long threshold");
+
+        let strict = db.clone().with_slow_query_threshold(std::time::Duration::from_millis(1));
+        let tour = async { strict.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap() }
+            .with_subscriber(SlowQueryRecorder(events.clone()))
+            .await;
+        assert_eq!(tour["scenes"].as_array().unwrap().len(), 2000);
+        assert!(events.lock().unwrap().iter().any(|name| name == "get_tour_with_scenes"), "{:?}", events.lock().unwrap());
+
+        // A zero threshold turns logging off
+        events.lock().unwrap().clear();
+        let off = db.clone().with_slow_query_threshold(std::time::Duration::ZERO);
+        async { off.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap() }
+            .with_subscriber(SlowQueryRecorder(events.clone()))
+            .await;
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connection_type_counts() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let expected = |transition: i64, closeup: i64, info: i64| HashMap::from([
+            ("transition".to_string(), transition),
+            ("closeup".to_string(), closeup),
+            ("info".to_string(), info),
+        ]);
+        assert_eq!(db.connection_type_counts(tour_id).await.unwrap(), expected(0, 0, 0));
+
+        db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall, Some(lobby), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(plaque), 20.0, 0.0, ConnectionType::Closeup, None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, None, 30.0, 0.0, ConnectionType::Info, Some("Built 1920"), None, None).await.unwrap();
+        db.save_connection(tour_id, hall, None, 40.0, 0.0, ConnectionType::Info, Some("Fireplace"), None, None).await.unwrap();
+        db.save_connection(tour_id, hall, None, 50.0, 0.0, ConnectionType::Info, Some("Window"), None, None).await.unwrap();
+        // Floorplan markers live in the same table but aren't hotspots
+        let floorplan = db.save_floorplan(tour_id, "Plan", "/assets/floorplans/plan.png").await.unwrap();
+        db.save_floorplan_marker(tour_id, floorplan, lobby, 0.5, 0.5).await.unwrap();
+        // Nor do other tours' hotspots count
+        let other = db.create_tour("testuser", "Other", "").await.unwrap();
+        let attic = db.save_scene(other, "Attic", "/assets/insta360/attic.jpg", None, None, None).await.unwrap();
+        db.save_connection(other, attic, None, 0.0, 0.0, ConnectionType::Info, None, None, None).await.unwrap();
+
+        assert_eq!(db.connection_type_counts(tour_id).await.unwrap(), expected(2, 1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_tours_need_an_existing_owner() {
+        let db = setup_test_db().await;
+        let error = db.create_tour("ghost", "Haunted", "").await.unwrap_err();
+        assert!(is_missing_owner(&error), "unexpected error {}", error);
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tours").fetch_one(&*db.pool).await.unwrap();
+        assert_eq!(count, 0);
+
+        db.register_user("ghost", "password").await.unwrap();
+        let tour_id = db.create_tour("ghost", "Haunted", "").await.unwrap();
+        assert!(sqlx::query("UPDATE tours SET owner = 'nobody' WHERE id = ?1").bind(tour_id).execute(&*db.pool).await.is_err());
+
+        // Older database files (no triggers, tours table without the foreign key) gain the check
+        // on migrate; tours orphaned before then are kept
+        sqlx::raw_sql("DROP TRIGGER tours_owner_exists_insert; DROP TRIGGER tours_owner_exists_update;
+                       PRAGMA foreign_keys = OFF;
+                       INSERT INTO tours (owner, tour_name) VALUES ('nobody', 'Orphan');
+                       PRAGMA foreign_keys = ON;")
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+        migrate(&db.pool).await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&*db.pool).await.unwrap();
+        let error = db.create_tour("nobody", "Another", "").await.unwrap_err();
+        assert!(error.to_string().contains("tour owner does not exist"), "unexpected error {}", error);
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tours").fetch_one(&*db.pool).await.unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_connections_enumerated() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let kitchen = db.save_scene(tour_id, "Kitchen", "/assets/insta360/kitchen.jpg", None, None, None).await.unwrap();
+        let from_hall = db.save_connection(tour_id, hall, Some(lobby), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let from_kitchen = db.save_connection(tour_id, kitchen, Some(lobby), 20.0, 0.0, ConnectionType::Transition, Some("Back"), None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(hall), 30.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let floorplan = db.save_floorplan(tour_id, "Plan", "/assets/floorplans/plan.png").await.unwrap();
+        db.save_floorplan_marker(tour_id, floorplan, lobby, 0.5, 0.5).await.unwrap();
+
+        let incoming = db.get_incoming_connections(lobby).await.unwrap();
+        let ids: Vec<i64> = incoming.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![from_hall, from_kitchen]);
+        assert_eq!(incoming[1].start_id, kitchen);
+        assert_eq!(incoming[1].start_scene_name.as_deref(), Some("Kitchen"));
+        assert!(db.get_incoming_connections(kitchen).await.unwrap().is_empty());
+
+        db.delete_scene(lobby).await.unwrap();
+        assert!(db.get_incoming_connections(lobby).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_copy_connections_across_tours() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let source_tour = db.create_tour("testuser", "Source", "").await.unwrap();
+        let lobby = db.save_scene(source_tour, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(source_tour, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let attic = db.save_scene(source_tour, "Attic", "/assets/insta360/attic.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(source_tour, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        db.save_connection(source_tour, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, Some("To hall"), None, None).await.unwrap();
+        db.save_connection(source_tour, lobby, Some(attic), 20.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(source_tour, lobby, Some(plaque), 30.0, 5.0, ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), Some(2)).await.unwrap();
+
+        // Destination tour has a "Hall" but no "Attic"
+        let dest_tour = db.create_tour("testuser", "Copy", "").await.unwrap();
+        let dest_lobby = db.save_scene(dest_tour, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let dest_hall = db.save_scene(dest_tour, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+
+        let report = db.copy_connections(lobby, dest_lobby, "testuser").await.unwrap().expect("both scenes owned");
+        assert_eq!(report, CopyConnectionsReport { tour_id: dest_tour, copied: 2, dropped: 1 });
+
+        let copied = db.get_scene_connections(dest_tour, dest_lobby).await.unwrap();
+        assert_eq!(copied.len(), 2);
+        let transition = copied.iter().find(|c| c["connection_type"] == "Transition").unwrap();
+        assert_eq!(transition["target_scene_id"].as_i64(), Some(dest_hall));
+        assert_eq!(transition["name"], "To hall");
+        let closeup = copied.iter().find(|c| c["connection_type"] == "Closeup").unwrap();
+        assert_ne!(closeup["target_scene_id"].as_i64(), Some(plaque), "closeup asset duplicated into the destination tour");
+        assert_eq!(closeup["icon_index"].as_i64(), Some(2));
+        assert!(copied.iter().all(|c| c["created_by"] == "testuser"));
+
+        // Another user's scene can't be used
+        db.register_user("other", "password").await.unwrap();
+        assert!(db.copy_connections(lobby, dest_lobby, "other").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restore_snapshot_reverts_tour() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", Some(12.0), Some(3.0), Some(90.0)).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let to_hall = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, Some("Hall"), None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(plaque), 30.0, 5.0, ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), Some(2)).await.unwrap();
+        db.set_initial_scene(tour_id, lobby).await.unwrap();
+        db.set_tour_path(tour_id, &[lobby, hall]).await.unwrap();
+
+        let before = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        let snapshot = db.create_snapshot(tour_id, "Before edits", 5).await.unwrap();
+
+        // Mutate: rename, move a hotspot, add and delete scenes
+        db.update_scene(&SceneUpdate { id: lobby, name: Some("Entrance".to_string()), ..Default::default() }).await.unwrap();
+        db.update_connection(&ConnectionUpdate { id: to_hall, world_lon: Some(200.0), ..Default::default() }).await.unwrap();
+        db.save_scene(tour_id, "Attic", "/assets/insta360/attic.jpg", None, None, None).await.unwrap();
+        db.delete_scene(hall).await.unwrap();
+        assert_ne!(db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap()["scenes"], before["scenes"]);
+
+        assert!(db.restore_snapshot(tour_id, snapshot).await.unwrap());
+        let after = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        for key in ["scenes", "initial_scene_id", "tour_path", "scene_groups"] {
+            assert_eq!(after[key], before[key], "{} differs after restore", key);
+        }
+
+        // Unknown snapshot, and the per-tour cap
+        assert!(!db.restore_snapshot(tour_id, snapshot + 100).await.unwrap());
+        for i in 0..6 {
+            db.create_snapshot(tour_id, &format!("Snapshot {}", i), 5).await.unwrap();
+        }
+        let snapshots = db.list_snapshots(tour_id).await.unwrap();
+        assert_eq!(snapshots.len(), 5);
+        assert_eq!(snapshots[0].label, "Snapshot 5");
+    }
 }
@@ -0,0 +1,43 @@
+//! Slow query logging
+//!
+//! `Database` methods that do the heavy lifting (tour loads, bulk copies, flushes of
+//! deferred writes) hold a `QueryTimer` for their duration. When one takes at least
+//! the configured threshold it is reported as a `slow_query` tracing event carrying
+//! the method name and `elapsed_ms`, or on stderr when no tracing subscriber is
+//! installed, like the rest of the server's logging.
+
+use std::time::{Duration, Instant};
+
+/// Target of the events emitted for slow queries
+pub const SLOW_QUERY_TARGET: &str = "slow_query";
+
+/// Measures one database call and reports it on drop if it was slow
+pub(crate) struct QueryTimer {
+    name: &'static str,
+    threshold: Option<Duration>,
+    started: Instant,
+}
+
+impl QueryTimer {
+    /// `None` disables reporting
+    pub(crate) fn start(name: &'static str, threshold: Option<Duration>) -> Self {
+        Self { name, threshold, started: Instant::now() }
+    }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        let Some(threshold) = self.threshold else { return };
+        let elapsed = self.started.elapsed();
+        if elapsed < threshold {
+            return;
+        }
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let has_subscriber = tracing::dispatcher::get_default(|dispatch| !dispatch.is::<tracing::subscriber::NoSubscriber>());
+        if has_subscriber {
+            tracing::warn!(target: SLOW_QUERY_TARGET, query = self.name, elapsed_ms, "slow database query");
+        } else {
+            eprintln!("Slow database query: {} took {} ms", self.name, elapsed_ms);
+        }
+    }
+}
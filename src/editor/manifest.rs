@@ -0,0 +1,297 @@
+//! Machine-readable list of the editor actions the server accepts
+//!
+//! Served at `GET /api/editor/actions` so client UIs can build forms without reading
+//! `EditorAction`. The table is maintained by hand next to the enum; the test below
+//! fails when a variant is added without an entry, or when an entry's fields no
+//! longer deserialize into the variant.
+//!
+//! Field types are JSON-level: `integer`, `number`, `string`, `boolean`, `position`
+//! (a `[lon, lat]` array of numbers), `integer[]` and `connection_type` (one of
+//! `Transition`, `Closeup`, `Info`). Optional fields may be omitted or `null`;
+//! actions without fields are sent without `data`.
+
+use serde::Serialize;
+
+/// One field of an action's `data` object
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    pub required: bool,
+}
+
+/// One `EditorAction` variant, as sent in `{"action": name, "data": {...}}`
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ActionSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: &'static [FieldSpec],
+}
+
+const fn required(name: &'static str, ty: &'static str) -> FieldSpec {
+    FieldSpec { name, ty, required: true }
+}
+
+const fn optional(name: &'static str, ty: &'static str) -> FieldSpec {
+    FieldSpec { name, ty, required: false }
+}
+
+/// Every editor action, in `EditorAction` order
+pub const ACTIONS: &[ActionSpec] = &[
+    ActionSpec {
+        name: "AddScene",
+        description: "Adds a scene from an uploaded panorama",
+        fields: &[required("name", "string"), required("file_path", "string"), optional("north_direction", "number"), optional("captured_at", "string")],
+    },
+    ActionSpec {
+        name: "SwapScene",
+        description: "Swaps the panorama file of an existing scene",
+        fields: &[required("scene_id", "integer"), required("new_file_path", "string")],
+    },
+    ActionSpec {
+        name: "DeleteScene",
+        description: "Deletes a scene from the tour",
+        fields: &[required("scene_id", "integer")],
+    },
+    ActionSpec {
+        name: "SetInitialScene",
+        description: "Makes a scene the one the tour opens on",
+        fields: &[required("scene_id", "integer")],
+    },
+    ActionSpec {
+        name: "UpdateSceneName",
+        description: "Renames a scene",
+        fields: &[required("scene_id", "integer"), required("name", "string")],
+    },
+    ActionSpec {
+        name: "AddCloseup",
+        description: "Adds a closeup image linked from a hotspot in a scene",
+        fields: &[
+            required("name", "string"), required("file_path", "string"), required("parent_scene_id", "integer"),
+            required("position", "position"), optional("icon_type", "integer"),
+        ],
+    },
+    ActionSpec {
+        name: "AddConnection",
+        description: "Adds a hotspot from a scene to another scene or closeup",
+        fields: &[required("start_scene_id", "integer"), required("asset_id", "integer"), required("position", "position"), optional("name", "string")],
+    },
+    ActionSpec {
+        name: "AddConnectionToAllScenes",
+        description: "Adds the same hotspot to every scene except the target",
+        fields: &[
+            required("target_scene_id", "integer"), required("position", "position"), optional("name", "string"),
+            required("kind", "connection_type"),
+        ],
+    },
+    ActionSpec {
+        name: "PropagateConnection",
+        description: "Copies a connection to every scene whose name matches a substring or glob",
+        fields: &[required("connection_id", "integer"), required("name_pattern", "string")],
+    },
+    ActionSpec {
+        name: "EditConnection",
+        description: "Moves or retargets a connection and updates its optional styling",
+        fields: &[
+            required("connection_id", "integer"), required("new_asset_id", "integer"), required("new_position", "position"),
+            optional("new_name", "string"), optional("new_icon_type", "integer"), optional("new_file_path", "string"),
+            optional("new_transition_style", "string"), optional("new_icon_color", "string"), optional("new_icon_scale", "number"),
+            optional("new_url_target", "string"), optional("new_connection_type", "connection_type"), optional("new_z_index", "integer"),
+            optional("new_audio_path", "string"),
+        ],
+    },
+    ActionSpec {
+        name: "DeleteConnection",
+        description: "Deletes a connection",
+        fields: &[required("connection_id", "integer")],
+    },
+    ActionSpec {
+        name: "DeleteConnections",
+        description: "Deletes several connections at once",
+        fields: &[required("connection_ids", "integer[]")],
+    },
+    ActionSpec {
+        name: "RenameConnection",
+        description: "Renames a connection",
+        fields: &[required("connection_id", "integer"), required("name", "string")],
+    },
+    ActionSpec {
+        name: "NudgeConnection",
+        description: "Moves a connection by d_yaw/d_pitch degrees from where it is",
+        fields: &[required("connection_id", "integer"), required("d_yaw", "number"), required("d_pitch", "number")],
+    },
+    ActionSpec {
+        name: "SetInitialView",
+        description: "Sets where the camera looks when a scene opens, and optionally its field of view",
+        fields: &[required("scene_id", "integer"), required("position", "position"), optional("fov", "number")],
+    },
+    ActionSpec {
+        name: "SetNorthDirection",
+        description: "Sets a scene's north direction in degrees",
+        fields: &[required("scene_id", "integer"), required("direction", "number")],
+    },
+    ActionSpec {
+        name: "SetNorthDirectionAll",
+        description: "Sets the same north direction (0 to <360) on every scene",
+        fields: &[required("direction", "number")],
+    },
+    ActionSpec {
+        name: "ResetSceneCalibration",
+        description: "Clears a scene's initial view, north direction and field of view",
+        fields: &[required("scene_id", "integer")],
+    },
+    ActionSpec {
+        name: "ChangeAddress",
+        description: "Changes the tour address/location",
+        fields: &[required("address", "string")],
+    },
+    ActionSpec {
+        name: "AddFloorplan",
+        description: "Sets the tour's floorplan image",
+        fields: &[required("file_path", "string")],
+    },
+    ActionSpec {
+        name: "DeleteFloorplan",
+        description: "Removes the tour's floorplan",
+        fields: &[required("floorplan_id", "integer")],
+    },
+    ActionSpec {
+        name: "AddFloorplanConnection",
+        description: "Links a scene from the floorplan",
+        fields: &[required("scene_id", "integer")],
+    },
+    ActionSpec {
+        name: "DeleteFloorplanConnection",
+        description: "Unlinks a scene from the floorplan",
+        fields: &[required("scene_id", "integer")],
+    },
+    ActionSpec {
+        name: "AddFloorplanMarker",
+        description: "Places a scene marker on the floorplan",
+        fields: &[required("scene_id", "integer"), required("x", "number"), required("y", "number")],
+    },
+    ActionSpec {
+        name: "UpdateFloorplanMarker",
+        description: "Moves a floorplan marker",
+        fields: &[required("marker_id", "integer"), required("x", "number"), required("y", "number")],
+    },
+    ActionSpec {
+        name: "DeleteFloorplanMarker",
+        description: "Removes a floorplan marker",
+        fields: &[required("marker_id", "integer")],
+    },
+    ActionSpec {
+        name: "SetSceneSort",
+        description: "Sets how the scene list is ordered",
+        fields: &[required("mode", "string"), required("direction", "string")],
+    },
+    ActionSpec {
+        name: "CreateSceneGroup",
+        description: "Creates a scene group",
+        fields: &[required("name", "string")],
+    },
+    ActionSpec {
+        name: "AssignSceneToGroup",
+        description: "Moves a scene into a group, or out of any with null",
+        fields: &[required("scene_id", "integer"), optional("group_id", "integer")],
+    },
+    ActionSpec {
+        name: "SetSceneCapturedAt",
+        description: "Sets (or clears) when a scene was shot: YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+        fields: &[required("scene_id", "integer"), optional("captured_at", "string")],
+    },
+    ActionSpec {
+        name: "SetSceneNotes",
+        description: "Sets the author's private notes on a scene; null or blank clears them",
+        fields: &[required("scene_id", "integer"), optional("notes", "string")],
+    },
+    ActionSpec {
+        name: "SetFovLimits",
+        description: "Limits how far viewers can zoom a scene (field of view in degrees, 30-120)",
+        fields: &[required("scene_id", "integer"), optional("min_fov", "number"), optional("max_fov", "number")],
+    },
+    ActionSpec {
+        name: "SetSceneDefaultIcon",
+        description: "Sets the icon (1-3) exported for a scene's hotspots that don't pick their own; null uses the configured default",
+        fields: &[required("scene_id", "integer"), optional("icon_index", "integer")],
+    },
+    ActionSpec {
+        name: "SetSceneHidden",
+        description: "Hides a draft scene from exports, or shows it again",
+        fields: &[required("scene_id", "integer"), required("hidden", "boolean")],
+    },
+    ActionSpec {
+        name: "RotateScene",
+        description: "Rewrites a scene's panorama shifted right by yaw_offset_deg and/or flipped upside down",
+        fields: &[required("scene_id", "integer"), required("yaw_offset_deg", "number"), required("flip_vertical", "boolean")],
+    },
+    ActionSpec {
+        name: "SetTourPath",
+        description: "Sets the recommended visiting order of scenes (the viewer's next/previous)",
+        fields: &[required("scene_ids", "integer[]")],
+    },
+    ActionSpec {
+        name: "SetDeferredMode",
+        description: "Queues row updates in memory until SaveTour or autosave; turning it off flushes them",
+        fields: &[required("enabled", "boolean")],
+    },
+    ActionSpec {
+        name: "SaveTour",
+        description: "Writes queued edits to the database",
+        fields: &[],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::EditorAction;
+    use std::collections::HashSet;
+
+    fn sample(ty: &str) -> serde_json::Value {
+        match ty {
+            "integer" => serde_json::json!(1),
+            "number" => serde_json::json!(1.0),
+            "string" => serde_json::json!("x"),
+            "boolean" => serde_json::json!(true),
+            "position" => serde_json::json!([0.0, 0.0]),
+            "integer[]" => serde_json::json!([1]),
+            "connection_type" => serde_json::json!("Transition"),
+            other => panic!("manifest uses unknown type {}", other),
+        }
+    }
+
+    #[test]
+    fn test_manifest_lists_every_action() {
+        // Variant names straight from the enum declaration
+        let source = include_str!("mod.rs");
+        let body = source.split("pub enum EditorAction {").nth(1).unwrap().split("\n}").next().unwrap();
+        let variants: HashSet<&str> = body.lines()
+            .filter(|line| line.starts_with("    ") && !line.starts_with("     "))
+            .map(str::trim)
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_uppercase()))
+            .map(|line| line.split(|c: char| !c.is_ascii_alphanumeric()).next().unwrap())
+            .collect();
+        let listed: HashSet<&str> = ACTIONS.iter().map(|a| a.name).collect();
+        assert_eq!(listed.len(), ACTIONS.len(), "duplicate manifest entries");
+        assert_eq!(listed, variants);
+
+        // Each entry's fields are what the variant expects: required ones alone deserialize, all together too
+        for action in ACTIONS {
+            for with_optional in [false, true] {
+                let data: serde_json::Map<String, serde_json::Value> = action.fields.iter()
+                    .filter(|f| f.required || with_optional)
+                    .map(|f| (f.name.to_string(), sample(f.ty)))
+                    .collect();
+                let payload = match action.fields.is_empty() {
+                    true => serde_json::json!({ "action": action.name }),
+                    false => serde_json::json!({ "action": action.name, "data": data }),
+                };
+                let parsed: EditorAction = serde_json::from_value(payload.clone())
+                    .unwrap_or_else(|e| panic!("{} doesn't match its manifest entry: {}", payload, e));
+                assert_eq!(serde_json::to_value(&parsed).unwrap()["action"], action.name);
+            }
+        }
+    }
+}
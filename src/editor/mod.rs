@@ -14,16 +14,23 @@
 
 use serde::{Deserialize, Serialize};
 use axum::extract::ws::Message;
-use axum::extract::Multipart;
+use axum::extract::{multipart::Field, Multipart, State};
 use axum::response::IntoResponse;
 use axum::Json;
-use axum::http::StatusCode;
-use tokio::sync::mpsc;
+use axum::http::{HeaderMap, StatusCode};
+use crate::outbound::OutboundSender;
+use crate::database::{ConnectionUpdate, FieldUpdate, NewConnection, NewScene, PendingWrite, SceneUpdate};
+use crate::config::SceneNameCollision;
 use tokio::fs;
 use std::i32;
 use std::path::Path as StdPath;
 use std::collections::HashMap;
 use sqlx::Row; // for row.get()
+use sha2::{Digest, Sha256};
+
+pub mod manifest;
+mod validation;
+pub use validation::{parse_action, FieldError};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Coordinates {
@@ -39,15 +46,119 @@ pub struct Scene {
     pub connections: Vec<Connection>,
     pub initial_view: Option<Coordinates>,
     pub north_direction: Option<f32>,
+    pub group_id: Option<i64>,
+    pub media_type: MediaType,
+    /// Draft scene: editable, but left out of exports
+    #[serde(default)]
+    pub hidden: bool,
+    /// When the panorama was shot (`YYYY-MM-DD HH:MM:SS`), for progress tours sorted by date
+    #[serde(default)]
+    pub captured_at: Option<String>,
+    /// The author's private notes ("reshoot, tripod visible"); never exported
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Narrowest field of view viewers may zoom in to, in degrees (`None`: viewer default)
+    #[serde(default)]
+    pub min_fov: Option<f32>,
+    /// Widest field of view viewers may zoom out to, in degrees (`None`: viewer default)
+    #[serde(default)]
+    pub max_fov: Option<f32>,
+    /// User who added the scene; `None` for scenes added before this was recorded. Never exported
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// Icon for this scene's hotspots that don't pick their own (`None`: the configured default)
+    #[serde(default)]
+    pub default_icon_index: Option<i32>,
 }
  
-// Connection types: transition between scenes or closeup link
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Connection types: transition between scenes, closeup link or info hotspot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConnectionType {
     Transition,
     Closeup,
+    Info,
+}
+
+impl ConnectionType {
+    /// Parse the value stored in `connections.connection_type` (case-insensitive, so the
+    /// `"Transition"`-style names used in tour JSON parse too)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "transition" => Some(ConnectionType::Transition),
+            "closeup" => Some(ConnectionType::Closeup),
+            "info" => Some(ConnectionType::Info),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionType::Transition => "transition",
+            ConnectionType::Closeup => "closeup",
+            ConnectionType::Info => "info",
+        }
+    }
+}
+
+// Viewer transition effects selectable per connection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionStyle {
+    Fade,
+    Slide,
+    None,
+}
+
+impl TransitionStyle {
+    /// Parse the lowercase name stored in the DB / sent by the client
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fade" => Some(TransitionStyle::Fade),
+            "slide" => Some(TransitionStyle::Slide),
+            "none" => Some(TransitionStyle::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransitionStyle::Fade => "fade",
+            TransitionStyle::Slide => "slide",
+            TransitionStyle::None => "none",
+        }
+    }
+}
+
+/// What a scene's file holds; videos play as 360° panoramas in the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    #[default]
+    Image,
+    Video,
+}
+
+impl MediaType {
+    /// Video for `.mp4`/`.webm` files, image otherwise
+    pub fn from_path(path: &str) -> Self {
+        let ext = StdPath::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if VIDEO_EXTENSIONS.contains(&ext.as_str()) { MediaType::Video } else { MediaType::Image }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Image => "image",
+            MediaType::Video => "video",
+        }
+    }
 }
 
+/// File extensions accepted for video scenes
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm"];
+
+/// File extensions accepted for hotspot sounds
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "ogg", "wav", "m4a"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connection {
     pub id: i32,
@@ -56,23 +167,79 @@ pub struct Connection {
     pub position: Coordinates,
     pub name: Option<String>,
     pub icon_index: Option<i32>,
+    pub transition_style: Option<TransitionStyle>,
+    /// `#RRGGBB`; `None` leaves the hotspot in the viewer's theme colour
+    pub icon_color: Option<String>,
+    /// Size multiplier (0.5-3.0); `None` uses the viewer's default size
+    pub icon_scale: Option<f32>,
+    /// Where a URL hotspot opens its link (`_self` or `_blank`); `None` means `_blank`
+    pub url_target: Option<String>,
+    /// Stacking order among the scene's hotspots; higher is drawn on top
+    #[serde(default)]
+    pub z_index: i32,
+    /// `/assets/audio/...` sound the viewer plays when the hotspot is hovered or clicked
+    #[serde(default)]
+    pub audio_path: Option<String>,
+    /// User who added the hotspot; `None` for hotspots added before this was recorded. Never exported
+    #[serde(default)]
+    pub created_by: Option<String>,
 }
 
-// Actions received from the client/editor UI
+/// Payload of `EditorAction::EditConnection`; every `new_*` field left `None` keeps its value
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectionEdit {
+    pub connection_id: i32,
+    pub new_asset_id: i32,
+    pub new_position: (f32, f32),
+    pub new_name: Option<String>,
+    pub new_icon_type: Option<i32>,
+    pub new_file_path: Option<String>,
+    pub new_transition_style: Option<String>,
+    pub new_icon_color: Option<String>,
+    pub new_icon_scale: Option<f32>,
+    pub new_url_target: Option<String>,
+    pub new_connection_type: Option<ConnectionType>,
+    pub new_z_index: Option<i32>,
+    #[serde(default)]
+    pub new_audio_path: Option<String>,
+}
+
+// Actions received from the client/editor UI (listed for clients in `manifest::ACTIONS`)
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "action", content = "data")]
 pub enum EditorAction {
-    AddScene { name: String, file_path: String },
+    AddScene {
+        name: String,
+        file_path: String,
+        /// Pre-filled from the upload response when a heading was found in EXIF
+        #[serde(default)]
+        north_direction: Option<f32>,
+        /// Pre-filled from the upload response when EXIF has a capture date
+        #[serde(default)]
+        captured_at: Option<String>,
+    },
     SwapScene { scene_id: i32, new_file_path: String },
     DeleteScene { scene_id: i32 },
     SetInitialScene { scene_id: i32 },
     UpdateSceneName { scene_id: i32, name: String },
     AddCloseup { name: String, file_path: String, parent_scene_id: i32, position: (f32, f32), icon_type: Option<i32> },
     AddConnection { start_scene_id: i32, asset_id: i32, position: (f32, f32), name: Option<String> },
-    EditConnection { connection_id: i32, new_asset_id: i32, new_position: (f32, f32), new_name: Option<String>, new_icon_type: Option<i32>, new_file_path: Option<String> },
+    AddConnectionToAllScenes { target_scene_id: i32, position: (f32, f32), name: Option<String>, kind: ConnectionType },
+    /// Copies a connection to every scene whose name matches `name_pattern` (substring, or glob with `*`/`?`)
+    PropagateConnection { connection_id: i32, name_pattern: String },
+    EditConnection(ConnectionEdit),
     DeleteConnection { connection_id: i32 },
+    DeleteConnections { connection_ids: Vec<i32> },
+    RenameConnection { connection_id: i32, name: String },
+    /// Moves a connection by a delta in degrees; longitude wraps like `EditConnection`'s,
+    /// latitude is clamped to -90..90
+    NudgeConnection { connection_id: i32, d_yaw: f32, d_pitch: f32 },
     SetInitialView { scene_id: i32, position: (f32, f32), fov: Option<f32> },
     SetNorthDirection { scene_id: i32, direction: f32 },
+    /// Sets the same north direction (0 to <360) on every scene of the tour
+    SetNorthDirectionAll { direction: f32 },
+    /// Clears a scene's initial view, north direction and field of view
+    ResetSceneCalibration { scene_id: i32 },
     ChangeAddress { address: String },
     AddFloorplan { file_path: String },
     DeleteFloorplan { floorplan_id: i32 },
@@ -82,12 +249,217 @@ pub enum EditorAction {
     UpdateFloorplanMarker { marker_id: i32, x: f32, y: f32 },
     DeleteFloorplanMarker { marker_id: i32 },
     SetSceneSort { mode: String, direction: String },
+    CreateSceneGroup { name: String },
+    AssignSceneToGroup { scene_id: i32, group_id: Option<i64> },
+    /// Sets (or clears, with `null`) when a scene was shot: `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS`
+    SetSceneCapturedAt { scene_id: i32, captured_at: Option<String> },
+    /// Sets the author's private notes on a scene; `null` or blank clears them
+    SetSceneNotes { scene_id: i32, notes: Option<String> },
+    /// Limits how far viewers can zoom a scene (field of view in degrees, 30-120); `null`
+    /// leaves that end to the viewer's default
+    SetFovLimits { scene_id: i32, min_fov: Option<f32>, max_fov: Option<f32> },
+    /// Sets the icon exported for the scene's hotspots that don't pick their own; `null`
+    /// goes back to the configured default
+    SetSceneDefaultIcon { scene_id: i32, icon_index: Option<i32> },
+    /// Hides a draft scene from exports (or shows it again); the initial scene can't be hidden
+    SetSceneHidden { scene_id: i32, hidden: bool },
+    /// Rewrites a scene's panorama file: shifted right by `yaw_offset_deg` (wrapping around)
+    /// and/or flipped upside down
+    RotateScene { scene_id: i32, yaw_offset_deg: f32, flip_vertical: bool },
+    SetTourPath { scene_ids: Vec<i32> },
+    SetDeferredMode { enabled: bool },
+    SaveTour,
 }
 
 #[derive(Serialize)]
 pub struct UploadResponse {
     pub file_path: String,
     pub message: String,
+    /// Camera heading in degrees read from EXIF (only when `editor.infer_north` is enabled)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_north: Option<f32>,
+    /// Downscaled copy for the closeup picker (closeup uploads only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_path: Option<String>,
+    /// Capture date from EXIF `DateTimeOriginal` (scene uploads only), as `YYYY-MM-DD HH:MM:SS`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captured_at: Option<String>,
+    /// What the image's aspect ratio suggests it is (image uploads only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Projection>,
+    /// Something about the upload the user should know, e.g. a flat image uploaded as a scene
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// Stored size in pixels, after any downscaling to `uploads.max_closeup_megapixels` (closeup uploads only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+/// Image shape as judged from its aspect ratio
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    /// 2:1, like a full 360° panorama
+    Equirectangular,
+    /// Anything else, like an ordinary photo
+    Flat,
+}
+
+/// How far from exactly 2:1 an image may be and still count as equirectangular (relative)
+const EQUIRECT_RATIO_TOLERANCE: f64 = 0.02;
+
+/// Case-insensitive scene name match: a glob when the pattern contains `*` or `?`, a substring otherwise
+pub(crate) fn matches_name_pattern(name: &str, pattern: &str) -> bool {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    if !pattern.iter().any(|c| *c == '*' || *c == '?') {
+        let needle: String = pattern.iter().collect();
+        return name.iter().collect::<String>().contains(&needle);
+    }
+
+    // Iterative glob with backtracking to the last `*`
+    let (mut n, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            n += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// True for the link targets a URL hotspot may use
+pub(crate) fn is_url_target(value: &str) -> bool {
+    matches!(value, "_self" | "_blank")
+}
+
+/// True for `#RRGGBB` hex colours
+pub(crate) fn is_hex_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// True for absolute `http://` / `https://` URLs with a host and no query or fragment,
+/// usable as the base of generated links
+pub(crate) fn is_http_base_url(value: &str) -> bool {
+    let Some(rest) = value.strip_prefix("https://").or_else(|| value.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = rest.split('/').next().unwrap_or("");
+    !host.is_empty()
+        && !host.starts_with(':')
+        && !value.contains(['?', '#'])
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// Reads the camera heading (GPSImgDirection) from an image's EXIF block.
+///
+/// Returns `None` when there is no EXIF, no heading tag, or the value isn't a usable angle.
+pub fn read_exif_heading(data: &[u8]) -> Option<f32> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::GPSImgDirection, exif::In::PRIMARY)?;
+    let degrees = match &field.value {
+        exif::Value::Rational(values) => values.first()?.to_f64(),
+        _ => return None,
+    };
+    if !degrees.is_finite() || !(0.0..=360.0).contains(&degrees) {
+        return None;
+    }
+    Some((degrees % 360.0) as f32)
+}
+
+/// Reads when a photo was taken (EXIF `DateTimeOriginal`) as `YYYY-MM-DD HH:MM:SS`.
+///
+/// Returns `None` when there is no EXIF, no such tag, or the value isn't a valid date.
+pub fn read_exif_capture_date(data: &[u8]) -> Option<String> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(data))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(ref values) = field.value else { return None };
+    let taken = exif::DateTime::from_ascii(values.first()?).ok()?;
+    let value = format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", taken.year, taken.month, taken.day, taken.hour, taken.minute, taken.second);
+    is_capture_timestamp(&value).then_some(value)
+}
+
+/// `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` with in-range fields (the format scenes store capture dates in)
+pub fn is_capture_timestamp(value: &str) -> bool {
+    let (date, time) = match value.split_once(' ') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let parts = |text: &str, sep: char, widths: [usize; 3]| -> Option<[u32; 3]> {
+        let fields: Vec<&str> = text.split(sep).collect();
+        if fields.len() != 3 || fields.iter().zip(widths).any(|(f, w)| f.len() != w || !f.bytes().all(|b| b.is_ascii_digit())) {
+            return None;
+        }
+        Some([fields[0].parse().ok()?, fields[1].parse().ok()?, fields[2].parse().ok()?])
+    };
+    let Some([_, month, day]) = parts(date, '-', [4, 2, 2]) else { return false };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return false;
+    }
+    match time {
+        None => true,
+        Some(time) => parts(time, ':', [2, 2, 2]).is_some_and(|[h, m, s]| h < 24 && m < 60 && s < 60),
+    }
+}
+
+/// Starting view given to newly added scenes (from `[editor]` config)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneDefaults {
+    pub fov: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for SceneDefaults {
+    fn default() -> Self {
+        Self::from_config(&crate::config::EditorConfig::default())
+    }
+}
+
+impl SceneDefaults {
+    pub fn from_config(config: &crate::config::EditorConfig) -> Self {
+        Self { fov: config.default_fov, yaw: config.default_view_yaw, pitch: config.default_view_pitch }
+    }
+}
+
+/// Size caps applied to a tour while editing (from `[editor]` config)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TourLimits {
+    pub max_scenes: usize,
+    pub max_connections_per_scene: usize,
+}
+
+impl Default for TourLimits {
+    fn default() -> Self {
+        Self::from_config(&crate::config::EditorConfig::default())
+    }
+}
+
+impl TourLimits {
+    pub fn from_config(config: &crate::config::EditorConfig) -> Self {
+        Self { max_scenes: config.max_scenes_per_tour, max_connections_per_scene: config.max_connections_per_scene }
+    }
+}
+
+/// Error reply for an add rejected by `TourLimits`
+fn limit_reached_json(message: &str) -> String {
+    serde_json::json!({ "type": "error", "code": "limit_reached", "message": message }).to_string()
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -102,6 +474,20 @@ pub struct EditorState {
     pub scenes_index: HashMap<i32, usize>,
     #[serde(skip_serializing)]
     pub connection_index: HashMap<i32, (i32, usize)>,
+    /// When set, row updates are queued in `pending_writes` until `SaveTour` (or autosave)
+    pub deferred: bool,
+    #[serde(skip_serializing)]
+    pub pending_writes: Vec<PendingWrite>,
+    #[serde(skip_serializing)]
+    pub scene_defaults: SceneDefaults,
+    #[serde(skip_serializing)]
+    pub scene_name_collision: SceneNameCollision,
+    #[serde(skip_serializing)]
+    pub limits: TourLimits,
+    /// Set by actions that write tour rows the in-memory scenes don't mirror (floorplans,
+    /// groups, the tour path...), so `handle_action` can tell they changed something
+    #[serde(skip_serializing)]
+    wrote_tour_rows: bool,
 }
 
 impl EditorState {
@@ -114,6 +500,12 @@ impl EditorState {
             db,
             scenes_index: HashMap::new(),
             connection_index: HashMap::new(),
+            deferred: false,
+            pending_writes: Vec::new(),
+            scene_defaults: SceneDefaults::default(),
+            scene_name_collision: SceneNameCollision::default(),
+            limits: TourLimits::default(),
+            wrote_tour_rows: false,
         }
     }
 
@@ -151,8 +543,45 @@ impl EditorState {
         }
     }
 
+    /// Applies the `scene_name_collision` policy to a new scene name.
+    /// `renamed_scene` is left out of the comparison so a scene keeps its own name.
+    /// Returns the name to use, or the error message when the name is rejected.
+    fn unique_scene_name(&self, name: &str, renamed_scene: Option<i32>) -> Result<String, String> {
+        let taken = |candidate: &str| self.scenes.iter().any(|s| Some(s.id) != renamed_scene && s.name == candidate);
+        if !taken(name) {
+            return Ok(name.to_string());
+        }
+        match self.scene_name_collision {
+            SceneNameCollision::Allow => Ok(name.to_string()),
+            SceneNameCollision::Reject => Err(format!("A scene named '{}' already exists in this tour", name)),
+            SceneNameCollision::Suffix => Ok((2..)
+                .map(|n| format!("{} ({})", name, n))
+                .find(|candidate| !taken(candidate))
+                .expect("unbounded suffix range")),
+        }
+    }
+
+    /// Message for a scene add that would exceed `limits.max_scenes`
+    fn scene_limit_error(&self) -> Option<String> {
+        (self.scenes.len() >= self.limits.max_scenes)
+            .then(|| format!("Tours are limited to {} scenes", self.limits.max_scenes))
+    }
+
+    /// Message for a hotspot add on `scene_id` that would exceed `limits.max_connections_per_scene`
+    fn connection_limit_error(&self, scene_id: i32) -> Option<String> {
+        let count = self.scenes_index.get(&scene_id)
+            .and_then(|&si| self.scenes.get(si))
+            .map_or(0, |scene| scene.connections.len());
+        (count >= self.limits.max_connections_per_scene)
+            .then(|| format!("Scenes are limited to {} connections", self.limits.max_connections_per_scene))
+    }
+
     /// Touch (update modified_at) for a scene asset in DB
     async fn touch_scene(&self, scene_id: i32) {
+        // Deferred connection writes touch their scene when flushed
+        if self.deferred {
+            return;
+        }
         if let Some(ref db) = self.db {
             let _ = sqlx::query("UPDATE assets SET modified_at = CURRENT_TIMESTAMP WHERE id = ?1")
                 .bind(scene_id as i64)
@@ -161,16 +590,81 @@ impl EditorState {
         }
     }
 
-    /// Handle editor actions and return response messages
+    /// Persist a row update now, or queue it while in deferred mode
+    async fn persist(&mut self, write: PendingWrite) -> Result<(), sqlx::Error> {
+        if self.deferred {
+            self.pending_writes.push(write);
+            return Ok(());
+        }
+        match self.db {
+            Some(ref db) => db.apply_pending_writes(std::slice::from_ref(&write)).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Write all queued updates in one transaction; returns how many were applied
+    pub async fn flush_pending_writes(&mut self) -> Result<usize, sqlx::Error> {
+        if self.pending_writes.is_empty() {
+            return Ok(0);
+        }
+        let Some(ref db) = self.db else {
+            return Ok(0);
+        };
+        let flushed = db.apply_pending_writes(&self.pending_writes).await?;
+        self.pending_writes.clear();
+        Ok(flushed)
+    }
+
+    /// Switch deferred mode; leaving it flushes anything still queued
+    async fn set_deferred_mode(&mut self, enabled: bool, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !enabled {
+            self.flush_pending_writes().await?;
+        }
+        self.deferred = enabled;
+        let msg = serde_json::json!({ "type": "deferred_mode", "enabled": enabled });
+        let _ = tx.send(Message::Text(msg.to_string()));
+        Ok(())
+    }
+
+    async fn save_tour(&mut self, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self.flush_pending_writes().await {
+            Ok(flushed) => {
+                let msg = serde_json::json!({ "type": "tour_saved", "flushed": flushed });
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            Err(e) => {
+                eprintln!("Failed to flush deferred writes for tour {}: {}", self.tour_id, e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to save tour; changes are still pending."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle editor actions and return response messages.
+    ///
+    /// Returns whether the action changed the tour; refused or no-op actions (which only
+    /// reply to the client) return `Ok(false)`.
     pub async fn handle_action(
         &mut self, 
         action: EditorAction,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let snapshot = |state: &Self| (serde_json::to_string(&state.scenes).ok(), state.current_scene_id, state.pending_writes.len());
+        let before = snapshot(self);
+        self.wrote_tour_rows = false;
+        self.apply_action(action, tx).await?;
+        Ok(self.wrote_tour_rows || snapshot(self) != before)
+    }
+
+    async fn apply_action(
+        &mut self,
+        action: EditorAction,
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         println!("Handling editor action: {:?}\n", action);
         match action {
-            EditorAction::AddScene { name, file_path } => {
-                self.add_scene(name, file_path, tx).await?;
+            EditorAction::AddScene { name, file_path, north_direction, captured_at } => {
+                self.add_scene(name, file_path, north_direction, captured_at, tx).await?;
             }
             EditorAction::SwapScene { scene_id, new_file_path } => {
                 self.swap_scene(scene_id, new_file_path, tx).await?;
@@ -179,7 +673,7 @@ impl EditorState {
                 self.delete_scene(scene_id, tx).await?;
             }
             EditorAction::SetInitialScene { scene_id } => {
-                self.set_initial_scene(scene_id).await?;
+                self.set_initial_scene(scene_id, tx).await?;
             }
             EditorAction::UpdateSceneName { scene_id, name } => {
                 self.update_scene_name(scene_id, name, tx).await?;
@@ -205,18 +699,39 @@ impl EditorState {
                 }
                 self.add_connection(start_scene_id, asset_id, position, name, tx).await?;
             }
-            EditorAction::EditConnection { connection_id, new_asset_id, new_position, new_name, new_icon_type, new_file_path } => {
-                self.edit_connection(connection_id, new_asset_id, new_position, new_name, new_icon_type, new_file_path, tx).await?;
+            EditorAction::AddConnectionToAllScenes { target_scene_id, position, name, kind } => {
+                self.add_connection_to_all_scenes(target_scene_id, position, name, kind, tx).await?;
+            }
+            EditorAction::PropagateConnection { connection_id, name_pattern } => {
+                self.propagate_connection(connection_id, name_pattern, tx).await?;
+            }
+            EditorAction::EditConnection(edit) => {
+                self.edit_connection(edit, tx).await?;
             }
             EditorAction::DeleteConnection { connection_id } => {
                 self.delete_connection(connection_id, tx).await?;
             }
+            EditorAction::DeleteConnections { connection_ids } => {
+                self.delete_connections(connection_ids, tx).await?;
+            }
+            EditorAction::RenameConnection { connection_id, name } => {
+                self.rename_connection(connection_id, name, tx).await?;
+            }
+            EditorAction::NudgeConnection { connection_id, d_yaw, d_pitch } => {
+                self.nudge_connection(connection_id, d_yaw, d_pitch, tx).await?;
+            }
             EditorAction::SetInitialView { scene_id, position, fov } => {
                 self.set_initial_view(scene_id, position, fov, tx).await?;
             }
             EditorAction::SetNorthDirection { scene_id, direction } => {
                 self.set_north_direction(scene_id, direction, tx).await?;
             }
+            EditorAction::SetNorthDirectionAll { direction } => {
+                self.set_north_direction_all(direction, tx).await?;
+            }
+            EditorAction::ResetSceneCalibration { scene_id } => {
+                self.reset_scene_calibration(scene_id, tx).await?;
+            }
             EditorAction::ChangeAddress { address } => {
                 self.change_address(address, tx).await?;
             }
@@ -244,6 +759,39 @@ impl EditorState {
             EditorAction::SetSceneSort { mode, direction } => {
                 self.set_scene_sort(mode, direction, tx).await?;
             }
+            EditorAction::CreateSceneGroup { name } => {
+                self.create_scene_group(name, tx).await?;
+            }
+            EditorAction::AssignSceneToGroup { scene_id, group_id } => {
+                self.assign_scene_to_group(scene_id, group_id, tx).await?;
+            }
+            EditorAction::SetSceneCapturedAt { scene_id, captured_at } => {
+                self.set_scene_captured_at(scene_id, captured_at, tx).await?;
+            }
+            EditorAction::SetSceneNotes { scene_id, notes } => {
+                self.set_scene_notes(scene_id, notes, tx).await?;
+            }
+            EditorAction::SetFovLimits { scene_id, min_fov, max_fov } => {
+                self.set_fov_limits(scene_id, min_fov, max_fov, tx).await?;
+            }
+            EditorAction::SetSceneDefaultIcon { scene_id, icon_index } => {
+                self.set_scene_default_icon(scene_id, icon_index, tx).await?;
+            }
+            EditorAction::SetSceneHidden { scene_id, hidden } => {
+                self.set_scene_hidden(scene_id, hidden, tx).await?;
+            }
+            EditorAction::RotateScene { scene_id, yaw_offset_deg, flip_vertical } => {
+                self.rotate_scene(scene_id, yaw_offset_deg, flip_vertical, tx).await?;
+            }
+            EditorAction::SetTourPath { scene_ids } => {
+                self.set_tour_path(scene_ids, tx).await?;
+            }
+            EditorAction::SetDeferredMode { enabled } => {
+                self.set_deferred_mode(enabled, tx).await?;
+            }
+            EditorAction::SaveTour => {
+                self.save_tour(tx).await?;
+            }
         }
         Ok(())
     }
@@ -252,13 +800,44 @@ impl EditorState {
         &mut self,
         name: String,
         file_path: String,
-        tx: &mpsc::UnboundedSender<Message>
+        north_direction: Option<f32>,
+        captured_at: Option<String>,
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         println!("ADD_SCENE: Creating scene '{}' with file_path: '{}' for tour: {}", name, file_path, self.tour_id);
+        if let Some(message) = self.scene_limit_error() {
+            let _ = tx.send(Message::Text(limit_reached_json(&message)));
+            return Ok(());
+        }
+        let name = match self.unique_scene_name(&name, None) {
+            Ok(name) => name,
+            Err(message) => {
+                let msg = serde_json::json!({ "type": "error", "message": message, "field": "data.name" });
+                let _ = tx.send(Message::Text(msg.to_string()));
+                return Ok(());
+            }
+        };
         
-        // Save to database first to get the auto-generated ID
+        // Save to database first to get the auto-generated ID; the scene starts at the configured view
+        let defaults = self.scene_defaults;
         let scene_id = if let Some(ref db) = self.db {
-            match db.save_scene(self.tour_id, &name, &file_path, None, None, None).await {
+            let saved = match db.insert_scene(&NewScene {
+                tour_id: self.tour_id,
+                name: &name,
+                file_path: &file_path,
+                initial_view_x: Some(defaults.yaw),
+                initial_view_y: Some(defaults.pitch),
+                north_direction,
+                created_by: Some(&self.username),
+            }).await {
+                Ok(db_id) => db.update_scene(&SceneUpdate { id: db_id, pov: FieldUpdate::Set(defaults.fov), ..Default::default() }).await.map(|_| db_id),
+                Err(e) => Err(e),
+            };
+            let saved = match (saved, captured_at.as_deref()) {
+                (Ok(db_id), Some(captured_at)) => db.set_scene_captured_at(self.tour_id, db_id, Some(captured_at)).await.map(|_| db_id),
+                (saved, _) => saved,
+            };
+            match saved {
                 Ok(db_id) => {
                     println!("Scene '{}' saved to database with NEW unique ID: {}", name, db_id);
                     db_id
@@ -279,8 +858,17 @@ impl EditorState {
             name: name.clone(),
             file_path: file_path.clone(),
             connections: Vec::new(),
-            initial_view: None,
-            north_direction: None,
+            initial_view: Some(Coordinates { x: defaults.yaw, y: defaults.pitch }),
+            north_direction,
+            group_id: None,
+            media_type: MediaType::from_path(&file_path),
+            hidden: false,
+            captured_at,
+            notes: None,
+            min_fov: None,
+            max_fov: None,
+            created_by: Some(self.username.clone()),
+            default_icon_index: None,
         };
         
         self.scenes.push(scene);
@@ -289,6 +877,7 @@ impl EditorState {
         
         // If this is the first scene, set it as the initial scene in the database
         if self.scenes.len() == 1 {
+            self.current_scene_id = Some(scene_id as i32);
             if let Some(ref db) = self.db {
                 if let Err(e) = db.set_initial_scene(self.tour_id, scene_id).await {
                     eprintln!("Failed to set initial scene in database: {}", e);
@@ -306,7 +895,7 @@ impl EditorState {
         Ok(())
     }
 
-    async fn set_scene_sort(&mut self, mode: String, direction: String, tx: &mpsc::UnboundedSender<Message>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn set_scene_sort(&mut self, mode: String, direction: String, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Persist to database
         if let Some(ref db) = self.db {
             let _ = sqlx::query("UPDATE tours SET sort_mode = ?1, sort_direction = ?2, modified_at = CURRENT_TIMESTAMP WHERE id = ?3")
@@ -315,25 +904,273 @@ impl EditorState {
                 .bind(self.tour_id)
                 .execute(&*db.pool)
                 .await;
+            self.wrote_tour_rows = true;
         }
         // Respond to client to acknowledge
         let _ = tx.send(Message::Text(format!("{{\"type\":\"sort_updated\",\"mode\":\"{}\",\"direction\":\"{}\"}}", mode, direction)));
         Ok(())
     }
 
+    /// Create a named group (e.g. a building) that scenes can be filed under
+    async fn create_scene_group(&mut self, name: String, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(ref db) = self.db {
+            let group_id = db.create_scene_group(self.tour_id, &name).await?;
+            self.wrote_tour_rows = true;
+            let msg = serde_json::json!({
+                "type": "scene_group_created",
+                "group": { "id": group_id, "name": name }
+            });
+            let _ = tx.send(Message::Text(msg.to_string()));
+        } else {
+            let _ = tx.send(Message::Text(r#"{"type":"error","message":"Database not available."}"#.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Move a scene into a group, or back to the default group when `group_id` is None
+    async fn assign_scene_to_group(&mut self, scene_id: i32, group_id: Option<i64>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let assigned = match self.db {
+            Some(ref db) => db.assign_scene_to_group(self.tour_id, scene_id as i64, group_id).await?,
+            None => false,
+        };
+        if assigned {
+            if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
+                scene.group_id = group_id;
+            }
+            let msg = serde_json::json!({
+                "type": "scene_group_assigned",
+                "scene_id": scene_id,
+                "group_id": group_id
+            });
+            let _ = tx.send(Message::Text(msg.to_string()));
+        } else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene or group not found."}"#.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn set_scene_captured_at(&mut self, scene_id: i32, captured_at: Option<String>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let updated = match self.db {
+            Some(ref db) => db.set_scene_captured_at(self.tour_id, scene_id as i64, captured_at.as_deref()).await?,
+            None => false,
+        };
+        match self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            Some(scene) if updated => {
+                let msg = serde_json::json!({
+                    "type": "scene_captured_at_changed",
+                    "scene_id": scene_id,
+                    "captured_at": captured_at
+                });
+                scene.captured_at = captured_at;
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            _ => {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_scene_notes(&mut self, scene_id: i32, notes: Option<String>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let notes = notes.filter(|n| !n.trim().is_empty());
+        let updated = match self.db {
+            Some(ref db) => db.set_scene_notes(self.tour_id, scene_id as i64, notes.as_deref()).await?,
+            None => false,
+        };
+        match self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            Some(scene) if updated => {
+                let msg = serde_json::json!({
+                    "type": "scene_notes_changed",
+                    "scene_id": scene_id,
+                    "notes": notes
+                });
+                scene.notes = notes;
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            _ => {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_fov_limits(&mut self, scene_id: i32, min_fov: Option<f32>, max_fov: Option<f32>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let updated = match self.db {
+            Some(ref db) => db.set_scene_fov_limits(self.tour_id, scene_id as i64, min_fov, max_fov).await?,
+            None => false,
+        };
+        match self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            Some(scene) if updated => {
+                scene.min_fov = min_fov;
+                scene.max_fov = max_fov;
+                let msg = serde_json::json!({
+                    "type": "fov_limits_changed",
+                    "scene_id": scene_id,
+                    "min_fov": min_fov,
+                    "max_fov": max_fov
+                });
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            _ => {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_scene_default_icon(&mut self, scene_id: i32, icon_index: Option<i32>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(ref db) = self.db else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "No database connection; the scene's default icon can't be changed."}"#.to_string()));
+            return Ok(());
+        };
+        let updated = db.set_scene_default_icon(self.tour_id, scene_id as i64, icon_index).await?;
+        match self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            Some(scene) if updated => {
+                scene.default_icon_index = icon_index;
+                let msg = serde_json::json!({
+                    "type": "scene_default_icon_changed",
+                    "scene_id": scene_id,
+                    "icon_index": icon_index
+                });
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            _ => {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn rotate_scene(&mut self, scene_id: i32, yaw_offset_deg: f32, flip_vertical: bool, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(scene) = self.scenes.iter().find(|s| s.id == scene_id) else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            return Ok(());
+        };
+        if scene.media_type == MediaType::Video {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Video scenes can't be rotated."}"#.to_string()));
+            return Ok(());
+        }
+
+        let Some(ref db) = self.db else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "No database connection; the scene can't be rotated."}"#.to_string()));
+            return Ok(());
+        };
+        let Some(source) = scene_upload_path(&scene.file_path) else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Only uploaded scene panoramas can be rotated."}"#.to_string()));
+            return Ok(());
+        };
+
+        // The source may be shared with other scenes and tours (copies, templates, deduplicated
+        // uploads), so the result goes to a new file only this scene is switched to
+        let file_name = source.file_name().and_then(|n| n.to_str()).unwrap_or("panorama.jpg");
+        let original_name = file_name.strip_prefix("rotated_").and_then(|rest| rest.split_once('_')).map_or(file_name, |(_, name)| name);
+        let target_name = format!("rotated_{}_{}", &uuid::Uuid::new_v4().simple().to_string()[..8], original_name);
+        let target = source.with_file_name(&target_name);
+
+        // Decoding and re-encoding a full panorama is CPU-bound
+        let write_to = target.clone();
+        let rewritten = tokio::task::spawn_blocking(move || -> Result<String, image::ImageError> {
+            let image = image::open(&source)?;
+            let format = image::ImageFormat::from_path(&source)?;
+            let mut encoded = std::io::Cursor::new(Vec::new());
+            correct_equirect(&image, yaw_offset_deg, flip_vertical).write_to(&mut encoded, format)?;
+            let bytes = encoded.into_inner();
+            std::fs::write(&write_to, &bytes).map_err(image::ImageError::IoError)?;
+            Ok(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect())
+        }).await?;
+        let content_hash = match rewritten {
+            Ok(hash) => hash,
+            Err(e) => {
+                eprintln!("Failed to rotate scene {} ({}): {}", scene_id, scene.file_path, e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to rotate scene image."}"#.to_string()));
+                return Ok(());
+            }
+        };
+
+        // Also bumps modified_at so cached cubemap faces and clients pick up the new image
+        let file_path = format!("/assets/insta360/{}", target_name);
+        if let Err(e) = db.update_scene(&SceneUpdate { id: scene_id as i64, file_path: Some(file_path.clone()), ..Default::default() }).await {
+            let _ = fs::remove_file(&target).await;
+            return Err(e.into());
+        }
+        if let Err(e) = db.record_upload(&self.username, &file_path, &content_hash).await {
+            eprintln!("Failed to record rotated panorama {}: {}", file_path, e);
+        }
+        if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            scene.file_path = file_path.clone();
+        }
+        let msg = serde_json::json!({
+            "type": "scene_rotated",
+            "scene_id": scene_id,
+            "file_path": file_path,
+            "yaw_offset_deg": yaw_offset_deg,
+            "flip_vertical": flip_vertical
+        });
+        let _ = tx.send(Message::Text(msg.to_string()));
+        Ok(())
+    }
+
+    async fn set_scene_hidden(&mut self, scene_id: i32, hidden: bool, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if hidden && self.current_scene_id == Some(scene_id) {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "The initial scene can't be hidden."}"#.to_string()));
+            return Ok(());
+        }
+        let updated = match self.db {
+            Some(ref db) => db.set_scene_hidden(self.tour_id, scene_id as i64, hidden).await?,
+            None => false,
+        };
+        match self.scenes.iter_mut().find(|s| s.id == scene_id) {
+            Some(scene) if updated => {
+                scene.hidden = hidden;
+                let msg = serde_json::json!({
+                    "type": "scene_hidden_changed",
+                    "scene_id": scene_id,
+                    "hidden": hidden
+                });
+                let _ = tx.send(Message::Text(msg.to_string()));
+            }
+            _ => {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Store the recommended visiting order of scenes (exported for the viewer's next/previous)
+    async fn set_tour_path(&mut self, scene_ids: Vec<i32>, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(ref db) = self.db else {
+            let _ = tx.send(Message::Text(r#"{"type":"error","message":"Database not available."}"#.to_string()));
+            return Ok(());
+        };
+
+        let ids: Vec<i64> = scene_ids.iter().map(|&id| id as i64).collect();
+        if db.set_tour_path(self.tour_id, &ids).await? {
+            self.wrote_tour_rows = true;
+            let msg = serde_json::json!({
+                "type": "tour_path_set",
+                "scene_ids": scene_ids
+            });
+            let _ = tx.send(Message::Text(msg.to_string()));
+        } else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour path contains scenes that are not part of this tour."}"#.to_string()));
+        }
+        Ok(())
+    }
+
     /// Swap the image file of an existing scene
     async fn swap_scene(
         &mut self,
         scene_id: i32,
         new_file_path: String,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
             scene.file_path = new_file_path.clone();
+            scene.media_type = MediaType::from_path(&new_file_path);
             
             // Update database if available using numeric ID directly
             if let Some(ref db) = self.db {
-                if let Err(e) = db.update_scene(scene.id as i64, None, Some(&new_file_path), None, None, None, None).await {
+                if let Err(e) = db.update_scene(&SceneUpdate { id: scene.id as i64, file_path: Some(new_file_path.clone()), ..Default::default() }).await {
                     eprintln!("Failed to update scene in database: {}", e);
                 }
             }
@@ -353,12 +1190,19 @@ impl EditorState {
     async fn delete_scene(
         &mut self,
         scene_id: i32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         println!("DELETE_SCENE: Attempting to delete scene with ID: {}", scene_id);
-        
+        // Collect connection IDs that will be removed (outgoing from the scene itself and incoming from others)
+        let mut removed_connection_ids: Vec<i32> = Vec::new();
+
         // Delete from database if available using numeric ID directly
         if let Some(ref db) = self.db {
+            // Incoming links go with the scene, so list them before deleting
+            match db.get_incoming_connections(scene_id as i64).await {
+                Ok(incoming) => removed_connection_ids.extend(incoming.iter().map(|c| c.id as i32)),
+                Err(e) => eprintln!("Failed to list incoming connections: {}", e),
+            }
             if let Err(e) = db.delete_scene(scene_id as i64).await {
                 eprintln!("Failed to delete scene from database: {}", e);
             } else {
@@ -366,9 +1210,10 @@ impl EditorState {
             }
         } else {
             eprintln!("DELETE_SCENE: Database not available");
+            for scene in &self.scenes {
+                removed_connection_ids.extend(scene.connections.iter().filter(|c| c.target_scene_id == scene_id).map(|c| c.id));
+            }
         }
-        // Collect connection IDs that will be removed (outgoing from the scene itself and incoming from others)
-        let mut removed_connection_ids: Vec<i32> = Vec::new();
 
         // Outgoing: find the scene first to capture connection ids
         if let Some(&si) = self.scenes_index.get(&scene_id) {
@@ -379,26 +1224,25 @@ impl EditorState {
             }
         }
 
-        // Remove the scene
+        // Remove the scene and the connections in other scenes that target it
         self.scenes.retain(|s| s.id != scene_id);
-
-        // Incoming: remove connections in other scenes that target this scene and record their ids
         for scene in &mut self.scenes {
-            for c in &scene.connections {
-                if c.target_scene_id == scene_id {
-                    removed_connection_ids.push(c.id);
-                }
-            }
             scene.connections.retain(|c| c.target_scene_id != scene_id);
         }
 
     // Rebuild indices to reflect removals
     self.rebuild_indices();
         
-        // If this was the current scene, clear it
-        if self.current_scene_id.as_ref() == Some(&scene_id) {
-            self.current_scene_id = self.scenes.first().map(|s| s.id);
-            // Persist new or cleared initial scene
+        let response = format!(
+            r#"{{"type": "scene_deleted", "scene_id": "{}"}}"#,
+            scene_id
+        );
+        let _ = tx.send(Message::Text(response));
+
+        // If this was the initial scene, hand the role to the oldest remaining scene (lowest id)
+        if self.current_scene_id.as_ref() == Some(&scene_id) {
+            self.current_scene_id = self.scenes.iter().map(|s| s.id).min();
+            // Persist new or cleared initial scene
             if let Some(ref db) = self.db {
                 if let Some(new_id) = self.current_scene_id {
                     if let Err(e) = db.set_initial_scene(self.tour_id, new_id as i64).await {
@@ -410,13 +1254,12 @@ impl EditorState {
                     }
                 }
             }
+            let response = serde_json::json!({
+                "type": "initial_scene_changed",
+                "scene_id": self.current_scene_id
+            });
+            let _ = tx.send(Message::Text(response.to_string()));
         }
-        
-        let response = format!(
-            r#"{{"type": "scene_deleted", "scene_id": "{}"}}"#,
-            scene_id
-        );
-        let _ = tx.send(Message::Text(response));
 
         // Notify clients of each removed connection so UIs can clean up markers
         for cid in removed_connection_ids {
@@ -428,9 +1271,14 @@ impl EditorState {
         Ok(())
     }
 
-    async fn set_initial_scene(&mut self, scene_id: i32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn set_initial_scene(&mut self, scene_id: i32, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Set the current scene to the specified one
-        if self.scenes.iter().any(|s| s.id == scene_id) {
+        if let Some(scene) = self.scenes.iter().find(|s| s.id == scene_id) {
+            if scene.hidden {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "A hidden scene can't be the initial scene."}"#.to_string()));
+                return Ok(());
+            }
+            self.current_scene_id = Some(scene_id);
             if let Some(ref db) = self.db {
                 // Update the database with the new initial scene
                 if let Err(e) = db.set_initial_scene(self.tour_id, scene_id as i64).await {
@@ -443,17 +1291,38 @@ impl EditorState {
         }
     }
 
-    async fn update_scene_name(&mut self, scene_id: i32, new_name: String, _tx: &mpsc::UnboundedSender<Message>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn update_scene_name(&mut self, scene_id: i32, new_name: String, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let new_name = match self.unique_scene_name(&new_name, Some(scene_id)) {
+            Ok(name) if name != new_name => {
+                // Let the client show the suffixed name instead of the one it sent
+                let msg = serde_json::json!({ "type": "scene_renamed", "scene_id": scene_id, "name": name });
+                let _ = tx.send(Message::Text(msg.to_string()));
+                name
+            }
+            Ok(name) => name,
+            Err(message) => {
+                let msg = serde_json::json!({ "type": "error", "message": message, "field": "data.name" });
+                let _ = tx.send(Message::Text(msg.to_string()));
+                return Ok(());
+            }
+        };
+
         // Update the scene name in the in-memory structure
         if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
             scene.name = new_name.clone();
         }
 
-        // Update the scene name in the database if available
-        if let Some(ref db) = self.db {
-            if let Err(e) = db.update_scene(scene_id as i64, Some(&new_name), None, None, None, None, None).await {
-                eprintln!("Failed to update scene name in database: {}", e);
-            }
+        // Update the scene name in the database (or queue it in deferred mode)
+        if let Err(e) = self.persist(PendingWrite::Scene(SceneUpdate {
+            id: scene_id as i64,
+            name: Some(new_name),
+            file_path: None,
+            initial_view_x: FieldUpdate::Keep,
+            initial_view_y: FieldUpdate::Keep,
+            north_direction: FieldUpdate::Keep,
+            pov: FieldUpdate::Keep,
+        })).await {
+            eprintln!("Failed to update scene name in database: {}", e);
         }
         Ok(())
     }
@@ -466,73 +1335,80 @@ impl EditorState {
         parent_scene_id: i32,
         position: (f32, f32),
         icon_type: Option<i32>,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        
-        // Save closeup to database if available
-        if let Some(ref db) = self.db {
-            match db.save_closeup(self.tour_id, &name, &file_path, icon_type).await {
-                Ok(closeup_db_id) => {
-                    println!("Closeup '{}' saved to database with ID: {}", name, closeup_db_id);
-                    
-                    // Find the parent scene and add the connection
-                    if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == parent_scene_id) {
-                        // Save connection to the closeup using numeric scene ID
-                        match db.save_connection(
-                            self.tour_id,
-                            scene.id as i64,
-                            Some(closeup_db_id),
-                            position.0 as f32,
-                            position.1 as f32,
-                            false,
-                            Some(&name),
-                            Some(&file_path),
-                            icon_type,
-                        ).await {
-                            Ok(conn_db_id) => {
-                                println!("Connection to closeup saved with ID: {}", conn_db_id);
-                                
-                                // Add connection to in-memory structure using database ID
-                                let connection = Connection {
-                                    id: conn_db_id as i32,
-                                    connection_type: ConnectionType::Closeup,
-                                    target_scene_id: closeup_db_id as i32,
-                                    position: Coordinates { x: position.0 as f32, y: position.1 as f32 },
-                                    name: Some(name.clone()),
-                                    icon_index: icon_type,
-                                };
-                                scene.connections.push(connection);
-                                // Update index for this new closeup so edits can find it
-                                if let Some(last) = scene.connections.last() {
-                                    if last.id != 0 {
-                                        self.connection_index.insert(last.id, (parent_scene_id, scene.connections.len() - 1));
-                                    }
-                                }
-                                
-                                let response = format!(
-                                    r#"{{"type": "closeup_added", "name": "{}", "file_path": "{}", "parent_scene": "{}", "connection_id": "{}", "icon_type": {}}}"#,
-                                    name, file_path, parent_scene_id, conn_db_id, icon_type.unwrap_or(1)
-                                );
-                                let _ = tx.send(Message::Text(response));
-                                // Update parent scene modified timestamp
-                                self.touch_scene(parent_scene_id).await;
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to save closeup connection to database: {}", e);
-                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to save closeup connection"}"#.to_string()));
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to save closeup to database: {}", e);
-                    let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to save closeup to database"}"#.to_string()));
-                }
-            }
-        } else {
+        if let Some(message) = self.connection_limit_error(parent_scene_id) {
+            let _ = tx.send(Message::Text(limit_reached_json(&message)));
+            return Ok(());
+        }
+
+        let Some(ref db) = self.db else {
             let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Database not available for closeup storage"}"#.to_string()));
+            return Ok(());
+        };
+        if !self.scenes.iter().any(|s| s.id == parent_scene_id) {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            return Ok(());
         }
-        
+
+        // The closeup asset and its connection are written together or not at all
+        let (tour_id, closeup_name, closeup_path, username) = (self.tour_id, name.clone(), file_path.clone(), self.username.clone());
+        let saved = db.transaction(move |db_tx| Box::pin(async move {
+            let closeup_db_id = db_tx.save_closeup(tour_id, &closeup_name, &closeup_path, Some(&username)).await?;
+            let conn_db_id = db_tx.save_connection(&NewConnection {
+                tour_id,
+                start_scene_db_id: parent_scene_id as i64,
+                end_scene_db_id: Some(closeup_db_id),
+                world_lon: position.0,
+                world_lat: position.1,
+                connection_type: ConnectionType::Closeup,
+                name: Some(&closeup_name),
+                file_path: Some(&closeup_path),
+                icon_type,
+                created_by: Some(&username),
+            }).await?;
+            Ok((closeup_db_id, conn_db_id))
+        })).await;
+        let (closeup_db_id, conn_db_id) = match saved {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to save closeup to database: {}", e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to save closeup to database"}"#.to_string()));
+                return Ok(());
+            }
+        };
+        println!("Closeup '{}' saved to database with ID: {} (connection {})", name, closeup_db_id, conn_db_id);
+
+        if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == parent_scene_id) {
+            // Add connection to in-memory structure using database ID
+            let connection = Connection {
+                id: conn_db_id as i32,
+                connection_type: ConnectionType::Closeup,
+                target_scene_id: closeup_db_id as i32,
+                position: Coordinates { x: position.0, y: position.1 },
+                name: Some(name.clone()),
+                icon_index: icon_type,
+                transition_style: None,
+                icon_color: None,
+                icon_scale: None,
+                url_target: None,
+                z_index: 0,
+                audio_path: None,
+                created_by: Some(self.username.clone()),
+            };
+            scene.connections.push(connection);
+            // Update index for this new closeup so edits can find it
+            self.connection_index.insert(conn_db_id as i32, (parent_scene_id, scene.connections.len() - 1));
+        }
+
+        let response = format!(
+            r#"{{"type": "closeup_added", "name": "{}", "file_path": "{}", "parent_scene": "{}", "connection_id": "{}", "icon_type": {}}}"#,
+            name, file_path, parent_scene_id, conn_db_id, icon_type.unwrap_or(1)
+        );
+        let _ = tx.send(Message::Text(response));
+        // Update parent scene modified timestamp
+        self.touch_scene(parent_scene_id).await;
+
         Ok(())
     }
 
@@ -543,8 +1419,12 @@ impl EditorState {
         target_scene_id: i32,
         position: (f32, f32),
         name: Option<String>,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(message) = self.connection_limit_error(start_scene_id) {
+            let _ = tx.send(Message::Text(limit_reached_json(&message)));
+            return Ok(());
+        }
         if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == start_scene_id) {
             // Determine if provided position is lon/lat and normalize longitude to 0..360
             let mut world_lon = position.0 as f32;
@@ -556,17 +1436,18 @@ impl EditorState {
 
             // Save connection to database first to get auto-generated ID
             let connection_db_id = if let Some(ref db) = self.db {
-                match db.save_connection(
-                    self.tour_id,
-                    start_scene_id as i64,
-                    Some(target_scene_id as i64),
+                match db.insert_connection(&NewConnection {
+                    tour_id: self.tour_id,
+                    start_scene_db_id: start_scene_id as i64,
+                    end_scene_db_id: Some(target_scene_id as i64),
                     world_lon,
                     world_lat,
-                    true,
-                    name.as_deref(),
-                    None,
-                    None
-                ).await {
+                    connection_type: ConnectionType::Transition,
+                    name: name.as_deref(),
+                    file_path: None,
+                    icon_type: None,
+                    created_by: Some(&self.username),
+                }).await {
                     Ok(conn_db_id) => {
                         println!("Connection saved to database with ID: {}", conn_db_id);
                         Some(conn_db_id)
@@ -590,6 +1471,13 @@ impl EditorState {
                 position: Coordinates { x: position.0 as f32, y: position.1 as f32 },
                 name,
                 icon_index: None,
+                transition_style: None,
+                icon_color: None,
+                icon_scale: None,
+                url_target: None,
+                z_index: 0,
+                audio_path: None,
+                created_by: Some(self.username.clone()),
             };
 
             scene.connections.push(connection);
@@ -613,17 +1501,244 @@ impl EditorState {
         Ok(())
     }
 
+    /// Add the same connection to every scene in the tour (except the target itself)
+    async fn add_connection_to_all_scenes(
+        &mut self,
+        target_scene_id: i32,
+        position: (f32, f32),
+        name: Option<String>,
+        kind: ConnectionType,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db = match self.db {
+            Some(ref db) => db.clone(),
+            None => {
+                let _ = tx.send(Message::Text(r#"{"type":"error","message":"Database not available."}"#.to_string()));
+                return Ok(());
+            }
+        };
+
+        let mut world_lon = position.0;
+        if world_lon.is_finite() {
+            world_lon = world_lon.rem_euclid(360.0);
+        }
+        let world_lat = position.1;
+        // The target must be an asset of this tour; transitions can only lead to scenes
+        let target = sqlx::query("SELECT file_path FROM assets WHERE id = ?1 AND tour_id = ?2")
+            .bind(target_scene_id as i64)
+            .bind(self.tour_id)
+            .fetch_optional(&*db.pool)
+            .await?;
+        let Some(target) = target.filter(|_| kind != ConnectionType::Transition || self.scenes_index.contains_key(&target_scene_id)) else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Target scene not found."}"#.to_string()));
+            return Ok(());
+        };
+        // Closeup hotspots carry the closeup image path like add_closeup does
+        let file_path: Option<String> = if kind == ConnectionType::Transition {
+            None
+        } else {
+            target.get::<Option<String>, _>("file_path")
+        };
+
+        let start_ids: Vec<i64> = self.scenes.iter()
+            .filter(|s| s.id != target_scene_id)
+            .map(|s| s.id as i64)
+            .collect();
+        if let Some(message) = start_ids.iter().find_map(|&id| self.connection_limit_error(id as i32)) {
+            let _ = tx.send(Message::Text(limit_reached_json(&message)));
+            return Ok(());
+        }
+
+        let connections: Vec<NewConnection> = start_ids.iter().map(|&start_scene_db_id| NewConnection {
+            tour_id: self.tour_id,
+            start_scene_db_id,
+            end_scene_db_id: Some(target_scene_id as i64),
+            world_lon,
+            world_lat,
+            connection_type: kind,
+            name: name.as_deref(),
+            file_path: file_path.as_deref(),
+            icon_type: None,
+            created_by: Some(&self.username),
+        }).collect();
+        let created = match db.save_connections_bulk(&connections).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to add connection to all scenes: {}", e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to add connections"}"#.to_string()));
+                return Ok(());
+            }
+        };
+
+        for (start_id, conn_id) in start_ids.iter().zip(created.iter()) {
+            if let Some(&si) = self.scenes_index.get(&(*start_id as i32)) {
+                if let Some(scene) = self.scenes.get_mut(si) {
+                    scene.connections.push(Connection {
+                        id: *conn_id as i32,
+                        connection_type: kind,
+                        target_scene_id,
+                        position: Coordinates { x: world_lon, y: world_lat },
+                        name: name.clone(),
+                        icon_index: None,
+                        transition_style: None,
+                        icon_color: None,
+                        icon_scale: None,
+                        url_target: None,
+                        z_index: 0,
+                        audio_path: None,
+                        created_by: Some(self.username.clone()),
+                    });
+                    self.connection_index.insert(*conn_id as i32, (*start_id as i32, scene.connections.len() - 1));
+                }
+            }
+            self.touch_scene(*start_id as i32).await;
+        }
+
+        let response = serde_json::json!({
+            "type": "connections_added",
+            "target_scene": target_scene_id,
+            "connection_ids": created
+        });
+        let _ = tx.send(Message::Text(response.to_string()));
+        Ok(())
+    }
+
+    /// Copy a connection onto every other scene whose name matches a pattern
+    async fn propagate_connection(
+        &mut self,
+        connection_id: i32,
+        name_pattern: String,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let db = match self.db {
+            Some(ref db) => db.clone(),
+            None => {
+                let _ = tx.send(Message::Text(r#"{"type":"error","message":"Database not available."}"#.to_string()));
+                return Ok(());
+            }
+        };
+
+        let source = self.connection_index.get(&connection_id).and_then(|&(scene_id, ci)| {
+            let scene = self.scenes.get(*self.scenes_index.get(&scene_id)?)?;
+            Some((scene_id, scene.connections.get(ci)?.clone()))
+        });
+        let Some((source_scene_id, connection)) = source else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Connection not found."}"#.to_string()));
+            return Ok(());
+        };
+        let is_transition = matches!(connection.connection_type, ConnectionType::Transition);
+
+        // Skip the source scene and, for transitions, the scene the hotspot leads to
+        let start_ids: Vec<i64> = self.scenes.iter()
+            .filter(|s| s.id != source_scene_id)
+            .filter(|s| !(is_transition && s.id == connection.target_scene_id))
+            .filter(|s| matches_name_pattern(&s.name, &name_pattern))
+            .map(|s| s.id as i64)
+            .collect();
+        if let Some(message) = start_ids.iter().find_map(|&id| self.connection_limit_error(id as i32)) {
+            let _ = tx.send(Message::Text(limit_reached_json(&message)));
+            return Ok(());
+        }
+
+        let created = match db.duplicate_connection(connection_id as i64, &start_ids, Some(&self.username)).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to propagate connection {}: {}", connection_id, e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to propagate connection"}"#.to_string()));
+                return Ok(());
+            }
+        };
+
+        for (start_id, conn_id) in start_ids.iter().zip(created.iter()) {
+            if let Some(&si) = self.scenes_index.get(&(*start_id as i32)) {
+                if let Some(scene) = self.scenes.get_mut(si) {
+                    scene.connections.push(Connection { id: *conn_id as i32, created_by: Some(self.username.clone()), ..connection.clone() });
+                    self.connection_index.insert(*conn_id as i32, (*start_id as i32, scene.connections.len() - 1));
+                }
+            }
+            self.touch_scene(*start_id as i32).await;
+        }
+
+        let response = serde_json::json!({
+            "type": "connection_propagated",
+            "source_connection_id": connection_id,
+            "scene_ids": start_ids,
+            "connection_ids": created
+        });
+        let _ = tx.send(Message::Text(response.to_string()));
+        Ok(())
+    }
+
     /// Edit an existing connection
     async fn edit_connection(
         &mut self,
-        connection_id: i32,
-        new_target_id: i32,
-        new_position: (f32, f32),
-        new_name: Option<String>,
-        new_icon_type: Option<i32>,
-        new_file_path: Option<String>,
-        tx: &mpsc::UnboundedSender<Message>
+        edit: ConnectionEdit,
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let ConnectionEdit { connection_id, new_asset_id: new_target_id, new_position, new_name, new_icon_type, new_file_path, new_transition_style,
+                             new_icon_color, new_icon_scale, new_url_target, new_connection_type, new_z_index, new_audio_path } = edit;
+        // Reject unknown transition styles before touching anything
+        let new_transition_style = match new_transition_style {
+            Some(style) => match TransitionStyle::parse(&style) {
+                Some(parsed) => Some(parsed),
+                None => {
+                    let _ = tx.send(Message::Text(format!(
+                        r#"{{"type": "error", "message": "Invalid transition style '{}'. Expected fade, slide or none."}}"#,
+                        style
+                    )));
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+        if let Some(color) = &new_icon_color {
+            if !is_hex_color(color) {
+                let _ = tx.send(Message::Text(format!(
+                    r#"{{"type": "error", "message": "Invalid icon color '{}'. Expected #RRGGBB."}}"#,
+                    color
+                )));
+                return Ok(());
+            }
+        }
+        if let Some(scale) = new_icon_scale {
+            if !(0.5..=3.0).contains(&scale) {
+                let _ = tx.send(Message::Text(format!(
+                    r#"{{"type": "error", "message": "Invalid icon scale {}. Expected 0.5 to 3.0."}}"#,
+                    scale
+                )));
+                return Ok(());
+            }
+        }
+        if let Some(target) = &new_url_target {
+            if !is_url_target(target) {
+                let _ = tx.send(Message::Text(format!(
+                    r#"{{"type": "error", "message": "Invalid link target '{}'. Expected _self or _blank."}}"#,
+                    target
+                )));
+                return Ok(());
+            }
+            // Link targets only mean something for info (URL) hotspots
+            let kind = new_connection_type.or_else(|| {
+                let &(scene_id, ci) = self.connection_index.get(&connection_id)?;
+                let scene = self.scenes.get(*self.scenes_index.get(&scene_id)?)?;
+                Some(scene.connections.get(ci)?.connection_type)
+            });
+            if kind.is_some_and(|kind| kind != ConnectionType::Info) {
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Link targets only apply to info hotspots."}"#.to_string()));
+                return Ok(());
+            }
+        }
+        if let Some(path) = &new_audio_path {
+            if !is_audio_asset(path) {
+                let _ = tx.send(Message::Text(serde_json::json!({
+                    "type": "error",
+                    "message": format!("Audio file '{}' not found. Upload it with type \"audio\" first.", path)
+                }).to_string()));
+                return Ok(());
+            }
+        }
+
+        let mut writes = Vec::new();
         let found = if let Some((start_scene_id, conn_idx)) = self.connection_index.get(&connection_id).cloned() {
             if let Some(&scene_idx) = self.scenes_index.get(&start_scene_id) {
                 if let Some(scene) = self.scenes.get_mut(scene_idx) {
@@ -634,29 +1749,49 @@ impl EditorState {
                         connection.position = Coordinates { x: lon_norm, y: new_position.1 as f32 };
                         if new_name.is_some() { connection.name = new_name.clone(); }
                         if new_icon_type.is_some() { connection.icon_index = new_icon_type; }
+                        if new_transition_style.is_some() { connection.transition_style = new_transition_style; }
+                        if new_icon_color.is_some() { connection.icon_color = new_icon_color.clone(); }
+                        if new_icon_scale.is_some() { connection.icon_scale = new_icon_scale; }
+                        if new_url_target.is_some() { connection.url_target = new_url_target.clone(); }
+                        if let Some(kind) = new_connection_type { connection.connection_type = kind; }
+                        if let Some(z) = new_z_index { connection.z_index = z; }
+                        if new_audio_path.is_some() { connection.audio_path = new_audio_path.clone(); }
                         // Persist update in DB
-                        if let Some(ref db) = self.db {
-                            let _ = db.update_connection(
-                                connection_id as i64,
-                                Some(new_target_id as i64),
-                                Some(lon_norm),
-                                Some(new_position.1 as f32),
-                                new_name.as_deref(),
-                                new_icon_type,
-                                new_file_path.as_deref()
-                            ).await;
-                            // If this connection represents a closeup and a new file path was provided,
-                            // also update the underlying asset (stored in the assets table) so the
-                            // closeup's asset file_path stays in sync with the connection's file_path.
-                            if new_file_path.is_some() {
-                                // Only attempt asset update for closeup-type connections
-                                if let ConnectionType::Closeup = connection.connection_type {
-                                    // target_scene_id stores the asset id for closeups
-                                    let asset_id = connection.target_scene_id as i64;
-                                    if asset_id != 0 {
-                                        // Update the asset's file_path column as well
-                                        let _ = db.update_scene(asset_id, None, new_file_path.as_deref(), None, None, None, None).await;
-                                    }
+                        writes.push(PendingWrite::Connection(ConnectionUpdate {
+                            id: connection_id as i64,
+                            end_id: Some(new_target_id as i64),
+                            world_lon: Some(lon_norm),
+                            world_lat: Some(new_position.1 as f32),
+                            name: new_name.clone(),
+                            icon_type: new_icon_type,
+                            file_path: new_file_path.clone(),
+                            transition_style: new_transition_style.map(|t| t.as_str().to_string()),
+                            icon_color: new_icon_color.clone(),
+                            icon_scale: new_icon_scale,
+                            url_target: new_url_target.clone(),
+                            connection_type: new_connection_type,
+                            z_index: new_z_index,
+                            audio_path: new_audio_path.clone(),
+                        }));
+                        // If this connection represents a closeup and a new file path was provided,
+                        // also update the underlying asset (stored in the assets table) so the
+                        // closeup's asset file_path stays in sync with the connection's file_path.
+                        if new_file_path.is_some() {
+                            // Only attempt asset update for closeup-type connections
+                            if let ConnectionType::Closeup = connection.connection_type {
+                                // target_scene_id stores the asset id for closeups
+                                let asset_id = connection.target_scene_id as i64;
+                                if asset_id != 0 {
+                                    // Update the asset's file_path column as well
+                                    writes.push(PendingWrite::Scene(SceneUpdate {
+                                        id: asset_id,
+                                        name: None,
+                                        file_path: new_file_path.clone(),
+                                        initial_view_x: FieldUpdate::Keep,
+                                        initial_view_y: FieldUpdate::Keep,
+                                        north_direction: FieldUpdate::Keep,
+                                        pov: FieldUpdate::Keep,
+                                    }));
                                 }
                             }
                         }
@@ -666,6 +1801,12 @@ impl EditorState {
             } else { false }
         } else { false };
 
+        for write in writes {
+            if let Err(e) = self.persist(write).await {
+                eprintln!("Failed to update connection in database: {}", e);
+            }
+        }
+
         if found {
             let response = format!(
                 r#"{{"type": "connection_edited", "connection_id": "{}"}}"#,
@@ -682,11 +1823,120 @@ impl EditorState {
         Ok(())
     }
 
+    /// Change only a connection's label, leaving its target and position untouched
+    async fn rename_connection(
+        &mut self,
+        connection_id: i32,
+        name: String,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let start_scene_id = match self.connection_index.get(&connection_id).cloned() {
+            Some((start_scene_id, conn_idx)) => {
+                let scene_idx = self.scenes_index.get(&start_scene_id).copied();
+                match scene_idx.and_then(|si| self.scenes.get_mut(si)).and_then(|s| s.connections.get_mut(conn_idx)) {
+                    Some(connection) => {
+                        connection.name = Some(name.clone());
+                        Some(start_scene_id)
+                    }
+                    None => None,
+                }
+            }
+            None => None,
+        };
+
+        if let Some(start_scene_id) = start_scene_id {
+            if let Err(e) = self.persist(PendingWrite::Connection(ConnectionUpdate {
+                id: connection_id as i64,
+                end_id: None,
+                world_lon: None,
+                world_lat: None,
+                name: Some(name.clone()),
+                icon_type: None,
+                file_path: None,
+                transition_style: None,
+                icon_color: None,
+                icon_scale: None,
+                url_target: None,
+                connection_type: None,
+                z_index: None,
+                audio_path: None,
+            })).await {
+                eprintln!("Failed to rename connection in database: {}", e);
+            }
+            let response = serde_json::json!({
+                "type": "connection_renamed",
+                "connection_id": connection_id,
+                "name": name
+            });
+            let _ = tx.send(Message::Text(response.to_string()));
+            self.touch_scene(start_scene_id).await;
+        } else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Connection not found."}"#.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Move a connection by a delta, relative to where it is now
+    async fn nudge_connection(
+        &mut self,
+        connection_id: i32,
+        d_yaw: f32,
+        d_pitch: f32,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let moved = match self.connection_index.get(&connection_id).cloned() {
+            Some((start_scene_id, conn_idx)) => {
+                let scene_idx = self.scenes_index.get(&start_scene_id).copied();
+                match scene_idx.and_then(|si| self.scenes.get_mut(si)).and_then(|s| s.connections.get_mut(conn_idx)) {
+                    Some(connection) => {
+                        let lon = (connection.position.x + d_yaw).rem_euclid(360.0);
+                        let lat = (connection.position.y + d_pitch).clamp(-90.0, 90.0);
+                        connection.position = Coordinates { x: lon, y: lat };
+                        Some((start_scene_id, lon, lat))
+                    }
+                    None => None,
+                }
+            }
+            None => None,
+        };
+
+        if let Some((start_scene_id, lon, lat)) = moved {
+            if let Err(e) = self.persist(PendingWrite::Connection(ConnectionUpdate {
+                id: connection_id as i64,
+                end_id: None,
+                world_lon: Some(lon),
+                world_lat: Some(lat),
+                name: None,
+                icon_type: None,
+                file_path: None,
+                transition_style: None,
+                icon_color: None,
+                icon_scale: None,
+                url_target: None,
+                connection_type: None,
+                z_index: None,
+                audio_path: None,
+            })).await {
+                eprintln!("Failed to move connection in database: {}", e);
+            }
+            let response = serde_json::json!({
+                "type": "connection_moved",
+                "connection_id": connection_id,
+                "position": [lon, lat]
+            });
+            let _ = tx.send(Message::Text(response.to_string()));
+            self.touch_scene(start_scene_id).await;
+        } else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Connection not found."}"#.to_string()));
+        }
+        Ok(())
+    }
+
     /// Delete a connection
     async fn delete_connection(
         &mut self,
         connection_id: i32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let found = if let Some((start_scene_id, conn_idx)) = self.connection_index.remove(&connection_id) {
             if let Some(&scene_idx) = self.scenes_index.get(&start_scene_id) {
@@ -719,13 +1969,57 @@ impl EditorState {
         Ok(())
     }
 
+    /// Delete several connections at once; unknown ids are reported rather than failing the batch
+    async fn delete_connections(
+        &mut self,
+        connection_ids: Vec<i32>,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut found: Vec<i32> = Vec::new();
+        let mut not_found: Vec<i32> = Vec::new();
+        for id in connection_ids {
+            if found.contains(&id) || not_found.contains(&id) { continue; }
+            if self.connection_index.contains_key(&id) { found.push(id); } else { not_found.push(id); }
+        }
+
+        if let Some(ref db) = self.db {
+            let db_ids: Vec<i64> = found.iter().map(|id| *id as i64).collect();
+            if let Err(e) = db.delete_connections(&db_ids).await {
+                eprintln!("Failed to delete connections: {}", e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to delete connections"}"#.to_string()));
+                return Ok(());
+            }
+        }
+
+        let mut touched: Vec<i32> = found.iter()
+            .filter_map(|id| self.connection_index.get(id).map(|&(scene_id, _)| scene_id))
+            .collect();
+        touched.sort();
+        touched.dedup();
+        for scene in self.scenes.iter_mut().filter(|s| touched.contains(&s.id)) {
+            scene.connections.retain(|c| !found.contains(&c.id));
+        }
+        self.rebuild_indices();
+        for scene_id in touched {
+            self.touch_scene(scene_id).await;
+        }
+
+        let response = serde_json::json!({
+            "type": "connections_deleted",
+            "connection_ids": found,
+            "not_found": not_found
+        });
+        let _ = tx.send(Message::Text(response.to_string()));
+        Ok(())
+    }
+
     /// Set the initial view position for a scene
     async fn set_initial_view(
         &mut self,
         scene_id: i32,
         position: (f32, f32),
         fov: Option<f32>,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
             let mut yaw = position.0;
@@ -734,10 +2028,16 @@ impl EditorState {
             print!("{:?}", position);
 
             // Update database if available
-            if let Some(ref db) = self.db {
-                if let Err(e) = db.update_scene(scene.id as i64, None, None, Some(yaw as f32), Some(position.1 as f32), None, fov).await {
-                        eprintln!("Failed to update scene initial view in database: {}", e);
-                    }
+            if let Err(e) = self.persist(PendingWrite::Scene(SceneUpdate {
+                id: scene_id as i64,
+                name: None,
+                file_path: None,
+                initial_view_x: FieldUpdate::Set(yaw),
+                initial_view_y: FieldUpdate::Set(position.1),
+                north_direction: FieldUpdate::Keep,
+                pov: fov.into(),
+            })).await {
+                eprintln!("Failed to update scene initial view in database: {}", e);
             }
             
             let _ = tx.send(Message::Text(r#"{"type": "success", "message": "Initial view position saved."}"#.to_string()));
@@ -754,7 +2054,7 @@ impl EditorState {
         &mut self,
         scene_id: i32,
         direction: f32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) {
             // Normalize to 0..360
@@ -762,15 +2062,6 @@ impl EditorState {
             if d < 0.0 { d += 360.0; }
             scene.north_direction = Some(d);
             
-            // Update database if available
-            if let Some(ref db) = self.db {
-                if let Err(e) = db.update_scene(scene.id as i64, None, None, None, None, Some(d), None).await {
-                        eprintln!("Failed to update scene north direction in database: {}", e);
-                    } else {
-                        println!("North direction updated for scene '{}' in database", scene.name);
-                    }
-                }
-            
             // Broadcast an update so other connected clients (and this one) can refresh scene state
             let scene_update = serde_json::json!({
                 "type": "scene_updated",
@@ -783,6 +2074,19 @@ impl EditorState {
                     "north_dir": scene.north_direction,
                 }
             });
+
+            // Update database if available
+            if let Err(e) = self.persist(PendingWrite::Scene(SceneUpdate {
+                id: scene_id as i64,
+                name: None,
+                file_path: None,
+                initial_view_x: FieldUpdate::Keep,
+                initial_view_y: FieldUpdate::Keep,
+                north_direction: FieldUpdate::Set(d),
+                pov: FieldUpdate::Keep,
+            })).await {
+                eprintln!("Failed to update scene north direction in database: {}", e);
+            }
             let _ = tx.send(Message::Text(scene_update.to_string()));
             let _ = tx.send(Message::Text(r#"{"type": "success", "message": "North direction saved."}"#.to_string()));
         } else {
@@ -791,11 +2095,90 @@ impl EditorState {
         Ok(())
     }
 
+    /// Set one north direction on every scene, written in a single transaction
+    async fn set_north_direction_all(
+        &mut self,
+        direction: f32,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let writes: Vec<PendingWrite> = self.scenes.iter().map(|scene| PendingWrite::Scene(SceneUpdate {
+            id: scene.id as i64,
+            name: None,
+            file_path: None,
+            initial_view_x: FieldUpdate::Keep,
+            initial_view_y: FieldUpdate::Keep,
+            north_direction: FieldUpdate::Set(direction),
+            pov: FieldUpdate::Keep,
+        })).collect();
+
+        if self.deferred {
+            self.pending_writes.extend(writes);
+        } else if let Some(ref db) = self.db {
+            if let Err(e) = db.apply_pending_writes(&writes).await {
+                eprintln!("Failed to update north direction of all scenes: {}", e);
+                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to save north direction."}"#.to_string()));
+                return Ok(());
+            }
+        }
+
+        for scene in &mut self.scenes {
+            scene.north_direction = Some(direction);
+        }
+        let msg = serde_json::json!({
+            "type": "success",
+            "message": format!("North direction saved for {} scenes.", self.scenes.len()),
+            "updated": self.scenes.len()
+        });
+        let _ = tx.send(Message::Text(msg.to_string()));
+        Ok(())
+    }
+
+    /// Clear the initial view, north direction and field of view of a scene
+    async fn reset_scene_calibration(
+        &mut self,
+        scene_id: i32,
+        tx: &OutboundSender
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(scene) = self.scenes.iter_mut().find(|s| s.id == scene_id) else {
+            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Scene not found."}"#.to_string()));
+            return Ok(());
+        };
+        scene.initial_view = None;
+        scene.north_direction = None;
+
+        let scene_update = serde_json::json!({
+            "type": "scene_updated",
+            "scene": {
+                "id": scene.id,
+                "name": scene.name,
+                "file_path": scene.file_path,
+                "initial_view_x": null,
+                "initial_view_y": null,
+                "north_dir": null,
+            }
+        });
+
+        if let Err(e) = self.persist(PendingWrite::Scene(SceneUpdate {
+            id: scene_id as i64,
+            name: None,
+            file_path: None,
+            initial_view_x: FieldUpdate::Clear,
+            initial_view_y: FieldUpdate::Clear,
+            north_direction: FieldUpdate::Clear,
+            pov: FieldUpdate::Clear,
+        })).await {
+            eprintln!("Failed to reset scene calibration in database: {}", e);
+        }
+        let _ = tx.send(Message::Text(scene_update.to_string()));
+        let _ = tx.send(Message::Text(r#"{"type": "success", "message": "Scene calibration reset."}"#.to_string()));
+        Ok(())
+    }
+
     /// Change the tour address/location
     async fn change_address(
         &mut self,
         _address: String,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // TODO: Store address in tour metadata
         let _ = tx.send(Message::Text(r#"{"type": "success", "message": "Address updated."}"#.to_string()));
@@ -806,7 +2189,7 @@ impl EditorState {
     async fn add_floorplan(
         &mut self,
         file_path: String,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Persist floorplan as an asset (is_floorplan=1) and update tour flags
         if let Some(ref db) = self.db {
@@ -826,6 +2209,7 @@ impl EditorState {
                 .execute(&*db.pool)
                 .await?;
 
+            self.wrote_tour_rows = true;
             let msg = serde_json::json!({
                 "type": "floorplan_added",
                 "floorplan": {"id": floorplan_id, "file_path": file_path}
@@ -841,7 +2225,7 @@ impl EditorState {
     async fn delete_floorplan(
         &mut self,
         floorplan_id: i32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(ref db) = self.db {
             sqlx::query("DELETE FROM assets WHERE id = ?1 AND tour_id = ?2 AND is_floorplan = 1")
@@ -853,6 +2237,7 @@ impl EditorState {
                 .bind(self.tour_id)
                 .execute(&*db.pool)
                 .await?;
+            self.wrote_tour_rows = true;
             let _ = tx.send(Message::Text(format!("{{\"type\":\"floorplan_deleted\",\"floorplan_id\":{}}}", floorplan_id)));
         } else {
             let _ = tx.send(Message::Text(r#"{"type":"error","message":"Database not available."}"#.to_string()));
@@ -864,7 +2249,7 @@ impl EditorState {
     async fn add_floorplan_connection(
         &mut self,
     scene_id: i32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Placeholder: future implementation will store per-scene coordinates on floorplan
     let _ = tx.send(Message::Text(format!("{{\"type\":\"floorplan_connection_added\",\"scene_id\":{}}}", scene_id)));
@@ -875,13 +2260,13 @@ impl EditorState {
     async fn delete_floorplan_connection(
         &mut self,
     scene_id: i32,
-        tx: &mpsc::UnboundedSender<Message>
+        tx: &OutboundSender
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let _ = tx.send(Message::Text(format!("{{\"type\":\"floorplan_connection_deleted\",\"scene_id\":{}}}", scene_id)));
         Ok(())
     }
 
-    async fn add_floorplan_marker(&mut self, scene_id: i32, x: f32, y: f32, tx: &mpsc::UnboundedSender<Message>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn add_floorplan_marker(&mut self, scene_id: i32, x: f32, y: f32, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(ref db) = self.db {
             // Get floorplan id from tour row
             let row = sqlx::query("SELECT floorplan_id FROM tours WHERE id = ?1")
@@ -899,6 +2284,7 @@ impl EditorState {
                     .execute(&*db.pool)
                     .await?;
                 let marker_id = result.last_insert_rowid();
+                self.wrote_tour_rows = true;
                 let msg = serde_json::json!({
                     "type": "floorplan_marker_added",
                     "marker": { "id": marker_id, "scene_id": scene_id, "position": [x, y] }
@@ -908,7 +2294,7 @@ impl EditorState {
         }
         Ok(())
     }
-    async fn update_floorplan_marker(&mut self, marker_id: i32, x: f32, y: f32, tx: &mpsc::UnboundedSender<Message>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn update_floorplan_marker(&mut self, marker_id: i32, x: f32, y: f32, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(ref db) = self.db {
             sqlx::query("UPDATE connections SET world_lon = ?1, world_lat = ?2 WHERE id = ?3 AND is_floorplan = 1")
                 .bind(x)
@@ -916,6 +2302,7 @@ impl EditorState {
                 .bind(marker_id as i64)
                 .execute(&*db.pool)
                 .await?;
+            self.wrote_tour_rows = true;
             let msg = serde_json::json!({
                 "type": "floorplan_marker_updated",
                 "marker_id": marker_id,
@@ -925,12 +2312,13 @@ impl EditorState {
         }
         Ok(())
     }
-    async fn delete_floorplan_marker(&mut self, marker_id: i32, tx: &mpsc::UnboundedSender<Message>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn delete_floorplan_marker(&mut self, marker_id: i32, tx: &OutboundSender) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(ref db) = self.db {
             sqlx::query("DELETE FROM connections WHERE id = ?1 AND is_floorplan = 1")
                 .bind(marker_id as i64)
                 .execute(&*db.pool)
                 .await?;
+            self.wrote_tour_rows = true;
             let _ = tx.send(Message::Text(format!("{{\"type\":\"floorplan_marker_deleted\",\"marker_id\":{}}}", marker_id)));
         }
         Ok(())
@@ -970,12 +2358,15 @@ impl EditorState {
                                     (0.0, 0.0)
                                 };
                                 let name = conn_json["name"].as_str().map(|s| s.to_string());
-                                let ctype = conn_json["connection_type"].as_str().unwrap_or("Transition");
+                                let connection_type = conn_json["connection_type"].as_str()
+                                    .and_then(ConnectionType::parse)
+                                    .unwrap_or(ConnectionType::Transition);
                                 let icon_index = conn_json["icon_index"].as_i64().map(|v| v as i32);
+                                let transition_style = conn_json["transition_style"].as_str().and_then(TransitionStyle::parse);
                                 
                                 connections.push(Connection {
                                     id: conn_json["id"].as_i64().unwrap_or(0) as i32,
-                                    connection_type: if ctype.eq_ignore_ascii_case("closeup") { ConnectionType::Closeup } else { ConnectionType::Transition },
+                                    connection_type,
                                     target_scene_id: target_id as i32,
                                     position: Coordinates {
                                         x: position.0 as f32,
@@ -983,6 +2374,13 @@ impl EditorState {
                                     },
                                     name,
                                     icon_index,
+                                    transition_style,
+                                    icon_color: conn_json["icon_color"].as_str().map(|s| s.to_string()),
+                                    icon_scale: conn_json["icon_scale"].as_f64().map(|v| v as f32),
+                                    url_target: conn_json["url_target"].as_str().map(|s| s.to_string()),
+                                    z_index: conn_json["z_index"].as_i64().unwrap_or(0) as i32,
+                                    audio_path: conn_json["audio_path"].as_str().map(|s| s.to_string()),
+                                    created_by: conn_json["created_by"].as_str().map(|s| s.to_string()),
                                 });
                             }
                         }
@@ -1000,6 +2398,15 @@ impl EditorState {
                     
                     // Parse north direction
                     let north_direction = scene_json["north_dir"].as_i64().map(|n| n as f32);
+                    let group_id = scene_json["group_id"].as_i64();
+                    let media_type = MediaType::from_path(&file_path);
+                    let hidden = scene_json["hidden"].as_bool().unwrap_or(false);
+                    let captured_at = scene_json["captured_at"].as_str().map(str::to_string);
+                    let notes = scene_json["notes"].as_str().map(str::to_string);
+                    let min_fov = scene_json["min_fov"].as_f64().map(|f| f as f32);
+                    let max_fov = scene_json["max_fov"].as_f64().map(|f| f as f32);
+                    let created_by = scene_json["created_by"].as_str().map(str::to_string);
+                    let default_icon_index = scene_json["default_icon_index"].as_i64().map(|i| i as i32);
                     
                     let scene = Scene {
                         id: scene_id,
@@ -1008,6 +2415,15 @@ impl EditorState {
                         connections,
                         initial_view,
                         north_direction,
+                        group_id,
+                        media_type,
+                        hidden,
+                        captured_at,
+                        notes,
+                        min_fov,
+                        max_fov,
+                        created_by,
+                        default_icon_index,
                     };
                     
                     println!("Loaded scene from database: ID={}, name={}", scene_id, scene_name);
@@ -1016,6 +2432,19 @@ impl EditorState {
                 
                 println!("Total scenes loaded: {}", self.scenes.len());
             }
+
+            // Make sure the stored initial scene still exists; a stale id leaves the viewer blank
+            let stored_initial = tour_data["initial_scene_id"].as_i64().map(|id| id as i32);
+            let initial_valid = stored_initial.is_some_and(|id| self.scenes.iter().any(|s| s.id == id));
+            if initial_valid {
+                self.current_scene_id = stored_initial;
+            } else if let Some(first) = self.scenes.first().map(|s| s.id) {
+                eprintln!("Warning: tour {} has initial scene {:?} which is not among its scenes; reassigning to {}", self.tour_id, stored_initial, first);
+                if let Err(e) = database.set_initial_scene(self.tour_id, first as i64).await {
+                    eprintln!("Failed to persist reassigned initial scene: {}", e);
+                }
+                self.current_scene_id = Some(first);
+            }
         }
     // Build fast indices after loading
     self.rebuild_indices();
@@ -1034,96 +2463,431 @@ impl EditorState {
 // (Removed reciprocal angle helpers; logic now handled client-side only.)
 
 /// Handle file upload for assets
-pub async fn upload_asset_handler(mut multipart: Multipart) -> impl IntoResponse {
-    println!("Upload handler called");
+/// Maps the client's upload `type` to a folder under `assets/` (only known folders are allowed)
+pub(crate) fn upload_subdir(kind: &str) -> &'static str {
+    match kind.trim().to_lowercase().as_str() {
+        "closeups" => "closeups",
+        "floorplan" => "floorplans",
+        "branding" => "branding",
+        "video" => "video",
+        "audio" => "audio",
+        _ => "insta360",
+    }
+}
 
-    // Collect fields (order is not guaranteed across all clients)
-    let mut dest_subdir = String::from("insta360"); // default folder for scenes
-    let mut file_bytes: Option<Vec<u8>> = None;
-    let mut orig_filename: Option<String> = None;
+/// True if the bytes start like an image the viewer can display (JPEG, PNG or WebP)
+pub(crate) fn is_supported_image(data: &[u8]) -> bool {
+    matches!(
+        image::guess_format(data),
+        Ok(image::ImageFormat::Jpeg | image::ImageFormat::Png | image::ImageFormat::WebP)
+    )
+}
 
-    loop {
-        match multipart.next_field().await {
-            Ok(Some(field)) => {
-                let name = field.name().unwrap_or("").to_string();
-                println!("Processing field: {}", name);
+/// True if the bytes start like an MP4 (`ftyp` box) or WebM (EBML header) file
+pub(crate) fn is_supported_video(data: &[u8]) -> bool {
+    data.get(4..8) == Some(b"ftyp".as_slice()) || data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3])
+}
 
-                if name == "type" {
-                    match field.text().await {
-                        Ok(t) => {
-                            let t = t.trim().to_lowercase();
-                            println!("Upload type: {}", t);
-                            // Only allow known subdirs
-                            if t == "closeups" { dest_subdir = "closeups".to_string(); }
-                            else if t == "floorplan" { dest_subdir = "floorplans".to_string(); }
-                            else { dest_subdir = "insta360".to_string(); }
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to read type field: {}", e);
-                        }
-                    }
-                } else if name == "file" {
-                    let filename = field.file_name().unwrap_or("uploaded_file").to_string();
-                    println!("Uploading file: {}", filename);
-                    match field.bytes().await {
-                        Ok(data) => {
-                            println!("File data read successfully, size: {} bytes", data.len());
-                            file_bytes = Some(data.to_vec());
-                            orig_filename = Some(filename);
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to read file data: Error parsing `multipart/form-data` request: {}", e);
-                            return (StatusCode::BAD_REQUEST, format!("Failed to read file data: Error parsing `multipart/form-data` request: {}", e)).into_response();
-                        }
-                    }
-                } else {
-                    // Read and discard other fields to advance the stream
-                    if let Err(e) = field.bytes().await { eprintln!("Error reading field '{}': {}", name, e); }
-                }
-            }
-            Ok(None) => { break; }
-            Err(e) => {
-                eprintln!("Failed to get next field: Error parsing `multipart/form-data` request: {}", e);
-                return (StatusCode::BAD_REQUEST, format!("Failed to read multipart data: {}", e)).into_response();
-            }
-        }
+/// Checks an upload bound for `assets/video/`: mp4/webm name, matching contents, within `max_bytes`.
+pub(crate) fn check_video_upload(filename: &str, data: &[u8], max_bytes: u64) -> Result<(), (StatusCode, String)> {
+    if data.len() as u64 > max_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Video exceeds the {} byte limit", max_bytes)));
+    }
+    if MediaType::from_path(filename) != MediaType::Video || !is_supported_video(data) {
+        return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Video scenes must be MP4 or WebM files".to_string()));
     }
+    Ok(())
+}
 
-    // After collecting fields, save if we have a file
-    if let (Some(data), Some(filename)) = (file_bytes, orig_filename) {
-        // Generate unique filename
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+/// True if the bytes start like an MP3 (ID3 tag or frame sync), Ogg, WAV or M4A file
+pub(crate) fn is_supported_audio(data: &[u8]) -> bool {
+    data.starts_with(b"ID3")
+        || data.first() == Some(&0xFF) && data.get(1).is_some_and(|b| b & 0xE0 == 0xE0)
+        || data.starts_with(b"OggS")
+        || data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE".as_slice())
+        || data.get(4..8) == Some(b"ftyp".as_slice())
+}
+
+/// Checks an upload bound for `assets/audio/`: mp3/ogg/wav/m4a name, matching contents, within `max_bytes`.
+pub(crate) fn check_audio_upload(filename: &str, data: &[u8], max_bytes: u64) -> Result<(), (StatusCode, String)> {
+    if data.len() as u64 > max_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Audio exceeds the {} byte limit", max_bytes)));
+    }
+    let ext = StdPath::new(filename).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if !AUDIO_EXTENSIONS.contains(&ext.as_str()) || !is_supported_audio(data) {
+        return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Hotspot sounds must be MP3, Ogg, WAV or M4A files".to_string()));
+    }
+    Ok(())
+}
+
+/// True if `path` is an uploaded sound (`/assets/audio/...`) whose file is on disk
+pub(crate) fn is_audio_asset(path: &str) -> bool {
+    let rel = path.trim_start_matches('/');
+    rel.starts_with("assets/audio/")
+        && !rel.split('/').any(|part| part == "..")
+        && StdPath::new(rel).is_file()
+}
+
+/// Judges an image's projection from its header dimensions; `None` if they can't be read
+pub(crate) fn detect_projection(data: &[u8]) -> Option<Projection> {
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(data))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()?;
+    if height == 0 {
+        return None;
+    }
+    let ratio = width as f64 / height as f64;
+    Some(if (ratio / 2.0 - 1.0).abs() <= EQUIRECT_RATIO_TOLERANCE { Projection::Equirectangular } else { Projection::Flat })
+}
+
+/// Detects the projection of an image upload bound for `subdir`. Scenes (`insta360`) must be panoramas:
+/// a flat image is refused with `422` when `reject_flat` is set and gets a warning otherwise.
+pub(crate) fn check_upload_projection(subdir: &str, data: &[u8], reject_flat: bool)
+    -> Result<(Option<Projection>, Option<String>), (StatusCode, String)> {
+    if subdir == "video" {
+        return Ok((None, None));
+    }
+    let projection = detect_projection(data);
+    if subdir != "insta360" || projection != Some(Projection::Flat) {
+        return Ok((projection, None));
+    }
+    let message = "Scene image is not a 2:1 equirectangular panorama and will look distorted in the viewer".to_string();
+    if reject_flat {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, message));
+    }
+    Ok((projection, Some(message)))
+}
+
+/// The on-disk path of an uploaded scene panorama (`/assets/insta360/<file>`), or `None` for
+/// anything else, including paths with `..` or other non-plain components.
+pub(crate) fn scene_upload_path(file_path: &str) -> Option<std::path::PathBuf> {
+    let path = StdPath::new(file_path.strip_prefix('/')?);
+    let plain = path.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    (plain && path.starts_with("assets/insta360") && path.components().count() > 2).then(|| path.to_path_buf())
+}
+
+/// Shifts an equirectangular panorama right by `yaw_offset_deg` with wraparound (what was at
+/// yaw 0 ends up at `yaw_offset_deg`) and, with `flip_vertical`, turns it upside down.
+pub(crate) fn correct_equirect(image: &image::DynamicImage, yaw_offset_deg: f32, flip_vertical: bool) -> image::DynamicImage {
+    let mut corrected = if flip_vertical { image.flipv() } else { image.clone() };
+    let width = corrected.width();
+    let shift = ((yaw_offset_deg / 360.0 * width as f32).round() as i64).rem_euclid(width.max(1) as i64) as u32;
+    if shift > 0 {
+        let source = corrected.clone();
+        image::imageops::replace(&mut corrected, &source.crop_imm(0, 0, width - shift, source.height()), shift as i64, 0);
+        image::imageops::replace(&mut corrected, &source.crop_imm(width - shift, 0, shift, source.height()), 0, 0);
+    }
+    corrected
+}
+
+/// Downscales an image with more than `max_megapixels` pixels so it fits, keeping its aspect
+/// ratio and format; smaller images (or a budget of 0) are returned untouched.
+///
+/// # Returns
+/// * `Ok((bytes, width, height))` - The image to store and its size in pixels.
+/// * `Err(ImageError)` - If the image can't be decoded or re-encoded.
+pub(crate) fn fit_to_pixel_budget(data: Vec<u8>, max_megapixels: f64) -> Result<(Vec<u8>, u32, u32), image::ImageError> {
+    let reader = image::ImageReader::new(std::io::Cursor::new(&data)).with_guessed_format().map_err(image::ImageError::IoError)?;
+    let format = reader.format().unwrap_or(image::ImageFormat::Jpeg);
+    let (width, height) = reader.into_dimensions()?;
+    let pixels = width as f64 * height as f64;
+    let budget = max_megapixels * 1_000_000.0;
+    if budget <= 0.0 || pixels <= budget {
+        return Ok((data, width, height));
+    }
+
+    let scale = (budget / pixels).sqrt();
+    let fitted_width = ((width as f64 * scale).floor() as u32).max(1);
+    let fitted_height = ((height as f64 * scale).floor() as u32).max(1);
+    let resized = image::load_from_memory_with_format(&data, format)?
+        .resize_exact(fitted_width, fitted_height, image::imageops::FilterType::Triangle);
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    resized.write_to(&mut encoded, format)?;
+    println!("Downscaled closeup from {}x{} to {}x{} ({} MP budget)", width, height, fitted_width, fitted_height, max_megapixels);
+    Ok((encoded.into_inner(), fitted_width, fitted_height))
+}
+
+/// Fits an upload bound for `closeups/` to the pixel budget. Other uploads, and closeups that
+/// can't be decoded, are passed through without dimensions; failures are logged, not fatal.
+pub(crate) async fn fit_closeup_upload(subdir: &str, data: Vec<u8>, max_megapixels: f64) -> (Vec<u8>, Option<(u32, u32)>) {
+    if subdir != "closeups" {
+        return (data, None);
+    }
+    let original = data.clone();
+    match tokio::task::spawn_blocking(move || fit_to_pixel_budget(data, max_megapixels)).await {
+        Ok(Ok((fitted, width, height))) => (fitted, Some((width, height))),
+        Ok(Err(e)) => {
+            eprintln!("Failed to fit closeup to the pixel budget: {}", e);
+            (original, None)
+        }
+        Err(e) => {
+            eprintln!("Closeup resize task failed: {}", e);
+            (original, None)
+        }
+    }
+}
+
+/// Longest side of a closeup picker thumbnail, in pixels
+const CLOSEUP_THUMBNAIL_SIZE: u32 = 320;
+
+/// Public path of the picker thumbnail for a closeup image under `/assets/closeups/`.
+/// Keyed by the whole file name, so `plaque.jpg` and `plaque.png` get their own thumbnails.
+pub(crate) fn closeup_thumbnail_path(file_path: &str) -> Option<String> {
+    let name = file_path.strip_prefix("/assets/closeups/").filter(|n| !n.is_empty() && !n.contains('/'))?;
+    Some(format!("/assets/closeups/thumbs/{}.jpg", name))
+}
+
+/// The closeup's thumbnail path, if one has been generated for it
+pub(crate) fn existing_closeup_thumbnail(file_path: &str) -> Option<String> {
+    closeup_thumbnail_path(file_path).filter(|thumb| StdPath::new(thumb.trim_start_matches('/')).exists())
+}
+
+/// Writes a downscaled JPEG of an uploaded closeup image under `assets_root`.
+///
+/// # Returns
+/// * `Ok(Some(path))` - Public path of the thumbnail (an existing one is reused).
+/// * `Ok(None)` - If `file_path` isn't a closeup upload.
+/// * `Err(ImageError)` - If the image can't be decoded or the thumbnail can't be written.
+pub(crate) fn write_closeup_thumbnail(assets_root: &StdPath, file_path: &str, data: &[u8]) -> Result<Option<String>, image::ImageError> {
+    let Some(thumb_path) = closeup_thumbnail_path(file_path) else { return Ok(None) };
+    let dest = assets_root.join(thumb_path.trim_start_matches("/assets/"));
+    if dest.exists() {
+        return Ok(Some(thumb_path));
+    }
+    let thumbnail = image::load_from_memory(data)?
+        .thumbnail(CLOSEUP_THUMBNAIL_SIZE, CLOSEUP_THUMBNAIL_SIZE)
+        .to_rgb8();
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(image::ImageError::IoError)?;
+    }
+    thumbnail.save_with_format(&dest, image::ImageFormat::Jpeg)?;
+    Ok(Some(thumb_path))
+}
+
+/// Generates the thumbnail for a stored upload when it went to `closeups/`; failures are logged, not fatal
+pub(crate) async fn closeup_thumbnail_for_upload(subdir: &str, file_path: &str, data: Vec<u8>) -> Option<String> {
+    if subdir != "closeups" {
+        return None;
+    }
+    let path = file_path.to_string();
+    match tokio::task::spawn_blocking(move || write_closeup_thumbnail(StdPath::new("assets"), &path, &data)).await {
+        Ok(Ok(thumbnail)) => thumbnail,
+        Ok(Err(e)) => {
+            eprintln!("Failed to create thumbnail for {}: {}", file_path, e);
+            None
+        }
+        Err(e) => {
+            eprintln!("Thumbnail task failed for {}: {}", file_path, e);
+            None
+        }
+    }
+}
+
+/// Writes uploaded bytes under `assets_root/subdir` and returns the public `/assets/...` path.
+///
+/// When `username` is known and they already uploaded identical bytes (same SHA-256)
+/// whose file is still on disk, that file's path is returned and nothing is written.
+pub(crate) async fn store_upload(
+    db: &crate::database::Database,
+    username: Option<&str>,
+    assets_root: &StdPath,
+    subdir: &str,
+    filename: &str,
+    data: &[u8],
+) -> std::io::Result<String> {
+    let content_hash: String = Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(username) = username {
+        match db.find_upload_by_hash(username, &content_hash).await {
+            Ok(Some(existing)) => {
+                let relative = existing.trim_start_matches("/assets/");
+                if fs::try_exists(assets_root.join(relative)).await.unwrap_or(false) {
+                    println!("Upload matches existing file {}; reusing it", existing);
+                    return Ok(existing);
+                }
+                let _ = db.forget_upload(&existing).await;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to look up upload hash: {}", e),
+        }
+    }
+
+    // Generate unique filename
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
 
-        // Remove extension from original filename to avoid double extensions
-        let base_name = StdPath::new(&filename)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("uploaded_file");
-        let ext = StdPath::new(&filename)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("jpg");
-        let new_filename = format!("uploaded_{}_{}.{}", timestamp, base_name.replace(" ", "_"), ext);
+    // Remove extension from original filename to avoid double extensions
+    let base_name = StdPath::new(filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("uploaded_file");
+    let ext = StdPath::new(filename)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("jpg");
+    let new_filename = format!("uploaded_{}_{}.{}", timestamp, base_name.replace(" ", "_"), ext);
 
-        // Save under selected subdirectory
-        let file_path = format!("assets/{}/{}", dest_subdir, new_filename);
+    // Save under selected subdirectory
+    let dir = assets_root.join(subdir);
+    fs::create_dir_all(&dir).await?;
+    fs::write(dir.join(&new_filename), data).await?;
+    let file_path = format!("/assets/{}/{}", subdir, new_filename);
+    println!("File saved successfully to: {}", file_path);
+
+    if let Some(username) = username {
+        if let Err(e) = db.record_upload(username, &file_path, &content_hash).await {
+            eprintln!("Failed to record upload hash: {}", e);
+        }
+    }
+    Ok(file_path)
+}
+
+/// Caps on one upload form, so a client can't keep the handler reading junk fields forever
+struct FormBudget {
+    fields_left: usize,
+    bytes_left: u64,
+    max_fields: usize,
+    max_bytes: u64,
+}
+
+impl FormBudget {
+    fn new(limits: &crate::config::UploadsConfig) -> Self {
+        Self {
+            fields_left: limits.max_form_fields,
+            bytes_left: limits.max_form_bytes,
+            max_fields: limits.max_form_fields,
+            max_bytes: limits.max_form_bytes,
+        }
+    }
+
+    /// Counts one more field against the limit
+    fn take_field(&mut self) -> Result<(), (StatusCode, String)> {
+        if self.fields_left == 0 {
+            eprintln!("Upload rejected: more than {} form fields", self.max_fields);
+            return Err((StatusCode::BAD_REQUEST, format!("Too many form fields (at most {})", self.max_fields)));
+        }
+        self.fields_left -= 1;
+        Ok(())
+    }
+
+    /// Reads a field's data chunk by chunk, stopping as soon as the form goes over its byte limit
+    async fn read(&mut self, field: &mut Field<'_>) -> Result<Vec<u8>, (StatusCode, String)> {
+        let mut data = Vec::new();
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    let len = chunk.len() as u64;
+                    if len > self.bytes_left {
+                        eprintln!("Upload rejected: form larger than {} bytes", self.max_bytes);
+                        return Err((StatusCode::BAD_REQUEST, format!("Upload form exceeds {} bytes", self.max_bytes)));
+                    }
+                    self.bytes_left -= len;
+                    data.extend_from_slice(&chunk);
+                }
+                Ok(None) => return Ok(data),
+                Err(e) => {
+                    eprintln!("Failed to read field data: Error parsing `multipart/form-data` request: {}", e);
+                    return Err((StatusCode::BAD_REQUEST, format!("Failed to read field data: Error parsing `multipart/form-data` request: {}", e)));
+                }
+            }
+        }
+    }
+}
+
+pub async fn upload_asset_handler(State(state): State<crate::AppState>, headers: HeaderMap, mut multipart: Multipart) -> impl IntoResponse {
+    println!("Upload handler called");
+
+    // Collect fields (order is not guaranteed across all clients)
+    let mut dest_subdir = String::from("insta360"); // default folder for scenes
+    let mut file_bytes: Option<Vec<u8>> = None;
+    let mut orig_filename: Option<String> = None;
+    let mut form = FormBudget::new(&state.config.uploads);
+
+    loop {
+        match multipart.next_field().await {
+            Ok(Some(mut field)) => {
+                if let Err(rejection) = form.take_field() {
+                    return rejection.into_response();
+                }
+                let name = field.name().unwrap_or("").to_string();
+                println!("Processing field: {}", name);
 
-        // Ensure the directory exists
-        if let Some(parent) = StdPath::new(&file_path).parent() {
-            if let Err(e) = fs::create_dir_all(parent).await {
-                eprintln!("Failed to create directory: {}", e);
+                if name == "type" {
+                    match form.read(&mut field).await {
+                        Ok(t) => {
+                            let t = String::from_utf8_lossy(&t).trim().to_lowercase();
+                            println!("Upload type: {}", t);
+                            dest_subdir = upload_subdir(&t).to_string();
+                        }
+                        Err(rejection) => return rejection.into_response(),
+                    }
+                } else if name == "file" {
+                    let filename = field.file_name().unwrap_or("uploaded_file").to_string();
+                    println!("Uploading file: {}", filename);
+                    match form.read(&mut field).await {
+                        Ok(data) => {
+                            println!("File data read successfully, size: {} bytes", data.len());
+                            file_bytes = Some(data);
+                            orig_filename = Some(filename);
+                        }
+                        Err(rejection) => return rejection.into_response(),
+                    }
+                } else {
+                    // Read and discard other fields to advance the stream (still counted against the budget)
+                    if let Err(rejection) = form.read(&mut field).await {
+                        return rejection.into_response();
+                    }
+                }
+            }
+            Ok(None) => { break; }
+            Err(e) => {
+                eprintln!("Failed to get next field: Error parsing `multipart/form-data` request: {}", e);
+                return (StatusCode::BAD_REQUEST, format!("Failed to read multipart data: {}", e)).into_response();
             }
         }
+    }
 
-        match fs::write(&file_path, &data).await {
-            Ok(_) => {
-                println!("File saved successfully to: {}", file_path);
+    // After collecting fields, save if we have a file
+    if let (Some(data), Some(filename)) = (file_bytes, orig_filename) {
+        if dest_subdir == "video" {
+            if let Err(rejection) = check_video_upload(&filename, &data, state.config.uploads.max_video_bytes) {
+                return rejection.into_response();
+            }
+        } else if dest_subdir == "audio" {
+            if let Err(rejection) = check_audio_upload(&filename, &data, state.config.uploads.max_audio_bytes) {
+                return rejection.into_response();
+            }
+        }
+        let (projection, warning) = match check_upload_projection(&dest_subdir, &data, state.config.uploads.reject_flat_scenes) {
+            Ok(detected) => detected,
+            Err(rejection) => return rejection.into_response(),
+        };
+        let (data, dimensions) = fit_closeup_upload(&dest_subdir, data, state.config.uploads.max_closeup_megapixels).await;
+        // Signed-in uploads are deduplicated per user; anonymous ones are always written
+        let username = crate::authenticate_request(&headers, &state.database).await.ok();
+        match store_upload(&state.database, username.as_deref(), StdPath::new("assets"), &dest_subdir, &filename, &data).await {
+            Ok(file_path) => {
+                let detected_north = if state.config.editor.infer_north && dest_subdir == "insta360" {
+                    read_exif_heading(&data)
+                } else {
+                    None
+                };
+                let captured_at = if dest_subdir == "insta360" { read_exif_capture_date(&data) } else { None };
+                let thumbnail_path = closeup_thumbnail_for_upload(&dest_subdir, &file_path, data).await;
                 let response = UploadResponse {
-                    file_path: format!("/{}", file_path),
+                    file_path,
                     message: "File uploaded successfully".to_string(),
+                    detected_north,
+                    thumbnail_path,
+                    captured_at,
+                    projection,
+                    warning,
+                    width: dimensions.map(|(width, _)| width),
+                    height: dimensions.map(|(_, height)| height),
                 };
                 return (StatusCode::OK, Json(response)).into_response();
             }
@@ -1139,3 +2903,1266 @@ pub async fn upload_asset_handler(mut multipart: Multipart) -> impl IntoResponse
 }
 
 // Derivative generation removed
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn setup_test_db() -> Database {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        let schema_sql = include_str!("../schema.sql");
+        sqlx::raw_sql(schema_sql).execute(&pool).await.unwrap();
+        Database::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_stale_initial_scene_reassigned_on_load() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let first = db.save_scene(tour_id, "First", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let second = db.save_scene(tour_id, "Second", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, second).await.unwrap();
+
+        // Remove the initial scene behind the editor's back
+        db.delete_scene(second).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+
+        assert_eq!(state.current_scene_id, Some(first as i32));
+        let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        assert_eq!(tour["initial_scene_id"].as_i64(), Some(first));
+    }
+
+    #[tokio::test]
+    async fn test_transition_style_validated_and_exported() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let edit = |style: &str| EditorAction::EditConnection(ConnectionEdit {
+            connection_id: conn_id as i32,
+            new_asset_id: b as i32,
+            new_position: (10.0, 0.0),
+            new_name: None,
+            new_icon_type: None,
+            new_file_path: None,
+            new_transition_style: Some(style.to_string()),
+            new_icon_color: None,
+            new_icon_scale: None,
+            new_url_target: None,
+            new_connection_type: None,
+            new_z_index: None,
+            new_audio_path: None,
+        });
+
+        // Invalid style is rejected and nothing is persisted
+        state.handle_action(edit("spin"), &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(text) => assert!(text.contains("Invalid transition style"), "unexpected reply {}", text),
+            other => panic!("unexpected message {:?}", other),
+        }
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        let conn = exported["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .unwrap();
+        assert_eq!(conn["transition_style"], "fade", "unset style exports as the default");
+
+        // Valid style round-trips through export
+        state.handle_action(edit("slide"), &tx).await.unwrap();
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        let conn = exported["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .unwrap();
+        assert_eq!(conn["transition_style"], "slide");
+
+        // The editor state sends the same lowercase names
+        let editor_conn = serde_json::to_value(&state.scenes.iter().find(|s| s.id as i64 == a).unwrap().connections[0]).unwrap();
+        assert_eq!(editor_conn["transition_style"], "slide");
+        for style in [TransitionStyle::Fade, TransitionStyle::Slide, TransitionStyle::None] {
+            assert_eq!(serde_json::to_value(style).unwrap(), style.as_str());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deleting_initial_scene_picks_lowest_id() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let first = db.save_scene(tour_id, "First", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let second = db.save_scene(tour_id, "Second", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let third = db.save_scene(tour_id, "Third", "/assets/insta360/c.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, second).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        // In-memory order must not matter
+        state.scenes.reverse();
+        state.rebuild_indices();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let initial_changes = |rx: &mut tokio::sync::mpsc::Receiver<Message>| {
+            let mut changes = Vec::new();
+            while let Ok(Message::Text(t)) = rx.try_recv() {
+                let reply: serde_json::Value = serde_json::from_str(&t).unwrap();
+                if reply["type"] == "initial_scene_changed" {
+                    changes.push(reply["scene_id"].clone());
+                }
+            }
+            changes
+        };
+
+        state.handle_action(EditorAction::DeleteScene { scene_id: second as i32 }, &tx).await.unwrap();
+        assert_eq!(initial_changes(&mut rx), vec![serde_json::json!(first)]);
+        let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        assert_eq!(tour["initial_scene_id"].as_i64(), Some(first));
+
+        // Deleting a non-initial scene leaves the initial scene alone
+        state.handle_action(EditorAction::DeleteScene { scene_id: third as i32 }, &tx).await.unwrap();
+        assert!(initial_changes(&mut rx).is_empty());
+
+        state.handle_action(EditorAction::DeleteScene { scene_id: first as i32 }, &tx).await.unwrap();
+        assert_eq!(initial_changes(&mut rx), vec![serde_json::Value::Null]);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_upload_reuses_existing_file() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let root = std::path::PathBuf::from("target/test_uploads").join(uuid::Uuid::new_v4().to_string());
+        let bytes = b"same panorama bytes".to_vec();
+
+        let first = store_upload(&db, Some("testuser"), &root, "insta360", "hall.jpg", &bytes).await.unwrap();
+        let second = store_upload(&db, Some("testuser"), &root, "insta360", "hall copy.jpg", &bytes).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(std::fs::read_dir(root.join("insta360")).unwrap().count(), 1);
+
+        // Different content is still stored
+        store_upload(&db, Some("testuser"), &root, "insta360", "kitchen.jpg", b"other bytes").await.unwrap();
+        assert_eq!(std::fs::read_dir(root.join("insta360")).unwrap().count(), 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_delete_connections_batch_reports_missing() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let first = db.save_connection(tour_id, a, Some(b), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let second = db.save_connection(tour_id, b, Some(a), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let kept = db.save_connection(tour_id, a, Some(b), 60.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::DeleteConnections {
+            connection_ids: vec![first as i32, 9999, second as i32],
+        }, &tx).await.unwrap();
+
+        let reply: serde_json::Value = match rx.try_recv().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(reply["type"], "connections_deleted");
+        assert_eq!(reply["connection_ids"], serde_json::json!([first, second]));
+        assert_eq!(reply["not_found"], serde_json::json!([9999]));
+        assert!(rx.try_recv().is_err(), "a single message for the batch");
+
+        let remaining: Vec<i64> = db.get_scene_connections(tour_id, a).await.unwrap().iter()
+            .chain(db.get_scene_connections(tour_id, b).await.unwrap().iter())
+            .filter_map(|c| c["id"].as_i64())
+            .collect();
+        assert_eq!(remaining, vec![kept]);
+        assert_eq!(state.connection_index.len(), 1);
+        assert!(state.connection_index.contains_key(&(kept as i32)));
+    }
+
+    #[tokio::test]
+    async fn test_propagate_connection_to_matching_scenes() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let hallway = db.save_scene(tour_id, "Hallway", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let unit1 = db.save_scene(tour_id, "Unit 1 Room", "/assets/insta360/u1.jpg", None, None, None).await.unwrap();
+        let unit2 = db.save_scene(tour_id, "Unit 2 Room", "/assets/insta360/u2.jpg", None, None, None).await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let source = db.save_connection(tour_id, unit1, Some(hallway), 120.0, -5.0, ConnectionType::Transition, Some("To hallway"), None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::PropagateConnection {
+            connection_id: source as i32,
+            name_pattern: "Unit".to_string(),
+        }, &tx).await.unwrap();
+
+        let reply: serde_json::Value = match rx.try_recv().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(reply["type"], "connection_propagated");
+        assert_eq!(reply["scene_ids"], serde_json::json!([unit2]));
+        assert_eq!(reply["connection_ids"].as_array().unwrap().len(), 1);
+
+        let copies = db.get_scene_connections(tour_id, unit2).await.unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0]["target_scene_id"].as_i64(), Some(hallway));
+        assert_eq!(copies[0]["name"], "To hallway");
+        assert!(db.get_scene_connections(tour_id, lobby).await.unwrap().is_empty());
+        assert_eq!(db.get_scene_connections(tour_id, unit1).await.unwrap().len(), 1, "source scene not duplicated");
+
+        assert!(matches_name_pattern("Unit 12 Room", "unit*room"));
+        assert!(!matches_name_pattern("Lobby", "Unit*"));
+
+        // A matching scene at the connection cap refuses the whole propagation
+        state.limits.max_connections_per_scene = 1;
+        state.handle_action(EditorAction::PropagateConnection {
+            connection_id: source as i32,
+            name_pattern: "*".to_string(),
+        }, &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(reply["code"], "limit_reached");
+        assert!(db.get_scene_connections(tour_id, lobby).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_new_scene_uses_configured_view_defaults() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+
+        let config = crate::config::EditorConfig {
+            default_fov: 90.0,
+            default_view_yaw: 45.0,
+            default_view_pitch: -10.0,
+            ..Default::default()
+        };
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.scene_defaults = SceneDefaults::from_config(&config);
+        state.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::AddScene {
+            name: "Lobby".to_string(),
+            file_path: "/assets/insta360/lobby.jpg".to_string(),
+            north_direction: None,
+            captured_at: None,
+        }, &tx).await.unwrap();
+
+        let scene = state.scenes.last().unwrap();
+        assert_eq!(scene.initial_view.as_ref().map(|v| (v.x, v.y)), Some((45.0, -10.0)));
+
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        let stored = &tour["scenes"][0];
+        assert_eq!(stored["initial_view_x"].as_f64(), Some(45.0));
+        assert_eq!(stored["initial_view_y"].as_f64(), Some(-10.0));
+        assert_eq!(stored["initial_fov"].as_f64(), Some(90.0));
+    }
+
+    #[tokio::test]
+    async fn test_scene_name_collisions_rejected_or_suffixed() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let add = |name: &str| EditorAction::AddScene {
+            name: name.to_string(),
+            file_path: "/assets/insta360/room.jpg".to_string(),
+            north_direction: None,
+            captured_at: None,
+        };
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.scene_name_collision = SceneNameCollision::Reject;
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        state.handle_action(add("Lobby"), &tx).await.unwrap();
+        state.handle_action(add("Kitchen"), &tx).await.unwrap();
+        while rx.try_recv().is_ok() {}
+
+        state.handle_action(add("Lobby"), &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(reply["type"], "error");
+        assert_eq!(reply["field"], "data.name");
+        let kitchen = state.scenes[1].id;
+        state.handle_action(EditorAction::UpdateSceneName { scene_id: kitchen, name: "Lobby".to_string() }, &tx).await.unwrap();
+        let names: Vec<&str> = state.scenes.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Lobby", "Kitchen"]);
+        // Renaming a scene to its own name is not a collision
+        state.handle_action(EditorAction::UpdateSceneName { scene_id: kitchen, name: "Kitchen".to_string() }, &tx).await.unwrap();
+        assert_eq!(state.scenes.len(), 2);
+
+        state.scene_name_collision = SceneNameCollision::Suffix;
+        state.handle_action(add("Lobby"), &tx).await.unwrap();
+        state.handle_action(add("Lobby"), &tx).await.unwrap();
+        state.handle_action(EditorAction::UpdateSceneName { scene_id: kitchen, name: "Lobby".to_string() }, &tx).await.unwrap();
+        let names: Vec<&str> = state.scenes.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Lobby", "Lobby (4)", "Lobby (2)", "Lobby (3)"]);
+
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        let mut stored: Vec<&str> = tour["scenes"].as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap()).collect();
+        stored.sort();
+        assert_eq!(stored, vec!["Lobby", "Lobby (2)", "Lobby (3)", "Lobby (4)"]);
+    }
+
+    #[tokio::test]
+    async fn test_tour_limits_reject_extra_scenes_and_connections() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.limits = TourLimits { max_scenes: 2, max_connections_per_scene: 1 };
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let mut last_reply = || -> serde_json::Value {
+            let mut last = None;
+            while let Ok(Message::Text(text)) = rx.try_recv() {
+                last = Some(serde_json::from_str(&text).unwrap());
+            }
+            last.expect("a reply")
+        };
+
+        for name in ["Lobby", "Hall", "Attic"] {
+            state.handle_action(EditorAction::AddScene {
+                name: name.to_string(),
+                file_path: format!("/assets/insta360/{}.jpg", name),
+                north_direction: None,
+                captured_at: None,
+            }, &tx).await.unwrap();
+        }
+        let reply = last_reply();
+        assert_eq!(reply["code"], "limit_reached");
+        assert_eq!(reply["message"], "Tours are limited to 2 scenes");
+        assert_eq!(state.scenes.len(), 2);
+
+        let (lobby, hall) = (state.scenes[0].id, state.scenes[1].id);
+        state.handle_action(EditorAction::AddConnection { start_scene_id: lobby, asset_id: hall, position: (10.0, 0.0), name: None }, &tx).await.unwrap();
+        assert_eq!(last_reply()["type"], "connection_added");
+
+        state.handle_action(EditorAction::AddCloseup {
+            name: "Plaque".to_string(),
+            file_path: "/assets/closeups/plaque.jpg".to_string(),
+            parent_scene_id: lobby,
+            position: (40.0, 0.0),
+            icon_type: None,
+        }, &tx).await.unwrap();
+        let reply = last_reply();
+        assert_eq!(reply["code"], "limit_reached");
+        assert_eq!(reply["message"], "Scenes are limited to 1 connections");
+
+        state.handle_action(EditorAction::AddConnection { start_scene_id: lobby, asset_id: lobby, position: (200.0, 0.0), name: None }, &tx).await.unwrap();
+        assert_eq!(last_reply()["code"], "limit_reached");
+        assert_eq!(db.get_scene_connections(tour_id, lobby as i64).await.unwrap().len(), 1);
+
+        // Other scenes still have room
+        state.handle_action(EditorAction::AddConnection { start_scene_id: hall, asset_id: lobby, position: (190.0, 0.0), name: None }, &tx).await.unwrap();
+        assert_eq!(last_reply()["type"], "connection_added");
+    }
+
+    #[tokio::test]
+    async fn test_set_north_direction_all_updates_every_scene() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        for (name, north) in [("Lobby", Some(10.0)), ("Hall", None), ("Attic", Some(300.0))] {
+            db.save_scene(tour_id, name, &format!("/assets/insta360/{}.jpg", name), None, None, north).await.unwrap();
+        }
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let action = parse_action(serde_json::json!({ "action": "SetNorthDirectionAll", "data": { "direction": 360.0 } }));
+        assert_eq!(action.unwrap_err()[0].field, "data.direction");
+
+        state.handle_action(EditorAction::SetNorthDirectionAll { direction: 135.0 }, &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv() {
+            Ok(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        assert_eq!(reply["type"], "success");
+        assert_eq!(reply["updated"].as_u64(), Some(3));
+        assert!(rx.try_recv().is_err(), "expected a single message");
+
+        assert!(state.scenes.iter().all(|s| s.north_direction == Some(135.0)));
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        let stored: Vec<Option<f64>> = tour["scenes"].as_array().unwrap().iter().map(|s| s["north_dir"].as_f64()).collect();
+        assert_eq!(stored, vec![Some(135.0); 3]);
+    }
+
+    #[tokio::test]
+    async fn test_reset_scene_calibration_clears_view_fields() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::AddScene {
+            name: "Lobby".to_string(),
+            file_path: "/assets/insta360/lobby.jpg".to_string(),
+            north_direction: None,
+            captured_at: None,
+        }, &tx).await.unwrap();
+        let scene_id = state.scenes.last().unwrap().id;
+        state.handle_action(EditorAction::SetInitialView { scene_id, position: (120.0, 15.0), fov: Some(80.0) }, &tx).await.unwrap();
+        state.handle_action(EditorAction::SetNorthDirection { scene_id, direction: 90.0 }, &tx).await.unwrap();
+
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        assert_eq!(tour["scenes"][0]["north_dir"].as_f64(), Some(90.0));
+        assert_eq!(tour["scenes"][0]["initial_fov"].as_f64(), Some(80.0));
+
+        state.handle_action(EditorAction::ResetSceneCalibration { scene_id }, &tx).await.unwrap();
+
+        let scene = state.scenes.iter().find(|s| s.id == scene_id).unwrap();
+        assert!(scene.initial_view.is_none());
+        assert!(scene.north_direction.is_none());
+
+        let tour = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        let stored = &tour["scenes"][0];
+        assert_eq!(stored["initial_view_x"].as_f64(), Some(0.0));
+        assert_eq!(stored["initial_view_y"].as_f64(), Some(0.0));
+        assert!(stored["north_dir"].is_null());
+        assert!(stored["initial_fov"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_add_closeup_rolls_back_when_connection_fails() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let lobby = state.scenes[0].id;
+
+        sqlx::query("CREATE TRIGGER fail_connection_insert BEFORE INSERT ON connections BEGIN SELECT RAISE(ABORT, 'forced failure'); END")
+            .execute(&*db.pool).await.unwrap();
+        state.handle_action(EditorAction::AddCloseup {
+            name: "Plaque".to_string(),
+            file_path: "/assets/closeups/plaque.jpg".to_string(),
+            parent_scene_id: lobby,
+            position: (30.0, 5.0),
+            icon_type: None,
+        }, &tx).await.unwrap();
+
+        let reply: serde_json::Value = match rx.try_recv() {
+            Ok(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        assert_eq!(reply["type"], "error");
+        let closeups: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM assets WHERE tour_id = ?1 AND is_scene = 0")
+            .bind(tour_id).fetch_one(&*db.pool).await.unwrap();
+        assert_eq!(closeups, 0, "closeup asset left behind without its connection");
+        assert!(state.scenes[0].connections.is_empty());
+    }
+
+    #[test]
+    fn test_closeup_thumbnails_are_keyed_by_the_whole_file_name() {
+        assert_eq!(closeup_thumbnail_path("/assets/closeups/plaque.jpg").as_deref(), Some("/assets/closeups/thumbs/plaque.jpg.jpg"));
+        assert_eq!(closeup_thumbnail_path("/assets/closeups/plaque.png").as_deref(), Some("/assets/closeups/thumbs/plaque.png.jpg"));
+        assert_eq!(closeup_thumbnail_path("/assets/closeups/a/plaque.jpg"), None);
+        assert_eq!(closeup_thumbnail_path("/assets/insta360/plaque.jpg"), None);
+    }
+
+    #[tokio::test]
+    async fn test_add_closeup_without_database_replies_with_an_error() {
+        let mut state = EditorState::new(1, "testuser".to_string(), None);
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::AddCloseup {
+            name: "Plaque".to_string(),
+            file_path: "/assets/closeups/plaque.jpg".to_string(),
+            parent_scene_id: 1,
+            position: (30.0, 5.0),
+            icon_type: None,
+        }, &tx).await.unwrap();
+
+        let reply: serde_json::Value = match rx.try_recv() {
+            Ok(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        assert_eq!(reply["type"], "error");
+        assert_eq!(reply["message"], "Database not available for closeup storage");
+    }
+
+    #[tokio::test]
+    async fn test_rotate_scene_shifts_columns_with_wraparound() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+
+        // Every column and row gets its own colour so shifts and flips are visible
+        std::fs::create_dir_all("assets/insta360").unwrap();
+        let file_path = format!("/assets/insta360/rotate_test_{}.png", uuid::Uuid::new_v4().simple());
+        let file = file_path.trim_start_matches('/').to_string();
+        let original = image::RgbImage::from_fn(8, 4, |x, y| image::Rgb([x as u8 * 30, y as u8 * 60, 0]));
+        original.save(&file).unwrap();
+        db.save_scene(tour_id, "Lobby", &file_path, None, None, None).await.unwrap();
+        // A copy of the tour shares the panorama file
+        let copy_id = db.duplicate_tour("testuser", tour_id, None).await.unwrap().unwrap();
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let scene_id = state.scenes[0].id;
+        let reply = |rx: &mut tokio::sync::mpsc::Receiver<Message>| -> serde_json::Value {
+            match rx.try_recv() {
+                Ok(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+                other => panic!("unexpected reply: {:?}", other),
+            }
+        };
+
+        state.handle_action(EditorAction::RotateScene { scene_id, yaw_offset_deg: 90.0, flip_vertical: false }, &tx).await.unwrap();
+        let rotated_reply = reply(&mut rx);
+        assert_eq!(rotated_reply["type"], "scene_rotated");
+        let rotated_path = rotated_reply["file_path"].as_str().unwrap().to_string();
+        assert_ne!(rotated_path, file_path);
+        assert_eq!(state.scenes[0].file_path, rotated_path);
+        let stored = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        assert_eq!(stored["scenes"][0]["file_path"], rotated_path.as_str());
+
+        // 90 degrees of an 8 px wide panorama is 2 columns to the right
+        let rotated = image::open(rotated_path.trim_start_matches('/')).unwrap().to_rgb8();
+        assert_eq!(rotated.dimensions(), (8, 4));
+        for x in 0..8 {
+            assert_eq!(rotated.get_pixel(x, 1)[0], ((x + 6) % 8) as u8 * 30, "column {}", x);
+        }
+        // The source, and the copy still pointing at it, are untouched
+        assert_eq!(image::open(&file).unwrap().to_rgb8(), original);
+        let copy = db.get_tour_with_scenes_by_id(copy_id).await.unwrap().unwrap();
+        assert_eq!(copy["scenes"][0]["file_path"], file_path.as_str());
+
+        state.handle_action(EditorAction::RotateScene { scene_id, yaw_offset_deg: -90.0, flip_vertical: true }, &tx).await.unwrap();
+        let restored_path = reply(&mut rx)["file_path"].as_str().unwrap().to_string();
+        let restored = image::open(restored_path.trim_start_matches('/')).unwrap().to_rgb8();
+        for x in 0..8 {
+            assert_eq!(restored.get_pixel(x, 0).0, [x as u8 * 30, 180, 0], "column {}", x);
+        }
+
+        // Files outside the scene uploads are never written
+        for outside in ["/static/assets/info1_icon.png", "/assets/insta360/../closeups/x.png", "/assets/closeups/x.png"] {
+            sqlx::query("UPDATE assets SET file_path = ?1 WHERE id = ?2").bind(outside).bind(scene_id).execute(&*db.pool).await.unwrap();
+            state.scenes[0].file_path = outside.to_string();
+            state.handle_action(EditorAction::RotateScene { scene_id, yaw_offset_deg: 90.0, flip_vertical: false }, &tx).await.unwrap();
+            assert_eq!(reply(&mut rx)["message"], "Only uploaded scene panoramas can be rotated.", "{}", outside);
+        }
+        for path in [file, rotated_path.trim_start_matches('/').to_string(), restored_path.trim_start_matches('/').to_string()] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_url_target_exports_and_rejects_invalid() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 10.0, 0.0, ConnectionType::Info, Some("Website"), None, None).await.unwrap();
+        let door = db.save_connection(tour_id, a, Some(b), 40.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let edit = |conn_id: i64, target: &str| EditorAction::EditConnection(ConnectionEdit {
+            connection_id: conn_id as i32,
+            new_asset_id: b as i32,
+            new_position: (10.0, 0.0),
+            new_name: None,
+            new_icon_type: None,
+            new_file_path: None,
+            new_transition_style: None,
+            new_icon_color: None,
+            new_icon_scale: None,
+            new_url_target: Some(target.to_string()),
+            new_connection_type: None,
+            new_z_index: None,
+            new_audio_path: None,
+        });
+        let exported_target = |tour: serde_json::Value, conn_id: i64| tour["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .unwrap()
+            .get("url_target")
+            .cloned();
+
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert_eq!(exported_target(exported.clone(), conn_id), Some(serde_json::json!("_blank")));
+        assert_eq!(exported_target(exported, door), None, "transitions don't get a link target");
+
+        state.handle_action(edit(conn_id, "_self"), &tx).await.unwrap();
+        assert_eq!(exported_target(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap(), conn_id), Some(serde_json::json!("_self")));
+        while rx.try_recv().is_ok() {}
+
+        state.handle_action(edit(door, "_self"), &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(text) => assert!(text.contains("only apply to info hotspots"), "unexpected reply {}", text),
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert_eq!(exported_target(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap(), door), None);
+
+        state.handle_action(edit(conn_id, "_parent"), &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(text) => assert!(text.contains("Invalid link target"), "unexpected reply {}", text),
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert_eq!(exported_target(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap(), conn_id), Some(serde_json::json!("_self")));
+    }
+
+    #[tokio::test]
+    async fn test_icon_color_and_scale_round_trip() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let edit = |color: &str, scale: f32| EditorAction::EditConnection(ConnectionEdit {
+            connection_id: conn_id as i32,
+            new_asset_id: b as i32,
+            new_position: (10.0, 0.0),
+            new_name: None,
+            new_icon_type: None,
+            new_file_path: None,
+            new_transition_style: None,
+            new_icon_color: Some(color.to_string()),
+            new_icon_scale: Some(scale),
+            new_url_target: None,
+            new_connection_type: None,
+            new_z_index: None,
+            new_audio_path: None,
+        });
+        let exported_conn = |tour: serde_json::Value| tour["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .unwrap();
+
+        // Unset styling stays null so the viewer theme applies
+        let conn = exported_conn(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap());
+        assert!(conn["icon_color"].is_null());
+        assert!(conn["icon_scale"].is_null());
+
+        state.handle_action(edit("#FF0000", 1.5), &tx).await.unwrap();
+        let conn = exported_conn(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap());
+        assert_eq!(conn["icon_color"], "#FF0000");
+        assert_eq!(conn["icon_scale"].as_f64(), Some(1.5));
+        while rx.try_recv().is_ok() {}
+
+        // Malformed colour and out-of-range scale are rejected without touching the stored values
+        for bad in [edit("red", 1.0), edit("#FF00000", 1.0), edit("#00FF00", 5.0)] {
+            state.handle_action(bad, &tx).await.unwrap();
+            match rx.try_recv().unwrap() {
+                Message::Text(text) => assert!(text.contains("\"error\""), "unexpected reply {}", text),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        let conn = exported_conn(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap());
+        assert_eq!(conn["icon_color"], "#FF0000");
+        assert_eq!(conn["icon_scale"].as_f64(), Some(1.5));
+    }
+
+    #[tokio::test]
+    async fn test_z_index_orders_exported_hotspots() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let mut hotspots = Vec::new();
+        for lon in [10.0, 11.0, 12.0] {
+            hotspots.push(db.save_connection(tour_id, a, Some(b), lon, 0.0, ConnectionType::Transition, None, None, None).await.unwrap());
+        }
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = crate::outbound::channel(64);
+
+        // Stack the first-created hotspot on top and the last one at the bottom
+        for (conn_id, lon, z) in [(hotspots[0], 10.0, 5), (hotspots[1], 11.0, 0), (hotspots[2], 12.0, -2)] {
+            state.handle_action(EditorAction::EditConnection(ConnectionEdit {
+                connection_id: conn_id as i32,
+                new_asset_id: b as i32,
+                new_position: (lon, 0.0),
+                new_name: None,
+                new_icon_type: None,
+                new_file_path: None,
+                new_transition_style: None,
+                new_icon_color: None,
+                new_icon_scale: None,
+                new_url_target: None,
+                new_connection_type: None,
+                new_z_index: Some(z),
+                new_audio_path: None,
+            }), &tx).await.unwrap();
+        }
+
+        let tour = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        let scene = tour["scenes"].as_array().unwrap().iter().find(|s| s["id"].as_i64() == Some(a)).unwrap();
+        let exported: Vec<(i64, i64)> = scene["connections"].as_array().unwrap().iter()
+            .map(|c| (c["id"].as_i64().unwrap(), c["z_index"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(exported, vec![(hotspots[2], -2), (hotspots[1], 0), (hotspots[0], 5)]);
+    }
+
+    #[tokio::test]
+    async fn test_audio_path_on_connection_is_exported() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "Courtyard", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "Garden", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let sound = b"ID3\x04\x00\x00\x00\x00\x00\x00fountain";
+        assert!(check_audio_upload("fountain.mp3", sound, 1024).is_ok());
+        assert_eq!(check_audio_upload("fountain.jpg", sound, 1024).unwrap_err().0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(check_audio_upload("fountain.mp3", sound, 4).unwrap_err().0, StatusCode::PAYLOAD_TOO_LARGE);
+        let audio_path = format!("/assets/audio/fountain_{}.mp3", uuid::Uuid::new_v4());
+        std::fs::create_dir_all("assets/audio").unwrap();
+        std::fs::write(audio_path.trim_start_matches('/'), sound).unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let attach = |path: &str| EditorAction::EditConnection(ConnectionEdit {
+            connection_id: conn_id as i32,
+            new_asset_id: b as i32,
+            new_position: (10.0, 0.0),
+            new_name: None,
+            new_icon_type: None,
+            new_file_path: None,
+            new_transition_style: None,
+            new_icon_color: None,
+            new_icon_scale: None,
+            new_url_target: None,
+            new_connection_type: None,
+            new_z_index: None,
+            new_audio_path: Some(path.to_string()),
+        });
+        let exported_audio = |tour: serde_json::Value| tour["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .map(|c| c["audio_path"].clone())
+            .unwrap();
+
+        // Files that were never uploaded are refused
+        for path in ["/assets/audio/missing.mp3", "/assets/insta360/a.jpg", "/assets/audio/../insta360/a.jpg"] {
+            state.handle_action(attach(path), &tx).await.unwrap();
+            match rx.try_recv().unwrap() {
+                Message::Text(text) => assert!(text.contains("not found"), "unexpected reply {}", text),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        assert_eq!(exported_audio(crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap()), serde_json::Value::Null);
+
+        state.handle_action(attach(&audio_path), &tx).await.unwrap();
+        state.flush_pending_writes().await.unwrap();
+        let tour = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert_eq!(exported_audio(tour), serde_json::json!(audio_path));
+
+        let _ = std::fs::remove_file(audio_path.trim_start_matches('/'));
+    }
+
+    #[tokio::test]
+    async fn test_scene_notes_stay_out_of_exports() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let note = "Reshoot this, tripod visible";
+        state.handle_action(EditorAction::SetSceneNotes { scene_id: lobby as i32, notes: Some(note.to_string()) }, &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(text) => assert!(text.contains("scene_notes_changed"), "unexpected reply {}", text),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        // The editor sees the notes, also after a reload
+        let mut reloaded = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        reloaded.load_from_database(&db).await.unwrap();
+        assert_eq!(reloaded.scenes[0].notes.as_deref(), Some(note));
+        let editor_data = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        assert_eq!(editor_data["scenes"][0]["notes"], note);
+
+        // Nothing that leaves the editor carries them
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert!(exported["scenes"][0].get("notes").is_none());
+        assert!(!exported.to_string().contains(note));
+        let token = db.create_share_token(tour_id, 0).await.unwrap();
+        assert!(!db.get_tour_by_share_token(&token).await.unwrap().unwrap().to_string().contains(note));
+        // The owner's own backup keeps them
+        assert!(db.export_all_json("testuser").await.unwrap().to_string().contains(note));
+
+        // Blank notes clear them
+        state.handle_action(EditorAction::SetSceneNotes { scene_id: lobby as i32, notes: Some("  ".to_string()) }, &tx).await.unwrap();
+        let editor_data = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        assert!(editor_data["scenes"][0]["notes"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_fov_limits_round_trip_into_export() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let action = parse_action(serde_json::json!({
+            "action": "SetFovLimits",
+            "data": { "scene_id": lobby, "min_fov": 40.0, "max_fov": 100.0 }
+        })).unwrap();
+        state.handle_action(action, &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(text) => assert!(text.contains("fov_limits_changed"), "unexpected reply {}", text),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let mut reloaded = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        reloaded.load_from_database(&db).await.unwrap();
+        assert_eq!((reloaded.scenes[0].min_fov, reloaded.scenes[0].max_fov), (Some(40.0), Some(100.0)));
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert_eq!(exported["scenes"][0]["min_fov"], 40.0);
+        assert_eq!(exported["scenes"][0]["max_fov"], 100.0);
+
+        // An inverted or out-of-range pair is refused before it reaches the scene
+        let errors = parse_action(serde_json::json!({
+            "action": "SetFovLimits",
+            "data": { "scene_id": lobby, "min_fov": 90.0, "max_fov": 90.0 }
+        })).unwrap_err();
+        assert_eq!(errors[0].field, "data.min_fov");
+        assert_eq!(errors[0].message, "min_fov must be less than max_fov");
+        let errors = parse_action(serde_json::json!({
+            "action": "SetFovLimits",
+            "data": { "scene_id": lobby, "min_fov": 20.0, "max_fov": null }
+        })).unwrap_err();
+        assert_eq!(errors[0].message, "min_fov 20 out of range (30 to 120)");
+
+        // Clearing goes back to the viewer's defaults
+        state.handle_action(EditorAction::SetFovLimits { scene_id: lobby as i32, min_fov: None, max_fov: None }, &tx).await.unwrap();
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert!(exported["scenes"][0]["min_fov"].is_null() && exported["scenes"][0]["max_fov"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_added_connection_records_its_creator() {
+        let db = setup_test_db().await;
+        db.register_user("alice", "password").await.unwrap();
+        let tour_id = db.create_tour("alice", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "alice".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = crate::outbound::channel(64);
+        state.handle_action(EditorAction::AddConnection { start_scene_id: lobby as i32, asset_id: hall as i32, position: (10.0, 0.0), name: None }, &tx).await.unwrap();
+        let added = state.scenes.iter().find(|s| s.id as i64 == lobby).unwrap().connections[0].clone();
+        assert_eq!(added.created_by.as_deref(), Some("alice"));
+
+        let stored: Option<String> = sqlx::query_scalar("SELECT created_by FROM connections WHERE id = ?1")
+            .bind(added.id as i64)
+            .fetch_one(&*db.pool)
+            .await
+            .unwrap();
+        assert_eq!(stored.as_deref(), Some("alice"));
+
+        // The editor sees it after a reload; public copies of the tour don't
+        let mut reloaded = EditorState::new(tour_id, "alice".to_string(), Some(db.clone()));
+        reloaded.load_from_database(&db).await.unwrap();
+        assert_eq!(reloaded.scenes.iter().find(|s| s.id as i64 == lobby).unwrap().connections[0].created_by.as_deref(), Some("alice"));
+        let mut public = db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap();
+        crate::database::strip_private_fields(&mut public);
+        assert!(!public.to_string().contains("created_by"));
+    }
+
+    #[tokio::test]
+    async fn test_nudge_connection_accumulates_and_wraps_longitude() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let door = db.save_connection(tour_id, lobby, Some(hall), 350.0, 10.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        let nudge = |d_yaw: f32, d_pitch: f32| parse_action(serde_json::json!({
+            "action": "NudgeConnection",
+            "data": { "connection_id": door, "d_yaw": d_yaw, "d_pitch": d_pitch }
+        })).unwrap();
+
+        // Past 360 wraps round to the start
+        state.handle_action(nudge(15.0, -4.0), &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        assert_eq!(reply["type"], "connection_moved");
+        assert_eq!(reply["position"], serde_json::json!([5.0, 6.0]));
+
+        // ...and back again below 0; latitude stops at the pole
+        state.handle_action(nudge(-12.5, 100.0), &tx).await.unwrap();
+        let reloaded = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        let lobby_json = reloaded["scenes"].as_array().unwrap().iter().find(|s| s["id"] == lobby).unwrap().clone();
+        assert_eq!(lobby_json["connections"][0]["position"], serde_json::json!([352.5, 90.0]));
+
+        state.handle_action(EditorAction::NudgeConnection { connection_id: 9999, d_yaw: 1.0, d_pitch: 0.0 }, &tx).await.unwrap();
+        let errors: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|m| match m { Message::Text(text) => Some(text), _ => None })
+            .filter(|text| text.contains("\"error\""))
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(parse_action(serde_json::json!({
+            "action": "NudgeConnection",
+            "data": { "connection_id": door, "d_yaw": 1.0, "d_pitch": 1e39 }
+        })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_add_connection_to_all_scenes_skips_self() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let mut ids = Vec::new();
+        for name in ["Exit", "Kitchen", "Bedroom", "Bath"] {
+            ids.push(db.save_scene(tour_id, name, &format!("/assets/insta360/{}.jpg", name), None, None, None).await.unwrap());
+        }
+        let exit = ids[0];
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::AddConnectionToAllScenes {
+            target_scene_id: exit as i32,
+            position: (-90.0, 0.0),
+            name: Some("Exit".to_string()),
+            kind: ConnectionType::Transition,
+        }, &tx).await.unwrap();
+
+        let reply = match rx.try_recv().unwrap() { Message::Text(t) => t, other => panic!("unexpected {:?}", other) };
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["type"], "connections_added");
+        assert_eq!(reply["connection_ids"].as_array().unwrap().len(), 3);
+
+        // Persisted: every scene except the exit links to it, the exit has no self-link
+        let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        for scene in tour["scenes"].as_array().unwrap() {
+            let conns = scene["connections"].as_array().unwrap();
+            if scene["id"].as_i64() == Some(exit) {
+                assert!(conns.is_empty());
+            } else {
+                assert_eq!(conns.len(), 1);
+                assert_eq!(conns[0]["target_scene_id"].as_i64(), Some(exit));
+                assert_eq!(conns[0]["position"][0].as_f64(), Some(270.0));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_connection_to_all_scenes_respects_connection_cap() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let exit = db.save_scene(tour_id, "Exit", "/assets/insta360/exit.jpg", None, None, None).await.unwrap();
+        let kitchen = db.save_scene(tour_id, "Kitchen", "/assets/insta360/kitchen.jpg", None, None, None).await.unwrap();
+        let bath = db.save_scene(tour_id, "Bath", "/assets/insta360/bath.jpg", None, None, None).await.unwrap();
+        db.save_connection(tour_id, kitchen, Some(bath), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.limits = TourLimits { max_scenes: 10, max_connections_per_scene: 1 };
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::AddConnectionToAllScenes {
+            target_scene_id: exit as i32,
+            position: (90.0, 0.0),
+            name: None,
+            kind: ConnectionType::Transition,
+        }, &tx).await.unwrap();
+
+        let reply = match rx.try_recv().unwrap() { Message::Text(t) => t, other => panic!("unexpected {:?}", other) };
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["code"], "limit_reached");
+        assert_eq!(db.get_scene_connections(tour_id, kitchen).await.unwrap().len(), 1);
+        assert!(db.get_scene_connections(tour_id, bath).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_connection_to_all_scenes_needs_a_target_in_the_tour() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let other_tour = db.create_tour("testuser", "Other", "").await.unwrap();
+        let kitchen = db.save_scene(tour_id, "Kitchen", "/assets/insta360/kitchen.jpg", None, None, None).await.unwrap();
+        let foreign = db.save_scene(other_tour, "Foreign", "/assets/insta360/foreign.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        for target in [foreign as i32, 999_999] {
+            state.handle_action(EditorAction::AddConnectionToAllScenes {
+                target_scene_id: target,
+                position: (90.0, 0.0),
+                name: None,
+                kind: ConnectionType::Transition,
+            }, &tx).await.unwrap();
+            let reply = match rx.try_recv().unwrap() { Message::Text(t) => t, other => panic!("unexpected {:?}", other) };
+            let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+            assert_eq!(reply["type"], "error");
+        }
+        assert!(db.get_scene_connections(tour_id, kitchen).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rename_connection_keeps_position_and_target() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 123.5, -7.25, ConnectionType::Transition, Some("Old"), None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        state.handle_action(EditorAction::RenameConnection { connection_id: conn_id as i32, name: "To Bedroom".to_string() }, &tx).await.unwrap();
+        let reply = match rx.try_recv().unwrap() { Message::Text(t) => t, other => panic!("unexpected {:?}", other) };
+        assert!(reply.contains("connection_renamed"));
+
+        let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        let conn = tour["scenes"].as_array().unwrap().iter()
+            .flat_map(|s| s["connections"].as_array().unwrap().clone())
+            .find(|c| c["id"].as_i64() == Some(conn_id))
+            .unwrap();
+        assert_eq!(conn["name"], "To Bedroom");
+        assert_eq!(conn["target_scene_id"].as_i64(), Some(b));
+        assert_eq!(conn["position"][0].as_f64(), Some(123.5));
+        assert_eq!(conn["position"][1].as_f64(), Some(-7.25));
+    }
+
+    #[tokio::test]
+    async fn test_scene_groups_in_loaded_tour() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Campus", "").await.unwrap();
+        let library = db.save_scene(tour_id, "Library Hall", "/assets/insta360/l.jpg", None, None, None).await.unwrap();
+        let gym = db.save_scene(tour_id, "Gym Floor", "/assets/insta360/g.jpg", None, None, None).await.unwrap();
+        let quad = db.save_scene(tour_id, "Quad", "/assets/insta360/q.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let mut group_ids = Vec::new();
+        for name in ["Library", "Gym"] {
+            state.handle_action(EditorAction::CreateSceneGroup { name: name.to_string() }, &tx).await.unwrap();
+            let reply = match rx.try_recv().unwrap() { Message::Text(t) => t, other => panic!("unexpected {:?}", other) };
+            let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+            group_ids.push(reply["group"]["id"].as_i64().unwrap());
+        }
+        state.handle_action(EditorAction::AssignSceneToGroup { scene_id: library as i32, group_id: Some(group_ids[0]) }, &tx).await.unwrap();
+        state.handle_action(EditorAction::AssignSceneToGroup { scene_id: gym as i32, group_id: Some(group_ids[1]) }, &tx).await.unwrap();
+
+        let tour = db.get_tour_with_scenes("testuser", tour_id).await.unwrap().unwrap();
+        let groups = tour["scene_groups"].as_array().unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0]["name"], "Library");
+        assert_eq!(groups[0]["scene_ids"], serde_json::json!([library]));
+        assert_eq!(groups[1]["name"], "Gym");
+        assert_eq!(groups[1]["scene_ids"], serde_json::json!([gym]));
+        assert!(groups[2]["id"].is_null());
+        assert_eq!(groups[2]["scene_ids"], serde_json::json!([quad]));
+
+        // Groups from another tour can't be used
+        let other_tour = db.create_tour("testuser", "Other", "").await.unwrap();
+        let foreign = db.create_scene_group(other_tour, "Foreign").await.unwrap();
+        assert!(!db.assign_scene_to_group(tour_id, quad, Some(foreign)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tour_path_survives_export() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Guided", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let c = db.save_scene(tour_id, "C", "/assets/insta360/c.jpg", None, None, None).await.unwrap();
+        let other_tour = db.create_tour("testuser", "Other", "").await.unwrap();
+        let foreign = db.save_scene(other_tour, "X", "/assets/insta360/x.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        let path = vec![c as i32, a as i32, b as i32];
+        state.handle_action(EditorAction::SetTourPath { scene_ids: path.clone() }, &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(t) => assert!(t.contains("tour_path_set")),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // A scene from another tour is rejected and the stored path is untouched
+        state.handle_action(EditorAction::SetTourPath { scene_ids: vec![a as i32, foreign as i32] }, &tx).await.unwrap();
+        match rx.try_recv().unwrap() {
+            Message::Text(t) => assert!(t.contains("error")),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let exported = crate::exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert_eq!(exported["tour_path"], serde_json::json!([c, a, b]));
+    }
+
+    // Minimal JPEG whose EXIF carries only a GPS IFD with GPSImgDirection = num/den
+    fn jpeg_with_heading(num: u32, den: u32) -> Vec<u8> {
+        let mut tiff: Vec<u8> = b"II\x2a\x00".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        // IFD0: one entry pointing at the GPS IFD (offset 26)
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(0x8825u16.to_le_bytes());
+        tiff.extend(4u16.to_le_bytes());
+        tiff.extend(1u32.to_le_bytes());
+        tiff.extend(26u32.to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        // GPS IFD: GPSImgDirection rational stored at offset 44
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(0x0011u16.to_le_bytes());
+        tiff.extend(5u16.to_le_bytes());
+        tiff.extend(1u32.to_le_bytes());
+        tiff.extend(44u32.to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(num.to_le_bytes());
+        tiff.extend(den.to_le_bytes());
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((app1.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_exif_heading_surfaced() {
+        assert_eq!(read_exif_heading(&jpeg_with_heading(2705, 10)), Some(270.5));
+        // Division by zero and plain non-EXIF data are ignored
+        assert_eq!(read_exif_heading(&jpeg_with_heading(1, 0)), None);
+        assert_eq!(read_exif_heading(b"not an image"), None);
+    }
+
+    #[tokio::test]
+    async fn test_deferred_edits_flush_in_one_save() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 0.0, 0.0, ConnectionType::Transition, Some("Door"), None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        state.handle_action(EditorAction::SetDeferredMode { enabled: true }, &tx).await.unwrap();
+
+        // Simulate dragging a hotspot: ten position updates
+        for step in 1..=10 {
+            state.handle_action(EditorAction::EditConnection(ConnectionEdit {
+                connection_id: conn_id as i32,
+                new_asset_id: b as i32,
+                new_position: (step as f32 * 10.0, 1.0),
+                new_name: None,
+                new_icon_type: None,
+                new_file_path: None,
+                new_transition_style: None,
+                new_icon_color: None,
+                new_icon_scale: None,
+                new_url_target: None,
+                new_connection_type: None,
+                new_z_index: None,
+                new_audio_path: None,
+            }), &tx).await.unwrap();
+        }
+        assert_eq!(state.pending_writes.len(), 10);
+
+        let stored_lon = |db: Database| async move {
+            sqlx::query("SELECT world_lon FROM connections WHERE id = ?1")
+                .bind(conn_id)
+                .fetch_one(&*db.pool)
+                .await
+                .unwrap()
+                .get::<f32, _>("world_lon")
+        };
+        assert_eq!(stored_lon(db.clone()).await, 0.0);
+
+        state.handle_action(EditorAction::SaveTour, &tx).await.unwrap();
+        let mut saves = Vec::new();
+        while let Ok(Message::Text(t)) = rx.try_recv() {
+            let reply: serde_json::Value = serde_json::from_str(&t).unwrap();
+            if reply["type"] == "tour_saved" {
+                saves.push(reply);
+            }
+        }
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0]["flushed"], 10);
+        assert!(state.pending_writes.is_empty());
+        assert_eq!(stored_lon(db.clone()).await, 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_action_reports_whether_the_tour_changed() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = crate::outbound::channel(64);
+        let notes = |scene_id: i64| EditorAction::SetSceneNotes { scene_id: scene_id as i32, notes: Some("Retake".to_string()) };
+
+        assert!(state.handle_action(notes(lobby), &tx).await.unwrap());
+        // Refused or no-op actions only reply
+        assert!(!state.handle_action(notes(9999), &tx).await.unwrap());
+        assert!(!state.handle_action(EditorAction::ChangeAddress { address: "Main St".to_string() }, &tx).await.unwrap());
+        // Rows the scenes don't mirror count too
+        assert!(state.handle_action(EditorAction::CreateSceneGroup { name: "East wing".to_string() }, &tx).await.unwrap());
+    }
+}
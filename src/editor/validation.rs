@@ -0,0 +1,266 @@
+//! Field-level checks for incoming editor actions
+//!
+//! `parse_action` turns the raw `editor_action` JSON into an `EditorAction` and
+//! reports what was wrong with it per field, so the client can point at the bad
+//! input instead of getting a generic "Editor action failed".
+
+use super::{ConnectionEdit, EditorAction};
+use serde::Serialize;
+
+/// One problem with an incoming action
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path of the offending field, e.g. `data.position`
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// Camera field of view accepted for initial views, in degrees (matches the editor's zoom limits)
+const FOV_RANGE: std::ops::RangeInclusive<f32> = 10.0..=120.0;
+
+/// Field of view range scene zoom limits must fall in, in degrees
+const FOV_LIMIT_RANGE: std::ops::RangeInclusive<f32> = 30.0..=120.0;
+
+/// Longest scene notes accepted, in characters
+const MAX_NOTES_CHARS: usize = 10_000;
+
+/// Fields holding a `[lon, lat]` pair
+const POSITION_FIELDS: &[&str] = &["position", "new_position"];
+
+/// Parses and validates an `editor_action` object (`{"action": ..., "data": ...}`).
+pub fn parse_action(value: serde_json::Value) -> Result<EditorAction, Vec<FieldError>> {
+    match value.get("action") {
+        Some(serde_json::Value::String(_)) => {}
+        Some(_) => return Err(vec![FieldError::new("action", "action must be a string")]),
+        None => return Err(vec![FieldError::new("action", "action is required")]),
+    }
+
+    let action: EditorAction = serde_path_to_error::deserialize(value).map_err(|e| {
+        let field = e.path().to_string();
+        let leaf = field.rsplit('.').next().unwrap_or("").split('[').next().unwrap_or("");
+        let message = if POSITION_FIELDS.contains(&leaf) {
+            format!("{} must be a 2-element array of numbers", leaf)
+        } else {
+            format!("{}: {}", field, e.inner())
+        };
+        vec![FieldError { field, message }]
+    })?;
+    action.validate()?;
+    Ok(action)
+}
+
+fn check_position(errors: &mut Vec<FieldError>, field: &str, (lon, lat): (f32, f32)) {
+    if !lon.is_finite() || !lat.is_finite() {
+        errors.push(FieldError::new(field, format!("{} must contain finite numbers", field)));
+    } else if !(-90.0..=90.0).contains(&lat) {
+        errors.push(FieldError::new(field, format!("{} latitude {} out of range (-90 to 90)", field, lat)));
+    }
+}
+
+fn check_name(errors: &mut Vec<FieldError>, field: &str, name: &str) {
+    if name.trim().is_empty() {
+        errors.push(FieldError::new(field, format!("{} must not be empty", field)));
+    }
+}
+
+fn check_id(errors: &mut Vec<FieldError>, field: &str, id: i32) {
+    if id <= 0 {
+        errors.push(FieldError::new(field, format!("{} must be a positive id", field)));
+    }
+}
+
+fn check_captured_at(errors: &mut Vec<FieldError>, captured_at: Option<&str>) {
+    if captured_at.is_some_and(|value| !super::is_capture_timestamp(value)) {
+        errors.push(FieldError::new("data.captured_at", "captured_at must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS"));
+    }
+}
+
+impl EditorAction {
+    /// Checks values serde accepts but the editor can't use (empty names, out-of-range angles, ...).
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        match self {
+            EditorAction::AddScene { name, file_path, north_direction, captured_at } => {
+                check_name(&mut errors, "data.name", name);
+                check_name(&mut errors, "data.file_path", file_path);
+                if north_direction.is_some_and(|d| !d.is_finite()) {
+                    errors.push(FieldError::new("data.north_direction", "north_direction must be a finite number"));
+                }
+                check_captured_at(&mut errors, captured_at.as_deref());
+            }
+            EditorAction::SetSceneCapturedAt { scene_id, captured_at } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                check_captured_at(&mut errors, captured_at.as_deref());
+            }
+            EditorAction::SetSceneNotes { scene_id, notes } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                if notes.as_ref().is_some_and(|n| n.chars().count() > MAX_NOTES_CHARS) {
+                    errors.push(FieldError::new("data.notes", format!("notes must be at most {} characters", MAX_NOTES_CHARS)));
+                }
+            }
+            EditorAction::SetSceneDefaultIcon { scene_id, icon_index } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                let icons = crate::exporter::ICON_INDEXES;
+                if let Some(icon) = icon_index.filter(|icon| !icons.contains(icon)) {
+                    errors.push(FieldError::new("data.icon_index", format!("icon_index {} is not a defined icon ({} to {})", icon, icons.start(), icons.end())));
+                }
+            }
+            EditorAction::UpdateSceneName { scene_id, name } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                check_name(&mut errors, "data.name", name);
+            }
+            EditorAction::AddCloseup { name, file_path, parent_scene_id, position, .. } => {
+                check_name(&mut errors, "data.name", name);
+                check_name(&mut errors, "data.file_path", file_path);
+                check_id(&mut errors, "data.parent_scene_id", *parent_scene_id);
+                check_position(&mut errors, "data.position", *position);
+            }
+            EditorAction::AddConnection { start_scene_id, asset_id, position, .. } => {
+                check_id(&mut errors, "data.start_scene_id", *start_scene_id);
+                check_id(&mut errors, "data.asset_id", *asset_id);
+                check_position(&mut errors, "data.position", *position);
+            }
+            EditorAction::AddConnectionToAllScenes { target_scene_id, position, .. } => {
+                check_id(&mut errors, "data.target_scene_id", *target_scene_id);
+                check_position(&mut errors, "data.position", *position);
+            }
+            EditorAction::EditConnection(ConnectionEdit { connection_id, new_position, .. }) => {
+                check_id(&mut errors, "data.connection_id", *connection_id);
+                check_position(&mut errors, "data.new_position", *new_position);
+            }
+            EditorAction::PropagateConnection { connection_id, name_pattern } => {
+                check_id(&mut errors, "data.connection_id", *connection_id);
+                check_name(&mut errors, "data.name_pattern", name_pattern);
+            }
+            EditorAction::RenameConnection { connection_id, name } => {
+                check_id(&mut errors, "data.connection_id", *connection_id);
+                check_name(&mut errors, "data.name", name);
+            }
+            EditorAction::NudgeConnection { connection_id, d_yaw, d_pitch } => {
+                check_id(&mut errors, "data.connection_id", *connection_id);
+                if !d_yaw.is_finite() || !d_pitch.is_finite() {
+                    errors.push(FieldError::new("data", "d_yaw and d_pitch must be finite numbers"));
+                }
+            }
+            EditorAction::SetInitialView { scene_id, position, fov } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                check_position(&mut errors, "data.position", *position);
+                if let Some(fov) = fov {
+                    if !FOV_RANGE.contains(fov) {
+                        errors.push(FieldError::new("data.fov", format!(
+                            "fov {} out of range ({} to {})", fov, FOV_RANGE.start(), FOV_RANGE.end()
+                        )));
+                    }
+                }
+            }
+            EditorAction::SetFovLimits { scene_id, min_fov, max_fov } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                for (name, fov) in [("min_fov", min_fov), ("max_fov", max_fov)] {
+                    if let Some(fov) = fov.filter(|fov| !FOV_LIMIT_RANGE.contains(fov)) {
+                        errors.push(FieldError::new(&format!("data.{}", name), format!(
+                            "{} {} out of range ({} to {})", name, fov, FOV_LIMIT_RANGE.start(), FOV_LIMIT_RANGE.end()
+                        )));
+                    }
+                }
+                if let (Some(min), Some(max)) = (min_fov, max_fov) {
+                    if min >= max {
+                        errors.push(FieldError::new("data.min_fov", "min_fov must be less than max_fov"));
+                    }
+                }
+            }
+            EditorAction::SetNorthDirection { scene_id, direction } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                if !direction.is_finite() {
+                    errors.push(FieldError::new("data.direction", "direction must be a finite number"));
+                }
+            }
+            EditorAction::RotateScene { scene_id, yaw_offset_deg, .. } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+                if !yaw_offset_deg.is_finite() {
+                    errors.push(FieldError::new("data.yaw_offset_deg", "yaw_offset_deg must be a finite number"));
+                }
+            }
+            EditorAction::SetNorthDirectionAll { direction } if !(0.0..360.0).contains(direction) => {
+                errors.push(FieldError::new("data.direction", format!("direction {} out of range (0 to <360)", direction)));
+            }
+            EditorAction::ResetSceneCalibration { scene_id } | EditorAction::SetSceneHidden { scene_id, .. } => {
+                check_id(&mut errors, "data.scene_id", *scene_id);
+            }
+            EditorAction::AddFloorplanMarker { x, y, .. } | EditorAction::UpdateFloorplanMarker { x, y, .. }
+                if !x.is_finite() || !y.is_finite() =>
+            {
+                errors.push(FieldError::new("data", "x and y must be finite numbers"));
+            }
+            EditorAction::CreateSceneGroup { name } => check_name(&mut errors, "data.name", name),
+            _ => {}
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors_for(value: serde_json::Value) -> Vec<FieldError> {
+        parse_action(value).expect_err("payload should be rejected")
+    }
+
+    #[test]
+    fn test_malformed_actions_report_the_offending_field() {
+        let errors = errors_for(serde_json::json!({
+            "action": "AddConnection",
+            "data": { "start_scene_id": 1, "asset_id": 2, "position": [10.0], "name": null }
+        }));
+        assert_eq!(errors[0].field, "data.position");
+        assert_eq!(errors[0].message, "position must be a 2-element array of numbers");
+
+        let errors = errors_for(serde_json::json!({
+            "action": "SetInitialView",
+            "data": { "scene_id": 3, "position": [10.0, 5.0], "fov": 170.0 }
+        }));
+        assert_eq!(errors, vec![FieldError::new("data.fov", "fov 170 out of range (10 to 120)")]);
+
+        let errors = errors_for(serde_json::json!({
+            "action": "RenameConnection",
+            "data": { "connection_id": "seven", "name": "Door" }
+        }));
+        assert_eq!(errors[0].field, "data.connection_id");
+
+        let errors = errors_for(serde_json::json!({
+            "action": "EditConnection",
+            "data": { "connection_id": 4, "new_asset_id": 2, "new_position": [10.0, 95.0] }
+        }));
+        assert_eq!(errors[0].field, "data.new_position");
+        assert!(errors[0].message.contains("out of range"));
+
+        let errors = errors_for(serde_json::json!({
+            "action": "AddScene",
+            "data": { "name": "  ", "file_path": "" }
+        }));
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["data.name", "data.file_path"]);
+
+        assert_eq!(errors_for(serde_json::json!({ "data": {} }))[0].field, "action");
+
+        let errors = errors_for(serde_json::json!({
+            "action": "SetSceneDefaultIcon",
+            "data": { "scene_id": 3, "icon_index": 4 }
+        }));
+        assert_eq!(errors, vec![FieldError::new("data.icon_index", "icon_index 4 is not a defined icon (1 to 3)")]);
+
+        // Well-formed payloads still parse
+        let action = parse_action(serde_json::json!({
+            "action": "SetInitialView",
+            "data": { "scene_id": 3, "position": [10.0, 5.0], "fov": 75.0 }
+        })).unwrap();
+        assert!(matches!(action, EditorAction::SetInitialView { scene_id: 3, .. }));
+    }
+}
@@ -0,0 +1,788 @@
+//! Exporter module
+//!
+//! Builds the `tourData` object embedded in an exported package as
+//! `js/tourData.js`. The raw graph comes from the database; this module adds the
+//! viewer-facing fields that can't be read straight off the rows.
+//!
+//! Added per connection:
+//! * `target_thumbnail` - image path of whatever the hotspot leads to. Transitions
+//!   resolve to the target scene's image, closeups (whose `target_scene_id` stores the
+//!   closeup asset id) resolve to the closeup image.
+//! * `transition_style` - defaulted to `"fade"` when the author hasn't picked one.
+//! * `url_target` - link target for URL (`Info`) hotspots, defaulted to `"_blank"`;
+//!   left out for other connection types.
+//! * `icon_index` - filled by `apply_default_icon` when the author didn't pick an icon:
+//!   with the scene's `default_icon_index` if it has one, else the configured default,
+//!   so the viewer never has to guess.
+//!
+//! With `flatten_closeups`, closeup hotspots are exported as `Info` hotspots whose
+//! `popup_url` is the closeup image, for basic viewers without closeup overlays.
+//! `TourDataOptions` bundles these per-export adjustments.
+//!
+//! Scenes carry `media_type` (`"image"` or `"video"`) straight from the database.
+//!
+//! Hidden (draft) scenes are left out, along with every connection, group entry,
+//! path step and floorplan marker pointing at them. Scene `notes` are private to
+//! the author and never exported.
+//!
+//! A tour without a (valid) initial scene starts at its first scene.
+//!
+//! `write_package` lays out one tour's viewer package (index.html, js/, assets/,
+//! branding.json, manifest.json) inside a ZIP, optionally under a folder so several
+//! tours can share one archive. `manifest.json` records the bundled viewer versions
+//! (see `viewer_info`) so a later re-import or upgrade knows what it is dealing with.
+//!
+//! The engine and three.js builds are read from disk at export time. When either is
+//! missing the package is still written, but without that file and with a
+//! `WARNINGS.txt` explaining what to add, and the problem is reported back as an
+//! `ExportWarning` so the handler can flag it. Tour and viewer files that can't be
+//! written into the ZIP are skipped the same way (`FilesSkipped`) rather than
+//! failing the whole export.
+//!
+//! The app's own files from `static/assets` are packaged under `assets/`, next to
+//! the tour's uploads. A tour file whose path matches one of them would silently
+//! replace it in the ZIP, so it is stored under `assets/tour/` instead, `tourData`
+//! is rewritten to point there, and an `AssetPathCollision` warning is raised.
+
+use crate::database::Database;
+use crate::editor::TransitionStyle;
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use std::io::{Seek, Write};
+use std::path::Path;
+
+/// Version of the bundled `js/engine.min.js`; bump when the engine changes
+pub const ENGINE_VERSION: &str = "1.0.0";
+/// three.js release bundled as `js/three.min.js`
+pub const THREE_VERSION: &str = "r128";
+/// Shape of the exported `tourData` object; bump on incompatible changes
+pub const TOUR_DATA_FORMAT_VERSION: u32 = 1;
+/// Hotspot icons the viewer ships (`assets/info<n>_icon.png`)
+pub const ICON_INDEXES: std::ops::RangeInclusive<i32> = 1..=3;
+
+/// Something wrong with an export that didn't stop the package from being written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExportWarning {
+    /// `engine.min.js` was not found in the viewer folder
+    EngineMissing,
+    /// `three.min.js` was not found in the viewer folder
+    ThreeMissing,
+    /// Tour files shared a path with the app's static assets and were moved under `assets/tour/`
+    AssetPathCollision,
+    /// Some files couldn't be written into the ZIP and were left out
+    FilesSkipped,
+}
+
+impl ExportWarning {
+    /// Short code reported in the `X-Export-Warnings` header
+    pub fn code(self) -> &'static str {
+        match self {
+            ExportWarning::EngineMissing => "engine-missing",
+            ExportWarning::ThreeMissing => "three-missing",
+            ExportWarning::AssetPathCollision => "asset-path-collision",
+            ExportWarning::FilesSkipped => "files-skipped",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            ExportWarning::EngineMissing => "js/engine.min.js is missing: the server had no viewer engine to bundle. Copy it from static/export-viewer/js/ before hosting this package.",
+            ExportWarning::ThreeMissing => "js/three.min.js is missing: the server had no three.js build to bundle. Add three.js r128 as js/three.min.js before hosting this package.",
+            ExportWarning::AssetPathCollision => "tour files with the same path as the viewer's static assets were stored under assets/tour/ instead",
+            ExportWarning::FilesSkipped => "some files couldn't be written to the package and were left out; the server log lists them.",
+        }
+    }
+}
+
+/// Comma-separated warning codes, as sent in `X-Export-Warnings`
+pub fn warnings_header(warnings: &[ExportWarning]) -> String {
+    warnings.iter().map(|w| w.code()).collect::<Vec<_>>().join(", ")
+}
+
+/// Versions of the viewer files shipped with exports
+pub fn viewer_info() -> serde_json::Value {
+    serde_json::json!({
+        "engine_version": ENGINE_VERSION,
+        "three_version": THREE_VERSION,
+        "tour_data_format_version": TOUR_DATA_FORMAT_VERSION
+    })
+}
+
+/// Viewer files bundled from `viewer_js_dir`, as `(key, file name)`
+pub const VIEWER_FILES: [(&str, &str); 2] = [("engine", "engine.min.js"), ("three", "three.min.js")];
+
+/// Size and content hash of one viewer file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewerFileDigest {
+    pub size: u64,
+    /// Hex SHA-256 of the file bytes
+    pub sha256: String,
+}
+
+/// Reads and hashes the viewer files in `viewer_js_dir`, in `VIEWER_FILES` order.
+/// A file that can't be read is reported with the error instead.
+pub fn viewer_file_digests(viewer_js_dir: &Path) -> Vec<(&'static str, std::io::Result<ViewerFileDigest>)> {
+    use sha2::{Digest, Sha256};
+    VIEWER_FILES.iter().map(|(key, file)| {
+        let digest = std::fs::read(viewer_js_dir.join(file)).map(|data| ViewerFileDigest {
+            size: data.len() as u64,
+            sha256: Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect(),
+        });
+        (*key, digest)
+    }).collect()
+}
+
+/// Startup check: logs the size and hash of each viewer file, and warns about missing ones
+/// (exports would go out without them)
+pub fn log_viewer_files(viewer_js_dir: &Path) {
+    for ((_, file), (_, digest)) in VIEWER_FILES.iter().zip(viewer_file_digests(viewer_js_dir)) {
+        match digest {
+            Ok(digest) => println!("Viewer {}: {} bytes, sha256 {}", file, digest.size, digest.sha256),
+            Err(e) => eprintln!("Warning: viewer file {} unavailable, exports will lack it: {}",
+                                viewer_js_dir.join(file).display(), e),
+        }
+    }
+}
+
+/// Builds the export `tourData` JSON for a tour (no owner filter).
+///
+/// Returns `Ok(None)` if the tour does not exist.
+pub async fn build_tour_data(db: &Database, tour_id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
+    let mut tour = match db.get_tour_with_scenes_by_id(tour_id).await? {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+
+    // Every asset of the tour keyed by id, so both scene and closeup targets resolve from one query.
+    // Video scenes have no still to show, so hotspots leading to them get no thumbnail.
+    let asset_paths: HashMap<i64, Option<String>> = sqlx::query("SELECT id, file_path, media_type FROM assets WHERE tour_id = ?1")
+        .bind(tour_id)
+        .fetch_all(&*db.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let path = row.get::<Option<String>, _>("file_path")
+                .filter(|_| row.get::<String, _>("media_type") != "video");
+            (row.get::<i64, _>("id"), path)
+        })
+        .collect();
+
+    crate::database::drop_hidden_scenes(&mut tour);
+    crate::database::strip_private_fields(&mut tour);
+
+    if let Some(scenes) = tour.get_mut("scenes").and_then(|v| v.as_array_mut()) {
+        for scene in scenes {
+            if let Some(conns) = scene.get_mut("connections").and_then(|v| v.as_array_mut()) {
+                for conn in conns {
+                    let thumbnail = conn
+                        .get("target_scene_id")
+                        .and_then(|v| v.as_i64())
+                        .and_then(|target| asset_paths.get(&target).cloned().flatten());
+                    conn["target_thumbnail"] = serde_json::json!(thumbnail);
+                    if conn["transition_style"].is_null() {
+                        conn["transition_style"] = serde_json::json!(TransitionStyle::Fade.as_str());
+                    }
+                    // Only info (URL) hotspots open links
+                    if conn["connection_type"] != "Info" {
+                        if let Some(fields) = conn.as_object_mut() {
+                            fields.remove("url_target");
+                        }
+                    } else if conn["url_target"].is_null() {
+                        conn["url_target"] = serde_json::json!("_blank");
+                    }
+                }
+            }
+        }
+    }
+
+    // The viewer needs somewhere to start; fall back to the first scene
+    let scene_ids: Vec<i64> = tour["scenes"]
+        .as_array()
+        .map(|scenes| scenes.iter().filter_map(|s| s["id"].as_i64()).collect())
+        .unwrap_or_default();
+    let initial_valid = tour["initial_scene_id"].as_i64().is_some_and(|id| scene_ids.contains(&id));
+    if !initial_valid {
+        if let Some(first) = scene_ids.first() {
+            tour["initial_scene_id"] = serde_json::json!(first);
+        }
+    }
+
+    Ok(Some(tour))
+}
+
+/// Per-export adjustments made to `tourData` after `build_tour_data`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TourDataOptions {
+    /// Icon for hotspots that don't have one, in scenes without a default of their own (one of `ICON_INDEXES`)
+    pub default_icon_index: i32,
+    /// Export closeups as info hotspots (see `flatten_closeups`)
+    pub flatten_closeups: bool,
+}
+
+impl TourDataOptions {
+    pub fn apply(&self, tour: &mut serde_json::Value) {
+        if self.flatten_closeups {
+            flatten_closeups(tour);
+        }
+        apply_default_icon(tour, self.default_icon_index);
+    }
+}
+
+/// Turns closeup hotspots into `Info` hotspots with the closeup image as `popup_url`,
+/// for viewers that can't show closeup overlays. The image stays in `file_path` so it is
+/// still packaged.
+pub fn flatten_closeups(tour: &mut serde_json::Value) {
+    let Some(scenes) = tour["scenes"].as_array_mut() else { return };
+    for scene in scenes {
+        if let Some(conns) = scene["connections"].as_array_mut() {
+            for conn in conns.iter_mut().filter(|c| c["connection_type"] == "Closeup") {
+                // Older closeup hotspots only reach their image through the target asset
+                let image = conn["file_path"].as_str().or(conn["target_thumbnail"].as_str()).map(str::to_string);
+                conn["connection_type"] = serde_json::json!("Info");
+                conn["target_scene_id"] = serde_json::Value::Null;
+                conn["target_thumbnail"] = serde_json::Value::Null;
+                conn["file_path"] = serde_json::json!(image);
+                conn["popup_url"] = serde_json::json!(image);
+            }
+        }
+    }
+}
+
+/// Gives every connection of `tour` without an `icon_index` its scene's `default_icon_index`,
+/// or `icon_index` (the configured default) when the scene has none
+pub fn apply_default_icon(tour: &mut serde_json::Value, icon_index: i32) {
+    let Some(scenes) = tour["scenes"].as_array_mut() else { return };
+    for scene in scenes {
+        let icon_index = scene["default_icon_index"].as_i64().map_or(icon_index, |i| i as i32);
+        if let Some(conns) = scene["connections"].as_array_mut() {
+            for conn in conns.iter_mut().filter(|c| c["icon_index"].is_null()) {
+                conn["icon_index"] = serde_json::json!(icon_index);
+            }
+        }
+    }
+}
+
+/// Options used for every file in an export ZIP
+pub fn zip_options() -> zip::write::FileOptions {
+    zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644)
+}
+
+fn add_file<W: Write + Seek>(zip: &mut zip::ZipWriter<W>, path_in_zip: &str, bytes: &[u8]) -> zip::result::ZipResult<()> {
+    zip.start_file(path_in_zip, zip_options())?;
+    zip.write_all(bytes)?;
+    Ok(())
+}
+
+/// Adds a file the package can do without; a write error is logged and recorded as
+/// `FilesSkipped` instead of failing the export
+fn add_optional_file<W: Write + Seek>(zip: &mut zip::ZipWriter<W>, path_in_zip: &str, bytes: &[u8], warnings: &mut Vec<ExportWarning>) {
+    if let Err(e) = add_file(zip, path_in_zip, bytes) {
+        eprintln!("export: skipped {}: {}", path_in_zip, e);
+        if !warnings.contains(&ExportWarning::FilesSkipped) {
+            warnings.push(ExportWarning::FilesSkipped);
+        }
+    }
+}
+
+/// Image paths referenced by scenes and hotspots, sorted and deduplicated
+fn referenced_paths(tour: &serde_json::Value) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    if let Some(scenes) = tour.get("scenes").and_then(|v| v.as_array()) {
+        for s in scenes {
+            if let Some(fp) = s.get("file_path").and_then(|v| v.as_str()) {
+                paths.push(fp.to_string());
+            }
+            if let Some(conns) = s.get("connections").and_then(|v| v.as_array()) {
+                for c in conns {
+                    if let Some(fp) = c.get("file_path").and_then(|v| v.as_str()) { paths.push(fp.to_string()); }
+                    if let Some(audio) = c.get("audio_path").and_then(|v| v.as_str()) { paths.push(audio.to_string()); }
+                }
+            }
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Files under `static_assets_dir` with their ZIP path (`assets/...`)
+fn static_asset_entries(static_assets_dir: &Path) -> Vec<(std::path::PathBuf, String)> {
+    walkdir::WalkDir::new(static_assets_dir).into_iter().flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            let rel = entry.path().strip_prefix(static_assets_dir).ok()?;
+            let zip_path = format!("assets/{}", rel.to_string_lossy().replace('\\', "/"));
+            Some((entry.path().to_path_buf(), zip_path))
+        })
+        .collect()
+}
+
+/// `path` moved from `assets/...` to `assets/tour/...`, keeping a leading `/` if it had one
+fn namespaced(path: &str) -> String {
+    let slash = if path.starts_with('/') { "/" } else { "" };
+    format!("{}assets/tour/{}", slash, path.trim_start_matches('/').trim_start_matches("assets/"))
+}
+
+/// Replaces every string in `value` that is a key of `moved` with its new path
+fn rewrite_paths(value: &mut serde_json::Value, moved: &HashMap<String, String>) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(new_path) = moved.get(s.as_str()) {
+                *s = new_path.clone();
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| rewrite_paths(item, moved)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| rewrite_paths(field, moved)),
+        _ => {}
+    }
+}
+
+/// Writes the viewer package for `tour` (as returned by `build_tour_data`) into `zip`.
+///
+/// Entries are placed under `prefix` (e.g. `"tour_3/"`, or `""` for a single-tour export).
+/// The engine and three.js are read from `viewer_js_dir`; whichever is missing is left out
+/// and returned as a warning. Files from `static_assets_dir` go under `assets/`, with tour
+/// files that collide moved aside (see the module docs). Missing asset files are logged and
+/// skipped, and files that can't be written are left out with a `FilesSkipped` warning; only
+/// failing to write the page, `tourData` or the manifest aborts the package.
+pub async fn write_package<W: Write + Seek>(
+    db: &Database,
+    tour_id: i64,
+    tour: &serde_json::Value,
+    zip: &mut zip::ZipWriter<W>,
+    prefix: &str,
+    viewer_js_dir: &Path,
+    static_assets_dir: &Path,
+) -> zip::result::ZipResult<Vec<ExportWarning>> {
+    let entry = |path: &str| format!("{}{}", prefix, path);
+
+    // 1) Viewer page
+    add_file(zip, &entry("index.html"), include_str!("../static/export-viewer/index.html").as_bytes())?;
+
+    // 2) Engine and three.js
+    let mut warnings = Vec::new();
+    for (file, warning) in [("engine.min.js", ExportWarning::EngineMissing), ("three.min.js", ExportWarning::ThreeMissing)] {
+        match std::fs::read(viewer_js_dir.join(file)) {
+            Ok(bytes) => add_optional_file(zip, &entry(&format!("js/{}", file)), &bytes, &mut warnings),
+            Err(e) => {
+                eprintln!("export: viewer file {} unavailable: {}", viewer_js_dir.join(file).display(), e);
+                warnings.push(warning);
+            }
+        }
+    }
+
+    // Tour files that would land on a static asset's path move under assets/tour/
+    let static_assets = static_asset_entries(static_assets_dir);
+    let static_paths: HashSet<&str> = static_assets.iter().map(|(_, zip_path)| zip_path.as_str()).collect();
+    let asset_paths = referenced_paths(tour);
+    let moved: HashMap<String, String> = asset_paths.iter()
+        .filter(|p| static_paths.contains(p.trim_start_matches('/')))
+        .map(|p| (p.clone(), namespaced(p)))
+        .collect();
+    let mut tour = tour.clone();
+    if !moved.is_empty() {
+        eprintln!("export: tour {}: {}: {}", tour_id, ExportWarning::AssetPathCollision.description(),
+                  moved.keys().cloned().collect::<Vec<_>>().join(", "));
+        rewrite_paths(&mut tour, &moved);
+        warnings.push(ExportWarning::AssetPathCollision);
+    }
+
+    // 3) tourData.js
+    add_file(zip, &entry("js/tourData.js"), format!("const tourData = {};", tour).as_bytes())?;
+
+    // 4) Referenced images, keeping the assets/... structure
+    for p in &asset_paths {
+        let rel = p.trim_start_matches('/');
+        if rel.is_empty() { continue; }
+        let zip_path = moved.get(p).map_or(rel, |to| to.trim_start_matches('/'));
+        match std::fs::read(rel) {
+            Ok(bytes) => add_optional_file(zip, &entry(zip_path), &bytes, &mut warnings),
+            Err(_) => eprintln!("export: missing asset file: {}", rel),
+        }
+    }
+
+    // 4b) Static icons/sprites from static/assets
+    for (source, zip_path) in &static_assets {
+        if let Ok(bytes) = std::fs::read(source) {
+            add_optional_file(zip, &entry(zip_path), &bytes, &mut warnings);
+        }
+    }
+
+    // 4c) Branding: branding.json for the viewer plus the logo file
+    match db.get_tour_branding(tour_id).await {
+        Ok(Some(branding)) if !branding.is_empty() => {
+            let mut logo_zip_path = None;
+            if let Some(ref logo) = branding.logo_path {
+                let rel = logo.trim_start_matches('/');
+                let zip_path = if static_paths.contains(rel) {
+                    eprintln!("export: tour {}: logo {} moved under assets/tour/", tour_id, rel);
+                    if !warnings.contains(&ExportWarning::AssetPathCollision) {
+                        warnings.push(ExportWarning::AssetPathCollision);
+                    }
+                    namespaced(rel)
+                } else {
+                    rel.to_string()
+                };
+                match std::fs::read(rel) {
+                    Ok(bytes) => add_optional_file(zip, &entry(&zip_path), &bytes, &mut warnings),
+                    Err(_) => eprintln!("export: missing logo file: {}", rel),
+                }
+                logo_zip_path = Some(zip_path);
+            }
+            let branding_json = serde_json::json!({
+                "logo_path": logo_zip_path,
+                "primary_color": branding.primary_color,
+                "welcome_text": branding.welcome_text
+            });
+            add_file(zip, &entry("branding.json"), branding_json.to_string().as_bytes())?;
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("export: failed to load branding for tour {}: {}", tour_id, e),
+    }
+
+    // 5) manifest.json with the viewer versions this package was built against
+    let manifest = serde_json::json!({ "tour_id": tour_id, "viewer": viewer_info() });
+    add_file(zip, &entry("manifest.json"), serde_json::to_string_pretty(&manifest).unwrap_or_default().as_bytes())?;
+
+    // 6) WARNINGS.txt when the package is incomplete (moved files are only logged)
+    let missing: Vec<&ExportWarning> = warnings.iter().filter(|w| **w != ExportWarning::AssetPathCollision).collect();
+    if !missing.is_empty() {
+        let text: String = missing.iter().map(|w| format!("- {}\n", w.description())).collect();
+        add_file(zip, &entry("WARNINGS.txt"), format!("This tour package is incomplete:\n{}", text).as_bytes())?;
+    }
+
+    Ok(warnings)
+}
+
+/// Exports every tour owned by `username` into one archive.
+///
+/// Each tour with scenes gets a `tour_<id>/` folder holding its package; the
+/// top-level `index.json` lists all tours, including skipped (empty) ones, and
+/// `index.html` lets the recipient browse them offline, each shown with its
+/// opening scene image from the package.
+/// Returns the number of tours packaged and the warnings raised by any of them.
+pub async fn write_all_packages<W: Write + Seek>(
+    db: &Database,
+    username: &str,
+    zip: &mut zip::ZipWriter<W>,
+    viewer_js_dir: &Path,
+    static_assets_dir: &Path,
+    options: &TourDataOptions,
+) -> Result<(usize, Vec<ExportWarning>), Box<dyn std::error::Error + Send + Sync>> {
+    let tours = db.get_tours(username, crate::database::TourOrder::default(), crate::database::SortDirection::default()).await?;
+    let static_paths: HashSet<String> = static_asset_entries(static_assets_dir).into_iter().map(|(_, zip_path)| zip_path).collect();
+    let mut index = Vec::new();
+    let mut packaged = 0;
+    let mut warnings = Vec::new();
+
+    for tour in tours {
+        let tour_id = tour.get_id() as i64;
+        let mut data = match build_tour_data(db, tour_id).await? {
+            Some(data) => data,
+            None => continue,
+        };
+        options.apply(&mut data);
+        let scene_count = data["scenes"].as_array().map_or(0, |scenes| scenes.len());
+        let folder = if scene_count > 0 {
+            let folder = format!("tour_{}", tour_id);
+            warnings.extend(write_package(db, tour_id, &data, zip, &format!("{}/", folder), viewer_js_dir, static_assets_dir).await?);
+            packaged += 1;
+            Some(folder)
+        } else {
+            None
+        };
+        let thumbnail = folder.as_ref()
+            .and_then(|folder| Some(format!("{}/{}", folder, packaged_thumbnail(&data, &static_paths)?)));
+        index.push(serde_json::json!({
+            "id": tour_id,
+            "name": tour.name,
+            "modified_at": tour.modified_at,
+            "scene_count": scene_count,
+            "folder": folder,
+            "thumbnail": thumbnail
+        }));
+    }
+
+    add_file(zip, "index.html", index_html(username, &index).as_bytes())?;
+    let index = serde_json::json!({ "owner": username, "tours": index });
+    add_file(zip, "index.json", serde_json::to_string_pretty(&index)?.as_bytes())?;
+    warnings.sort();
+    warnings.dedup();
+    Ok((packaged, warnings))
+}
+
+/// Where the tour's opening scene image sits inside its package, if it is a still that
+/// was packaged (the viewer starts at `initial_scene_id`, see `build_tour_data`)
+fn packaged_thumbnail(tour: &serde_json::Value, static_paths: &HashSet<String>) -> Option<String> {
+    let initial = tour["scenes"].as_array()?.iter()
+        .find(|s| s["id"] == tour["initial_scene_id"] && s["media_type"] != "video")?;
+    let rel = initial["file_path"].as_str()?.trim_start_matches('/');
+    if !Path::new(rel).is_file() {
+        return None;
+    }
+    let moved = static_paths.contains(rel);
+    Some(if moved { namespaced(rel) } else { rel.to_string() })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+/// Landing page of an export-all archive: one card per tour, linking into its folder's viewer.
+/// `tours` are the `index.json` entries; tours that weren't packaged are listed without a link.
+fn index_html(username: &str, tours: &[serde_json::Value]) -> String {
+    let cards: String = tours.iter().map(|tour| {
+        let name = escape_html(tour["name"].as_str().unwrap_or(""));
+        let scenes = match tour["scene_count"].as_u64().unwrap_or(0) {
+            1 => "1 scene".to_string(),
+            n => format!("{} scenes", n),
+        };
+        let picture = match tour["thumbnail"].as_str() {
+            Some(src) => format!(r#"<img src="{}" alt="" loading="lazy">"#, escape_html(src)),
+            None => r#"<div class="blank"></div>"#.to_string(),
+        };
+        match tour["folder"].as_str() {
+            Some(folder) => format!(
+                "    <li><a href=\"{}/index.html\">{}<span class=\"name\">{}</span></a><span class=\"meta\">{}</span></li>\n",
+                escape_html(folder), picture, name, scenes
+            ),
+            None => format!(
+                "    <li class=\"empty\">{}<span class=\"name\">{}</span><span class=\"meta\">No scenes, not exported</span></li>\n",
+                picture, name
+            ),
+        }
+    }).collect();
+
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>Tours of {owner}</title>
+  <style>
+    body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}
+    ul {{ list-style: none; padding: 0; display: grid; grid-template-columns: repeat(auto-fill, minmax(220px, 1fr)); gap: 1.5rem; }}
+    li a {{ color: inherit; text-decoration: none; }}
+    img, .blank {{ display: block; width: 100%; aspect-ratio: 2 / 1; object-fit: cover; background: #ddd; border-radius: 6px; }}
+    .name {{ display: block; font-weight: 600; margin-top: .5rem; }}
+    .meta {{ color: #666; font-size: .9em; }}
+    .empty {{ opacity: .6; }}
+  </style>
+</head>
+<body>
+  <h1>Tours of {owner}</h1>
+  <ul>
+{cards}  </ul>
+</body>
+</html>
+"#, owner = escape_html(username), cards = cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::ConnectionType;
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn setup_test_db() -> Database {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        let schema_sql = include_str!("./schema.sql");
+        sqlx::raw_sql(schema_sql).execute(&pool).await.unwrap();
+        Database::new(pool)
+    }
+
+    /// In-memory ZIP target whose writes fail while `failing` is set
+    struct FlakyWriter {
+        inner: std::io::Cursor<Vec<u8>>,
+        failing: std::rc::Rc<std::cell::Cell<bool>>,
+    }
+
+    impl Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.failing.get() {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl Seek for FlakyWriter {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_unwritable_files_are_skipped_with_a_warning() {
+        let failing = std::rc::Rc::new(std::cell::Cell::new(true));
+        let mut zip = zip::ZipWriter::new(FlakyWriter { inner: std::io::Cursor::new(Vec::new()), failing: failing.clone() });
+        let mut warnings = Vec::new();
+
+        add_optional_file(&mut zip, "assets/insta360/lobby.jpg", b"lobby", &mut warnings);
+        add_optional_file(&mut zip, "assets/insta360/hall.jpg", b"hall", &mut warnings);
+        assert_eq!(warnings, vec![ExportWarning::FilesSkipped]);
+
+        // The rest of the package still gets written
+        failing.set(false);
+        add_optional_file(&mut zip, "assets/insta360/attic.jpg", b"attic", &mut warnings);
+        add_file(&mut zip, "manifest.json", b"{}").unwrap();
+        let written = zip.finish().unwrap().inner.into_inner();
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(written)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["assets/insta360/attic.jpg", "manifest.json"]);
+        assert_eq!(warnings, vec![ExportWarning::FilesSkipped]);
+    }
+
+    #[tokio::test]
+    async fn test_export_resolves_target_thumbnails() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let closeup = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+
+        db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, hall, Some(lobby), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(closeup), 50.0, 5.0, ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), None).await.unwrap();
+
+        let data = build_tour_data(&db, tour_id).await.unwrap().expect("tour exists");
+        let scenes = data["scenes"].as_array().unwrap();
+        let scene_paths: HashMap<i64, &str> = scenes
+            .iter()
+            .map(|s| (s["id"].as_i64().unwrap(), s["file_path"].as_str().unwrap()))
+            .collect();
+
+        let mut transitions = 0;
+        for scene in scenes {
+            for conn in scene["connections"].as_array().unwrap() {
+                let thumb = conn["target_thumbnail"].as_str().expect("target_thumbnail resolved");
+                let target = conn["target_scene_id"].as_i64().unwrap();
+                if conn["connection_type"] == "Transition" {
+                    transitions += 1;
+                    assert_eq!(Some(&thumb), scene_paths.get(&target));
+                } else {
+                    assert_eq!(thumb, "/assets/closeups/plaque.jpg");
+                }
+            }
+        }
+        assert_eq!(transitions, 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_initial_scene_falls_back_to_first() {
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        db.clear_initial_scene(tour_id).await.unwrap();
+
+        let data = build_tour_data(&db, tour_id).await.unwrap().expect("tour exists");
+        assert_eq!(data["scenes"][0]["id"].as_i64(), Some(lobby));
+        assert_eq!(data["initial_scene_id"].as_i64(), Some(lobby));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_scene_left_out_of_export() {
+        use crate::editor::{EditorAction, EditorState};
+
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let draft = db.save_scene(tour_id, "Draft", "/assets/insta360/draft.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, lobby).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(draft), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_connection(tour_id, draft, Some(lobby), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        db.set_tour_path(tour_id, &[lobby, draft]).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+
+        // The initial scene stays visible
+        state.handle_action(EditorAction::SetSceneHidden { scene_id: lobby as i32, hidden: true }, &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv() {
+            Ok(axum::extract::ws::Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        assert_eq!(reply["type"], "error");
+
+        state.handle_action(EditorAction::SetSceneHidden { scene_id: draft as i32, hidden: true }, &tx).await.unwrap();
+        while rx.try_recv().is_ok() {}
+
+        // ...and a hidden scene can't become the initial scene
+        state.handle_action(EditorAction::SetInitialScene { scene_id: draft as i32 }, &tx).await.unwrap();
+        let reply: serde_json::Value = match rx.try_recv() {
+            Ok(axum::extract::ws::Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        assert_eq!(reply["message"], "A hidden scene can't be the initial scene.");
+
+        let data = build_tour_data(&db, tour_id).await.unwrap().expect("tour exists");
+        let scenes = data["scenes"].as_array().unwrap();
+        assert_eq!(scenes.len(), 1);
+        assert_eq!(scenes[0]["id"].as_i64(), Some(lobby));
+        assert!(scenes[0]["connections"].as_array().unwrap().is_empty(), "connection into the hidden scene exported");
+        assert_eq!(data["tour_path"], serde_json::json!([lobby]));
+
+        // The editor still sees the hidden scene and its hotspots
+        let mut reloaded = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        reloaded.load_from_database(&db).await.unwrap();
+        assert_eq!(reloaded.scenes.len(), 2);
+        let hidden = reloaded.scenes.iter().find(|s| s.id == draft as i32).unwrap();
+        assert!(hidden.hidden);
+        assert_eq!(hidden.connections.len(), 1);
+        let lobby_scene = reloaded.scenes.iter().find(|s| s.id == lobby as i32).unwrap();
+        assert_eq!(lobby_scene.connections.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scene_default_icon_applies_unless_a_connection_overrides_it() {
+        use crate::editor::{EditorAction, EditorState};
+
+        let db = setup_test_db().await;
+        db.register_user("testuser", "password").await.unwrap();
+        let tour_id = db.create_tour("testuser", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let door = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+        let info = db.save_connection(tour_id, lobby, None, 60.0, 5.0, ConnectionType::Info, Some("Front desk"), None, None).await.unwrap();
+        let styled = db.save_connection(tour_id, lobby, Some(hall), 120.0, 0.0, ConnectionType::Transition, None, None, Some(5)).await.unwrap();
+        let back = db.save_connection(tour_id, hall, Some(lobby), 190.0, 0.0, ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let mut state = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        state.load_from_database(&db).await.unwrap();
+        let (tx, mut rx) = crate::outbound::channel(64);
+        state.handle_action(EditorAction::SetSceneDefaultIcon { scene_id: lobby as i32, icon_index: Some(2) }, &tx).await.unwrap();
+        match rx.try_recv() {
+            Ok(axum::extract::ws::Message::Text(text)) => assert!(text.contains("scene_default_icon_changed"), "unexpected reply {}", text),
+            other => panic!("unexpected reply: {:?}", other),
+        }
+
+        let mut data = build_tour_data(&db, tour_id).await.unwrap().expect("tour exists");
+        TourDataOptions { default_icon_index: 1, flatten_closeups: false }.apply(&mut data);
+        let icons: std::collections::HashMap<i64, i64> = data["scenes"].as_array().unwrap().iter()
+            .flat_map(|scene| scene["connections"].as_array().unwrap().iter())
+            .map(|c| (c["id"].as_i64().unwrap(), c["icon_index"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(icons[&door], 2);
+        assert_eq!(icons[&info], 2);
+        assert_eq!(icons[&styled], 5);
+        // A scene without its own default keeps the configured one
+        assert_eq!(icons[&back], 1);
+
+        let mut reloaded = EditorState::new(tour_id, "testuser".to_string(), Some(db.clone()));
+        reloaded.load_from_database(&db).await.unwrap();
+        assert_eq!(reloaded.scenes.iter().find(|s| s.id == lobby as i32).unwrap().default_icon_index, Some(2));
+    }
+}
@@ -0,0 +1,70 @@
+//! glTF anchors module
+//!
+//! Turns a tour's scene graph into a minimal glTF 2.0 document: one node per
+//! scene, no meshes. It's an interop convenience for 3D/AR tools that want to
+//! know where the scenes are, not a 3D export of the panoramas.
+//!
+//! Scenes with a floorplan marker are placed by it (the plan spans
+//! `FLOORPLAN_SIZE` units, centred on the origin, on the ground plane); the
+//! rest are spread evenly on a circle of `CIRCLE_RADIUS`. Each node's `extras`
+//! carry the scene id, its name and the scenes its transitions lead to.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+/// Width and depth the floorplan is mapped onto, in glTF units (metres)
+pub const FLOORPLAN_SIZE: f64 = 10.0;
+/// Radius of the circle scenes without a floorplan marker are placed on
+pub const CIRCLE_RADIUS: f64 = 5.0;
+
+/// Builds the glTF document for a tour as returned by `Database::get_tour_with_scenes`.
+pub fn scene_anchors(tour: &serde_json::Value) -> serde_json::Value {
+    let scenes = tour["scenes"].as_array().cloned().unwrap_or_default();
+
+    // Floorplan marker positions are normalised (0-1) across the plan image
+    let markers: HashMap<i64, (f64, f64)> = tour["floorplan_markers"].as_array()
+        .map(|markers| markers.iter().filter_map(|m| {
+            Some((m["scene_id"].as_i64()?, (m["position"][0].as_f64()?, m["position"][1].as_f64()?)))
+        }).collect())
+        .unwrap_or_default();
+
+    let nodes: Vec<serde_json::Value> = scenes.iter().enumerate().map(|(i, scene)| {
+        let scene_id = scene["id"].as_i64().unwrap_or(0);
+        let (translation, placement) = match markers.get(&scene_id) {
+            Some((x, y)) => ([(x - 0.5) * FLOORPLAN_SIZE, 0.0, (y - 0.5) * FLOORPLAN_SIZE], "floorplan"),
+            None => {
+                let angle = TAU * i as f64 / scenes.len() as f64;
+                ([CIRCLE_RADIUS * angle.cos(), 0.0, CIRCLE_RADIUS * angle.sin()], "circle")
+            }
+        };
+        let targets: Vec<i64> = scene["connections"].as_array()
+            .map(|connections| connections.iter()
+                .filter(|c| c["connection_type"] == "Transition")
+                .filter_map(|c| c["target_scene_id"].as_i64())
+                .collect())
+            .unwrap_or_default();
+        serde_json::json!({
+            "name": scene["name"],
+            "translation": translation,
+            "extras": {
+                "scene_id": scene_id,
+                "name": scene["name"],
+                "placement": placement,
+                "connection_targets": targets
+            }
+        })
+    }).collect();
+
+    // glTF forbids empty `nodes` arrays, so a tour without scenes gets an empty scene
+    let mut gltf_scene = serde_json::json!({ "name": tour["name"] });
+    let mut document = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "Virtual Tour Editor" },
+        "scene": 0
+    });
+    if !nodes.is_empty() {
+        gltf_scene["nodes"] = serde_json::json!((0..nodes.len()).collect::<Vec<_>>());
+        document["nodes"] = serde_json::Value::Array(nodes);
+    }
+    document["scenes"] = serde_json::json!([gltf_scene]);
+    document
+}
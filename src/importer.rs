@@ -12,9 +12,32 @@
 //!
 //! Note: Export loses original DB IDs context when re-importing; we assign new IDs.
 //! Scenes are matched by name for connections mapping during this import process.
+//!
+//! `validate_upload` offers a dry run of the same parsing for an uploaded
+//! tourData.js or export ZIP, reporting problems without creating any rows.
+//!
+//! `restore_backup` feeds the tours of a JSON backup (`GET /api/backup.json`)
+//! through the same ID remapping.
+//!
+//! `import_from_pannellum` builds a tour from a Pannellum `config.json` instead:
+//! each equirectangular scene becomes a scene asset (its panorama copied into the
+//! assets folder) and each `scene`/`info` hotspot a Transition/Info connection.
+//! Pannellum's yaw (-180..180) maps onto our longitude (0..360) and its pitch is
+//! our latitude. Hotspot URLs, cubemap and multires scenes have no equivalent and
+//! are left out with a warning.
+//!
+//! `import_upload` takes either format as an upload (`POST /api/import`) and
+//! detects which one it is.
+//!
+//! Asset paths come from the client, so only plain paths under `assets/` (see
+//! `asset_relative_path`) are ever copied, probed or stored; anything else is left
+//! out with a warning.
 
 use crate::database::Database;
-use serde::Deserialize;
+use crate::editor::ConnectionType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::sync::Arc;
@@ -54,7 +77,7 @@ struct RawConnection {
     position: [f32; 2],
     name: Option<String>,
     file_path: Option<String>,
-    connection_type: Option<String>, // "Transition" | "Closeup"
+    connection_type: Option<String>, // "Transition" | "Closeup" | "Info"
     icon_index: Option<i64>,
 }
 
@@ -72,13 +95,41 @@ struct RawScene {
     connections: Vec<RawConnection>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ImportResult {
     pub tour_id: i64,
     pub scene_count: usize,
     pub connection_count: usize,
     pub closeup_count: usize,
     pub floorplan_id: Option<i64>,
+    /// Scenes, hotspots and files that were left out, and similar
+    pub warnings: Vec<String>,
+}
+
+/// Result of validating an import without touching the database.
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub tour_name: Option<String>,
+    pub scene_count: usize,
+    pub connection_count: usize,
+    /// Asset paths referenced by the tour data but not available
+    pub missing_assets: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// The path of an asset reference (`/assets/...`) relative to the server root, or `None` when it
+/// isn't a plain path under `assets/` (absolute, `..`, URLs and the like) and must not be touched.
+pub(crate) fn asset_relative_path(path: &str) -> Option<&Path> {
+    let relative = Path::new(path.strip_prefix('/').unwrap_or(path));
+    let plain = relative.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    (plain && relative.starts_with("assets") && relative.components().count() > 1).then_some(relative)
+}
+
+/// Hex SHA-256 of a file, as recorded in the `uploads` table
+fn file_hash(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    Ok(Sha256::digest(fs::read(path)?).iter().map(|b| format!("{:02x}", b)).collect())
 }
 
 /// Parse the tourData.js file and strip the leading assignment.
@@ -91,6 +142,94 @@ fn parse_tourdata_js(contents: &str) -> Result<RawTourData, String> {
     serde_json::from_str::<RawTourData>(json_slice).map_err(|e| format!("Failed to parse JSON: {e}"))
 }
 
+/// Every asset path referenced by scenes, the floorplan and connections, deduplicated in order.
+fn referenced_asset_paths(raw: &RawTourData) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let scene_paths = raw.scenes.iter().filter_map(|s| s.file_path.as_deref());
+    let floorplan_path = raw.floorplan.as_ref().and_then(|f| f.file_path.as_deref());
+    let connection_paths = raw.scenes.iter()
+        .flat_map(|s| s.connections.iter())
+        .filter_map(|c| c.file_path.as_deref());
+    scene_paths
+        .chain(floorplan_path)
+        .chain(connection_paths)
+        .filter(|p| !p.is_empty())
+        .filter(|p| seen.insert(p.to_string()))
+        .map(|p| p.to_string())
+        .collect()
+}
+
+/// Validates tourData.js contents.
+///
+/// Pure: `asset_exists` decides whether a referenced path (leading `/` stripped) is available.
+/// It only ever sees plain paths under `assets/`; anything else is reported as an error.
+pub fn validate_tourdata(contents: &str, asset_exists: impl Fn(&str) -> bool) -> ValidationReport {
+    let raw = match parse_tourdata_js(contents) {
+        Ok(raw) => raw,
+        Err(e) => return ValidationReport { errors: vec![e], ..Default::default() },
+    };
+
+    let mut missing_assets = Vec::new();
+    let mut errors = Vec::new();
+    for path in referenced_asset_paths(&raw) {
+        match asset_relative_path(&path).and_then(Path::to_str) {
+            Some(relative) if asset_exists(relative) => {}
+            Some(_) => missing_assets.push(path),
+            None => errors.push(format!("asset path {path} is not under assets/")),
+        }
+    }
+
+    ValidationReport {
+        valid: missing_assets.is_empty() && errors.is_empty(),
+        tour_name: Some(raw.name.clone()),
+        scene_count: raw.scenes.len(),
+        connection_count: raw.scenes.iter().map(|s| s.connections.len()).sum(),
+        missing_assets,
+        errors,
+    }
+}
+
+/// Validates an uploaded tourData.js or export ZIP.
+///
+/// For a ZIP, referenced assets are checked against the archive entries next to the
+/// tourData.js; for a bare tourData.js they're checked against the server's asset folders.
+pub fn validate_upload(filename: &str, data: &[u8]) -> ValidationReport {
+    let is_zip = filename.to_lowercase().ends_with(".zip") || data.starts_with(b"PK\x03\x04");
+    if !is_zip {
+        return match std::str::from_utf8(data) {
+            Ok(contents) => validate_tourdata(contents, |p| Path::new(p).exists()),
+            Err(_) => ValidationReport { errors: vec!["tourData.js is not valid UTF-8".to_string()], ..Default::default() },
+        };
+    }
+
+    let mut archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(a) => a,
+        Err(e) => return ValidationReport { errors: vec![format!("Invalid ZIP: {e}")], ..Default::default() },
+    };
+    let entries: HashSet<String> = archive.file_names().map(|n| n.to_string()).collect();
+    // Prefer js/tourData.js (export layout), fall back to a tourData.js at any root
+    let tourdata_entry = entries.iter()
+        .filter(|n| n.ends_with("tourData.js"))
+        .min_by_key(|n| (!n.ends_with("js/tourData.js"), n.len()))
+        .cloned();
+    let Some(tourdata_entry) = tourdata_entry else {
+        return ValidationReport { errors: vec!["tourData.js not found in ZIP".to_string()], ..Default::default() };
+    };
+    let root = tourdata_entry
+        .strip_suffix("js/tourData.js")
+        .or_else(|| tourdata_entry.strip_suffix("tourData.js"))
+        .unwrap_or("")
+        .to_string();
+
+    let mut contents = String::new();
+    let read = archive.by_name(&tourdata_entry).map_err(|e| e.to_string())
+        .and_then(|mut f| f.read_to_string(&mut contents).map_err(|e| e.to_string()));
+    if let Err(e) = read {
+        return ValidationReport { errors: vec![format!("Failed to read {tourdata_entry}: {e}")], ..Default::default() };
+    }
+    validate_tourdata(&contents, |p| entries.contains(&format!("{root}{p}")))
+}
+
 /// Imports a tour from an exported folder.
 ///
 /// Parameters:
@@ -102,42 +241,426 @@ fn parse_tourdata_js(contents: &str) -> Result<RawTourData, String> {
 ///
 /// Returns `ImportResult` on success.
 pub async fn import_tour_from_export(db: Arc<Database>, owner: &str, export_dir: impl AsRef<Path>, copy_assets_to: impl AsRef<Path>) -> Result<ImportResult, Box<dyn std::error::Error>> {
-    let export_dir = export_dir.as_ref();
+    import_export_dir(db, owner, export_dir.as_ref(), copy_assets_to.as_ref(), |_| true).await
+}
+
+/// `import_tour_from_export`, keeping references to files the export doesn't bring along only
+/// when `may_reference` allows them. Copied files are recorded as `owner`'s uploads.
+async fn import_export_dir(db: Arc<Database>, owner: &str, export_dir: &Path, copy_assets_to: &Path,
+                           may_reference: impl Fn(&str) -> bool) -> Result<ImportResult, Box<dyn std::error::Error>> {
     // Support sample export structure: <export>/js/tourData.js or directly under export root
     let tourdata_path_root = export_dir.join("tourData.js");
     let tourdata_path_js = export_dir.join("js").join("tourData.js");
     let tourdata_path = if tourdata_path_js.exists() { tourdata_path_js } else { tourdata_path_root };
-    if !tourdata_path.exists() { return Err(format!("tourData.js not found (looked in root and js/)").into()); }
+    if !tourdata_path.exists() { return Err("tourData.js not found (looked in root and js/)".into()); }
     let contents = fs::read_to_string(&tourdata_path)?;
     let raw = parse_tourdata_js(&contents).map_err(|e| format!("parse error: {e}"))?;
 
+    // Copy assets first; other references are kept when `may_reference` allows them
+    let mut copied = HashSet::new();
+    for path in referenced_asset_paths(&raw) {
+        let Some(relative) = asset_relative_path(&path) else { continue };
+        if copy_asset_if_exists(export_dir, relative, copy_assets_to)? {
+            match file_hash(&copy_assets_to.join(relative)) {
+                Ok(hash) => { let _ = db.record_upload(owner, &format!("/{}", relative.display()), &hash).await; }
+                Err(e) => eprintln!("Failed to hash imported asset {}: {}", path, e),
+            }
+            copied.insert(path);
+        }
+    }
+    let mut warnings = Vec::new();
+    let available = |path: &str| copied.contains(path) || may_reference(path);
+    let mut result = insert_tour(&db, owner, &raw, available, &mut warnings).await?;
+    result.warnings = warnings;
+    Ok(result)
+}
+
+#[derive(Debug, Deserialize)]
+struct PannellumConfig {
+    #[serde(default)]
+    default: PannellumDefaults,
+    #[serde(default)]
+    scenes: BTreeMap<String, PannellumScene>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PannellumDefaults {
+    first_scene: Option<String>,
+    base_path: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PannellumScene {
+    title: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    panorama: Option<String>,
+    yaw: Option<f32>,
+    pitch: Option<f32>,
+    hfov: Option<f32>,
+    north_offset: Option<f32>,
+    #[serde(default)]
+    hot_spots: Vec<PannellumHotSpot>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PannellumHotSpot {
+    pitch: f32,
+    yaw: f32,
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+    scene_id: Option<String>,
+    #[serde(rename = "URL")]
+    url: Option<String>,
+}
+
+/// Whether `contents` looks like a Pannellum config rather than tour data
+fn is_pannellum_config(contents: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(contents)
+        .is_ok_and(|v| v.get("scenes").is_some_and(|s| s.is_object()) || v.get("default").is_some_and(|d| d.is_object()))
+}
+
+/// Pannellum yaw (-180..180, or anything) as our longitude in 0..360
+fn yaw_to_lon(yaw: f32) -> f32 {
+    yaw.rem_euclid(360.0)
+}
+
+/// Resolves a panorama path from the config against its folder, refusing absolute
+/// paths, URLs and `..` so an uploaded config can't reach outside its archive.
+fn panorama_source(config_dir: &Path, base_path: Option<&str>, panorama: &str) -> Option<PathBuf> {
+    let relative = Path::new(base_path.unwrap_or("")).join(panorama);
+    let safe = !panorama.contains("://")
+        && relative.components().all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+    safe.then(|| config_dir.join(relative))
+}
+
+/// Converts a parsed Pannellum config into tour data. `file_paths` holds the stored
+/// panorama of each scene key; scenes without one are left out with a warning.
+fn pannellum_tour_data(config: &PannellumConfig, name: &str, file_paths: &HashMap<String, String>,
+                       warnings: &mut Vec<String>) -> RawTourData {
+    // Scene keys get IDs in key order; hotspots resolve their sceneId through this
+    let ids: HashMap<&str, i64> = config.scenes.keys()
+        .filter(|key| file_paths.contains_key(*key))
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), i as i64 + 1))
+        .collect();
+
+    let mut scenes = Vec::new();
+    for (key, scene) in &config.scenes {
+        let Some(&id) = ids.get(key.as_str()) else { continue };
+        let mut connections = Vec::new();
+        for (i, hotspot) in scene.hot_spots.iter().enumerate() {
+            let position = [yaw_to_lon(hotspot.yaw), hotspot.pitch.clamp(-90.0, 90.0)];
+            let label = hotspot.text.clone();
+            let connection = match hotspot.kind.as_str() {
+                "scene" => {
+                    let Some(target) = hotspot.scene_id.as_deref().and_then(|t| ids.get(t)) else {
+                        warnings.push(format!("{}: skipped hotspot {} of scene '{}', its target scene '{}' wasn't imported",
+                                              name, i + 1, key, hotspot.scene_id.as_deref().unwrap_or("")));
+                        continue;
+                    };
+                    RawConnection { id: None, target_scene_id: Some(*target), position, name: label, file_path: None,
+                                    connection_type: Some("Transition".to_string()), icon_index: None }
+                }
+                "info" => {
+                    if hotspot.url.is_some() {
+                        warnings.push(format!("{}: dropped the URL of hotspot {} of scene '{}'", name, i + 1, key));
+                    }
+                    RawConnection { id: None, target_scene_id: None, position, name: label, file_path: None,
+                                    connection_type: Some("Info".to_string()), icon_index: None }
+                }
+                other => {
+                    warnings.push(format!("{}: skipped hotspot {} of scene '{}', type '{}' isn't supported", name, i + 1, key, other));
+                    continue;
+                }
+            };
+            connections.push(connection);
+        }
+        scenes.push(RawScene {
+            id: Some(id),
+            name: scene.title.clone().unwrap_or_else(|| key.clone()),
+            file_path: file_paths.get(key).cloned(),
+            created_at: None,
+            modified_at: None,
+            initial_view_x: scene.yaw.map(yaw_to_lon),
+            initial_view_y: scene.pitch,
+            north_dir: scene.north_offset,
+            initial_fov: scene.hfov,
+            connections,
+        });
+    }
+
+    RawTourData {
+        id: None,
+        name: name.to_string(),
+        created_at: None,
+        modified_at: None,
+        initial_scene_id: config.default.first_scene.as_deref().and_then(|key| ids.get(key).copied()),
+        has_floorplan: None,
+        floorplan_id: None,
+        floorplan: None,
+        floorplan_markers: None,
+        scenes,
+    }
+}
+
+/// Imports a Pannellum `config.json` as a new tour.
+///
+/// Parameters:
+/// * `db` - database handle
+/// * `owner` - username that will own the imported tour (user must exist)
+/// * `config_path` - the config file; panoramas are resolved against its folder and `default.basePath`
+/// * `assets_dir` - the server's assets folder; panoramas are copied into its `insta360/`
+///
+/// The tour is named after `default.title`, falling back to "Pannellum tour", and opens
+/// on `default.firstScene`. Scenes whose panorama is missing are left out with a warning.
+pub async fn import_from_pannellum(db: Arc<Database>, owner: &str, config_path: impl AsRef<Path>, assets_dir: impl AsRef<Path>) -> Result<ImportResult, Box<dyn std::error::Error>> {
+    let config_path = config_path.as_ref();
+    let contents = fs::read_to_string(config_path)?;
+    let config: PannellumConfig = serde_json::from_str(&contents).map_err(|e| format!("parse error: {e}"))?;
+    if config.scenes.is_empty() {
+        return Err("Pannellum config has no scenes".into());
+    }
+    let name = config.default.title.clone().unwrap_or_else(|| "Pannellum tour".to_string());
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
+
+    let mut warnings = Vec::new();
+    let scene_dir = assets_dir.as_ref().join("insta360");
+    fs::create_dir_all(&scene_dir)?;
+    let mut file_paths = HashMap::new();
+    for (key, scene) in &config.scenes {
+        if scene.kind.as_deref().is_some_and(|kind| kind != "equirectangular") {
+            warnings.push(format!("{}: skipped scene '{}', {} panoramas aren't supported", name, key, scene.kind.as_deref().unwrap_or("")));
+            continue;
+        }
+        let panorama = scene.panorama.as_deref().unwrap_or("");
+        let source = panorama_source(config_dir, config.default.base_path.as_deref(), panorama).filter(|p| p.is_file());
+        let Some(source) = source else {
+            warnings.push(format!("{}: skipped scene '{}', panorama '{}' is missing", name, key, panorama));
+            continue;
+        };
+        let file_name = source.file_name().and_then(|n| n.to_str()).unwrap_or("panorama.jpg");
+        let stored = format!("imported_{}_{}", &uuid::Uuid::new_v4().simple().to_string()[..8], file_name);
+        fs::copy(&source, scene_dir.join(&stored))?;
+        let file_path = format!("/assets/insta360/{stored}");
+        match file_hash(&scene_dir.join(&stored)) {
+            Ok(hash) => { let _ = db.record_upload(owner, &file_path, &hash).await; }
+            Err(e) => eprintln!("Failed to hash imported panorama {}: {}", file_path, e),
+        }
+        file_paths.insert(key.clone(), file_path);
+    }
+
+    let raw = pannellum_tour_data(&config, &name, &file_paths, &mut warnings);
+    let mut result = insert_tour(&db, owner, &raw, |_| true, &mut warnings).await?;
+    result.warnings = warnings;
+    Ok(result)
+}
+
+/// Which kind of file `import_upload` found
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// tourData.js from our own export, bare or zipped with its assets
+    Export,
+    /// A zipped Pannellum config.json with its panoramas
+    Pannellum,
+}
+
+/// Why an upload couldn't be imported
+#[derive(Debug)]
+pub enum ImportError {
+    /// Not a format we recognise, or malformed; nothing is created
+    InvalidUpload(String),
+    /// Reading, copying or writing failed
+    Failed(String),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::InvalidUpload(reason) => write!(f, "Invalid import: {reason}"),
+            ImportError::Failed(reason) => write!(f, "Import failed: {reason}"),
+        }
+    }
+}
+
+/// Imports an uploaded tourData.js, export ZIP or ZIP holding a Pannellum config.json
+/// as a new tour for `owner`, detecting the format from the contents.
+///
+/// The upload is unpacked into a scratch folder under the system temp dir, removed
+/// afterwards. Export assets are copied under `server_root` keeping their `assets/...`
+/// paths; Pannellum panoramas go to `server_root/assets/insta360`. A bare Pannellum
+/// config is refused, since its panoramas can't come with it. Exports may only refer to
+/// files they bring along or that are already `owner`'s (`Database::user_asset_paths`).
+pub async fn import_upload(db: Arc<Database>, owner: &str, filename: &str, data: &[u8], server_root: &Path) -> Result<(ImportFormat, ImportResult), ImportError> {
+    let work = std::env::temp_dir().join(format!("tour-import-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work).map_err(|e| ImportError::Failed(e.to_string()))?;
+    let result = import_upload_in(db, owner, filename, data, server_root, &work).await;
+    let _ = fs::remove_dir_all(&work);
+    result
+}
+
+async fn import_upload_in(db: Arc<Database>, owner: &str, filename: &str, data: &[u8], server_root: &Path, work: &Path) -> Result<(ImportFormat, ImportResult), ImportError> {
+    let is_zip = filename.to_lowercase().ends_with(".zip") || data.starts_with(b"PK\x03\x04");
+    // Database failures are the server's fault; anything else is down to the upload
+    let classify = |e: Box<dyn std::error::Error>| match e.is::<sqlx::Error>() {
+        true => ImportError::Failed(e.to_string()),
+        false => ImportError::InvalidUpload(e.to_string()),
+    };
+
+    let owned = db.user_asset_paths(owner).await.map_err(|e| ImportError::Failed(e.to_string()))?;
+    let may_reference = |path: &str| owned.contains(path);
+
+    if !is_zip {
+        let contents = std::str::from_utf8(data).map_err(|_| ImportError::InvalidUpload("file is not valid UTF-8".to_string()))?;
+        if is_pannellum_config(contents) {
+            return Err(ImportError::InvalidUpload("upload a Pannellum config.json zipped together with its panoramas".to_string()));
+        }
+        fs::write(work.join("tourData.js"), contents).map_err(|e| ImportError::Failed(e.to_string()))?;
+        let result = import_export_dir(db, owner, work, server_root, may_reference).await.map_err(classify)?;
+        return Ok((ImportFormat::Export, result));
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| ImportError::InvalidUpload(format!("Invalid ZIP: {e}")))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| ImportError::InvalidUpload(format!("Invalid ZIP: {e}")))?;
+        // Entries with absolute or `..` paths are skipped rather than written outside `work`
+        let Some(relative) = entry.enclosed_name().map(Path::to_path_buf) else { continue };
+        if entry.is_dir() {
+            continue;
+        }
+        let dest = work.join(relative);
+        let write = dest.parent().map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::File::create(&dest))
+            .and_then(|mut file| std::io::copy(&mut entry, &mut file));
+        write.map_err(|e| ImportError::Failed(e.to_string()))?;
+    }
+
+    // The shallowest config.json / tourData.js wins, as in `validate_upload`
+    let files: Vec<PathBuf> = walkdir::WalkDir::new(work).into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    let shallowest = |name: &str| files.iter()
+        .filter(|p| p.file_name().is_some_and(|n| n == name))
+        .min_by_key(|p| p.components().count())
+        .cloned();
+
+    if let Some(config) = shallowest("config.json").filter(|p| fs::read_to_string(p).is_ok_and(|c| is_pannellum_config(&c))) {
+        let result = import_from_pannellum(db, owner, &config, server_root.join("assets")).await.map_err(classify)?;
+        return Ok((ImportFormat::Pannellum, result));
+    }
+    if let Some(tourdata) = shallowest("tourData.js") {
+        let parent = tourdata.parent().unwrap_or(work);
+        let root = if parent.file_name().is_some_and(|n| n == "js") { parent.parent().unwrap_or(work) } else { parent };
+        let result = import_export_dir(db, owner, root, server_root, may_reference).await.map_err(classify)?;
+        return Ok((ImportFormat::Export, result));
+    }
+    Err(ImportError::InvalidUpload("neither tourData.js nor a Pannellum config.json found in ZIP".to_string()))
+}
+
+/// What restoring a JSON backup created
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreReport {
+    /// New tour IDs, in backup order
+    pub tour_ids: Vec<i64>,
+    pub scene_count: usize,
+    pub connection_count: usize,
+    /// Scenes, closeups and floorplans left out because their file is missing, and similar
+    pub warnings: Vec<String>,
+}
+
+/// Why a backup couldn't be restored
+#[derive(Debug)]
+pub enum RestoreError {
+    /// Not JSON, or not an array of tours
+    InvalidBackup(String),
+    /// A write failed; tours restored before it are kept
+    Database { tour_name: String, source: sqlx::Error },
+}
+
+impl std::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestoreError::InvalidBackup(reason) => write!(f, "Invalid backup: {reason}"),
+            RestoreError::Database { tour_name, source } => write!(f, "Failed to restore '{tour_name}': {source}"),
+        }
+    }
+}
+
+/// Recreates the tours of a backup (the array `GET /api/backup.json` returns) for `owner`.
+///
+/// Everything gets new IDs; connection targets, floorplan markers and initial scenes are
+/// remapped onto them. Assets whose file isn't available (`asset_exists`, given the path
+/// with its leading `/` stripped) are skipped with a warning, along with connections into them.
+/// Paths outside `assets/` are skipped without asking `asset_exists`.
+///
+/// # Returns
+/// * `Ok(RestoreReport)` - What was created.
+/// * `Err(RestoreError)` - If the backup isn't valid JSON of the expected shape (nothing is created),
+///   or a database error occurred part way.
+pub async fn restore_backup(db: &Database, owner: &str, contents: &str, asset_exists: impl Fn(&str) -> bool) -> Result<RestoreReport, RestoreError> {
+    let tours: Vec<RawTourData> = serde_json::from_str(contents).map_err(|e| RestoreError::InvalidBackup(e.to_string()))?;
+    let available = |path: &str| asset_exists(path.trim_start_matches('/'));
+
+    let mut report = RestoreReport::default();
+    for raw in &tours {
+        let result = insert_tour(db, owner, raw, available, &mut report.warnings).await
+            .map_err(|source| RestoreError::Database { tour_name: raw.name.clone(), source })?;
+        report.tour_ids.push(result.tour_id);
+        report.scene_count += result.scene_count;
+        report.connection_count += result.connection_count;
+    }
+    Ok(report)
+}
+
+/// Creates a tour for `owner` from parsed tour data, assigning new IDs and remapping
+/// connection targets, floorplan markers and the initial scene onto them.
+///
+/// Assets for which `asset_available` is false are left out (with a line in `warnings`),
+/// and so are connections leading to a left-out scene.
+async fn insert_tour(db: &Database, owner: &str, raw: &RawTourData, asset_available: impl Fn(&str) -> bool,
+                     warnings: &mut Vec<String>) -> Result<ImportResult, sqlx::Error> {
+    // Paths outside assets/ are never available, whatever the caller's check says
+    let missing = |path: Option<&str>| path.is_some_and(|p| !p.is_empty() && (asset_relative_path(p).is_none() || !asset_available(p)));
+
     // Create new tour (ignore original id / timestamps)
     let new_tour_id = db.create_tour(owner, &raw.name, "").await?;
 
     // Map of old scene id -> new scene asset id
-    use std::collections::HashMap;
     let mut scene_id_map: HashMap<i64, i64> = HashMap::new();
     let mut name_to_new_scene: HashMap<String, i64> = HashMap::new();
 
-    // Copy & insert scenes
+    // Insert scenes
+    let mut scene_count = 0usize;
     for scene in &raw.scenes {
-        // Determine file path; maintain relative path inside assets folder
-        if let Some(fp) = &scene.file_path {
-            copy_asset_if_exists(export_dir, fp, copy_assets_to.as_ref())?;
+        if missing(scene.file_path.as_deref()) {
+            warnings.push(format!("{}: skipped scene '{}', file {} is missing", raw.name, scene.name, scene.file_path.as_deref().unwrap_or("")));
+            continue;
         }
         let new_scene_id = db.save_scene(new_tour_id, &scene.name, scene.file_path.as_deref().unwrap_or(""), scene.initial_view_x, scene.initial_view_y, scene.north_dir).await?;
         if let Some(old_id) = scene.id { scene_id_map.insert(old_id, new_scene_id); }
         name_to_new_scene.insert(scene.name.clone(), new_scene_id);
+        scene_count += 1;
     }
 
     // Floorplan (if any)
     let mut new_floorplan_id: Option<i64> = None;
     if raw.has_floorplan.unwrap_or(false) {
         if let Some(fp) = raw.floorplan.as_ref() {
-            if let Some(path) = &fp.file_path { copy_asset_if_exists(export_dir, path, copy_assets_to.as_ref())?; }
-            let fname = fp.name.clone().unwrap_or_else(|| "Floorplan".to_string());
-            let id = db.save_floorplan(new_tour_id, &fname, fp.file_path.as_deref().unwrap_or("")).await?;
-            new_floorplan_id = Some(id);
+            if missing(fp.file_path.as_deref()) {
+                warnings.push(format!("{}: skipped floorplan, file {} is missing", raw.name, fp.file_path.as_deref().unwrap_or("")));
+            } else {
+                let fname = fp.name.clone().unwrap_or_else(|| "Floorplan".to_string());
+                let id = db.save_floorplan(new_tour_id, &fname, fp.file_path.as_deref().unwrap_or("")).await?;
+                new_floorplan_id = Some(id);
+            }
         }
     }
 
@@ -146,15 +669,36 @@ pub async fn import_tour_from_export(db: Arc<Database>, owner: &str, export_dir:
     let mut closeup_count = 0usize;
     for scene in &raw.scenes {
         // Lookup new start scene id
-        let start_new_id = scene_id_map.get(&scene.id.unwrap_or(-1)).copied().unwrap_or_else(|| *name_to_new_scene.get(&scene.name).expect("scene name present"));
+        let start_new_id = scene.id.and_then(|old| scene_id_map.get(&old).copied())
+            .or_else(|| name_to_new_scene.get(&scene.name).copied());
+        let Some(start_new_id) = start_new_id else { continue };
         for conn in &scene.connections {
-            if let Some(fp) = &conn.file_path { copy_asset_if_exists(export_dir, fp, copy_assets_to.as_ref())?; }
-            let is_transition = matches!(conn.connection_type.as_deref(), Some("Transition"));
-            let end_id = conn.target_scene_id.and_then(|old| scene_id_map.get(&old).copied());
+            let connection_type = conn.connection_type.as_deref()
+                .and_then(ConnectionType::parse)
+                .unwrap_or(ConnectionType::Closeup);
+            let mut end_id = conn.target_scene_id.and_then(|old| scene_id_map.get(&old).copied());
+            if connection_type == ConnectionType::Closeup {
+                if missing(conn.file_path.as_deref()) {
+                    warnings.push(format!("{}: skipped closeup '{}', file {} is missing",
+                                          raw.name, conn.name.as_deref().unwrap_or(""), conn.file_path.as_deref().unwrap_or("")));
+                    continue;
+                }
+                // Closeups point at their own (non-scene) asset
+                if let Some(fp) = conn.file_path.as_deref().filter(|p| !p.is_empty()) {
+                    end_id = Some(db.save_closeup(new_tour_id, conn.name.as_deref().unwrap_or("Closeup"), fp, None).await?);
+                }
+            } else if connection_type == ConnectionType::Transition && conn.target_scene_id.is_some() && end_id.is_none() {
+                // Its target scene was skipped
+                continue;
+            } else if missing(conn.file_path.as_deref()) {
+                warnings.push(format!("{}: skipped hotspot '{}', file {} is missing",
+                                      raw.name, conn.name.as_deref().unwrap_or(""), conn.file_path.as_deref().unwrap_or("")));
+                continue;
+            }
             let icon_type = conn.icon_index.map(|v| v as i32);
-            db.save_connection(new_tour_id, start_new_id, end_id, conn.position[0], conn.position[1], is_transition, conn.name.as_deref(), conn.file_path.as_deref(), icon_type).await?;
+            db.save_connection(new_tour_id, start_new_id, end_id, conn.position[0], conn.position[1], connection_type, conn.name.as_deref(), conn.file_path.as_deref(), icon_type).await?;
             connection_count += 1;
-            if !is_transition { closeup_count += 1; }
+            if connection_type == ConnectionType::Closeup { closeup_count += 1; }
         }
     }
 
@@ -170,26 +714,30 @@ pub async fn import_tour_from_export(db: Arc<Database>, owner: &str, export_dir:
 
     // Set initial scene if we can map it
     if let Some(old_initial) = raw.initial_scene_id { if let Some(mapped) = scene_id_map.get(&old_initial) { let _ = db.set_initial_scene(new_tour_id, *mapped).await; } }
+    // Markers and closeups are written without announcing the tour; do it once it's complete
+    db.notify_tour_changed(new_tour_id).await;
 
-    Ok(ImportResult { tour_id: new_tour_id, scene_count: raw.scenes.len(), connection_count, closeup_count, floorplan_id: new_floorplan_id })
+    Ok(ImportResult { tour_id: new_tour_id, scene_count, connection_count, closeup_count, floorplan_id: new_floorplan_id, warnings: Vec::new() })
 }
 
-fn copy_asset_if_exists(export_root: &Path, relative_path: &str, dest_assets_root: &Path) -> Result<(), Box<dyn std::error::Error>> {
+/// Copies `rel` (an `asset_relative_path`) from the export into the same place under
+/// `dest_assets_root`, returning whether a new file was written.
+fn copy_asset_if_exists(export_root: &Path, rel: &Path, dest_assets_root: &Path) -> Result<bool, Box<dyn std::error::Error>> {
     // Paths in export likely like "assets/insta360/XYZ.jpg"; we preserve after dest root.
-    let rel = relative_path.trim_start_matches('/');
     let source = export_root.join(rel);
-    if source.exists() {
+    if source.is_file() {
         let dest = dest_assets_root.join(rel);
         if let Some(parent) = dest.parent() { fs::create_dir_all(parent)?; }
         // Only copy if not already present (avoid overwriting newer local edits)
         if !dest.exists() {
             fs::copy(&source, &dest)?;
             println!("Imported asset file {:?} -> {:?}", source, dest);
+            return Ok(true);
         }
     } else {
-        eprintln!("Warning: asset referenced but missing in export: {}", relative_path);
+        eprintln!("Warning: asset referenced but missing in export: {}", rel.display());
     }
-    Ok(())
+    Ok(false)
 }
 
 #[cfg(test)]
@@ -210,4 +758,109 @@ mod tests {
         let parsed = parse_tourdata_js(sample).unwrap();
         assert_eq!(parsed.name, "Sample");
     }
+
+    #[test]
+    fn test_validate_tourdata_reports_missing_assets() {
+        let sample = r#"const tourData = { "name": "Campus", "floorplan_markers": [], "scenes": [
+            { "id": 1, "name": "Lobby", "file_path": "/assets/insta360/lobby.jpg", "connections": [
+                { "target_scene_id": 2, "position": [10, 0], "connection_type": "Transition" },
+                { "target_scene_id": 7, "position": [40, 2], "connection_type": "Closeup", "file_path": "/assets/closeups/plaque.jpg" } ] },
+            { "id": 2, "name": "Hall", "file_path": "/assets/insta360/hall.jpg", "connections": [
+                { "target_scene_id": 1, "position": [190, 0], "connection_type": "Transition" } ] } ] };"#;
+
+        let all_present = validate_tourdata(sample, |_| true);
+        assert!(all_present.valid);
+        assert_eq!(all_present.tour_name.as_deref(), Some("Campus"));
+        assert_eq!(all_present.scene_count, 2);
+        assert_eq!(all_present.connection_count, 3);
+        assert!(all_present.missing_assets.is_empty());
+        assert!(all_present.errors.is_empty());
+
+        let no_closeup = validate_tourdata(sample, |p| p != "assets/closeups/plaque.jpg");
+        assert!(!no_closeup.valid);
+        assert_eq!(no_closeup.missing_assets, vec!["/assets/closeups/plaque.jpg".to_string()]);
+
+        // Paths outside assets/ are never probed
+        let escaping = sample.replace("/assets/insta360/hall.jpg", "/assets/../config.toml");
+        let probed = std::cell::RefCell::new(Vec::new());
+        let report = validate_tourdata(&escaping, |p| { probed.borrow_mut().push(p.to_string()); true });
+        assert!(!report.valid);
+        assert!(report.missing_assets.is_empty());
+        assert_eq!(report.errors, vec!["asset path /assets/../config.toml is not under assets/".to_string()]);
+        assert!(!probed.borrow().iter().any(|p| p.contains("config.toml")));
+
+        let broken = validate_tourdata("const tourData = { \"scenes\": ", |_| true);
+        assert!(!broken.valid);
+        assert_eq!(broken.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_from_pannellum_builds_the_scene_graph() {
+        let db = Arc::new(setup_test_db().await);
+        db.register_user("pano", "secret").await.unwrap();
+
+        let dir = std::env::temp_dir().join(format!("pannellum_test_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("images")).unwrap();
+        fs::write(dir.join("images/lobby.jpg"), b"lobby").unwrap();
+        fs::write(dir.join("images/hall.jpg"), b"hall").unwrap();
+        fs::write(dir.join("config.json"), r#"{
+            "default": { "firstScene": "lobby", "basePath": "images", "title": "Old Mill" },
+            "scenes": {
+                "lobby": { "title": "Lobby", "panorama": "lobby.jpg", "yaw": -90, "pitch": 5, "hfov": 100, "northOffset": 30,
+                           "hotSpots": [ { "pitch": -10, "yaw": -45, "type": "scene", "text": "To the hall", "sceneId": "hall" } ] },
+                "hall": { "title": "Hall", "panorama": "hall.jpg" }
+            }
+        }"#).unwrap();
+
+        let result = import_from_pannellum(db.clone(), "pano", dir.join("config.json"), dir.join("assets")).await.unwrap();
+        assert_eq!((result.scene_count, result.connection_count), (2, 1));
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        let tour = db.get_tour_with_scenes("pano", result.tour_id).await.unwrap().unwrap();
+        assert_eq!(tour["name"], "Old Mill");
+        let scenes = tour["scenes"].as_array().unwrap();
+        let scene = |name: &str| scenes.iter().find(|s| s["name"] == name).unwrap().clone();
+        let (lobby, hall) = (scene("Lobby"), scene("Hall"));
+        assert_eq!(tour["initial_scene_id"], lobby["id"]);
+        assert_eq!((lobby["initial_view_x"].as_f64(), lobby["initial_view_y"].as_f64()), (Some(270.0), Some(5.0)));
+        assert_eq!(lobby["north_dir"].as_f64(), Some(30.0));
+
+        // The panorama was copied in under a new name
+        let stored = lobby["file_path"].as_str().unwrap().strip_prefix("/assets/").unwrap();
+        assert_eq!(fs::read(dir.join("assets").join(stored)).unwrap(), b"lobby");
+
+        let connections = lobby["connections"].as_array().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0]["connection_type"], "Transition");
+        assert_eq!(connections[0]["target_scene_id"], hall["id"]);
+        assert_eq!(connections[0]["name"], "To the hall");
+        assert_eq!(connections[0]["position"], serde_json::json!([315.0, -10.0]));
+        assert!(hall["connections"].as_array().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_upload_drops_paths_outside_the_callers_assets() {
+        let db = Arc::new(setup_test_db().await);
+        db.register_user("importer", "secret").await.unwrap();
+        db.record_upload("importer", "/assets/insta360/mine.jpg", "hash").await.unwrap();
+
+        let tourdata = r#"const tourData = { "name": "Sneaky", "floorplan_markers": [], "scenes": [
+            { "id": 1, "name": "Mine", "file_path": "/assets/insta360/mine.jpg", "connections": [
+                { "position": [10, 0], "connection_type": "Info", "name": "Secrets", "file_path": "/etc/passwd" } ] },
+            { "id": 2, "name": "Up", "file_path": "/assets/../config.toml", "connections": [] },
+            { "id": 3, "name": "Theirs", "file_path": "/assets/insta360/theirs.jpg", "connections": [] } ] };"#;
+        let root = std::env::temp_dir().join(format!("import_paths_test_{}", uuid::Uuid::new_v4()));
+        let (format, result) = import_upload(db.clone(), "importer", "tourData.js", tourdata.as_bytes(), &root).await.unwrap();
+        assert_eq!(format, ImportFormat::Export);
+        assert_eq!((result.scene_count, result.connection_count), (1, 0));
+        assert_eq!(result.warnings.len(), 3, "{:?}", result.warnings);
+
+        let tour = db.get_tour_with_scenes("importer", result.tour_id).await.unwrap().unwrap();
+        let scenes = tour["scenes"].as_array().unwrap();
+        assert_eq!(scenes.len(), 1);
+        assert_eq!(scenes[0]["file_path"], "/assets/insta360/mine.jpg");
+        assert!(!root.exists());
+    }
 }
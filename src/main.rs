@@ -12,20 +12,29 @@ mod tour;
 mod config;
 mod user;
 mod importer; // new module for re-importing exported tours
+mod exporter;
+mod outbound;
+mod cubemap;
+mod presence;
+mod chunked_upload;
+mod sprite;
+mod gltf;
+mod throttle;
 
 use tour::Tour;
 
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State, Path, DefaultBodyLimit,
+        State, Path, Query, DefaultBodyLimit, Multipart,
     },
     response::{Html, IntoResponse},
     Json,
     routing::{get, post, delete},
     Router,
-    http::{StatusCode, HeaderValue},
+    http::{StatusCode, HeaderValue, HeaderMap},
 };
+use axum_server::tls_rustls::RustlsConfig;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::CorsLayer,
@@ -33,29 +42,51 @@ use tower_http::{
     set_header::SetResponseHeaderLayer,
 };
 use std::sync::Arc;
-use std::collections::HashMap;
-use sqlx::SqlitePool;
-use tokio::sync::{mpsc, RwLock, Mutex};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::{RwLock, Mutex};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use serde::Deserialize;
 use futures::{StreamExt, SinkExt};
-use std::io::Write;
 
 use database::Database;
 use user::User;
 
-// Global connection counter
-static ACTIVE_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+// Lifetime counters exposed on /metrics
+static TOTAL_LOGINS: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_TOURS_CREATED: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_EXPORTS: AtomicUsize = AtomicUsize::new(0);
 
-// Lazy database instance
+// Database instance (initialized at startup)
 static DATABASE: RwLock<Option<Arc<Database>>> = RwLock::const_new(None);
+const DATABASE_PATH: &str = "tours.db";
 
 // Global editor sessions store - key format: "username_tourid"
-static EDITOR_SESSIONS: RwLock<Option<HashMap<String, editor::EditorState>>> = RwLock::const_new(None);
+static EDITOR_SESSIONS: RwLock<Option<HashMap<String, EditorSession>>> = RwLock::const_new(None);
+
+// Editor state shared by every connection that has the tour open; actions lock it while they run
+type SharedEditorState = Arc<Mutex<editor::EditorState>>;
+
+struct EditorSession {
+    state: SharedEditorState,
+    /// Connections holding the session; it is flushed and dropped when the last one lets go
+    connections: usize,
+}
+
+// Who has which tour open in the editor
+static PRESENCE: Mutex<Option<presence::PresenceRegistry>> = Mutex::const_new(None);
+
+// Recent tour creations per user (created on first use with the configured limits)
+static TOUR_CREATIONS: Mutex<Option<throttle::CreationThrottle>> = Mutex::const_new(None);
+
+// Chunked uploads in progress (created on first use with the configured chunk directory)
+static CHUNKED_UPLOADS: Mutex<Option<chunked_upload::ChunkedUploads>> = Mutex::const_new(None);
 
 #[derive(Clone)]
 pub struct AppState {
     pub database: Arc<Database>,
+    pub config: Arc<config::Config>,
+    /// Open WebSocket connections, capped by `server.max_connections`
+    pub connections: Arc<AtomicUsize>,
 }
 
 #[derive(Deserialize)]
@@ -75,6 +106,76 @@ pub struct CreateTourRequest {
     name: String,
 }
 
+/// Fields of `PATCH /api/tours/:id`; omitted fields are left unchanged, empty strings clear them
+#[derive(Deserialize)]
+pub struct PatchTourRequest {
+    logo_path: Option<String>,
+    primary_color: Option<String>,
+    welcome_text: Option<String>,
+    /// Absolute http(s) URL share links for this tour are built on; empty clears it
+    share_base_url: Option<String>,
+    /// Lets other users start new tours from a copy of this one
+    is_template: Option<bool>,
+    /// Share links only open for signed-in users
+    requires_auth: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateSnapshotRequest {
+    label: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChunkedUploadInit {
+    filename: String,
+    /// Same values as the `type` field of `/upload-asset`
+    #[serde(rename = "type", default)]
+    kind: String,
+    total_size: u64,
+}
+
+#[derive(Deserialize)]
+pub struct MergeScenesRequest {
+    keep_id: i64,
+    remove_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TransferTourRequest {
+    username: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    password: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TourListQuery {
+    #[serde(default)]
+    order_by: database::TourOrder,
+    #[serde(default)]
+    direction: database::SortDirection,
+}
+
+#[derive(Deserialize)]
+pub struct ScenePathQuery {
+    from: i64,
+    to: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Export closeups as info hotspots for viewers without closeup overlays
+    #[serde(default)]
+    flatten_closeups: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AssetListQuery {
+    kind: database::AssetKind,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "action", content = "data")]
 enum ClientMessage {
@@ -86,10 +187,22 @@ enum ClientMessage {
     Quit,
     Logout,
     Help,
-    ShowTours,
+    /// Sends the tour list, ordered like `GET /api/tours`; later lists on this connection (after a
+    /// create, delete, ...) keep the order. Without `data` the default order is used
+    ShowTours(Option<TourListQuery>),
     CreateTour { name: String },
-    EditTour { tour_id: i32, editor_action: Option<editor::EditorAction> },
+    EditTour { tour_id: i32, editor_action: Option<serde_json::Value> },
     DeleteTour { tour_id: i32 },
+    /// Copies a tour into a new one; `name` defaults to "<original> (copy)"
+    DuplicateTour { tour_id: i32, #[serde(default)] name: Option<String> },
+    LoadScenesPage { tour_id: i32, offset: i64, limit: i64 },
+    /// Uploaded panoramas, each flagged `used` when already a scene of the tour
+    ListUploads { tour_id: i32 },
+    PresenceUpdate { tour_id: i32, cursor: Option<presence::HotspotPosition> },
+    /// Throws away the editor session's unsaved (deferred) edits and reloads it from the database
+    DiscardChanges { tour_id: i32 },
+    /// The `tourData` object an export would embed in js/tourData.js, for clients packaging tours themselves
+    ExportTourData { tour_id: i32 },
 }
 
 #[tokio::main]
@@ -128,11 +241,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Starting {} v{}", config.app.name, config.app.version);
     println!("Server configuration: {}", config.server_address());
-    println!("Database will be initialized when first client connects");
+    exporter::log_viewer_files(std::path::Path::new(&config.export.viewer_js_dir));
 
-    // Get database instance
-    let database = get_database().await;
-    let app_state = AppState { database };
+    // Initialize the database before accepting connections so schema problems surface now
+    let slow_query = std::time::Duration::from_millis(config.database.slow_query_ms);
+    let database = match init_database(DATABASE_PATH, slow_query).await {
+        Ok(database) => database,
+        Err(e) => {
+            eprintln!("Failed to initialize database {}: {}", DATABASE_PATH, e);
+            std::process::exit(1);
+        }
+    };
+    let app_state = AppState { database, config: Arc::new(config.clone()), connections: Default::default() };
 
     // Start periodic session cleanup task
     let cleanup_db = app_state.database.clone();
@@ -150,22 +270,182 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
+    // Start periodic autosave of deferred editor writes
+    let autosave_secs = config.editor.autosave_secs;
+    if autosave_secs > 0 {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(autosave_secs));
+            loop {
+                interval.tick().await;
+
+                let report = flush_all_editor_sessions().await;
+                if report.writes > 0 || report.busy > 0 {
+                    println!("Autosave flushed {} deferred editor writes from {} sessions ({} busy, retried next tick)",
+                             report.writes, report.sessions, report.busy);
+                }
+            }
+        });
+    }
+
+    // Start periodic share token pruning task
+    let prune_db = app_state.database.clone();
+    let prune_interval = config.sharing.prune_interval_secs.max(1);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(prune_interval));
+        loop {
+            interval.tick().await;
+
+            match prune_db.prune_expired_share_tokens().await {
+                Ok(0) => {}
+                Ok(n) => println!("Pruned {} expired share tokens", n),
+                Err(e) => eprintln!("Failed to prune share tokens: {}", e),
+            }
+        }
+    });
+
+    // Start periodic expiry of abandoned chunked uploads
+    let upload_expiry = std::time::Duration::from_secs(config.uploads.expiry_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
+        loop {
+            interval.tick().await;
+
+            if let Some(ref mut uploads) = *CHUNKED_UPLOADS.lock().await {
+                let expired = uploads.expire(upload_expiry);
+                if expired > 0 {
+                    println!("Discarded {} stale chunked uploads", expired);
+                }
+            }
+        }
+    });
+
+    // Start periodic audit of asset files (report only)
+    let audit_interval = config.uploads.audit_interval_secs;
+    if audit_interval > 0 {
+        let audit_db = app_state.database.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(audit_interval));
+            loop {
+                interval.tick().await;
+
+                match audit_db.audit_assets(None).await {
+                    Ok(missing) if missing.is_empty() => {}
+                    Ok(missing) => {
+                        eprintln!("Asset audit: {} assets point at missing files", missing.len());
+                        for asset in &missing {
+                            eprintln!("  asset {} (tour {}): {}", asset.id, asset.tour_id, asset.file_path);
+                        }
+                    }
+                    Err(e) => eprintln!("Asset audit failed: {}", e),
+                }
+            }
+        });
+    }
+
     // Build the application with routes
-    let app = Router::new()
+    let app = build_router(app_state, &config);
+
+    // Load TLS material up front so a bad cert/key path fails at startup
+    let tls = match &config.tls {
+        Some(tls) => Some(load_tls_config(tls).await?),
+        None => None,
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!("Server starting on {}://{}:{}", scheme, config.server.host, config.server.port);
+    
+    // Parse host address for server binding
+    let host: std::net::IpAddr = config.server.host.parse()
+        .unwrap_or_else(|_| std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)));
+    
+    let listener = std::net::TcpListener::bind((host, config.server.port))?;
+    serve(listener, app, tls).await?;
+    
+    Ok(())
+}
+
+// Read the PEM certificate chain and private key named in the [tls] config section
+async fn load_tls_config(tls: &config::TlsConfig) -> std::io::Result<RustlsConfig> {
+    // Only the ring provider is compiled in; installing twice is harmless
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await
+}
+
+// Serve over HTTPS when TLS is configured, plain HTTP otherwise
+async fn serve(listener: std::net::TcpListener, app: Router, tls: Option<RustlsConfig>) -> std::io::Result<()> {
+    listener.set_nonblocking(true)?;
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener, tls)
+                .serve(app.into_make_service())
+                .await
+        }
+        None => {
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            axum::serve(listener, app).await
+        }
+    }
+}
+
+// Assemble all routes, static file services and middleware
+fn build_router(app_state: AppState, config: &config::Config) -> Router {
+    let static_cache = HeaderValue::from_str(&config::Config::cache_control(config.server.static_cache_secs))
+        .unwrap_or(HeaderValue::from_static("public, max-age=86400"));
+    let assets_cache = HeaderValue::from_str(&config::Config::cache_control(config.server.assets_cache_secs))
+        .unwrap_or(HeaderValue::from_static("public, max-age=3600"));
+
+    Router::new()
         // WebSocket route
         .route("/connect", get(websocket_handler))
         // API routes
         .route("/api/login", post(login_handler))
         .route("/api/register", post(register_handler))
+        .route("/api/account", delete(delete_account_handler))
         .route("/api/tours", get(get_tours_handler))
         .route("/api/tours", post(create_tour_handler))
-        .route("/api/tours/:id", delete(delete_tour_handler))
+        .route("/api/tours/thumbnails-sprite", get(thumbnails_sprite_handler))
+        .route("/api/tours/from-template/:id", post(create_tour_from_template_handler))
+        .route("/api/tours/:id", delete(delete_tour_handler).patch(patch_tour_handler))
+        .route("/api/tours/:id/transfer", post(transfer_tour_handler))
+        .route("/api/tours/:id/share", post(create_share_handler))
+        .route("/api/shared/:token", get(shared_tour_handler))
+        .route("/view/slug/:slug", get(slug_tour_handler))
         // Upload route
         .route("/upload-asset", post(editor::upload_asset_handler))
+        .route("/upload-asset/init", post(chunked_upload_init_handler))
+        .route("/upload-asset/chunk/:id", post(chunked_upload_chunk_handler).get(chunked_upload_status_handler))
+        .route("/upload-asset/complete/:id", post(chunked_upload_complete_handler))
+        .route("/api/import", post(import_handler))
+        .route("/api/import/validate", post(import_validate_handler))
         // Export route
         .route("/api/export/:tour_id", get(export_tour_handler))
-        // Assets list route  
+        .route("/api/export-all", get(export_all_handler))
+        .route("/api/backup.json", get(backup_json_handler))
+        .route("/api/restore", post(restore_backup_handler))
+        .route("/api/viewer-info", get(viewer_info_handler))
+        .route("/api/viewer/engine-hash", get(engine_hash_handler))
+        .route("/api/editor/actions", get(editor_actions_handler))
+        // Assets list route (raw uploads on disk)
         .route("/api/assets", get(list_assets_handler))
+        .route("/api/uploads", get(list_assets_handler))
+        // Assets registered to a tour
+        .route("/api/tours/:id/assets", get(tour_assets_handler))
+        .route("/api/tours/:id/scenes/:scene_id/connections", get(scene_connections_handler))
+        .route("/api/tours/:id/completeness", get(tour_completeness_handler))
+        .route("/api/tours/:id/stats", get(tour_stats_handler))
+        .route("/api/tours/:id/modified", get(tour_modified_handler))
+        .route("/api/tours/:id/scenes.gltf", get(scene_anchors_gltf_handler))
+        .route("/api/tours/:id/path", get(scene_path_handler))
+        .route("/api/tours/:id/snapshots", get(list_snapshots_handler).post(create_snapshot_handler))
+        .route("/api/tours/:id/snapshots/:snapshot_id/restore", post(restore_snapshot_handler))
+        .route("/api/assets/:id/cubemap/:face", get(cubemap_face_handler))
+        .route("/api/assets/:id/download", get(asset_download_handler))
+        .route("/api/scenes/:id/incoming", get(incoming_connections_handler))
+        .route("/api/assets/audit", get(asset_audit_handler))
+        .route("/api/scenes/:id/copy-connections-from/:source_id", post(copy_connections_handler))
+        .route("/api/scenes/merge", post(merge_scenes_handler))
+        // Monitoring
+        .route("/metrics", get(metrics_handler))
+        .route("/sitemap.xml", get(sitemap_handler))
         // Static HTML pages
         .route("/", get(index_page))
         .route("/login", get(login_page))
@@ -176,7 +456,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             ServiceBuilder::new()
                 .layer(SetResponseHeaderLayer::overriding(
                     axum::http::header::CACHE_CONTROL, 
-                    HeaderValue::from_static("public, max-age=86400") // Cache for 24 hours
+                    static_cache
                 ))
                 .service(ServeDir::new("static"))
         )
@@ -184,7 +464,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             ServiceBuilder::new()
                 .layer(SetResponseHeaderLayer::overriding(
                     axum::http::header::CACHE_CONTROL, 
-                    HeaderValue::from_static("public, max-age=3600") // Cache assets for 1 hour
+                    assets_cache
                 ))
                 .service(ServeDir::new("assets"))
         )
@@ -193,147 +473,270 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .layer(DefaultBodyLimit::max(120 * 1024 * 1024)) // 100MB limit
                 .layer(CorsLayer::permissive())
         )
-        .with_state(app_state);
-
-    println!("Server starting on http://{}:{}", config.server.host, config.server.port);
-    
-    // Parse host address for server binding
-    let host: std::net::IpAddr = config.server.host.parse()
-        .unwrap_or_else(|_| std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)));
-    
-    let listener = tokio::net::TcpListener::bind((host, config.server.port)).await?;
-    axum::serve(listener, app).await?;
-    
-    Ok(())
+        .with_state(app_state)
 }
 
-async fn initialize_db() -> SqlitePool {
-    use std::path::Path;
-    use std::fs;
-    use sqlx::sqlite::SqlitePoolOptions;
-    
-    let db_path = "tours.db";
-    let schema_sql = include_str!("./schema.sql");
-    
-    // Create database file if it doesn't exist
-    if !Path::new(db_path).exists() {
-        fs::File::create(db_path).expect("Failed to create database file");
-        println!("Created new database file: {}", db_path);
-    }
-    
-    // Create connection pool
+// Open (creating if needed) the SQLite database, bring its schema up to date and
+// store it in the DATABASE global; calls slower than `slow_query` are logged
+async fn init_database(db_path: &str, slow_query: std::time::Duration) -> Result<Arc<Database>, sqlx::Error> {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true);
     let pool = SqlitePoolOptions::new()
         .max_connections(10)
-        .connect(&format!("sqlite:{}", db_path))
-        .await
-        .expect("Failed to create database pool");
-    
-    // Execute schema to create tables
-    sqlx::raw_sql(schema_sql)
-        .execute(&pool)
-        .await
-        .expect("Failed to execute schema");
-    
+        .connect_with(options)
+        .await?;
+    database::migrate(&pool).await?;
     println!("Database initialized successfully");
-    pool
-}
 
-// Get or initialize the database connection lazily
-async fn get_database() -> Arc<Database> {
-    let db_read = DATABASE.read().await;
-    if let Some(ref db) = *db_read {
-        return db.clone();
-    }
-    drop(db_read);
-    
-    // Initialize database
-    let pool = initialize_db().await;
-    let database = Arc::new(Database::new(pool));
-    
-    // Store in global
-    let mut db_write = DATABASE.write().await;
-    *db_write = Some(database.clone());
-    drop(db_write);
-    
-    database
+    let database = Arc::new(Database::new(pool).with_slow_query_threshold(slow_query));
+    *DATABASE.write().await = Some(database.clone());
+    Ok(database)
 }
 
-// Get or create an editor session for a user+tour combination
+// Get or create an editor session for a user+tour combination. `hold` counts the calling
+// connection as one more holder of the session.
 async fn get_or_create_editor_session(
     username: &str,
     tour_id: i64,
-    db: &Arc<Database>
-) -> Result<editor::EditorState, Box<dyn std::error::Error + Send + Sync>> {
+    db: &Arc<Database>,
+    editor_config: &config::EditorConfig,
+    hold: bool,
+) -> Result<SharedEditorState, Box<dyn std::error::Error + Send + Sync>> {
     let session_key = format!("{}_{}", username, tour_id);
-    
+    let holders = usize::from(hold);
+
     // First, try to get existing session
     {
-        let sessions_read = EDITOR_SESSIONS.read().await;
-        if let Some(ref sessions) = *sessions_read {
-            if let Some(editor_state) = sessions.get(&session_key) {
-                println!("Reusing existing editor session for {}", session_key);
-                return Ok(editor_state.clone());
-            }
+        let mut sessions_write = EDITOR_SESSIONS.write().await;
+        if let Some(session) = sessions_write.as_mut().and_then(|sessions| sessions.get_mut(&session_key)) {
+            println!("Reusing existing editor session for {}", session_key);
+            session.connections += holders;
+            return Ok(session.state.clone());
         }
     }
-    
+
     // Create new session if it doesn't exist
     println!("Creating new editor session for {}", session_key);
+    let editor_state = load_editor_state(username, tour_id, db, editor_config).await?;
+
+    // Store in global sessions, unless another connection got there while we were loading
+    let mut sessions_write = EDITOR_SESSIONS.write().await;
+    let session = sessions_write
+        .get_or_insert_with(HashMap::new)
+        .entry(session_key)
+        .or_insert_with(|| EditorSession { state: Arc::new(Mutex::new(editor_state)), connections: 0 });
+    session.connections += holders;
+    Ok(session.state.clone())
+}
+
+// A user's editor state for a tour, freshly read from the database
+async fn load_editor_state(
+    username: &str,
+    tour_id: i64,
+    db: &Arc<Database>,
+    editor_config: &config::EditorConfig,
+) -> Result<editor::EditorState, Box<dyn std::error::Error + Send + Sync>> {
     let mut editor_state = editor::EditorState::new(tour_id, username.to_string(), Some((**db).clone()));
+    editor_state.scene_defaults = editor::SceneDefaults::from_config(editor_config);
+    editor_state.scene_name_collision = editor_config.scene_name_collision;
+    editor_state.limits = editor::TourLimits::from_config(editor_config);
     editor_state.load_from_database(db).await?;
-    
-    // Store in global sessions
-    let mut sessions_write = EDITOR_SESSIONS.write().await;
-    if sessions_write.is_none() {
-        *sessions_write = Some(HashMap::new());
+    Ok(editor_state)
+}
+
+// Replace a session's state with a fresh load from the database, dropping its deferred writes
+// unsaved. Every connection sharing the session sees the reloaded state.
+async fn discard_editor_session(
+    session: &SharedEditorState,
+    db: &Arc<Database>,
+    editor_config: &config::EditorConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut editor_state = session.lock().await;
+    let mut fresh = load_editor_state(&editor_state.username, editor_state.tour_id, db, editor_config).await?;
+    // Discarding drops the edits, not the user's choice to keep deferring them
+    fresh.deferred = editor_state.deferred;
+    println!("Discarded {} deferred writes for {}_{}", editor_state.pending_writes.len(), fresh.username, fresh.tour_id);
+    *editor_state = fresh;
+    Ok(())
+}
+
+// Let go of the editor sessions one connection (`tx`) holds; each is flushed and dropped once no
+// other connection holds it. The connection also leaves those tours' presence.
+async fn release_editor_sessions(held: &mut HashSet<(String, i64)>, tx: &outbound::OutboundSender) {
+    if let Some(ref mut registry) = *PRESENCE.lock().await {
+        for (username, tour_id) in held.iter() {
+            registry.leave(*tour_id, username, tx);
+        }
+    }
+
+    let mut released = Vec::new();
+    {
+        let mut sessions_write = EDITOR_SESSIONS.write().await;
+        let Some(ref mut sessions) = *sessions_write else {
+            held.clear();
+            return;
+        };
+        for (username, tour_id) in held.drain() {
+            let session_key = format!("{}_{}", username, tour_id);
+            let Some(session) = sessions.get_mut(&session_key) else { continue };
+            if session.connections > 1 {
+                session.connections -= 1;
+            } else if let Some(session) = sessions.remove(&session_key) {
+                released.push((session_key, session.state));
+            }
+        }
     }
-    if let Some(ref mut sessions) = *sessions_write {
-        sessions.insert(session_key, editor_state.clone());
+    for (session_key, state) in released {
+        if let Err(e) = state.lock().await.flush_pending_writes().await {
+            eprintln!("Failed to flush deferred writes for {}: {}", session_key, e);
+        }
     }
-    
-    Ok(editor_state)
 }
 
-// Update an existing editor session
-async fn update_editor_session(
-    username: &str,
-    tour_id: i64,
-    editor_state: editor::EditorState
-) {
+// Drop a single user+tour editor session whoever holds it (e.g. after the tour changes hands)
+async fn remove_editor_session(username: &str, tour_id: i64) {
     let session_key = format!("{}_{}", username, tour_id);
-    
-    let mut sessions_write = EDITOR_SESSIONS.write().await;
-    if sessions_write.is_none() {
-        *sessions_write = Some(HashMap::new());
-    }
-    if let Some(ref mut sessions) = *sessions_write {
-        sessions.insert(session_key, editor_state);
+    let session = EDITOR_SESSIONS.write().await.as_mut().and_then(|sessions| sessions.remove(&session_key));
+    if let Some(session) = session {
+        if let Err(e) = session.state.lock().await.flush_pending_writes().await {
+            eprintln!("Failed to flush deferred writes for {}: {}", session_key, e);
+        }
     }
 }
 
-// Clean up editor sessions for a user (called on logout/disconnect)
+// Drop every editor session of a user, whichever connections hold them (e.g. account deletion)
 async fn cleanup_user_editor_sessions(username: &str) {
-    let mut sessions_write = EDITOR_SESSIONS.write().await;
-    if let Some(ref mut sessions) = *sessions_write {
-        sessions.retain(|key, _| !key.starts_with(&format!("{}_", username)));
+    if let Some(ref mut registry) = *PRESENCE.lock().await {
+        registry.remove_user(username);
+    }
+
+    let prefix = format!("{}_", username);
+    let mut removed = Vec::new();
+    if let Some(ref mut sessions) = *EDITOR_SESSIONS.write().await {
+        let keys: Vec<String> = sessions.keys().filter(|key| key.starts_with(&prefix)).cloned().collect();
+        for key in keys {
+            if let Some(session) = sessions.remove(&key) {
+                removed.push((key, session.state));
+            }
+        }
+    }
+    // Deferred edits must reach the database before the in-memory state goes away
+    for (key, state) in removed {
+        if let Err(e) = state.lock().await.flush_pending_writes().await {
+            eprintln!("Failed to flush deferred writes for {}: {}", key, e);
+        }
+    }
+}
+
+// Counts a tour creation by `username` against the creation limits, or says why it's refused.
+// Returns when it was counted, for `refund_tour_creation` if the creation then fails.
+async fn throttle_tour_creation(username: &str, editor_config: &config::EditorConfig) -> Result<std::time::Instant, throttle::Throttled> {
+    let now = std::time::Instant::now();
+    TOUR_CREATIONS.lock().await
+        .get_or_insert_with(|| throttle::CreationThrottle::from_config(editor_config))
+        .try_acquire(username, now)
+        .map(|()| now)
+}
+
+// Takes back a creation counted by `throttle_tour_creation` that didn't create a tour
+async fn refund_tour_creation(username: &str, counted_at: std::time::Instant) {
+    if let Some(ref mut throttle) = *TOUR_CREATIONS.lock().await {
+        throttle.refund(username, counted_at);
+    }
+}
+
+// 429 with a Retry-After header for a throttled HTTP tour creation
+fn throttled_response(throttled: throttle::Throttled) -> axum::response::Response {
+    let retry_after = throttled.retry_after().as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(axum::http::header::RETRY_AFTER, retry_after.to_string())],
+        throttled.message(),
+    ).into_response()
+}
+
+// Flush one session's deferred writes so the tour can be re-read from the database
+async fn flush_editor_session(username: &str, tour_id: i64) {
+    let session_key = format!("{}_{}", username, tour_id);
+    let state = EDITOR_SESSIONS.read().await.as_ref()
+        .and_then(|sessions| sessions.get(&session_key))
+        .map(|session| session.state.clone());
+    if let Some(state) = state {
+        if let Err(e) = state.lock().await.flush_pending_writes().await {
+            eprintln!("Failed to flush deferred writes for {}: {}", session_key, e);
+        }
+    }
+}
+
+// What one autosave pass did
+#[derive(Debug, Default, PartialEq)]
+struct AutosaveReport {
+    /// Sessions that had deferred writes and were flushed
+    sessions: usize,
+    writes: usize,
+    /// Sessions skipped because an action was running on them
+    busy: usize,
+}
+
+// Flush deferred writes of every open editor session (periodic autosave)
+async fn flush_all_editor_sessions() -> AutosaveReport {
+    let mut report = AutosaveReport::default();
+    let states: Vec<(String, SharedEditorState)> = EDITOR_SESSIONS.read().await.as_ref()
+        .map(|sessions| sessions.iter().map(|(key, session)| (key.clone(), session.state.clone())).collect())
+        .unwrap_or_default();
+    for (key, state) in states {
+        // Never wait on a running action; its writes are picked up next tick
+        let Ok(mut editor_state) = state.try_lock() else {
+            report.busy += 1;
+            continue;
+        };
+        if editor_state.pending_writes.is_empty() {
+            continue;
+        }
+        match editor_state.flush_pending_writes().await {
+            Ok(n) => {
+                report.sessions += 1;
+                report.writes += n;
+            }
+            Err(e) => eprintln!("Autosave failed for {}: {}", key, e),
+        }
     }
+    report
 }
 
 // WebSocket handler
 async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+) -> axum::response::Response {
+    // Take the connection slot before upgrading, so simultaneous upgrades can't overshoot the cap
+    let max_connections = state.config.server.max_connections;
+    let reserved = state.connections.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |open| {
+        (max_connections == 0 || open < max_connections).then_some(open + 1)
+    });
+    if reserved.is_err() {
+        eprintln!("Rejected WebSocket connection: limit of {} reached", max_connections);
+        return (StatusCode::SERVICE_UNAVAILABLE, "Too many connections to the server. Please try again later.").into_response();
+    }
+
+    let connections = state.connections.clone();
+    ws.on_failed_upgrade(move |e| {
+        connections.fetch_sub(1, Ordering::Relaxed);
+        eprintln!("WebSocket upgrade failed: {}", e);
+    })
+    .on_upgrade(|socket| handle_websocket(socket, state))
 }
 
 async fn handle_websocket(socket: WebSocket, state: AppState) {
-    // Increment connection counter
-    let connection_count = ACTIVE_CONNECTIONS.fetch_add(1, Ordering::Relaxed) + 1;
+    // The slot was counted by websocket_handler
+    let connection_count = state.connections.load(Ordering::Relaxed);
     println!("New client connected. Active connections: {}", connection_count);
     
     let (sender, receiver) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    let (tx, mut rx) = outbound::channel(state.config.server.ws_outbound_capacity);
     
     // Forward messages from our channel to the websocket
     let send_task = tokio::spawn(async move {
@@ -353,43 +756,88 @@ async fn handle_websocket(socket: WebSocket, state: AppState) {
     };
 
     // Send initial welcome message
-    let _ = tx.send(Message::Text(r#"{"message": "Welcome to Virtual Tour Editor!"}"#.to_string()));
+    let _ = tx.send_lossy(Message::Text(r#"{"message": "Welcome to Virtual Tour Editor!"}"#.to_string()));
     
-    loop {
-        // Handle login phase
-        println!("Waiting for user to log in...");
-        let logged_in_user = handle_login_phase(curr_user.clone(), state.database.clone()).await;
-        
-        // If login was successful, proceed to main client handling
-        if let Some(user) = logged_in_user {
-            println!("User logged in successfully.");
-            // handle_client returns: true = disconnect, false = logout (back to login)
-            if handle_client(user.clone(), state.database.clone()).await {
-                break; // Disconnect
-            }
-            // If false, continue loop to go back to login phase
-        } else {
-            println!("User login failed or disconnected.");
-            break;
+    // Editor sessions (user, tour) this connection holds, let go of on logout or disconnect
+    let mut held_sessions: HashSet<(String, i64)> = HashSet::new();
+
+    let session = async {
+        loop {
+            // Handle login phase
+            println!("Waiting for user to log in...");
+            let logged_in_user = handle_login_phase(curr_user.clone(), state.database.clone()).await;
+            
+            // If login was successful, proceed to main client handling
+            if let Some(user) = logged_in_user {
+                println!("User logged in successfully.");
+                let tour_changes = tokio::spawn(forward_tour_changes(
+                    state.database.subscribe_tour_changes(), user.name.clone(), user.tx.clone(),
+                ));
+                // handle_client returns: true = disconnect, false = logout (back to login)
+                let disconnect = handle_client(user.clone(), state.database.clone(), state.config.clone(), &mut held_sessions).await;
+                tour_changes.abort();
+                release_editor_sessions(&mut held_sessions, &tx).await;
+                if disconnect {
+                    break; // Disconnect
+                }
+                // If false, continue loop to go back to login phase
+            } else {
+                println!("User login failed or disconnected.");
+                break;
+            }
+        }
+    };
+
+    // A client that can't keep up with critical messages is dropped instead of buffered
+    tokio::select! {
+        _ = session => {}
+        _ = tx.lagged() => {
+            eprintln!("Dropping client whose outbound queue overflowed");
         }
     }
 
     let _ = state.database.cleanup_old_sessions().await;
     println!("Cleaned up session on connection close");
 
-    // Clean up editor sessions for the disconnected user
-    if !curr_user.name.is_empty() {
-        cleanup_user_editor_sessions(&curr_user.name).await;
-        println!("Cleaned up editor sessions for user: {}", curr_user.name);
-    }
+    // A dropped client never got back from handle_client, so let go of its sessions here
+    release_editor_sessions(&mut held_sessions, &tx).await;
 
     // Decrement connection counter and cleanup if needed
-    let remaining_connections = ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::Relaxed) - 1;
+    let remaining_connections = state.connections.fetch_sub(1, Ordering::Relaxed) - 1;
     println!("Client disconnected. Active connections: {}", remaining_connections);
     
     send_task.abort();
 }
 
+/// Tells this connection about writes to `username`'s tours made by any session, so other
+/// open tabs can refresh their tours list or warn about stale editor state.
+///
+/// Ends when the client goes away; a connection that fell behind just skips the missed changes.
+async fn forward_tour_changes(
+    mut changes: tokio::sync::broadcast::Receiver<database::TourChange>,
+    username: String,
+    tx: outbound::OutboundSender,
+) {
+    loop {
+        let change = match changes.recv().await {
+            Ok(change) => change,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if change.owner != username {
+            continue;
+        }
+        let message = serde_json::json!({
+            "type": "tour_updated",
+            "tour_id": change.tour_id,
+            "modified_at": change.modified_at
+        });
+        if tx.send_lossy(Message::Text(message.to_string())) == Err(outbound::SendError::Disconnected) {
+            return;
+        }
+    }
+}
+
 // Login phase handler
 async fn handle_login_phase(mut user: User, db: Arc<Database>) -> Option<User> {
     let tx = user.tx.clone();
@@ -408,6 +856,7 @@ async fn handle_login_phase(mut user: User, db: Arc<Database>) -> Option<User> {
                             // Generate session token
                             match db.login_user(&username).await {
                                 Ok(session_token) => {
+                                    TOTAL_LOGINS.fetch_add(1, Ordering::Relaxed);
                                     let _ = tx.send(Message::Text(
                                         format!(r#"{{"message": "Welcome back, {}!", "redirect": "homepage", "sessionToken": "{}", "username": "{}"}}"#, username, session_token, username)
                                     ));
@@ -431,6 +880,7 @@ async fn handle_login_phase(mut user: User, db: Arc<Database>) -> Option<User> {
                                 // Immediately create a session token (auto-login)
                                 match db.login_user(&username).await {
                                     Ok(session_token) => {
+                                        TOTAL_LOGINS.fetch_add(1, Ordering::Relaxed);
                                         let _ = tx.send(Message::Text(
                                             format!(r#"{{"message": "Registration successful! Welcome, {}!", "redirect": "homepage", "sessionToken": "{}", "username": "{}"}}"#, username, session_token, username)
                                         ));
@@ -445,9 +895,12 @@ async fn handle_login_phase(mut user: User, db: Arc<Database>) -> Option<User> {
                                     }
                                 }
                             }
+                            Err(e) if database::is_unique_violation(&e) => {
+                                let _ = tx.send(Message::Text(r#"{"message": "Registration failed. That username is already taken.", "code": "username_taken"}"#.to_string()));
+                            }
                             Err(e) => {
                                 eprintln!("Registration failed: {}", e);
-                                let _ = tx.send(Message::Text(r#"{"message": "Registration failed. Username might already be taken."}"#.to_string()));
+                                let _ = tx.send(Message::Text(r#"{"message": "Registration failed due to a server error. Please try again.", "code": "server_error"}"#.to_string()));
                             }
                         }
                     }
@@ -495,11 +948,17 @@ async fn handle_login_phase(mut user: User, db: Arc<Database>) -> Option<User> {
 
 // Main client handler after login
 // Returns: true = disconnect, false = logout (go back to login phase)
-async fn handle_client(user: User, db: Arc<Database>) -> bool {
+async fn handle_client(
+    user: User,
+    db: Arc<Database>,
+    config: Arc<config::Config>,
+    held_sessions: &mut HashSet<(String, i64)>,
+) -> bool {
     let tx = user.tx.clone();
     
     // Send tours list on login
-    let tours_json = get_tours_json(db.clone(), user.name.clone()).await;
+    let (mut tour_order, mut tour_direction) = (database::TourOrder::default(), database::SortDirection::default());
+    let tours_json = get_tours_json(db.clone(), user.name.clone(), tour_order, tour_direction).await;
     let _ = tx.send(Message::Text(tours_json));
     
     while let Some(result) = user.rx.lock().await.next().await {
@@ -509,22 +968,33 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                 let client_msg: Result<ClientMessage, serde_json::Error> = serde_json::from_str(&text);
                 println!("Parsed message: {:?}", client_msg);
                 match client_msg {
-                    Ok(ClientMessage::ShowTours) => {
-                        let tours_json = get_tours_json(db.clone(), user.name.clone()).await;
+                    Ok(ClientMessage::ShowTours(query)) => {
+                        let query = query.unwrap_or_default();
+                        (tour_order, tour_direction) = (query.order_by, query.direction);
+                        let tours_json = get_tours_json(db.clone(), user.name.clone(), tour_order, tour_direction).await;
                         let _ = tx.send(Message::Text(tours_json));
                     }
                     Ok(ClientMessage::CreateTour { name }) => {
+                        let counted_at = match throttle_tour_creation(&user.name, &config.editor).await {
+                            Ok(counted_at) => counted_at,
+                            Err(throttled) => {
+                                let _ = tx.send(Message::Text(serde_json::json!({ "message": throttled.message() }).to_string()));
+                                continue;
+                            }
+                        };
                         match db.create_tour(&user.name, &name, "").await {
                             Ok(tour_id) => {
+                                TOTAL_TOURS_CREATED.fetch_add(1, Ordering::Relaxed);
                                 let _ = tx.send(Message::Text(
                                     format!(r#"{{"message": "Tour '{}' created successfully!", "tour_id": {}}}"#, name, tour_id)
                                 ));
                                 // Send updated tours list
-                                let tours_json = get_tours_json(db.clone(), user.name.clone()).await;
+                                let tours_json = get_tours_json(db.clone(), user.name.clone(), tour_order, tour_direction).await;
                                 let _ = tx.send(Message::Text(tours_json));
                             }
                             Err(e) => {
                                 eprintln!("Failed to create tour: {}", e);
+                                refund_tour_creation(&user.name, counted_at).await;
                                 let _ = tx.send(Message::Text(r#"{"message": "Failed to create tour. Server error."}"#.to_string()));
                             }
                         }
@@ -535,7 +1005,7 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                             Ok(true) => {
                                 let _ = tx.send(Message::Text(r#"{"message": "Tour deleted successfully!"}"#.to_string()));
                                 // Send updated tours list
-                                let tours_json = get_tours_json(db.clone(), user.name.clone()).await;
+                                let tours_json = get_tours_json(db.clone(), user.name.clone(), tour_order, tour_direction).await;
                                 let _ = tx.send(Message::Text(tours_json));
                             }
                             Ok(false) => {
@@ -547,10 +1017,96 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                             }
                         }
                     }
+                    Ok(ClientMessage::DuplicateTour { tour_id, name }) => {
+                        let tour_id_i64 = tour_id as i64;
+                        // Edits still queued in the original's editor session belong in the copy too
+                        flush_editor_session(&user.name, tour_id_i64).await;
+                        // Every attempt counts against the creation limits; ones that create nothing are refunded
+                        let counted_at = match throttle_tour_creation(&user.name, &config.editor).await {
+                            Ok(at) => at,
+                            Err(throttled) => {
+                                let _ = tx.send(Message::Text(serde_json::json!({ "message": throttled.message() }).to_string()));
+                                continue;
+                            }
+                        };
+                        let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+                        let duplicated = db.duplicate_tour(&user.name, tour_id_i64, name.as_deref()).await;
+                        if !matches!(duplicated, Ok(Some(_))) {
+                            refund_tour_creation(&user.name, counted_at).await;
+                        }
+                        match duplicated {
+                            Ok(Some(copy_id)) => {
+                                TOTAL_TOURS_CREATED.fetch_add(1, Ordering::Relaxed);
+                                let _ = tx.send(Message::Text(serde_json::json!({
+                                    "message": "Tour duplicated successfully!",
+                                    "tour_id": copy_id,
+                                    "source_tour_id": tour_id
+                                }).to_string()));
+                                let tours_json = get_tours_json(db.clone(), user.name.clone(), tour_order, tour_direction).await;
+                                let _ = tx.send(Message::Text(tours_json));
+                            }
+                            Ok(None) => {
+                                let _ = tx.send(Message::Text(r#"{"message": "Tour not found or access denied."}"#.to_string()));
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to duplicate tour: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"message": "Failed to duplicate tour. Server error."}"#.to_string()));
+                            }
+                        }
+                    }
+                    Ok(ClientMessage::LoadScenesPage { tour_id, offset, limit }) => {
+                        // Clamp paging arguments so a client can't request the whole graph in one go
+                        let offset = offset.max(0);
+                        let limit = limit.clamp(1, 100);
+                        match db.get_scenes_page(&user.name, tour_id as i64, offset, limit).await {
+                            Ok(Some(page)) => {
+                                let response = serde_json::json!({
+                                    "type": "scenes_page",
+                                    "data": page
+                                });
+                                let _ = tx.send(Message::Text(response.to_string()));
+                            }
+                            Ok(None) => {
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour not found or access denied."}"#.to_string()));
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to load scenes page: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to load scenes."}"#.to_string()));
+                            }
+                        }
+                    }
+                    Ok(ClientMessage::ListUploads { tour_id }) => {
+                        if !matches!(db.is_tour_owner(tour_id as i64, &user.name).await, Ok(true)) {
+                            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour not found or access denied."}"#.to_string()));
+                            continue;
+                        }
+                        match uploads_with_usage(&db, tour_id as i64, SCENE_UPLOAD_DIR).await {
+                            Ok(assets) => {
+                                let response = serde_json::json!({
+                                    "type": "uploads",
+                                    "tour_id": tour_id,
+                                    "assets": assets
+                                });
+                                let _ = tx.send(Message::Text(response.to_string()));
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to list uploads: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Could not read assets directory"}"#.to_string()));
+                            }
+                        }
+                    }
+                    Ok(ClientMessage::PresenceUpdate { tour_id, cursor }) => {
+                        let joined = match *PRESENCE.lock().await {
+                            Some(ref mut registry) => registry.update(tour_id as i64, &user.name, cursor),
+                            None => false,
+                        };
+                        if !joined {
+                            let _ = tx.send_lossy(Message::Text(r#"{"type": "error", "message": "Open the tour in the editor before sending presence."}"#.to_string()));
+                        }
+                    }
                     Ok(ClientMessage::Logout) => {
                         let _ = db.logout_user(&user.name).await;
-                        // Clean up editor sessions for the logging out user
-                        cleanup_user_editor_sessions(&user.name).await;
+                        // This connection's editor sessions and presence are let go of by the caller
                         let _ = tx.send(Message::Text(r#"{"message": "Logged out successfully.", "redirect": "login"}"#.to_string()));
                         return false; // Go back to login phase
                     }
@@ -563,12 +1119,86 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                             let _ = db.validate_session(&user.name, session_token).await;
                         }
                     }
+                    Ok(ClientMessage::DiscardChanges { tour_id }) => {
+                        let tour_id_i64 = tour_id as i64;
+                        match db.is_tour_owner(tour_id_i64, &user.name).await {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour not found or access denied."}"#.to_string()));
+                                continue;
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to check tour owner: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to load tour data."}"#.to_string()));
+                                continue;
+                            }
+                        }
+                        let hold = held_sessions.insert((user.name.clone(), tour_id_i64));
+                        let session = match get_or_create_editor_session(&user.name, tour_id_i64, &db, &config.editor, hold).await {
+                            Ok(session) => session,
+                            Err(e) => {
+                                if hold {
+                                    held_sessions.remove(&(user.name.clone(), tour_id_i64));
+                                }
+                                eprintln!("Failed to get/create editor session: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to initialize editor session."}"#.to_string()));
+                                continue;
+                            }
+                        };
+                        match discard_editor_session(&session, &db, &config.editor).await {
+                            Ok(()) => {
+                                let response = serde_json::json!({
+                                    "type": "editor_ready",
+                                    "state": session.lock().await.to_json()
+                                });
+                                let _ = tx.send(Message::Text(response.to_string()));
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to reload editor session: {}", e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to reload the tour; unsaved changes were kept."}"#.to_string()));
+                            }
+                        }
+                    }
+                    Ok(ClientMessage::ExportTourData { tour_id }) => {
+                        let tour_id_i64 = tour_id as i64;
+                        if !matches!(db.is_tour_owner(tour_id_i64, &user.name).await, Ok(true)) {
+                            let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour not found or access denied."}"#.to_string()));
+                            continue;
+                        }
+                        // Deferred editor writes belong in the export
+                        flush_editor_session(&user.name, tour_id_i64).await;
+                        // Paths stay server-relative: moving files clear of the viewer's own assets
+                        // is left to whoever lays out the package (see exporter::write_package)
+                        match exporter::build_tour_data(&db, tour_id_i64).await {
+                            Ok(Some(mut tour)) => {
+                                exporter::TourDataOptions {
+                                    default_icon_index: config.export.default_icon_index,
+                                    flatten_closeups: false,
+                                }.apply(&mut tour);
+                                let response = serde_json::json!({
+                                    "type": "tour_export_data",
+                                    "tour_id": tour_id,
+                                    "tour": tour
+                                });
+                                let _ = tx.send(Message::Text(response.to_string()));
+                            }
+                            Ok(None) => {
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Tour not found or access denied."}"#.to_string()));
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to build export data for tour {}: {}", tour_id, e);
+                                let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to load tour data."}"#.to_string()));
+                            }
+                        }
+                    }
                     Ok(ClientMessage::EditTour { tour_id, editor_action }) => {
                         let tour_id_i64 = tour_id as i64;
                         // Check if this is the initial tour load or an editor action
                         match editor_action {
                             None => {
-                                // Initial tour load - return tour data and start editor session
+                                // Initial tour load - return tour data and start editor session.
+                                // A resumed session may still hold deferred edits; write them first.
+                                flush_editor_session(&user.name, tour_id_i64).await;
                                 match db.get_tour_with_scenes(&user.name, tour_id_i64).await {
                                     Ok(Some(tour_data)) => {
                                         let response = serde_json::json!({
@@ -577,17 +1207,24 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                                         });
                                         let _ = tx.send(Message::Text(response.to_string()));
                                         
-                                        // Initialize or get editor session
-                                        match get_or_create_editor_session(&user.name, tour_id_i64, &db).await {
+                                        // Initialize or join editor session
+                                        let hold = held_sessions.insert((user.name.clone(), tour_id_i64));
+                                        match get_or_create_editor_session(&user.name, tour_id_i64, &db, &config.editor, hold).await {
                                             Ok(editor_state) => {
                                                 // Start editor session
                                                 let response = serde_json::json!({
                                                     "type": "editor_ready",
-                                                    "state": editor_state.to_json()
+                                                    "state": editor_state.lock().await.to_json()
                                                 });
                                                 let _ = tx.send(Message::Text(response.to_string()));
+                                                PRESENCE.lock().await
+                                                    .get_or_insert_with(presence::PresenceRegistry::new)
+                                                    .join(tour_id_i64, &user.name, tx.clone());
                                             }
                                             Err(e) => {
+                                                if hold {
+                                                    held_sessions.remove(&(user.name.clone(), tour_id_i64));
+                                                }
                                                 eprintln!("Failed to initialize editor session: {}", e);
                                                 let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to initialize editor session."}"#.to_string()));
                                             }
@@ -602,23 +1239,40 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                                     }
                                 }
                             }
-                            Some(action) => {
-                                // Handle editor action using session-based state
-                                match get_or_create_editor_session(&user.name, tour_id_i64, &db).await {
-                                    Ok(mut editor_state) => {
+                            Some(raw_action) => {
+                                let action = match editor::parse_action(raw_action) {
+                                    Ok(action) => action,
+                                    Err(errors) => {
+                                        let _ = tx.send(Message::Text(validation_error_json(&errors)));
+                                        continue;
+                                    }
+                                };
+                                // Handle editor action on the session shared with the user's other tabs
+                                let hold = held_sessions.insert((user.name.clone(), tour_id_i64));
+                                match get_or_create_editor_session(&user.name, tour_id_i64, &db, &config.editor, hold).await {
+                                    Ok(session) => {
+                                        let mut editor_state = session.lock().await;
+                                        // A failed action leaves the session as it was
+                                        let before = editor_state.clone();
                                         match editor_state.handle_action(action, &tx).await {
-                                            Ok(_) => {
-                                                // Save changes to database and update session
+                                            Ok(changed) => {
+                                                // Save changes to database
                                                 let _ = editor_state.save_to_database(&db).await;
-                                                update_editor_session(&user.name, tour_id_i64, editor_state).await;
+                                                if changed {
+                                                    db.notify_tour_changed(tour_id_i64).await;
+                                                }
                                             }
                                             Err(e) => {
+                                                *editor_state = before;
                                                 eprintln!("Editor action failed: {}", e);
                                                 let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Editor action failed."}"#.to_string()));
                                             }
                                         }
                                     }
                                     Err(e) => {
+                                        if hold {
+                                            held_sessions.remove(&(user.name.clone(), tour_id_i64));
+                                        }
                                         eprintln!("Failed to get/create editor session: {}", e);
                                         let _ = tx.send(Message::Text(r#"{"type": "error", "message": "Failed to initialize editor session."}"#.to_string()));
                                     }
@@ -627,7 +1281,7 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
                         }
                     }
                     _ => {
-                        let _ = tx.send(Message::Text(r#"{"message": "Feature not implemented yet."}"#.to_string()));
+                        let _ = tx.send_lossy(Message::Text(r#"{"message": "Feature not implemented yet."}"#.to_string()));
                     }
                 }
             }
@@ -640,9 +1294,20 @@ async fn handle_client(user: User, db: Arc<Database>) -> bool {
     false // Should not reach here, but return false to go back to login
 }
 
-async fn get_tours_json(db: Arc<Database>, username: String) -> String {
-    let tours = db.get_tours(&username).await;
-    let mut tour_list = Vec::new();
+/// Error reply for a rejected editor action; `field` names the first offending field.
+fn validation_error_json(errors: &[editor::FieldError]) -> String {
+    let first = errors.first();
+    serde_json::json!({
+        "type": "error",
+        "message": first.map(|e| e.message.as_str()).unwrap_or("Invalid editor action."),
+        "field": first.map(|e| e.field.as_str()),
+        "errors": errors
+    }).to_string()
+}
+
+async fn get_tours_json(db: Arc<Database>, username: String, order_by: database::TourOrder, direction: database::SortDirection) -> String {
+    let tours = db.get_tours(&username, order_by, direction).await;
+    let mut tour_list = Vec::new();
 
     if tours.is_err() {
         return serde_json::json!({
@@ -650,16 +1315,12 @@ async fn get_tours_json(db: Arc<Database>, username: String) -> String {
         }).to_string();
     }
 
-    for tour in tours.unwrap() {
-        // Get the initial scene thumbnail - convert i32 to Option<i64>
-        let initial_scene_id_opt = if tour.initial_scene_id > 0 {
-            Some(tour.initial_scene_id as i64)
-        } else {
-            None
-        };
-        
-        let initial_scene_thumbnail = db.get_initial_scene_thumbnail(tour.get_id() as i64, initial_scene_id_opt).await
-            .unwrap_or(None);
+    let tours = tours.unwrap();
+    let tour_ids: Vec<i64> = tours.iter().map(|tour| tour.get_id() as i64).collect();
+    let thumbnails = db.get_thumbnails_for_tours(&tour_ids).await.unwrap_or_default();
+
+    for tour in tours {
+        let initial_scene_thumbnail = thumbnails.get(&(tour.get_id() as i64)).cloned().flatten();
 
         tour_list.push(serde_json::json!({
             "id": tour.get_id(),
@@ -670,7 +1331,7 @@ async fn get_tours_json(db: Arc<Database>, username: String) -> String {
             "initial_scene_thumbnail": initial_scene_thumbnail,
             "sort_mode": tour.sort_mode,
             "sort_direction": tour.sort_direction,
-            "views": 0
+            "views": tour.views
         }));
     }
 
@@ -679,6 +1340,18 @@ async fn get_tours_json(db: Arc<Database>, username: String) -> String {
     }).to_string()
 }
 
+// Resolve the logged-in user for HTTP API calls from the `X-Username` and
+// `X-Session-Token` headers (the same credentials used to restore a WebSocket session)
+async fn authenticate_request(headers: &HeaderMap, db: &Database) -> Result<String, StatusCode> {
+    let username = headers.get("x-username").and_then(|v| v.to_str().ok()).ok_or(StatusCode::UNAUTHORIZED)?;
+    let session_token = headers.get("x-session-token").and_then(|v| v.to_str().ok()).ok_or(StatusCode::UNAUTHORIZED)?;
+    match db.validate_session(username, session_token).await {
+        Ok(true) => Ok(username.to_string()),
+        Ok(false) => Err(StatusCode::UNAUTHORIZED),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
 // HTTP Route handlers
 async fn login_handler(
     State(state): State<AppState>,
@@ -687,11 +1360,14 @@ async fn login_handler(
     match state.database.authenticate_user(&payload.username, &payload.password).await {
         Ok(Some(_)) => {
             match state.database.login_user(&payload.username).await {
-                Ok(session_token) => Ok(Json(serde_json::json!({
-                    "success": true,
-                    "username": payload.username,
-                    "session_token": session_token
-                }))),
+                Ok(session_token) => {
+                    TOTAL_LOGINS.fetch_add(1, Ordering::Relaxed);
+                    Ok(Json(serde_json::json!({
+                        "success": true,
+                        "username": payload.username,
+                        "session_token": session_token
+                    })))
+                }
                 Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
             }
         },
@@ -703,48 +1379,100 @@ async fn login_handler(
 async fn register_handler(
     State(state): State<AppState>,
     Json(payload): Json<RegisterRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     match state.database.register_user(&payload.username, &payload.password).await {
         Ok(_) => Ok(Json(serde_json::json!({
             "success": true,
             "message": "User registered successfully"
         }))),
-        Err(_) => Err(StatusCode::CONFLICT)
+        Err(e) if database::is_unique_violation(&e) => Err((StatusCode::CONFLICT, Json(serde_json::json!({
+            "success": false,
+            "code": "username_taken",
+            "message": "Username is already taken"
+        })))),
+        Err(e) => {
+            eprintln!("Registration failed: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "success": false,
+                "code": "server_error",
+                "message": "Registration failed"
+            }))))
+        }
+    }
+}
+
+// Permanently deletes the authenticated user's account; the password must be re-entered
+async fn delete_account_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.authenticate_user(&username, &payload.password).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match state.database.delete_user(&username).await {
+        Ok(true) => {
+            // Sessions are gone from the DB; drop any in-memory editor state too
+            cleanup_user_editor_sessions(&username).await;
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "message": "Account deleted"
+            })))
+        }
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
 }
 
 async fn get_tours_handler(
-    State(_state): State<AppState>,
-    // TODO: Extract username from session/auth header
+    State(state): State<AppState>,
+    Query(query): Query<TourListQuery>,
+    headers: HeaderMap,
 ) -> Result<Json<Vec<Tour>>, StatusCode> {
-    // For now, return empty array - you'll need to implement auth extraction
-    Ok(Json(vec![]))
+    let username = authenticate_request(&headers, &state.database).await?;
+    state.database.get_tours(&username, query.order_by, query.direction).await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 async fn create_tour_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<CreateTourRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // TODO: Extract username from session/auth header
-    let username = "test_user"; // Placeholder
+) -> Result<Json<serde_json::Value>, axum::response::Response> {
+    let username = authenticate_request(&headers, &state.database).await.map_err(IntoResponse::into_response)?;
+    let counted_at = throttle_tour_creation(&username, &state.config.editor).await.map_err(throttled_response)?;
     
-    match state.database.create_tour(username, &payload.name, "").await {
-        Ok(tour_id) => Ok(Json(serde_json::json!({
-            "success": true,
-            "tour_id": tour_id
-        }))),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    let created = state.database.create_tour(&username, &payload.name, "").await;
+    if created.is_err() {
+        refund_tour_creation(&username, counted_at).await;
+    }
+    match created {
+        Ok(tour_id) => {
+            TOTAL_TOURS_CREATED.fetch_add(1, Ordering::Relaxed);
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "tour_id": tour_id
+            })))
+        }
+        // The account went away after the session was checked
+        Err(e) if database::is_missing_owner(&e) => Err((StatusCode::UNPROCESSABLE_ENTITY, "Tour owner does not exist").into_response()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
     }
 }
 
 async fn delete_tour_handler(
     State(state): State<AppState>,
     Path(tour_id): Path<i64>,
+    headers: HeaderMap,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    // TODO: Extract username from session/auth header
-    let username = "test_user"; // Placeholder
+    let username = authenticate_request(&headers, &state.database).await?;
     
-    match state.database.delete_tour(username, tour_id).await {
+    match state.database.delete_tour(&username, tour_id).await {
         Ok(true) => Ok(Json(serde_json::json!({
             "success": true,
             "message": "Tour deleted successfully"
@@ -754,203 +1482,3317 @@ async fn delete_tour_handler(
     }
 }
 
-// Assets list handler
-async fn list_assets_handler() -> impl IntoResponse {
-    use std::fs;
-    
-    let assets_dir = "assets/insta360";
-    
-    match fs::read_dir(assets_dir) {
-        Ok(entries) => {
-            let mut files = Vec::new();
-            
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(file_name) = path.file_name() {
-                            if let Some(file_name_str) = file_name.to_str() {
-                                // Only include image files
-                                if file_name_str.ends_with(".jpg") || 
-                                   file_name_str.ends_with(".jpeg") || 
-                                   file_name_str.ends_with(".png") {
-                                    files.push(file_name_str.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            
-            // Sort files for consistent ordering
-            files.sort();
-            
-            Json(serde_json::json!({
+// Creates a tour for the caller from a copy of a template tour's scenes and hotspots
+async fn create_tour_from_template_handler(
+    State(state): State<AppState>,
+    Path(template_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTourRequest>,
+) -> Result<Json<serde_json::Value>, axum::response::Response> {
+    let username = authenticate_request(&headers, &state.database).await.map_err(IntoResponse::into_response)?;
+    if payload.name.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+    let counted_at = throttle_tour_creation(&username, &state.config.editor).await.map_err(throttled_response)?;
+
+    let created = state.database.create_tour_from_template(&username, template_id, payload.name.trim()).await;
+    if !matches!(created, Ok(Some(_))) {
+        refund_tour_creation(&username, counted_at).await;
+    }
+    match created {
+        Ok(Some(tour_id)) => {
+            TOTAL_TOURS_CREATED.fetch_add(1, Ordering::Relaxed);
+            Ok(Json(serde_json::json!({
                 "success": true,
-                "assets": files
-            })).into_response()
+                "tour_id": tour_id
+            })))
         }
-        Err(_) => {
-            Json(serde_json::json!({
-                "success": false,
-                "message": "Could not read assets directory",
-                "assets": []
-            })).into_response()
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) if database::is_missing_owner(&e) => Err((StatusCode::UNPROCESSABLE_ENTITY, "Tour owner does not exist").into_response()),
+        Err(e) => {
+            eprintln!("Failed to create tour from template {}: {}", template_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
         }
     }
 }
 
-// Static page handlers
-async fn index_page() -> Html<&'static str> {
-    Html(include_str!("../static/index.html"))
-}
+// Updates a tour's viewer branding (logo, primary colour, welcome text)
+async fn patch_tour_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<PatchTourRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let username = authenticate_request(&headers, &state.database).await.map_err(|s| (s, String::new()))?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err((StatusCode::NOT_FOUND, String::new())),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new())),
+    }
+    let mut branding = match state.database.get_tour_branding(tour_id).await {
+        Ok(Some(branding)) => branding,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, String::new())),
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new())),
+    };
 
-async fn login_page() -> Html<&'static str> {
-    Html(include_str!("../static/login.html"))
+    let non_empty = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+    if let Some(logo_path) = payload.logo_path {
+        let logo_path = non_empty(logo_path);
+        if let Some(ref path) = logo_path {
+            // The logo is copied into the export package, so only uploaded assets are allowed
+            if !path.starts_with("/assets/") || path.contains("..") {
+                return Err((StatusCode::BAD_REQUEST, "logo_path must be an uploaded /assets/ file".to_string()));
+            }
+        }
+        branding.logo_path = logo_path;
+    }
+    if let Some(color) = payload.primary_color {
+        let color = non_empty(color);
+        if let Some(ref c) = color {
+            if !editor::is_hex_color(c) {
+                return Err((StatusCode::BAD_REQUEST, format!("Invalid primary_color '{}'. Expected #RRGGBB.", c)));
+            }
+        }
+        branding.primary_color = color;
+    }
+    if let Some(text) = payload.welcome_text {
+        branding.welcome_text = non_empty(text);
+    }
+    let share_base_url = payload.share_base_url.map(|url| non_empty(url).map(|u| u.trim_end_matches('/').to_string()));
+    if let Some(Some(ref url)) = share_base_url {
+        if !editor::is_http_base_url(url) {
+            return Err((StatusCode::BAD_REQUEST, format!("Invalid share_base_url '{}'. Expected an absolute http(s) URL.", url)));
+        }
+    }
+
+    if state.database.set_tour_branding(tour_id, &branding).await.is_err() {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new()));
+    }
+    if let Some(ref url) = share_base_url {
+        if state.database.set_share_base_url(tour_id, url.as_deref()).await.is_err() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new()));
+        }
+    }
+    if let Some(is_template) = payload.is_template {
+        if state.database.set_tour_template(tour_id, &username, is_template).await.is_err() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new()));
+        }
+    }
+    if let Some(requires_auth) = payload.requires_auth {
+        if state.database.set_tour_requires_auth(tour_id, &username, requires_auth).await.is_err() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new()));
+        }
+    }
+    let share_base_url = match state.database.get_share_base_url(tour_id).await {
+        Ok(url) => url,
+        Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, String::new())),
+    };
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "branding": branding,
+        "share_base_url": share_base_url
+    })))
 }
 
-async fn homepage() -> Html<&'static str> {
-    Html(include_str!("../static/homepage.html"))
+// Starts a chunked upload and returns its id
+async fn chunked_upload_init_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ChunkedUploadInit>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let limits = &state.config.uploads;
+    if payload.total_size == 0 || payload.total_size > limits.max_file_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("total_size must be between 1 and {} bytes", limits.max_file_bytes)));
+    }
+    let username = authenticate_request(&headers, &state.database).await.ok();
+
+    let mut uploads = CHUNKED_UPLOADS.lock().await;
+    let uploads = uploads.get_or_insert_with(|| chunked_upload::ChunkedUploads::new(&limits.chunk_dir));
+    uploads.expire(std::time::Duration::from_secs(limits.expiry_secs));
+    if uploads.len() >= limits.max_pending {
+        return Err((StatusCode::TOO_MANY_REQUESTS, "Too many uploads in progress, try again later".to_string()));
+    }
+    let info = chunked_upload::UploadInfo {
+        filename: payload.filename,
+        subdir: editor::upload_subdir(&payload.kind).to_string(),
+        total_size: payload.total_size,
+        username,
+    };
+    match uploads.start(info) {
+        Ok(upload_id) => Ok(Json(serde_json::json!({
+            "upload_id": upload_id,
+            "max_chunk_bytes": limits.max_chunk_bytes
+        }))),
+        Err(e) => {
+            eprintln!("Failed to start chunked upload: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to start upload".to_string()))
+        }
+    }
 }
 
-async fn editor_page() -> Html<&'static str> {
-    Html(include_str!("../static/editor.html"))
+// Parses `Content-Range: bytes <start>-<end>/<total>` and returns the start offset
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get("content-range")?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    let (span, _total) = range.split_once('/')?;
+    let (start, _end) = span.split_once('-')?;
+    start.trim().parse().ok()
 }
 
-// --- Export handler ---
-// Generates a downloadable ZIP containing a self-hostable tour package.
-async fn export_tour_handler(
+// Stores one byte range of a chunked upload; re-sending a range is allowed
+async fn chunked_upload_chunk_handler(
     State(state): State<AppState>,
-    Path(tour_id): Path<i64>,
-) -> impl IntoResponse {
-    println!("export: start packaging for tour {}", tour_id);
-    // TODO: auth/ownership check via session; for now, fetch by tour_id only
-    let db = state.database.clone();
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let offset = content_range_start(&headers)
+        .ok_or((StatusCode::BAD_REQUEST, "Missing or invalid Content-Range header".to_string()))?;
+    if body.len() > state.config.uploads.max_chunk_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("Chunks are limited to {} bytes", state.config.uploads.max_chunk_bytes)));
+    }
 
-    // Load tour data by id (no owner filter)
-    let tour = match db.get_tour_with_scenes_by_id(tour_id).await {
-        Ok(Some(t)) => t,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Tour not found").into_response(),
+    let mut uploads = CHUNKED_UPLOADS.lock().await;
+    let uploads = uploads.as_mut().ok_or((StatusCode::NOT_FOUND, "Unknown upload".to_string()))?;
+    match uploads.write_chunk(&upload_id, offset, &body) {
+        Ok(received) => Ok(Json(serde_json::json!({
+            "upload_id": upload_id,
+            "received": received
+        }))),
+        Err(chunked_upload::ChunkError::UnknownUpload) => Err((StatusCode::NOT_FOUND, "Unknown or expired upload".to_string())),
+        Err(chunked_upload::ChunkError::Io(e)) => {
+            eprintln!("Failed to store chunk for {}: {}", upload_id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to store chunk".to_string()))
+        }
+        Err(e) => Err((StatusCode::RANGE_NOT_SATISFIABLE, e.to_string())),
+    }
+}
+
+// Reports how many bytes of a chunked upload have arrived, so a client can resume
+async fn chunked_upload_status_handler(Path(upload_id): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let received = CHUNKED_UPLOADS.lock().await.as_ref().and_then(|uploads| uploads.received(&upload_id));
+    match received {
+        Some(received) => Ok(Json(serde_json::json!({
+            "upload_id": upload_id,
+            "received": received
+        }))),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+// Assembles a chunked upload and saves it like a regular `/upload-asset` upload
+async fn chunked_upload_complete_handler(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<editor::UploadResponse>, (StatusCode, String)> {
+    let completed = match CHUNKED_UPLOADS.lock().await.as_mut() {
+        Some(uploads) => uploads.complete(&upload_id),
+        None => Err(chunked_upload::ChunkError::UnknownUpload),
+    };
+    // Reading the chunks back happens after the registry's lock is released
+    let assembled = match completed {
+        Ok(completed) => tokio::task::spawn_blocking(move || completed.assemble())
+            .await
+            .unwrap_or_else(|e| Err(chunked_upload::ChunkError::Io(std::io::Error::other(e)))),
+        Err(e) => Err(e),
+    };
+    let (info, data) = match assembled {
+        Ok(assembled) => assembled,
+        Err(chunked_upload::ChunkError::UnknownUpload) => return Err((StatusCode::NOT_FOUND, "Unknown or expired upload".to_string())),
+        Err(chunked_upload::ChunkError::Incomplete { received, total }) => {
+            return Err((StatusCode::CONFLICT, format!("Upload incomplete: {} of {} bytes received", received, total)));
+        }
         Err(e) => {
-            eprintln!("export: failed to load tour {}: {}", tour_id, e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load tour").into_response();
+            eprintln!("Failed to assemble upload {}: {}", upload_id, e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to assemble upload".to_string()));
         }
     };
+    if info.subdir == "video" {
+        editor::check_video_upload(&info.filename, &data, state.config.uploads.max_video_bytes)?;
+    } else if info.subdir == "audio" {
+        editor::check_audio_upload(&info.filename, &data, state.config.uploads.max_audio_bytes)?;
+    } else if !editor::is_supported_image(&data) {
+        return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, "Uploaded file is not a JPEG, PNG or WebP image".to_string()));
+    }
+    let (projection, warning) = editor::check_upload_projection(&info.subdir, &data, state.config.uploads.reject_flat_scenes)?;
+    let (data, dimensions) = editor::fit_closeup_upload(&info.subdir, data, state.config.uploads.max_closeup_megapixels).await;
 
-    // Build a zip in memory
-    let cursor = std::io::Cursor::new(Vec::new());
-    let mut zip = zip::ZipWriter::new(cursor);
-    let options = zip::write::FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o644);
-
-    // Helper to add a file from bytes
-    let mut add_file = |path_in_zip: &str, bytes: &[u8]| -> Result<(), Box<dyn std::error::Error>> {
-        zip.start_file(path_in_zip, options)?;
-        zip.write_all(bytes)?;
-        Ok(())
+    let file_path = editor::store_upload(&state.database, info.username.as_deref(), std::path::Path::new("assets"), &info.subdir, &info.filename, &data)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to save assembled upload: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save file".to_string())
+        })?;
+    let detected_north = if state.config.editor.infer_north && info.subdir == "insta360" {
+        editor::read_exif_heading(&data)
+    } else {
+        None
     };
+    let captured_at = if info.subdir == "insta360" { editor::read_exif_capture_date(&data) } else { None };
+    let thumbnail_path = editor::closeup_thumbnail_for_upload(&info.subdir, &file_path, data).await;
+    Ok(Json(editor::UploadResponse {
+        file_path,
+        message: "File uploaded successfully".to_string(),
+        detected_north,
+        thumbnail_path,
+        captured_at,
+        projection,
+        warning,
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+    }))
+}
+
+async fn transfer_tour_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<TransferTourRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
 
-    // 1) Add viewer: Always include minimal viewer index
-    let viewer_html = include_str!("../static/export-viewer/index.html");
-    if let Err(e) = add_file("index.html", viewer_html.as_bytes()) {
-        eprintln!("export: add viewer index failed: {}", e);
+    match state.database.transfer_tour(tour_id, &username, &payload.username).await {
+        Ok(true) => {
+            // The old owner's in-memory editor state for this tour is no longer valid
+            remove_editor_session(&username, tour_id).await;
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "message": format!("Tour transferred to {}", payload.username)
+            })))
+        }
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(sqlx::Error::RowNotFound) => Err(StatusCode::BAD_REQUEST),
+        Err(e) if database::is_missing_owner(&e) => Err(StatusCode::BAD_REQUEST),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
+}
 
-    // 2) Ensure three/engine exist; prefer bundling our built-in engine
-    let mut engine_added = false;
-    let mut three_added = false;
-    // Bundle our minimal engine implementation
-    let builtin_engine = std::path::Path::new("static/export-viewer/js/engine.min.js");
-    if builtin_engine.exists() {
-        if let Ok(bytes) = std::fs::read(builtin_engine) { let _ = add_file("js/engine.min.js", &bytes); engine_added = true; }
+// Creates a read-only share link for a tour owned by the caller
+async fn create_share_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
-    let candidate_three = std::path::Path::new("static/export-viewer/js/three.min.js");
-    if candidate_three.exists() {
-        if let Ok(bytes) = std::fs::read(candidate_three) { let _ = add_file("js/three.min.js", &bytes); three_added = true; }
+
+    // The tour's own domain wins over the server-wide one
+    let base_url = match state.database.get_share_base_url(tour_id).await {
+        Ok(url) => url.or_else(|| state.config.sharing.base_url.clone()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let lifetime = state.config.sharing.token_lifetime_secs;
+    match state.database.create_share_token(tour_id, lifetime).await {
+        Ok(token) => Ok(Json(serde_json::json!({
+            "success": true,
+            "url": share_url(base_url.as_deref(), &token),
+            "token": token,
+            "expires_in": (lifetime > 0).then_some(lifetime)
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
+}
 
-    // 3) Build tourData.js from DB JSON and include
-    let tour_js = format!("const tourData = {};", tour);
-    if let Err(e) = add_file("js/tourData.js", tour_js.as_bytes()) {
-        eprintln!("export: add tourData.js failed: {}", e);
+/// Link for a share token; relative when no base URL is configured
+fn share_url(base_url: Option<&str>, token: &str) -> String {
+    format!("{}/api/shared/{}", base_url.unwrap_or("").trim_end_matches('/'), token)
+}
+
+// Sitemap of every tour with an active share link. Tours without their own base URL use
+// `sharing.base_url`; without one configured there is no sitemap, since the request's
+// Host header can't be trusted to build indexed links.
+async fn sitemap_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let Some(ref base_url) = state.config.sharing.base_url else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let tours = state.database.list_published_tours().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut body = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for tour in &tours {
+        let base = tour.share_base_url.as_deref().unwrap_or(base_url);
+        body.push_str(&format!(
+            "  <url>\n    <loc>{}</loc>\n    <lastmod>{}</lastmod>\n  </url>\n",
+            xml_escape(&share_url(Some(base), &tour.share_token)),
+            sitemap_lastmod(&tour.modified_at)
+        ));
     }
+    body.push_str("</urlset>\n");
 
-    // 4) Copy referenced image assets into assets/ (insta360 and closeups)
-    // Collect unique file paths from scenes and connections
-    let mut paths: Vec<String> = Vec::new();
-    if let Some(scenes) = tour.get("scenes").and_then(|v| v.as_array()) {
-        for s in scenes {
-            if let Some(fp) = s.get("file_path").and_then(|v| v.as_str()) {
-                paths.push(fp.to_string());
-            }
-            if let Some(conns) = s.get("connections").and_then(|v| v.as_array()) {
-                for c in conns {
-                    if let Some(fp) = c.get("file_path").and_then(|v| v.as_str()) { paths.push(fp.to_string()); }
+    Ok(([(axum::http::header::CONTENT_TYPE, "application/xml")], body))
+}
+
+/// W3C datetime for a SQLite `CURRENT_TIMESTAMP` value (stored in UTC)
+fn sitemap_lastmod(timestamp: &str) -> String {
+    match timestamp.split_once(' ') {
+        Some((date, time)) => format!("{}T{}+00:00", date, time),
+        None => timestamp.to_string(),
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Resolves a share token to the tour data; expired or revoked tokens are 404, members-only tours need a session
+async fn shared_tour_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    match state.database.get_tour_by_share_token(&token).await {
+        Ok(Some(tour)) => {
+            if let Some(tour_id) = tour["id"].as_i64() {
+                // Members-only tours need a signed-in viewer (which includes the owner)
+                if state.database.tour_requires_auth(tour_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)? {
+                    authenticate_request(&headers, &state.database).await?;
                 }
+                let _ = state.database.record_tour_view(tour_id).await;
             }
+            Ok(Json(tour))
         }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
-    paths.sort();
-    paths.dedup();
-    for p in paths {
-        let rel = p.trim_start_matches('/');
-        if rel.is_empty() { continue; }
-        if let Ok(bytes) = std::fs::read(rel) {
-        let zip_path = format!("{}", rel); // keep same assets/... structure
-            if let Err(e) = add_file(&zip_path, &bytes) { eprintln!("export: add asset {} failed: {}", rel, e); }
-        } else {
-            eprintln!("export: missing asset file: {}", rel);
+}
+
+// Same as a share link, but addressed by the tour's slug; only published tours resolve
+async fn slug_tour_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let tour = state.database.get_published_tour_by_slug(&slug).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(tour_id) = tour["id"].as_i64() {
+        if state.database.tour_requires_auth(tour_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)? {
+            authenticate_request(&headers, &state.database).await?;
         }
+        let _ = state.database.record_tour_view(tour_id).await;
     }
+    Ok(Json(tour))
+}
 
-    // 4b) Also copy static assets (icons/sprites) into assets/ from static/assets
-    let static_assets_root = std::path::Path::new("static/assets");
-    if static_assets_root.exists() {
-        for entry in walkdir::WalkDir::new(static_assets_root).into_iter().flatten() {
-            let p = entry.path();
-            if p.is_file() {
-                if let Ok(bytes) = std::fs::read(p) {
-                    if let Ok(rel) = p.strip_prefix("static") {
-                        let mut zip_path = rel.to_string_lossy().to_string();
-                        zip_path = zip_path.replace('\\', "/");
-                        let _ = add_file(&zip_path, &bytes);
-                    }
+// Dry-run check of an uploaded tourData.js or export ZIP; never creates rows
+async fn import_validate_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<importer::ValidationReport>, StatusCode> {
+    authenticate_request(&headers, &state.database).await?;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or("tourData.js").to_string();
+        let data = field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?;
+        return Ok(Json(importer::validate_upload(&filename, &data)));
+    }
+    Err(StatusCode::BAD_REQUEST)
+}
+
+// Creates a tour from an uploaded tourData.js, export ZIP or zipped Pannellum config.json;
+// the format is detected from the upload itself
+async fn import_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let username = authenticate_request(&headers, &state.database).await
+        .map_err(|status| (status, "Not signed in".to_string()))?;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| (StatusCode::BAD_REQUEST, "Invalid upload".to_string()))? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or("tourData.js").to_string();
+        let data = field.bytes().await.map_err(|_| (StatusCode::BAD_REQUEST, "Invalid upload".to_string()))?;
+        let (format, result) = importer::import_upload(state.database.clone(), &username, &filename, &data, std::path::Path::new("."))
+            .await
+            .map_err(|e| {
+                eprintln!("import: {} for {}", e, username);
+                match e {
+                    importer::ImportError::InvalidUpload(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+                    importer::ImportError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to import tour".to_string()),
                 }
-            }
+            })?;
+        for warning in &result.warnings {
+            eprintln!("import: {}", warning);
         }
+        println!("import: created tour {} for {} from a {:?} upload", result.tour_id, username, format);
+        return Ok(Json(serde_json::json!({ "success": true, "format": format, "result": result })));
+    }
+    Err((StatusCode::BAD_REQUEST, "Missing file field".to_string()))
+}
+
+// Lists a tour's scene or closeup assets from the database
+async fn tour_assets_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    Query(query): Query<AssetListQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match state.database.list_assets(tour_id, query.kind).await {
+        Ok(assets) => Ok(Json(serde_json::json!({
+            "success": true,
+            "assets": assets
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
+}
 
-    // 5) Fallback note for engine if missing
-    if !engine_added {
-        let note = b"// Engine not bundled; use your own viewer. tourData.js is included.";
-        let _ = add_file("js/engine.min.js", note);
+// Lists the raw outgoing connections of a single scene
+async fn scene_connections_handler(
+    State(state): State<AppState>,
+    Path((tour_id, scene_id)): Path<(i64, i64)>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
-    if !three_added {
-        let note = b"// Three.js not bundled. Include a compatible build in js/three.min.js.";
-        let _ = add_file("js/three.min.js", note);
+
+    match state.database.get_scene_connections(tour_id, scene_id).await {
+        Ok(connections) => Ok(Json(serde_json::json!({
+            "success": true,
+            "scene_id": scene_id,
+            "connections": connections
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
+}
 
-    let cursor = match zip.finish() { // finish writer and retrieve cursor
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("export: zip finish error: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to package").into_response();
+// Lists the connections leading to a scene so the UI can warn before deleting it
+async fn incoming_connections_handler(
+    State(state): State<AppState>,
+    Path(scene_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_asset_owner(scene_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match state.database.get_incoming_connections(scene_id).await {
+        Ok(connections) => {
+            let mut source_scenes: Vec<i64> = connections.iter().map(|c| c.start_id).collect();
+            source_scenes.sort();
+            source_scenes.dedup();
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "scene_id": scene_id,
+                "source_scene_count": source_scenes.len(),
+                "connections": connections
+            })))
         }
-    };
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
 
-    let buffer = cursor.into_inner();
+// Lists the caller's assets whose files are missing from disk
+async fn asset_audit_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.audit_assets(Some(&username)).await {
+        Ok(missing) => Ok(Json(serde_json::json!({
+            "success": true,
+            "missing_count": missing.len(),
+            "missing": missing
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
 
-    println!("export: finished packaging for tour {} ({} bytes)", tour_id, buffer.len());
+// Copies the hotspot layout of another scene (same owner, any tour) onto a scene
+async fn copy_connections_handler(
+    State(state): State<AppState>,
+    Path((scene_id, source_id)): Path<(i64, i64)>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.copy_connections(source_id, scene_id, &username).await {
+        Ok(Some(report)) => {
+            // The open editor session for the destination tour doesn't know about the copies
+            remove_editor_session(&username, report.tour_id).await;
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "scene_id": scene_id,
+                "source_id": source_id,
+                "copied": report.copied,
+                "dropped": report.dropped
+            })))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
 
-    // Build response
-    let filename = format!("tour_{}_export.zip", tour_id);
-    let mut headers = axum::http::HeaderMap::new();
-    headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/zip"));
-    headers.insert(
-        axum::http::header::CONTENT_DISPOSITION,
-        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename)).unwrap_or(HeaderValue::from_static("attachment"))
-    );
+// Folds one scene into another of the same tour, keeping the first scene's image
+async fn merge_scenes_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<MergeScenesRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    if payload.keep_id == payload.remove_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match state.database.merge_scenes(payload.keep_id, payload.remove_id, &username).await {
+        Ok(Some(report)) => {
+            // The open editor session still holds the removed scene
+            remove_editor_session(&username, report.tour_id).await;
+            Ok(Json(serde_json::json!({
+                "success": true,
+                "scene_id": payload.keep_id,
+                "removed_scene_id": payload.remove_id,
+                "moved": report.moved,
+                "repointed": report.repointed,
+                "duplicates_removed": report.duplicates_removed
+            })))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
 
-    (headers, buffer).into_response()
+// Saves a labelled snapshot of a tour's current scene graph
+async fn create_snapshot_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateSnapshotRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    // Deferred editor writes belong in the snapshot
+    flush_editor_session(&username, tour_id).await;
+    match state.database.create_snapshot(tour_id, payload.label.trim(), state.config.editor.max_snapshots).await {
+        Ok(snapshot_id) => Ok(Json(serde_json::json!({
+            "success": true,
+            "snapshot_id": snapshot_id
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+async fn list_snapshots_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match state.database.list_snapshots(tour_id).await {
+        Ok(snapshots) => Ok(Json(serde_json::json!({
+            "success": true,
+            "snapshots": snapshots
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+// Reverts a tour to a snapshot, replacing its current scenes and connections
+async fn restore_snapshot_handler(
+    State(state): State<AppState>,
+    Path((tour_id, snapshot_id)): Path<(i64, i64)>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    // Drop the editor session first so its deferred writes can't land on top of the restored tour
+    remove_editor_session(&username, tour_id).await;
+    match state.database.restore_snapshot(tour_id, snapshot_id).await {
+        Ok(true) => Ok(Json(serde_json::json!({
+            "success": true,
+            "message": "Tour restored"
+        }))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+// Reports how export-ready a tour is (0-100 score plus the outstanding issues)
+async fn tour_completeness_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<database::CompletenessReport>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    state.database.tour_completeness(tour_id).await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Figures for dashboards: hotspots per connection type
+async fn tour_stats_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    let connection_types = state.database.connection_type_counts(tour_id).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(serde_json::json!({
+        "tour_id": tour_id,
+        "connection_types": connection_types
+    })))
+}
+
+// When the tour last changed, for cache checks; 304 if it hasn't since `If-Modified-Since`
+async fn tour_modified_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    let modified = match state.database.tour_modified(tour_id).await {
+        Ok(Some(modified)) => modified,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let last_modified = http_date(&modified.modified_at)
+        .and_then(|date| HeaderValue::from_str(&date).ok());
+
+    // Both sides are `YYYY-MM-DD HH:MM:SS` in UTC, so they compare as strings
+    let since = headers.get(axum::http::header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_http_date);
+    let mut response = match since {
+        Some(since) if modified.modified_at <= since => StatusCode::NOT_MODIFIED.into_response(),
+        _ => Json(modified).into_response(),
+    };
+    if let Some(last_modified) = last_modified {
+        response.headers_mut().insert(axum::http::header::LAST_MODIFIED, last_modified);
+    }
+    Ok(response)
+}
+
+const HTTP_DATE_DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const HTTP_DATE_MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// HTTP-date (`Sun, 06 Nov 1994 08:49:37 GMT`) for a SQLite `CURRENT_TIMESTAMP` value (stored in UTC)
+fn http_date(timestamp: &str) -> Option<String> {
+    let (date, time) = timestamp.split_once(' ')?;
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<usize>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    // Sakamoto's day-of-week
+    const OFFSETS: [usize; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if month < 3 { year - 1 } else { year };
+    let weekday = (y + y / 4 - y / 100 + y / 400 + OFFSETS[month - 1] + day) % 7;
+    Some(format!("{}, {:02} {} {} {} GMT", HTTP_DATE_DAYS[weekday], day, HTTP_DATE_MONTHS[month - 1], year, time))
+}
+
+/// Parses an HTTP-date back into SQLite's `YYYY-MM-DD HH:MM:SS`; `None` if it isn't one
+fn parse_http_date(value: &str) -> Option<String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else { return None };
+    let month = HTTP_DATE_MONTHS.iter().position(|m| m == month)? + 1;
+    let day: u32 = day.parse().ok()?;
+    let year: u32 = year.parse().ok()?;
+    if time.len() != 8 || time.split(':').any(|n| n.parse::<u32>().is_err()) {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02} {}", year, month, day, time))
+}
+
+// Scene positions as glTF nodes for 3D/AR tools (no geometry)
+async fn scene_anchors_gltf_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    let tour = match state.database.get_tour_with_scenes(&username, tour_id).await {
+        Ok(Some(tour)) => tour,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "model/gltf+json")],
+        gltf::scene_anchors(&tour).to_string(),
+    ))
+}
+
+// Shortest click-path between two scenes; 404 when `to` can't be reached from `from`
+async fn scene_path_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    Query(query): Query<ScenePathQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    match state.database.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match state.database.shortest_path(tour_id, query.from, query.to).await {
+        Ok(Some(path)) => Ok(Json(serde_json::json!({
+            "success": true,
+            "path": path
+        }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+// Packs the caller's tour thumbnails into one cached PNG and returns its URL with each tour's frame
+async fn thumbnails_sprite_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    use sha2::{Digest, Sha256};
+
+    let db = state.database.clone();
+    let username = authenticate_request(&headers, &db).await?;
+    let tours = db.get_tours(&username, database::TourOrder::default(), database::SortDirection::default())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tour_ids: Vec<i64> = tours.iter().map(|tour| tour.get_id() as i64).collect();
+    let mut thumbnails = db.get_thumbnails_for_tours(&tour_ids).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut sources = Vec::with_capacity(tours.len());
+    let mut fingerprint = Sha256::new();
+    for (tour, tour_id) in tours.iter().zip(&tour_ids) {
+        let thumbnail = thumbnails.remove(tour_id).flatten();
+        fingerprint.update(format!("{}|{}|{}\n", tour_id, tour.modified_at, thumbnail.as_deref().unwrap_or("")));
+        sources.push(thumbnail.and_then(|path| importer::asset_relative_path(&path).map(std::path::Path::to_path_buf)));
+    }
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let fingerprint = hex(&fingerprint.finalize()[..12]);
+    let owner_key = hex(&Sha256::digest(username.as_bytes())[..8]);
+
+    let (width, height, frames) = sprite::layout(tours.len());
+    let sheet = tokio::task::spawn_blocking(move || {
+        sprite::cached_sheet(&sources, std::path::Path::new("assets/sprites"), &owner_key, &fingerprint)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|e| {
+        eprintln!("sprite: failed to build thumbnail sheet for {}: {}", username, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let frames: serde_json::Map<String, serde_json::Value> = tour_ids.iter()
+        .zip(frames)
+        .map(|(id, frame)| (id.to_string(), serde_json::json!(frame)))
+        .collect();
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "sprite_url": format!("/{}", sheet.to_string_lossy().replace('\\', "/")),
+        "width": width,
+        "height": height,
+        "frames": frames
+    })))
+}
+
+// Serves one cube face of one of the caller's equirectangular scene images, rendered on first request
+async fn cubemap_face_handler(
+    State(state): State<AppState>,
+    Path((asset_id, face)): Path<(i64, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let username = authenticate_request(&headers, &state.database).await?;
+    let face = cubemap::CubeFace::parse(&face).ok_or(StatusCode::BAD_REQUEST)?;
+    let file_path = match state.database.get_owned_scene_file_path(asset_id, &username).await {
+        Ok(Some(path)) => path,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let source = importer::asset_relative_path(&file_path).ok_or(StatusCode::NOT_FOUND)?.to_path_buf();
+    if !source.exists() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let bytes = tokio::task::spawn_blocking(move || {
+        cubemap::face_jpeg(&source, std::path::Path::new("assets/cubemaps"), asset_id, face)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(|e| {
+        eprintln!("cubemap: failed to render face for asset {}: {}", asset_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(([(axum::http::header::CONTENT_TYPE, "image/jpeg")], bytes))
+}
+
+// Serves one of the caller's asset files, honouring a single `Range: bytes=...` request
+async fn asset_download_handler(
+    State(state): State<AppState>,
+    Path(asset_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let username = authenticate_request(&headers, &state.database).await?;
+    let file_path = match state.database.get_owned_asset_file_path(asset_id, &username).await {
+        Ok(Some(path)) => path,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let relative = importer::asset_relative_path(&file_path).ok_or(StatusCode::NOT_FOUND)?;
+    let mut file = tokio::fs::File::open(relative).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    let len = file.metadata().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?.len();
+    let content_type = asset_content_type(&file_path);
+
+    let range = headers.get(axum::http::header::RANGE).and_then(|v| v.to_str().ok());
+    let (start, end) = match range.map(|r| parse_byte_range(r, len)) {
+        None | Some(Err(RangeError::Ignored)) => {
+            // Streamed, so large videos aren't read into memory
+            return Ok((
+                [
+                    (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+                    (axum::http::header::ACCEPT_RANGES, "bytes".to_string()),
+                    (axum::http::header::CONTENT_LENGTH, len.to_string()),
+                ],
+                axum::body::Body::from_stream(tokio_util::io::ReaderStream::new(file)),
+            ).into_response());
+        }
+        Some(Err(RangeError::Unsatisfiable)) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(axum::http::header::CONTENT_RANGE, format!("bytes */{}", len))],
+            ).into_response());
+        }
+        Some(Ok(range)) => range,
+    };
+
+    file.seek(std::io::SeekFrom::Start(start)).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let part_len = end - start + 1;
+    Ok((
+        StatusCode::PARTIAL_CONTENT,
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (axum::http::header::ACCEPT_RANGES, "bytes".to_string()),
+            (axum::http::header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)),
+            (axum::http::header::CONTENT_LENGTH, part_len.to_string()),
+        ],
+        axum::body::Body::from_stream(tokio_util::io::ReaderStream::new(file.take(part_len))),
+    ).into_response())
+}
+
+/// `Content-Type` for an asset file, from its extension
+fn asset_content_type(path: &str) -> &'static str {
+    let ext = std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RangeError {
+    /// Not a single byte range we understand; serve the whole file
+    Ignored,
+    /// Starts past the end of the file (416)
+    Unsatisfiable,
+}
+
+/// Parses a single `bytes=start-end`, `bytes=start-` or `bytes=-suffix` range into
+/// inclusive offsets clamped to a file of `len` bytes.
+fn parse_byte_range(header: &str, len: u64) -> Result<(u64, u64), RangeError> {
+    let spec = header.trim().strip_prefix("bytes=").ok_or(RangeError::Ignored)?;
+    if spec.contains(',') {
+        return Err(RangeError::Ignored);
+    }
+    let (start, end) = spec.split_once('-').ok_or(RangeError::Ignored)?;
+    let parse = |v: &str| v.trim().parse::<u64>().map_err(|_| RangeError::Ignored);
+    let (start, end) = match (start.trim().is_empty(), end.trim().is_empty()) {
+        // Last `suffix` bytes
+        (true, false) => {
+            let suffix = parse(end)?;
+            if suffix == 0 {
+                return Err(RangeError::Unsatisfiable);
+            }
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        (false, true) => (parse(start)?, len.saturating_sub(1)),
+        (false, false) => (parse(start)?, parse(end)?.min(len.saturating_sub(1))),
+        (true, true) => return Err(RangeError::Ignored),
+    };
+    if start >= len {
+        return Err(RangeError::Unsatisfiable);
+    }
+    if end < start {
+        return Err(RangeError::Ignored);
+    }
+    Ok((start, end))
+}
+
+// Assets list handler
+async fn list_assets_handler() -> impl IntoResponse {
+    match scene_upload_files(SCENE_UPLOAD_DIR) {
+        Ok(files) => {
+            Json(serde_json::json!({
+                "success": true,
+                "assets": files
+            })).into_response()
+        }
+        Err(_) => {
+            Json(serde_json::json!({
+                "success": false,
+                "message": "Could not read assets directory",
+                "assets": []
+            })).into_response()
+        }
+    }
+}
+
+/// Where uploaded scene panoramas are stored
+const SCENE_UPLOAD_DIR: &str = "assets/insta360";
+
+/// Sorted names of the image files in an upload directory
+fn scene_upload_files(dir: &str) -> std::io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(file_name_str) = path.file_name().and_then(|n| n.to_str()) {
+            // Only include image files
+            if file_name_str.ends_with(".jpg") ||
+               file_name_str.ends_with(".jpeg") ||
+               file_name_str.ends_with(".png") {
+                files.push(file_name_str.to_string());
+            }
+        }
+    }
+
+    // Sort files for consistent ordering
+    files.sort();
+    Ok(files)
+}
+
+/// Lists the uploads in `dir`, flagging the ones already placed as scenes in the tour
+async fn uploads_with_usage(db: &Database, tour_id: i64, dir: &str) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let used = db.scene_file_paths(tour_id).await?;
+    Ok(scene_upload_files(dir)?
+        .into_iter()
+        .map(|file_name| {
+            let file_path = format!("/{}/{}", dir, file_name);
+            serde_json::json!({
+                "file_name": file_name,
+                "used": used.contains(&file_path),
+                "file_path": file_path
+            })
+        })
+        .collect())
+}
+
+// Prometheus text-format metrics
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let editor_sessions = EDITOR_SESSIONS.read().await.as_ref().map(|s| s.len()).unwrap_or(0);
+
+    let metrics: [(&str, &str, &str, usize); 5] = [
+        ("vte_active_connections", "gauge", "Currently open WebSocket connections.", state.connections.load(Ordering::Relaxed)),
+        ("vte_editor_sessions", "gauge", "In-memory editor sessions.", editor_sessions),
+        ("vte_logins_total", "counter", "Successful logins since startup.", TOTAL_LOGINS.load(Ordering::Relaxed)),
+        ("vte_tours_created_total", "counter", "Tours created since startup.", TOTAL_TOURS_CREATED.load(Ordering::Relaxed)),
+        ("vte_exports_total", "counter", "Tour exports packaged since startup.", TOTAL_EXPORTS.load(Ordering::Relaxed)),
+    ];
+
+    let mut body = String::new();
+    for (name, kind, help, value) in metrics {
+        body.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+    }
+
+    (
+        [(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"))],
+        body,
+    )
+}
+
+// Engine, three.js and tourData format versions bundled with exports
+async fn viewer_info_handler() -> Json<serde_json::Value> {
+    Json(exporter::viewer_info())
+}
+
+// Content hashes of the viewer files exports bundle right now, for cache-busting; a file
+// that can't be read is null
+async fn engine_hash_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let viewer_js_dir = std::path::PathBuf::from(&state.config.export.viewer_js_dir);
+    let digests = tokio::task::spawn_blocking(move || exporter::viewer_file_digests(&viewer_js_dir)).await.unwrap_or_default();
+    let hashes: serde_json::Map<String, serde_json::Value> = digests.into_iter()
+        .map(|(key, digest)| (key.to_string(), digest.ok().map(|d| d.sha256).into()))
+        .collect();
+    Json(serde_json::Value::Object(hashes))
+}
+
+// Editor actions the WebSocket accepts, with their fields, for clients building forms
+async fn editor_actions_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "actions": editor::manifest::ACTIONS }))
+}
+
+// Static page handlers
+async fn index_page() -> Html<&'static str> {
+    Html(include_str!("../static/index.html"))
+}
+
+async fn login_page() -> Html<&'static str> {
+    Html(include_str!("../static/login.html"))
+}
+
+async fn homepage() -> Html<&'static str> {
+    Html(include_str!("../static/homepage.html"))
+}
+
+async fn editor_page() -> Html<&'static str> {
+    Html(include_str!("../static/editor.html"))
+}
+
+// --- Export handler ---
+// Generates a downloadable ZIP containing a self-hostable tour package.
+async fn export_tour_handler(
+    State(state): State<AppState>,
+    Path(tour_id): Path<i64>,
+    headers: HeaderMap,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let db = state.database.clone();
+    let username = match authenticate_request(&headers, &db).await {
+        Ok(username) => username,
+        Err(status) => return status.into_response(),
+    };
+    // Only the owner exports; anyone else's tour looks like it doesn't exist
+    match db.is_tour_owner(tour_id, &username).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Tour not found").into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+    println!("export: start packaging for tour {}", tour_id);
+
+    // Load tour data by id (ownership checked above)
+    let mut tour = match exporter::build_tour_data(&db, tour_id).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Tour not found").into_response(),
+        Err(e) => {
+            eprintln!("export: failed to load tour {}: {}", tour_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load tour").into_response();
+        }
+    };
+    if tour["scenes"].as_array().is_none_or(|scenes| scenes.is_empty()) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, "Tour has no scenes to export. Add at least one scene first.").into_response();
+    }
+    exporter::TourDataOptions {
+        default_icon_index: state.config.export.default_icon_index,
+        flatten_closeups: query.flatten_closeups,
+    }.apply(&mut tour);
+
+    // Build a zip in memory
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let viewer_js_dir = std::path::Path::new(&state.config.export.viewer_js_dir);
+    let static_assets_dir = std::path::Path::new(&state.config.export.static_assets_dir);
+    let warnings = match exporter::write_package(&db, tour_id, &tour, &mut zip, "", viewer_js_dir, static_assets_dir).await {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            eprintln!("export: packaging tour {} failed: {}", tour_id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to package").into_response();
+        }
+    };
+
+    let cursor = match zip.finish() { // finish writer and retrieve cursor
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("export: zip finish error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to package").into_response();
+        }
+    };
+
+    let buffer = cursor.into_inner();
+
+    println!("export: finished packaging for tour {} ({} bytes)", tour_id, buffer.len());
+    TOTAL_EXPORTS.fetch_add(1, Ordering::Relaxed);
+
+    // Build response
+    let filename = format!("tour_{}_export.zip", tour_id);
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename)).unwrap_or(HeaderValue::from_static("attachment"))
+    );
+    insert_export_warnings(&mut headers, &warnings);
+
+    (headers, buffer).into_response()
+}
+
+/// Flags an incomplete package (e.g. `X-Export-Warnings: engine-missing`) so a broken deploy doesn't go unnoticed
+fn insert_export_warnings(headers: &mut HeaderMap, warnings: &[exporter::ExportWarning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(&exporter::warnings_header(warnings)) {
+        headers.insert("x-export-warnings", value);
+    }
+}
+
+// Exports every tour of the signed-in user as one ZIP (one folder per tour plus index.json and a browsable index.html).
+// The archive is assembled in a temp file and streamed from disk rather than held in memory.
+async fn export_all_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ExportQuery>,
+) -> Result<axum::response::Response, StatusCode> {
+    let db = state.database.clone();
+    let username = authenticate_request(&headers, &db).await?;
+    println!("export-all: start packaging for {}", username);
+
+    let temp_path = std::env::temp_dir().join(format!("vte_export_all_{}.zip", uuid::Uuid::new_v4()));
+    let file = std::fs::File::create(&temp_path).map_err(|e| {
+        eprintln!("export-all: failed to create temp file: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut zip = zip::ZipWriter::new(file);
+    let viewer_js_dir = std::path::Path::new(&state.config.export.viewer_js_dir);
+    let static_assets_dir = std::path::Path::new(&state.config.export.static_assets_dir);
+    let options = exporter::TourDataOptions {
+        default_icon_index: state.config.export.default_icon_index,
+        flatten_closeups: query.flatten_closeups,
+    };
+    let packaged = exporter::write_all_packages(&db, &username, &mut zip, viewer_js_dir, static_assets_dir, &options).await;
+    let finished = zip.finish();
+    let (packaged, warnings) = match (packaged, finished) {
+        (Ok(result), Ok(_)) => result,
+        (Err(e), _) => {
+            eprintln!("export-all: packaging failed for {}: {}", username, e);
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        (_, Err(e)) => {
+            eprintln!("export-all: zip finish error: {}", e);
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // The open handle keeps the data readable after the path is removed
+    let file = tokio::fs::File::open(&temp_path).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let _ = std::fs::remove_file(&temp_path);
+    println!("export-all: packaged {} tours for {}", packaged, username);
+    TOTAL_EXPORTS.fetch_add(packaged, Ordering::Relaxed);
+
+    let stream = futures::stream::unfold(file, |mut file| async move {
+        use tokio::io::AsyncReadExt;
+        let mut buf = vec![0u8; 64 * 1024];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok::<_, std::io::Error>(axum::body::Bytes::from(buf)), file))
+            }
+            Err(e) => Some((Err(e), file)),
+        }
+    });
+
+    let mut headers = HeaderMap::new();
+    headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&format!("attachment; filename=\"{}_tours_export.zip\"", username)).unwrap_or(HeaderValue::from_static("attachment"))
+    );
+    insert_export_warnings(&mut headers, &warnings);
+    Ok((headers, axum::body::Body::from_stream(stream)).into_response())
+}
+
+// Every tour of the signed-in user with its full graph, as one JSON array (same shape as
+// `Database::export_all_json`). Tours are loaded and sent one at a time so large accounts
+// don't have to fit in memory at once.
+async fn backup_json_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, StatusCode> {
+    let db = state.database.clone();
+    let username = authenticate_request(&headers, &db).await?;
+    let tour_ids = db.tour_ids_of(&username).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for tour_id in &tour_ids {
+        flush_editor_session(&username, *tour_id).await;
+    }
+
+    let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{}_backup.json\"", username))
+        .unwrap_or(HeaderValue::from_static("attachment"));
+    let tours = futures::stream::iter(tour_ids)
+        .then(move |tour_id| {
+            let db = db.clone();
+            let username = username.clone();
+            async move { db.get_tour_with_scenes(&username, tour_id).await }
+        })
+        // A tour deleted while the backup is being sent is left out
+        .filter_map(|tour| async move { tour.transpose() })
+        .enumerate()
+        .map(|(i, tour)| match tour {
+            Ok(tour) => Ok(axum::body::Bytes::from(format!("{}{}", if i == 0 { "" } else { "," }, tour))),
+            Err(e) => {
+                eprintln!("backup: failed to load a tour: {}", e);
+                Err(std::io::Error::other(e))
+            }
+        });
+    let body = futures::stream::once(async { Ok(axum::body::Bytes::from_static(b"[")) })
+        .chain(tours)
+        .chain(futures::stream::once(async { Ok(axum::body::Bytes::from_static(b"]")) }));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(axum::http::header::CONTENT_DISPOSITION, disposition);
+    Ok((headers, axum::body::Body::from_stream(body)).into_response())
+}
+
+// Recreates the tours of a `/api/backup.json` download for the signed-in user, under new IDs
+async fn restore_backup_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let username = authenticate_request(&headers, &state.database).await
+        .map_err(|status| (status, "Not signed in".to_string()))?;
+    // Only the caller's own files may be attached; a backup can't reach anyone else's
+    let owned = state.database.user_asset_paths(&username).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let available = |path: &str| owned.contains(&format!("/{path}")) && std::path::Path::new(path).is_file();
+    let report = importer::restore_backup(&state.database, &username, &body, available)
+        .await
+        .map_err(|e| {
+            eprintln!("restore: {} for {}", e, username);
+            match e {
+                importer::RestoreError::InvalidBackup(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+                importer::RestoreError::Database { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to restore backup".to_string()),
+            }
+        })?;
+    for warning in &report.warnings {
+        eprintln!("restore: {}", warning);
+    }
+    println!("restore: recreated {} tours for {}", report.tour_ids.len(), username);
+    Ok(Json(serde_json::json!({ "success": true, "report": report })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn test_state() -> AppState {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(include_str!("./schema.sql")).execute(&pool).await.unwrap();
+        AppState { database: Arc::new(Database::new(pool)), config: Arc::new(config::Config::default()), connections: Default::default() }
+    }
+
+    async fn body_string(response: axum::response::Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("read body");
+        String::from_utf8(bytes.to_vec()).expect("utf8 body")
+    }
+
+    #[tokio::test]
+    async fn test_database_ready_before_first_client() {
+        let dir = std::path::PathBuf::from("target/test_db");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.db", uuid::Uuid::new_v4()));
+
+        let database = init_database(path.to_str().unwrap(), std::time::Duration::ZERO).await.expect("startup init");
+        let global = DATABASE.read().await.clone().expect("global set at startup");
+        assert!(Arc::ptr_eq(&global, &database));
+
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&*database.pool)
+            .await
+            .unwrap();
+        for table in ["users", "tours", "assets", "connections", "share_tokens"] {
+            assert!(tables.iter().any(|t| t == table), "missing table {}", table);
+        }
+
+        database.pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_exposes_expected_names() {
+        let response = metrics_handler(State(test_state().await)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_string(response).await;
+        for name in ["vte_active_connections", "vte_editor_sessions", "vte_logins_total", "vte_tours_created_total", "vte_exports_total"] {
+            assert!(body.contains(&format!("# TYPE {} ", name)), "missing metric {}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_cache_control_uses_config() {
+        let mut config = config::Config::default();
+        config.server.static_cache_secs = 120;
+        let app = build_router(test_state().await, &config);
+
+        let request = axum::http::Request::builder()
+            .uri("/static/css/login.css")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[axum::http::header::CACHE_CONTROL], "public, max-age=120");
+    }
+
+    #[tokio::test]
+    async fn test_scene_connections_route_returns_only_that_scene() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Tour", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let closeup = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let to_hall = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+        let to_plaque = db.save_connection(tour_id, lobby, Some(closeup), 40.0, 2.0, editor::ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), None).await.unwrap();
+        db.save_connection(tour_id, hall, Some(lobby), 190.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/tours/{}/scenes/{}/connections", tour_id, lobby))
+            .header("x-username", "owner")
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let connections = body["connections"].as_array().unwrap();
+        let mut ids: Vec<i64> = connections.iter().map(|c| c["id"].as_i64().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, vec![to_hall, to_plaque]);
+        for conn in connections {
+            let expected = if conn["id"].as_i64() == Some(to_hall) { "Transition" } else { "Closeup" };
+            assert_eq!(conn["connection_type"], expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_tour_without_scenes() {
+        let state = test_state().await;
+        state.database.register_user("owner", "password").await.unwrap();
+        let token = state.database.login_user("owner").await.unwrap();
+        let tour_id = state.database.create_tour("owner", "Empty", "").await.unwrap();
+
+        state.database.register_user("other", "password").await.unwrap();
+        let other_token = state.database.login_user("other").await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let export_as = |user: Option<(&str, &str)>| {
+            let mut request = axum::http::Request::builder().uri(format!("/api/export/{}", tour_id));
+            if let Some((username, token)) = user {
+                request = request.header("x-username", username).header("x-session-token", token);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+        // Signed-in owners only
+        assert_eq!(export_as(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(export_as(Some(("other", &other_token))).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let response = export_as(Some(("owner", &token))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_string(response).await.contains("no scenes"));
+    }
+
+    #[tokio::test]
+    async fn test_export_without_engine_warns() {
+        let viewer_dir = std::path::PathBuf::from("target/test_viewer").join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&viewer_dir).unwrap();
+        std::fs::write(viewer_dir.join("three.min.js"), b"// three").unwrap();
+        let mut config = config::Config::default();
+        config.export.viewer_js_dir = viewer_dir.to_string_lossy().to_string();
+
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        state.database.register_user("owner", "password").await.unwrap();
+        let token = state.database.login_user("owner").await.unwrap();
+        let tour_id = state.database.create_tour("owner", "Lobby only", "").await.unwrap();
+        state.database.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let app = build_router(state, &config);
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/export/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let _ = std::fs::remove_dir_all(&viewer_dir);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get("x-export-warnings").unwrap(), "engine-missing");
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        let warnings = {
+            let mut file = archive.by_name("WARNINGS.txt").expect("WARNINGS.txt in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            text
+        };
+        assert!(warnings.contains("engine.min.js"));
+        assert!(!warnings.contains("three.min.js"));
+        assert!(archive.by_name("js/engine.min.js").is_err(), "no stub engine");
+        assert!(archive.by_name("js/three.min.js").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_export_gives_iconless_connections_the_default_icon() {
+        let mut config = config::Config::default();
+        config.export.default_icon_index = 2;
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Icons", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let door = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+        let plain = db.save_connection(tour_id, lobby, Some(plaque), 50.0, 0.0, editor::ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), None).await.unwrap();
+        let picked = db.save_connection(tour_id, hall, Some(plaque), 90.0, 0.0, editor::ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), Some(3)).await.unwrap();
+
+        let app = build_router(state, &config);
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/export/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        let tour_data = {
+            let mut file = archive.by_name("js/tourData.js").expect("tourData.js in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            text
+        };
+        let tour: serde_json::Value = serde_json::from_str(tour_data.trim_start_matches("const tourData = ").trim_end_matches(';')).unwrap();
+        let icons: HashMap<i64, i64> = tour["scenes"].as_array().unwrap().iter()
+            .flat_map(|scene| scene["connections"].as_array().unwrap().iter())
+            .map(|c| (c["id"].as_i64().unwrap(), c["icon_index"].as_i64().expect("every connection has an icon")))
+            .collect();
+        assert_eq!(icons[&door], 2);
+        assert_eq!(icons[&plain], 2);
+        // An icon the author picked is kept
+        assert_eq!(icons[&picked], 3);
+    }
+
+    #[tokio::test]
+    async fn test_export_can_flatten_closeups_into_info_hotspots() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Gallery", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let plaque = db.save_closeup(tour_id, "Plaque", "/assets/closeups/plaque.jpg", None).await.unwrap();
+        let door = db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+        let closeup = db.save_connection(tour_id, lobby, Some(plaque), 50.0, 0.0, editor::ConnectionType::Closeup, Some("Plaque"), Some("/assets/closeups/plaque.jpg"), Some(2)).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let export = |flatten: bool| {
+            let (app, token) = (app.clone(), token.clone());
+            async move {
+                let uri = match flatten {
+                    true => format!("/api/export/{}?flatten_closeups=true", tour_id),
+                    false => format!("/api/export/{}", tour_id),
+                };
+                let request = axum::http::Request::builder().uri(uri)
+                    .header("x-username", "owner")
+                    .header("x-session-token", token.clone())
+                    .body(axum::body::Body::empty()).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+                let mut file = archive.by_name("js/tourData.js").expect("tourData.js in export");
+                let mut text = String::new();
+                std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+                let tour: serde_json::Value = serde_json::from_str(text.trim_start_matches("const tourData = ").trim_end_matches(';')).unwrap();
+                let connections: HashMap<i64, serde_json::Value> = tour["scenes"].as_array().unwrap().iter()
+                    .flat_map(|scene| scene["connections"].as_array().unwrap().clone())
+                    .map(|c| (c["id"].as_i64().unwrap(), c))
+                    .collect();
+                connections
+            }
+        };
+
+        let overlays = export(false).await;
+        assert_eq!(overlays[&closeup]["connection_type"], "Closeup");
+
+        let flat = export(true).await;
+        let info = &flat[&closeup];
+        assert_eq!(info["connection_type"], "Info");
+        assert_eq!(info["popup_url"], "/assets/closeups/plaque.jpg");
+        assert!(info["target_scene_id"].is_null());
+        assert_eq!(info["name"], "Plaque");
+        assert_eq!(info["icon_index"], 2);
+        // Transitions are left alone
+        assert_eq!(flat[&door]["connection_type"], "Transition");
+        assert_eq!(flat[&door]["target_scene_id"].as_i64(), Some(hall));
+    }
+
+    #[tokio::test]
+    async fn test_export_moves_tour_files_colliding_with_static_assets() {
+        let static_dir = std::path::PathBuf::from("target/test_static_assets").join(uuid::Uuid::new_v4().to_string());
+        let name = format!("collide_{}.jpg", uuid::Uuid::new_v4());
+        std::fs::create_dir_all(static_dir.join("insta360")).unwrap();
+        std::fs::write(static_dir.join("insta360").join(&name), b"app sprite").unwrap();
+        std::fs::create_dir_all("assets/insta360").unwrap();
+        let scene_rel = format!("assets/insta360/{}", name);
+        std::fs::write(&scene_rel, b"tour panorama").unwrap();
+        let mut config = config::Config::default();
+        config.export.static_assets_dir = static_dir.to_string_lossy().to_string();
+
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        state.database.register_user("owner", "password").await.unwrap();
+        let token = state.database.login_user("owner").await.unwrap();
+        let tour_id = state.database.create_tour("owner", "Collision", "").await.unwrap();
+        state.database.save_scene(tour_id, "Lobby", &format!("/{}", scene_rel), None, None, None).await.unwrap();
+
+        let app = build_router(state, &config);
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/export/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let _ = std::fs::remove_dir_all(&static_dir);
+        let _ = std::fs::remove_file(&scene_rel);
+        assert_eq!(response.status(), StatusCode::OK);
+        let warnings = response.headers().get("x-export-warnings").unwrap().to_str().unwrap().to_string();
+        assert!(warnings.contains("asset-path-collision"), "{}", warnings);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        let mut read = |path: &str| {
+            let mut file = archive.by_name(path).unwrap_or_else(|_| panic!("{} in export", path));
+            let mut contents = String::new();
+            std::io::Read::read_to_string(&mut file, &mut contents).unwrap();
+            contents
+        };
+        // Both files survive, and the tour points at its moved copy
+        assert_eq!(read(&scene_rel), "app sprite");
+        let moved = format!("assets/tour/insta360/{}", name);
+        assert_eq!(read(&moved), "tour panorama");
+        let tour_data = read("js/tourData.js");
+        assert!(tour_data.contains(&format!("\"/{}\"", moved)));
+        assert!(!tour_data.contains(&format!("\"/{}\"", scene_rel)));
+        assert_eq!(archive.file_names().filter(|n| *n == scene_rel).count(), 1, "no duplicate entries");
+    }
+
+    #[tokio::test]
+    async fn test_branded_export_includes_branding_and_logo() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Branded", "").await.unwrap();
+        db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let logo_rel = format!("assets/branding/test_logo_{}.png", uuid::Uuid::new_v4());
+        std::fs::create_dir_all("assets/branding").unwrap();
+        std::fs::write(&logo_rel, b"logo bytes").unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let patch = |body: serde_json::Value| axum::http::Request::builder()
+            .method("PATCH")
+            .uri(format!("/api/tours/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+
+        let response = app.clone().oneshot(patch(serde_json::json!({ "primary_color": "blue" }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(patch(serde_json::json!({
+            "logo_path": format!("/{}", logo_rel),
+            "primary_color": "#112233",
+            "welcome_text": "Welcome to the showroom"
+        }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = axum::http::Request::builder()
+            .uri(format!("/api/export/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("x-export-warnings").is_none(), "bundled viewer files found");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let _ = std::fs::remove_file(&logo_rel);
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        let branding: serde_json::Value = {
+            let mut file = archive.by_name("branding.json").expect("branding.json in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            serde_json::from_str(&text).unwrap()
+        };
+        assert_eq!(branding["primary_color"], "#112233");
+        assert_eq!(branding["welcome_text"], "Welcome to the showroom");
+        assert_eq!(branding["logo_path"], logo_rel.as_str());
+        assert!(archive.by_name(&logo_rel).is_ok(), "logo copied into export");
+    }
+
+    #[tokio::test]
+    async fn test_export_all_has_browsable_index_html() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("indexer", "password").await.unwrap();
+        let token = db.login_user("indexer").await.unwrap();
+        let attic = db.create_tour("indexer", "Attic & Loft", "").await.unwrap();
+        let cellar = db.create_tour("indexer", "Cellar", "").await.unwrap();
+        let empty = db.create_tour("indexer", "Unstarted", "").await.unwrap();
+        // Only the attic's opening image exists on disk, so only it gets a picture
+        let picture = format!("assets/insta360/index_thumb_{}.jpg", attic);
+        std::fs::create_dir_all("assets/insta360").unwrap();
+        std::fs::write(&picture, b"jpeg").unwrap();
+        let attic_scene = db.save_scene(attic, "Stairs", &format!("/{}", picture), None, None, None).await.unwrap();
+        db.set_initial_scene(attic, attic_scene).await.unwrap();
+        db.save_scene(cellar, "Racks", "/assets/insta360/missing_racks.jpg", None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let request = axum::http::Request::builder()
+            .uri("/api/export-all")
+            .header("x-username", "indexer")
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let _ = std::fs::remove_file(&picture);
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        let html = {
+            let mut file = archive.by_name("index.html").expect("index.html in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            text
+        };
+        for tour_id in [attic, cellar] {
+            let link = format!("tour_{}/index.html", tour_id);
+            assert!(html.contains(&format!(r#"href="{}""#, link)), "no link to {} in {}", link, html);
+            assert!(archive.by_name(&link).is_ok());
+        }
+        assert!(html.contains("Attic &amp; Loft"));
+        let thumbnail = format!("tour_{}/{}", attic, picture);
+        assert!(html.contains(&format!(r#"src="{}""#, thumbnail)));
+        assert!(archive.by_name(&thumbnail).is_ok(), "thumbnail is the packaged scene image");
+        // The empty tour is listed, but there's nothing to link to
+        assert!(html.contains("Unstarted"));
+        assert!(!html.contains(&format!("tour_{}/", empty)));
+    }
+
+    #[tokio::test]
+    async fn test_export_all_has_folder_per_tour_and_index() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let first = db.create_tour("owner", "First", "").await.unwrap();
+        let second = db.create_tour("owner", "Second", "").await.unwrap();
+        db.save_scene(first, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        db.save_scene(second, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let request = axum::http::Request::builder()
+            .uri("/api/export-all")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        let request = axum::http::Request::builder()
+            .uri("/api/export-all")
+            .header("x-username", "owner")
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec())).unwrap();
+        for tour_id in [first, second] {
+            assert!(archive.by_name(&format!("tour_{}/js/tourData.js", tour_id)).is_ok(), "tourData.js for tour {}", tour_id);
+            assert!(archive.by_name(&format!("tour_{}/index.html", tour_id)).is_ok());
+        }
+        let index: serde_json::Value = {
+            let mut file = archive.by_name("index.json").expect("index.json in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            serde_json::from_str(&text).unwrap()
+        };
+        let tours = index["tours"].as_array().unwrap();
+        assert_eq!(tours.len(), 2);
+        let folders: Vec<&str> = tours.iter().filter_map(|t| t["folder"].as_str()).collect();
+        assert!(folders.contains(&format!("tour_{}", first).as_str()));
+        assert!(folders.contains(&format!("tour_{}", second).as_str()));
+
+        let manifest: serde_json::Value = {
+            let mut file = archive.by_name(&format!("tour_{}/manifest.json", first)).expect("manifest.json in export");
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+            serde_json::from_str(&text).unwrap()
+        };
+        assert_eq!(manifest["tour_id"].as_i64(), Some(first));
+        assert_eq!(manifest["viewer"], exporter::viewer_info());
+    }
+
+    #[tokio::test]
+    async fn test_viewer_info_reports_bundled_versions() {
+        let state = test_state().await;
+        let config = config::Config::default();
+        let app = build_router(state, &config);
+        let request = axum::http::Request::builder().uri("/api/viewer-info").body(axum::body::Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let info: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(info["engine_version"], exporter::ENGINE_VERSION);
+        assert_eq!(info["three_version"], "r128");
+        assert_eq!(info["tour_data_format_version"].as_u64(), Some(exporter::TOUR_DATA_FORMAT_VERSION as u64));
+    }
+
+    #[tokio::test]
+    async fn test_engine_hash_is_stable_for_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("vte-engine-hash-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("engine.min.js"), b"engine v1").unwrap();
+        let mut config = config::Config::default();
+        config.export.viewer_js_dir = dir.to_str().unwrap().to_string();
+        let app = build_router(AppState { config: Arc::new(config.clone()), ..test_state().await }, &config);
+        let hashes = || async {
+            let request = axum::http::Request::builder().uri("/api/viewer/engine-hash").body(axum::body::Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            serde_json::from_str::<serde_json::Value>(&body_string(response).await).unwrap()
+        };
+
+        let first = hashes().await;
+        // SHA-256 of "engine v1"; three.min.js hasn't been added yet
+        assert_eq!(first["engine"], "309b25d32a00959b3545ba15c27984d7e046a04a44310ea0aef3912b910c4d21");
+        assert!(first["three"].is_null());
+        assert_eq!(hashes().await, first);
+
+        std::fs::write(dir.join("engine.min.js"), b"engine v2").unwrap();
+        std::fs::write(dir.join("three.min.js"), b"three").unwrap();
+        let second = hashes().await;
+        assert_ne!(second["engine"], first["engine"]);
+        assert_eq!(second["three"].as_str().map(str::len), Some(64));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_video_scene_exports_media_type() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let tour_id = db.create_tour("owner", "Mixed", "").await.unwrap();
+        let app = build_router(state, &config::Config::default());
+
+        let upload = |filename: &str, bytes: &[u8]| {
+            let boundary = "vte-test-boundary";
+            let mut body = format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"type\"\r\n\r\nvideo\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                b = boundary, f = filename
+            ).into_bytes();
+            body.extend_from_slice(bytes);
+            body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload-asset")
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+
+        // A JPEG renamed to .mp4 is refused
+        let response = app.clone().oneshot(upload("fake.mp4", b"\xFF\xD8\xFF\xE0 not a video")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut mp4 = vec![0, 0, 0, 0x18];
+        mp4.extend_from_slice(b"ftypisom");
+        mp4.extend_from_slice(&uuid::Uuid::new_v4().into_bytes());
+        let response = app.oneshot(upload("walkthrough.mp4", &mp4)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let video_path = uploaded["file_path"].as_str().unwrap().to_string();
+        assert!(video_path.starts_with("/assets/video/"));
+        let _ = std::fs::remove_file(video_path.trim_start_matches('/'));
+
+        let video = db.save_scene(tour_id, "Walkthrough", &video_path, None, None, None).await.unwrap();
+        let still = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        db.save_connection(tour_id, still, Some(video), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let data = exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        let scenes = data["scenes"].as_array().unwrap();
+        let media_of = |id: i64| scenes.iter().find(|s| s["id"] == id).unwrap()["media_type"].clone();
+        assert_eq!(media_of(video), "video");
+        assert_eq!(media_of(still), "image");
+        let lobby = scenes.iter().find(|s| s["id"] == still).unwrap();
+        assert!(lobby["connections"][0]["target_thumbnail"].is_null(), "no still thumbnail for a video target");
+    }
+
+    #[tokio::test]
+    async fn test_asset_audit_flags_file_deleted_out_of_band() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Audited", "").await.unwrap();
+
+        std::fs::create_dir_all("assets/insta360").unwrap();
+        let kept = format!("assets/insta360/audit_kept_{}.jpg", uuid::Uuid::new_v4());
+        let removed = format!("assets/insta360/audit_removed_{}.jpg", uuid::Uuid::new_v4());
+        std::fs::write(&kept, b"kept").unwrap();
+        std::fs::write(&removed, b"removed").unwrap();
+        db.save_scene(tour_id, "Kept", &format!("/{}", kept), None, None, None).await.unwrap();
+        let gone = db.save_scene(tour_id, "Removed", &format!("/{}", removed), None, None, None).await.unwrap();
+
+        std::fs::remove_file(&removed).unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let request = axum::http::Request::builder()
+            .uri("/api/assets/audit")
+            .header("x-username", "owner")
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["missing_count"], 1);
+        assert_eq!(body["missing"][0]["id"], gone);
+        assert_eq!(body["missing"][0]["file_path"], format!("/{}", removed));
+
+        // Reporting only: the row is still there
+        assert_eq!(db.audit_assets(None).await.unwrap().len(), 1);
+        let _ = std::fs::remove_file(&kept);
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_sprite_covers_every_tour() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        let username = format!("sprite_{}", uuid::Uuid::new_v4().simple());
+        db.register_user(&username, "password").await.unwrap();
+        let token = db.login_user(&username).await.unwrap();
+
+        let dir = std::path::PathBuf::from("assets/insta360");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut thumbs = Vec::new();
+        let mut tour_ids = Vec::new();
+        for (i, color) in [[255u8, 0, 0], [0, 0, 255]].into_iter().enumerate() {
+            let path = dir.join(format!("sprite_test_{}_{}.jpg", i, uuid::Uuid::new_v4()));
+            image::RgbImage::from_pixel(64, 32, image::Rgb(color)).save(&path).unwrap();
+            let tour_id = db.create_tour(&username, &format!("Tour {}", i), "").await.unwrap();
+            db.save_scene(tour_id, "Lobby", &format!("/{}", path.to_string_lossy()), None, None, None).await.unwrap();
+            thumbs.push(path);
+            tour_ids.push(tour_id);
+        }
+        // A tour with no scenes still gets a (placeholder) frame
+        tour_ids.push(db.create_tour(&username, "Empty", "").await.unwrap());
+
+        let app = build_router(state, &config::Config::default());
+        let request = || axum::http::Request::builder()
+            .uri("/api/tours/thumbnails-sprite")
+            .header("x-username", username.as_str())
+            .header("x-session-token", token.as_str())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+
+        let frames = body["frames"].as_object().unwrap();
+        assert_eq!(frames.len(), 3);
+        let sheet_path = body["sprite_url"].as_str().unwrap().trim_start_matches('/').to_string();
+        let sheet = image::open(&sheet_path).unwrap().to_rgb8();
+        assert_eq!((sheet.width() as u64, sheet.height() as u64), (body["width"].as_u64().unwrap(), body["height"].as_u64().unwrap()));
+        for tour_id in &tour_ids {
+            let frame = &frames[&tour_id.to_string()];
+            let (x, y, w, h) = (frame["x"].as_u64().unwrap(), frame["y"].as_u64().unwrap(), frame["w"].as_u64().unwrap(), frame["h"].as_u64().unwrap());
+            assert!(x + w <= sheet.width() as u64 && y + h <= sheet.height() as u64, "frame of tour {} outside the sheet", tour_id);
+        }
+        let red = &frames[&tour_ids[0].to_string()];
+        let pixel = sheet.get_pixel(red["x"].as_u64().unwrap() as u32 + 10, red["y"].as_u64().unwrap() as u32 + 10);
+        assert!(pixel[0] > 200 && pixel[2] < 60, "first tour's thumbnail drawn in its frame");
+
+        // Cached until a tour changes
+        let again: serde_json::Value = serde_json::from_str(&body_string(app.clone().oneshot(request()).await.unwrap()).await).unwrap();
+        assert_eq!(again["sprite_url"], body["sprite_url"]);
+        db.create_tour(&username, "Another", "").await.unwrap();
+        let changed: serde_json::Value = serde_json::from_str(&body_string(app.oneshot(request()).await.unwrap()).await).unwrap();
+        assert_ne!(changed["sprite_url"], body["sprite_url"]);
+        assert!(!std::path::Path::new(&sheet_path).exists(), "outdated sheet removed");
+
+        let _ = std::fs::remove_file(changed["sprite_url"].as_str().unwrap().trim_start_matches('/'));
+        for path in thumbs {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_distinguishes_taken_username_from_db_error() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        let app = build_router(state, &config::Config::default());
+        let register = || axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/register")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(r#"{"username": "alice", "password": "secret"}"#))
+            .unwrap();
+
+        assert_eq!(app.clone().oneshot(register()).await.unwrap().status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(register()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["code"], "username_taken");
+
+        // Any other database failure is a server error, not a conflict
+        sqlx::query("DROP TABLE users").execute(&*db.pool).await.unwrap();
+        let response = app.oneshot(register()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["code"], "server_error");
+    }
+
+    #[tokio::test]
+    async fn test_share_link_uses_tour_base_url() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Showroom", "").await.unwrap();
+
+        let mut config = config::Config::default();
+        config.sharing.base_url = Some("https://tours.example.com".to_string());
+        let app = build_router(AppState { database: db.clone(), config: Arc::new(config.clone()), connections: Default::default() }, &config);
+        let request = |method: &str, uri: String, body: serde_json::Value| axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-username", "owner")
+            .header("x-session-token", token.clone())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let share = || async {
+            let response = app.clone().oneshot(request("POST", format!("/api/tours/{}/share", tour_id), serde_json::json!({}))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            serde_json::from_str::<serde_json::Value>(&body_string(response).await).unwrap()
+        };
+
+        let shared = share().await;
+        assert_eq!(shared["url"], format!("https://tours.example.com/api/shared/{}", shared["token"].as_str().unwrap()));
+
+        for bad in ["ftp://tours.agency.test", "tours.agency.test", "https://", "https://tours.agency.test/?q=1"] {
+            let response = app.clone().oneshot(request("PATCH", format!("/api/tours/{}", tour_id), serde_json::json!({ "share_base_url": bad }))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{} should be rejected", bad);
+        }
+
+        let response = app.clone().oneshot(request("PATCH", format!("/api/tours/{}", tour_id), serde_json::json!({
+            "share_base_url": "https://view.agency.test/tours/"
+        }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["share_base_url"], "https://view.agency.test/tours");
+
+        let shared = share().await;
+        assert_eq!(shared["url"], format!("https://view.agency.test/tours/api/shared/{}", shared["token"].as_str().unwrap()));
+
+        // Clearing the tour's base falls back to the server-wide one
+        let response = app.clone().oneshot(request("PATCH", format!("/api/tours/{}", tour_id), serde_json::json!({ "share_base_url": "" }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(share().await["url"].as_str().unwrap().starts_with("https://tours.example.com/api/shared/"));
+    }
+
+    #[tokio::test]
+    async fn test_members_only_tour_blocks_anonymous_share_access() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("member", "password").await.unwrap();
+        let owner_token = db.login_user("owner").await.unwrap();
+        let member_token = db.login_user("member").await.unwrap();
+        let tour_id = db.create_tour("owner", "Premium", "").await.unwrap();
+        let share_token = db.create_share_token(tour_id, 0).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let view = |credentials: Option<(&str, &str)>| {
+            let mut request = axum::http::Request::builder().uri(format!("/api/shared/{}", share_token));
+            if let Some((username, token)) = credentials {
+                request = request.header("x-username", username).header("x-session-token", token);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+        let set_requires_auth = |requires_auth: bool| app.clone().oneshot(axum::http::Request::builder()
+            .method("PATCH")
+            .uri(format!("/api/tours/{}", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", owner_token.clone())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(serde_json::json!({ "requires_auth": requires_auth }).to_string()))
+            .unwrap());
+
+        assert_eq!(view(None).await.unwrap().status(), StatusCode::OK);
+
+        assert_eq!(set_requires_auth(true).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(view(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(view(Some(("member", "not-a-session"))).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(view(Some(("member", &member_token))).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(view(Some(("owner", &owner_token))).await.unwrap().status(), StatusCode::OK);
+        assert!(db.list_published_tours().await.unwrap().is_empty(), "members-only tours stay out of the sitemap");
+
+        assert_eq!(set_requires_auth(false).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(view(None).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_flag_files_used_as_scenes() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let tour_id = db.create_tour("owner", "Flat", "").await.unwrap();
+
+        let dir = format!("target/test_uploads_{}", uuid::Uuid::new_v4());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(format!("{}/lobby.jpg", dir), b"jpeg").unwrap();
+        std::fs::write(format!("{}/kitchen.jpg", dir), b"jpeg").unwrap();
+        std::fs::write(format!("{}/notes.txt", dir), b"not an image").unwrap();
+        db.save_scene(tour_id, "Lobby", &format!("/{}/lobby.jpg", dir), None, None, None).await.unwrap();
+
+        let assets = uploads_with_usage(&db, tour_id, &dir).await.unwrap();
+        // Usage is per tour
+        let other = db.create_tour("owner", "Other", "").await.unwrap();
+        let other_assets = uploads_with_usage(&db, other, &dir).await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let flags = |assets: &[serde_json::Value]| -> Vec<(String, bool)> {
+            assets.iter().map(|a| (a["file_name"].as_str().unwrap().to_string(), a["used"].as_bool().unwrap())).collect()
+        };
+        assert_eq!(flags(&assets), vec![("kitchen.jpg".to_string(), false), ("lobby.jpg".to_string(), true)]);
+        assert!(flags(&other_assets).iter().all(|(_, used)| !used));
+    }
+
+    #[tokio::test]
+    async fn test_closeup_upload_gets_thumbnail() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let tour_id = db.create_tour("owner", "Detail", "").await.unwrap();
+        let app = build_router(state, &config::Config::default());
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        let pixel = image::Rgb([uuid::Uuid::new_v4().as_bytes()[0], 90, 40]);
+        image::RgbImage::from_pixel(1200, 800, pixel).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let boundary = "vte-test-boundary";
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"type\"\r\n\r\ncloseups\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"plaque.png\"\r\nContent-Type: image/png\r\n\r\n",
+            b = boundary
+        ).into_bytes();
+        body.extend_from_slice(png.get_ref());
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload-asset")
+            .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let file_path = uploaded["file_path"].as_str().unwrap().to_string();
+        let thumbnail_path = uploaded["thumbnail_path"].as_str().expect("thumbnail for closeup upload").to_string();
+        let thumb = image::open(thumbnail_path.trim_start_matches('/')).expect("thumbnail written");
+        assert_eq!((thumb.width(), thumb.height()), (320, 213));
+
+        let scene = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let closeup = db.save_closeup(tour_id, "Plaque", &file_path, None).await.unwrap();
+        db.save_connection(tour_id, scene, Some(closeup), 20.0, 0.0, editor::ConnectionType::Closeup, Some("Plaque"), Some(&file_path), None).await.unwrap();
+        let tour = db.get_tour_with_scenes("owner", tour_id).await.unwrap().unwrap();
+        let _ = std::fs::remove_file(file_path.trim_start_matches('/'));
+        let _ = std::fs::remove_file(thumbnail_path.trim_start_matches('/'));
+        assert_eq!(tour["scenes"][0]["connections"][0]["thumbnail_path"], thumbnail_path.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_tour_slugs_are_unique_and_resolve_published_tours() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("other", "password").await.unwrap();
+        let first = db.create_tour("owner", "My Tour", "").await.unwrap();
+        let second = db.create_tour("other", "My Tour", "").await.unwrap();
+        let copy = db.duplicate_tour("owner", first, Some("My  Tour!")).await.unwrap().unwrap();
+
+        let slug_of = |tour_id: i64| {
+            let db = db.clone();
+            async move { db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap()["slug"].clone() }
+        };
+        assert_eq!(slug_of(first).await, "my-tour");
+        assert_eq!(slug_of(second).await, "my-tour-2");
+        assert_eq!(slug_of(copy).await, "my-tour-3");
+
+        let app = build_router(state, &config::Config::default());
+        let view = |slug: &str| app.clone().oneshot(
+            axum::http::Request::builder().uri(format!("/view/slug/{}", slug)).body(axum::body::Body::empty()).unwrap());
+
+        // Only tours with a live share link resolve
+        assert_eq!(view("my-tour-2").await.unwrap().status(), StatusCode::NOT_FOUND);
+        db.create_share_token(second, 0).await.unwrap();
+        let response = view("my-tour-2").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["id"], second);
+        assert_eq!(view("no-such-tour").await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_lists_shared_tours_with_lastmod() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let first = db.create_tour("owner", "Loft", "").await.unwrap();
+        let second = db.create_tour("owner", "Villa", "").await.unwrap();
+        let private = db.create_tour("owner", "Draft", "").await.unwrap();
+        let first_token = db.create_share_token(first, 0).await.unwrap();
+        db.set_share_base_url(second, Some("https://villa.example.com")).await.unwrap();
+        let second_token = db.create_share_token(second, 3600).await.unwrap();
+        let revoked = db.create_share_token(private, 0).await.unwrap();
+        sqlx::query("UPDATE share_tokens SET is_active = 0 WHERE token = ?1").bind(&revoked).execute(&*db.pool).await.unwrap();
+        for (tour_id, modified_at) in [(first, "2026-03-01 09:30:00"), (second, "2026-04-15 18:05:42")] {
+            sqlx::query("UPDATE tours SET modified_at = ?1 WHERE id = ?2").bind(modified_at).bind(tour_id).execute(&*db.pool).await.unwrap();
+        }
+
+        let mut config = config::Config::default();
+        config.sharing.base_url = Some("https://tours.example.com".to_string());
+        let app = build_router(AppState { database: db.clone(), config: Arc::new(config.clone()), connections: Default::default() }, &config);
+        let request = axum::http::Request::builder().uri("/sitemap.xml").body(axum::body::Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "application/xml");
+        let sitemap = body_string(response).await;
+
+        assert!(sitemap.contains(&format!(
+            "<loc>https://tours.example.com/api/shared/{}</loc>\n    <lastmod>2026-03-01T09:30:00+00:00</lastmod>", first_token
+        )), "{}", sitemap);
+        assert!(sitemap.contains(&format!(
+            "<loc>https://villa.example.com/api/shared/{}</loc>\n    <lastmod>2026-04-15T18:05:42+00:00</lastmod>", second_token
+        )), "{}", sitemap);
+        assert_eq!(sitemap.matches("<url>").count(), 2, "revoked links are not listed");
+
+        // Without a configured base URL the links would come from the client's Host header
+        let app = build_router(state.clone(), &state.config);
+        let request = axum::http::Request::builder()
+            .uri("/sitemap.xml")
+            .header(axum::http::header::HOST, "attacker.example")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cubemap_faces_need_the_owner() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("other", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let other_token = db.login_user("other").await.unwrap();
+        let tour_id = db.create_tour("owner", "Tour", "").await.unwrap();
+
+        let file_rel = format!("assets/insta360/test_cubemap_{}.png", uuid::Uuid::new_v4());
+        std::fs::create_dir_all("assets/insta360").unwrap();
+        image::RgbImage::from_pixel(64, 32, image::Rgb([20, 120, 200])).save(&file_rel).unwrap();
+        let scene = db.save_scene(tour_id, "Lobby", &format!("/{}", file_rel), None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let face = |auth: Option<(&str, &str)>| {
+            let mut builder = axum::http::Request::builder().uri(format!("/api/assets/{}/cubemap/px", scene));
+            if let Some((user, token)) = auth {
+                builder = builder.header("x-username", user).header("x-session-token", token);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        assert_eq!(app.clone().oneshot(face(None)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(app.clone().oneshot(face(Some(("other", &other_token)))).await.unwrap().status(), StatusCode::NOT_FOUND);
+        let response = app.clone().oneshot(face(Some(("owner", &token)))).await.unwrap();
+        let _ = std::fs::remove_file(&file_rel);
+        let _ = std::fs::remove_file(format!("assets/cubemaps/{}_px.jpg", scene));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "image/jpeg");
+    }
+
+    #[tokio::test]
+    async fn test_asset_download_serves_byte_ranges() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("other", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let other_token = db.login_user("other").await.unwrap();
+        let tour_id = db.create_tour("owner", "Tour", "").await.unwrap();
+
+        let file_rel = format!("assets/closeups/test_download_{}.png", uuid::Uuid::new_v4());
+        std::fs::create_dir_all("assets/closeups").unwrap();
+        std::fs::write(&file_rel, b"0123456789abcdef").unwrap();
+        let asset_id = db.save_closeup(tour_id, "Plaque", &format!("/{}", file_rel), None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let download = |user: &str, token: &str, range: Option<&str>| {
+            let mut builder = axum::http::Request::builder()
+                .uri(format!("/api/assets/{}/download", asset_id))
+                .header("x-username", user)
+                .header("x-session-token", token);
+            if let Some(range) = range {
+                builder = builder.header("range", range);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(download("owner", &token, Some("bytes=2-5"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[axum::http::header::CONTENT_RANGE], "bytes 2-5/16");
+        assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "image/png");
+        assert_eq!(body_string(response).await, "2345");
+
+        let response = app.clone().oneshot(download("owner", &token, Some("bytes=-3"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body_string(response).await, "def");
+
+        let response = app.clone().oneshot(download("owner", &token, Some("bytes=40-"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let response = app.clone().oneshot(download("owner", &token, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "0123456789abcdef");
+
+        let response = app.oneshot(download("other", &other_token, None)).await.unwrap();
+        let _ = std::fs::remove_file(&file_rel);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_asset_download_stays_inside_assets() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Tour", "").await.unwrap();
+        let app = build_router(state, &config::Config::default());
+
+        for path in ["/Cargo.toml", "/assets/../Cargo.toml", "//etc/passwd"] {
+            let asset_id = db.save_scene(tour_id, "Lobby", path, None, None, None).await.unwrap();
+            let request = axum::http::Request::builder()
+                .uri(format!("/api/assets/{}/download", asset_id))
+                .header("x-username", "owner")
+                .header("x-session-token", &token)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::NOT_FOUND, "{path}");
+
+            let request = axum::http::Request::builder()
+                .uri(format!("/api/assets/{}/cubemap/px", asset_id))
+                .header("x-username", "owner")
+                .header("x-session-token", &token)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::NOT_FOUND, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scene_upload_reads_exif_capture_date() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let tour_id = db.create_tour("owner", "Renovation", "").await.unwrap();
+        let app = build_router(state, &config::Config::default());
+
+        // IFD0 -> Exif IFD (offset 26) -> DateTimeOriginal, 20 ASCII bytes at offset 44
+        let mut tiff: Vec<u8> = b"II\x2a\x00".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(0x8769u16.to_le_bytes());
+        tiff.extend(4u16.to_le_bytes());
+        tiff.extend(1u32.to_le_bytes());
+        tiff.extend(26u32.to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(0x9003u16.to_le_bytes());
+        tiff.extend(2u16.to_le_bytes());
+        tiff.extend(20u32.to_le_bytes());
+        tiff.extend(44u32.to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"2025:11:03 14:22:05\0");
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((app1.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(app1);
+        jpeg.extend(uuid::Uuid::new_v4().into_bytes());
+        jpeg.extend([0xFF, 0xD9]);
+
+        let boundary = "vte-test-boundary";
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"type\"\r\n\r\ninsta360\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"floor2.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n",
+            b = boundary
+        ).into_bytes();
+        body.extend_from_slice(&jpeg);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload-asset")
+            .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let file_path = uploaded["file_path"].as_str().unwrap().to_string();
+        let _ = std::fs::remove_file(file_path.trim_start_matches('/'));
+        assert_eq!(uploaded["captured_at"], "2025-11-03 14:22:05");
+
+        // The client passes the date on when it adds the scene
+        let mut editor = editor::EditorState::new(tour_id, "owner".to_string(), Some((*db).clone()));
+        editor.load_from_database(&db).await.unwrap();
+        let (tx, _rx) = outbound::channel(64);
+        editor.handle_action(editor::EditorAction::AddScene {
+            name: "Floor 2".to_string(),
+            file_path,
+            north_direction: None,
+            captured_at: uploaded["captured_at"].as_str().map(str::to_string),
+        }, &tx).await.unwrap();
+        assert_eq!(editor.scenes[0].captured_at.as_deref(), Some("2025-11-03 14:22:05"));
+
+        let data = exporter::build_tour_data(&db, tour_id).await.unwrap().unwrap();
+        assert_eq!(data["scenes"][0]["captured_at"], "2025-11-03 14:22:05");
+    }
+
+    #[tokio::test]
+    async fn test_tour_changes_reach_every_connection_of_the_owner() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("other", "password").await.unwrap();
+
+        let connect = |username: &str| {
+            let (tx, rx) = outbound::channel(64);
+            let task = tokio::spawn(forward_tour_changes(db.subscribe_tour_changes(), username.to_string(), tx));
+            (task, rx)
+        };
+        let (first_task, mut first) = connect("owner");
+        let (second_task, mut second) = connect("owner");
+        let (other_task, mut other) = connect("other");
+
+        let tour_id = db.create_tour("owner", "Shared", "").await.unwrap();
+
+        for rx in [&mut first, &mut second] {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv()).await
+                .expect("notification in time").expect("open channel");
+            let Message::Text(text) = message else { panic!("expected a text message") };
+            let update: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(update["type"], "tour_updated");
+            assert_eq!(update["tour_id"], tour_id);
+            assert!(update["modified_at"].is_string());
+        }
+        tokio::task::yield_now().await;
+        assert!(other.try_recv().is_err(), "other users don't hear about this tour");
+
+        first_task.abort();
+        second_task.abort();
+        other_task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_tour_over_websocket() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let tour_id = db.create_tour("owner", "Showroom", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, lobby).await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config::Config::default()), None));
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/connect", addr)).await.expect("websocket connects");
+
+        // Reads server messages until one matches
+        async fn next_matching(
+            socket: &mut tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
+            matches: impl Fn(&serde_json::Value) -> bool,
+        ) -> serde_json::Value {
+            loop {
+                let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await
+                    .expect("reply in time").expect("socket open").unwrap();
+                if let WsMessage::Text(text) = message {
+                    let value: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+                    if matches(&value) {
+                        return value;
+                    }
+                }
+            }
+        }
+
+        let login = serde_json::json!({ "action": "Login", "data": { "username": "owner", "password": "password" } });
+        socket.send(WsMessage::Text(login.to_string().into())).await.unwrap();
+        next_matching(&mut socket, |v| v["redirect"] == "homepage").await;
+        next_matching(&mut socket, |v| v["tours"].is_array()).await;
+
+        // Someone else's tour can't be duplicated, and the refused attempt isn't held against the caller
+        db.register_user("other", "password").await.unwrap();
+        let foreign = db.create_tour("other", "Private", "").await.unwrap();
+        let duplicate_foreign = serde_json::json!({ "action": "DuplicateTour", "data": { "tour_id": foreign } });
+        socket.send(WsMessage::Text(duplicate_foreign.to_string().into())).await.unwrap();
+        let reply = next_matching(&mut socket, |v| v["message"].is_string()).await;
+        assert_eq!(reply["message"], "Tour not found or access denied.");
+
+        let duplicate = serde_json::json!({ "action": "DuplicateTour", "data": { "tour_id": tour_id } });
+        socket.send(WsMessage::Text(duplicate.to_string().into())).await.unwrap();
+        let reply = next_matching(&mut socket, |v| v["source_tour_id"].is_number()).await;
+        let copy_id = reply["tour_id"].as_i64().expect("copy id");
+        assert_ne!(copy_id, tour_id);
+
+        let tours = next_matching(&mut socket, |v| v["tours"].is_array()).await;
+        let names: Vec<&str> = tours["tours"].as_array().unwrap().iter().filter_map(|t| t["name"].as_str()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"Showroom (copy)"));
+
+        let copy = db.get_tour_with_scenes_by_id(copy_id).await.unwrap().unwrap();
+        let copied_lobby = copy["scenes"][0]["id"].as_i64().unwrap();
+        assert_ne!(copied_lobby, lobby);
+        assert_eq!(copy["initial_scene_id"].as_i64(), Some(copied_lobby));
+
+        // Any duplicate right after a creation is throttled, whoever owns the source
+        socket.send(WsMessage::Text(duplicate_foreign.to_string().into())).await.unwrap();
+        let reply = next_matching(&mut socket, |v| v["message"].is_string()).await;
+        assert!(reply["message"].as_str().unwrap().contains("too quickly"), "unexpected reply {}", reply);
+    }
+
+    #[tokio::test]
+    async fn test_upload_rejects_excess_form_fields() {
+        let upload = |body: Vec<u8>| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload-asset")
+                .header("content-type", "multipart/form-data; boundary=vte-test-boundary")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let junk_then_file = |junk: usize, payload: &str| {
+            let mut body = String::new();
+            for i in 0..junk {
+                body.push_str(&format!("--vte-test-boundary\r\nContent-Disposition: form-data; name=\"junk{}\"\r\n\r\nx\r\n", i));
+            }
+            body.push_str(&format!(
+                "--vte-test-boundary\r\nContent-Disposition: form-data; name=\"file\"; filename=\"junk.jpg\"\r\n\r\n{}\r\n--vte-test-boundary--\r\n",
+                payload
+            ));
+            body.into_bytes()
+        };
+
+        // Fifty filler fields are cut off at the field limit, before the file is read
+        let app = build_router(test_state().await, &config::Config::default());
+        let response = app.oneshot(upload(junk_then_file(50, "data"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body_string(response).await.contains("Too many form fields"));
+
+        // A form within the field limit but over the byte limit is rejected as well
+        let mut config = config::Config::default();
+        config.uploads.max_form_bytes = 64;
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        let app = build_router(state, &config);
+        let response = app.oneshot(upload(junk_then_file(2, &"x".repeat(100)))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body_string(response).await.contains("exceeds 64 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_tour_modified_honours_if_modified_since() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Loft", "").await.unwrap();
+        sqlx::query("UPDATE tours SET modified_at = '2026-03-01 09:30:00' WHERE id = ?1")
+            .bind(tour_id)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let check = |since: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .uri(format!("/api/tours/{}/modified", tour_id))
+                .header("x-username", "owner")
+                .header("x-session-token", token.clone());
+            if let Some(since) = since {
+                request = request.header("if-modified-since", since);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+
+        let response = check(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["last-modified"], "Sun, 01 Mar 2026 09:30:00 GMT");
+        let modified: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(modified["modified_at"], "2026-03-01 09:30:00");
+        let version = modified["version"].as_i64().unwrap();
+
+        // Unchanged since the client's copy
+        let response = check(Some("Sun, 01 Mar 2026 09:30:00 GMT")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(check(Some("Mon, 02 Mar 2026 00:00:00 GMT")).await.unwrap().status(), StatusCode::NOT_MODIFIED);
+
+        // A change moves both the timestamp and the version on
+        db.set_tour_requires_auth(tour_id, "owner", true).await.unwrap();
+        let response = check(Some("Sun, 01 Mar 2026 09:30:00 GMT")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let modified: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(modified["version"].as_i64(), Some(version + 1));
+        assert_ne!(modified["modified_at"], "2026-03-01 09:30:00");
+    }
+
+    #[tokio::test]
+    async fn test_autosave_flushes_dirty_sessions_but_skips_busy_ones() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("autosaver", "password").await.unwrap();
+        let tour_id = db.create_tour("autosaver", "Loft", "").await.unwrap();
+        let a = db.save_scene(tour_id, "A", "/assets/insta360/a.jpg", None, None, None).await.unwrap();
+        let b = db.save_scene(tour_id, "B", "/assets/insta360/b.jpg", None, None, None).await.unwrap();
+        let conn_id = db.save_connection(tour_id, a, Some(b), 0.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+
+        // A deferred session with one queued hotspot move
+        let session = get_or_create_editor_session("autosaver", tour_id, &db, &config::EditorConfig::default(), false).await.unwrap();
+        let mut editor_state = session.lock().await;
+        let (tx, _rx) = outbound::channel(16);
+        editor_state.handle_action(editor::EditorAction::SetDeferredMode { enabled: true }, &tx).await.unwrap();
+        let drag = editor::parse_action(serde_json::json!({
+            "action": "EditConnection",
+            "data": { "connection_id": conn_id, "new_asset_id": b, "new_position": [42.0, 0.0] }
+        })).unwrap();
+        editor_state.handle_action(drag, &tx).await.unwrap();
+        let stored_lon = || async {
+            sqlx::query_scalar::<_, f32>("SELECT world_lon FROM connections WHERE id = ?1")
+                .bind(conn_id)
+                .fetch_one(&*db.pool)
+                .await
+                .unwrap()
+        };
+
+        // While an action holds the session, autosave leaves it alone
+        assert!(flush_all_editor_sessions().await.busy >= 1);
+        assert_eq!(stored_lon().await, 0.0);
+        drop(editor_state);
+
+        let report = flush_all_editor_sessions().await;
+        assert!(report.sessions >= 1 && report.writes >= 1, "unexpected report {:?}", report);
+        assert_eq!(stored_lon().await, 42.0);
+        assert!(session.lock().await.pending_writes.is_empty());
+        remove_editor_session("autosaver", tour_id).await;
+    }
+
+    type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    // Reads server messages until one matches
+    async fn next_matching(socket: &mut Socket, matches: impl Fn(&serde_json::Value) -> bool) -> serde_json::Value {
+        loop {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await
+                .expect("reply in time").expect("socket open").unwrap();
+            if let tokio_tungstenite::tungstenite::Message::Text(text) = message {
+                let value: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+                if matches(&value) {
+                    return value;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_editor_session_outlives_one_of_two_tabs() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("two_tabs", "password").await.unwrap();
+        let tour_id = db.create_tour("two_tabs", "Loft", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config::Config::default()), None));
+
+        let open_tab = || async {
+            let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/connect", addr)).await.expect("websocket connects");
+            let login = serde_json::json!({ "action": "Login", "data": { "username": "two_tabs", "password": "password" } });
+            socket.send(WsMessage::Text(login.to_string().into())).await.unwrap();
+            next_matching(&mut socket, |v| v["tours"].is_array()).await;
+            let open = serde_json::json!({ "action": "EditTour", "data": { "tour_id": tour_id } });
+            socket.send(WsMessage::Text(open.to_string().into())).await.unwrap();
+            next_matching(&mut socket, |v| v["type"] == "editor_ready").await;
+            socket
+        };
+        let key = format!("two_tabs_{}", tour_id);
+        // Waits for the server to finish handling a closed tab
+        let holders_become = |expected: Option<usize>| {
+            let key = key.clone();
+            async move {
+                for _ in 0..100 {
+                    let holders = EDITOR_SESSIONS.read().await.as_ref()
+                        .and_then(|sessions| sessions.get(&key))
+                        .map(|session| session.connections);
+                    if holders == expected {
+                        return;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                panic!("session holders never became {:?}", expected);
+            }
+        };
+
+        let mut first = open_tab().await;
+        let mut second = open_tab().await;
+        holders_become(Some(2)).await;
+
+        let present = || async {
+            PRESENCE.lock().await.as_ref().map(|registry| registry.list(tour_id, "")).unwrap_or_default()
+        };
+        first.close(None).await.unwrap();
+        holders_become(Some(1)).await;
+        assert_eq!(present().await.len(), 1, "still present through the other tab");
+
+        // The remaining tab keeps editing the same session
+        let annotate = serde_json::json!({
+            "action": "EditTour",
+            "data": { "tour_id": tour_id, "editor_action": { "action": "SetSceneNotes", "data": { "scene_id": lobby, "notes": "Retake" } } }
+        });
+        second.send(WsMessage::Text(annotate.to_string().into())).await.unwrap();
+        next_matching(&mut second, |v| v["type"] == "scene_notes_changed").await;
+        let session = EDITOR_SESSIONS.read().await.as_ref().unwrap()[&key].state.clone();
+        let notes = session.lock().await.scenes.iter().find(|s| s.id as i64 == lobby).unwrap().notes.clone();
+        assert_eq!(notes.as_deref(), Some("Retake"));
+
+        second.close(None).await.unwrap();
+        holders_become(None).await;
+        assert!(present().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_discard_changes_drops_deferred_edits() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("undecided", "password").await.unwrap();
+        let tour_id = db.create_tour("undecided", "Barn", "").await.unwrap();
+        let loft = db.save_scene(tour_id, "Loft", "/assets/insta360/loft.jpg", None, None, None).await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config::Config::default()), None));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/connect", addr)).await.expect("websocket connects");
+        let edit = |action: serde_json::Value| serde_json::json!({ "action": "EditTour", "data": { "tour_id": tour_id, "editor_action": action } });
+        for message in [
+            serde_json::json!({ "action": "Login", "data": { "username": "undecided", "password": "password" } }),
+            serde_json::json!({ "action": "EditTour", "data": { "tour_id": tour_id } }),
+            edit(serde_json::json!({ "action": "SetDeferredMode", "data": { "enabled": true } })),
+            edit(serde_json::json!({ "action": "UpdateSceneName", "data": { "scene_id": loft, "name": "Hayloft" } })),
+            // Turning deferred mode on again is a no-op whose reply marks the rename as handled
+            edit(serde_json::json!({ "action": "SetDeferredMode", "data": { "enabled": true } })),
+        ] {
+            socket.send(WsMessage::Text(message.to_string().into())).await.unwrap();
+        }
+        next_matching(&mut socket, |v| v["type"] == "deferred_mode").await;
+        next_matching(&mut socket, |v| v["type"] == "deferred_mode").await;
+
+        let session = EDITOR_SESSIONS.read().await.as_ref().unwrap()[&format!("undecided_{}", tour_id)].state.clone();
+        assert_eq!(session.lock().await.pending_writes.len(), 1);
+        let stored_name = || async {
+            db.get_tour_with_scenes_by_id(tour_id).await.unwrap().unwrap()["scenes"][0]["name"].clone()
+        };
+        assert_eq!(stored_name().await, "Loft");
+
+        let discard = serde_json::json!({ "action": "DiscardChanges", "data": { "tour_id": tour_id } });
+        socket.send(WsMessage::Text(discard.to_string().into())).await.unwrap();
+        next_matching(&mut socket, |v| v["type"] == "editor_ready").await;
+
+        // The session was reloaded from the database and nothing reached it
+        let editor_state = session.lock().await;
+        assert!(editor_state.pending_writes.is_empty());
+        assert!(editor_state.deferred, "still in deferred mode");
+        assert_eq!(editor_state.scenes.iter().find(|s| s.id as i64 == loft).unwrap().name, "Loft");
+        drop(editor_state);
+        assert_eq!(stored_name().await, "Loft");
+
+        // Closing the tab no longer has anything to flush
+        socket.close(None).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(stored_name().await, "Loft");
+    }
+
+    #[tokio::test]
+    async fn test_show_tours_order_carries_over_to_later_lists() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("sorter", "password").await.unwrap();
+        for name in ["Beta", "Alpha"] {
+            db.create_tour("sorter", name, "").await.unwrap();
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config::Config::default()), None));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/connect", addr)).await.expect("websocket connects");
+        let names = |list: serde_json::Value| -> Vec<String> {
+            list["tours"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap().to_string()).collect()
+        };
+        let login = serde_json::json!({ "action": "Login", "data": { "username": "sorter", "password": "password" } });
+        socket.send(WsMessage::Text(login.to_string().into())).await.unwrap();
+        assert_eq!(names(next_matching(&mut socket, |v| v["tours"].is_array()).await), ["Beta", "Alpha"]);
+
+        // Without data the list keeps the default order
+        socket.send(WsMessage::Text(r#"{"action": "ShowTours"}"#.into())).await.unwrap();
+        assert_eq!(names(next_matching(&mut socket, |v| v["tours"].is_array()).await), ["Beta", "Alpha"]);
+
+        let show = serde_json::json!({ "action": "ShowTours", "data": { "order_by": "name", "direction": "desc" } });
+        socket.send(WsMessage::Text(show.to_string().into())).await.unwrap();
+        assert_eq!(names(next_matching(&mut socket, |v| v["tours"].is_array()).await), ["Beta", "Alpha"]);
+
+        let create = serde_json::json!({ "action": "CreateTour", "data": { "name": "Gamma" } });
+        socket.send(WsMessage::Text(create.to_string().into())).await.unwrap();
+        assert_eq!(names(next_matching(&mut socket, |v| v["tours"].is_array()).await), ["Gamma", "Beta", "Alpha"]);
+    }
+
+    #[tokio::test]
+    async fn test_export_tour_data_returns_the_owners_tour_graph() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("packager", "password").await.unwrap();
+        db.register_user("stranger", "password").await.unwrap();
+        let tour_id = db.create_tour("packager", "Chapel", "").await.unwrap();
+        let nave = db.save_scene(tour_id, "Nave", "/assets/insta360/nave.jpg", None, None, None).await.unwrap();
+        let crypt = db.save_scene(tour_id, "Crypt", "/assets/insta360/crypt.jpg", None, None, None).await.unwrap();
+        db.set_initial_scene(tour_id, nave).await.unwrap();
+        let stairs = db.save_connection(tour_id, nave, Some(crypt), 200.0, -20.0, editor::ConnectionType::Transition, Some("Stairs"), None, None).await.unwrap();
+        db.set_scene_notes(tour_id, crypt, Some("Too dark, reshoot")).await.unwrap();
+        let other_tour = db.create_tour("stranger", "Elsewhere", "").await.unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config::Config::default()), None));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/connect", addr)).await.expect("websocket connects");
+        for message in [
+            serde_json::json!({ "action": "Login", "data": { "username": "packager", "password": "password" } }),
+            serde_json::json!({ "action": "ExportTourData", "data": { "tour_id": other_tour } }),
+            serde_json::json!({ "action": "ExportTourData", "data": { "tour_id": tour_id } }),
+        ] {
+            socket.send(WsMessage::Text(message.to_string().into())).await.unwrap();
+        }
+        let refused = next_matching(&mut socket, |v| v["type"] == "error").await;
+        assert_eq!(refused["message"], "Tour not found or access denied.");
+
+        let reply = next_matching(&mut socket, |v| v["type"] == "tour_export_data").await;
+        assert_eq!(reply["tour_id"], tour_id);
+        let tour = &reply["tour"];
+        assert_eq!(tour["name"], "Chapel");
+        assert_eq!(tour["initial_scene_id"], nave);
+        let scenes = tour["scenes"].as_array().unwrap();
+        assert_eq!(scenes.iter().map(|s| s["name"].as_str().unwrap()).collect::<Vec<_>>(), vec!["Nave", "Crypt"]);
+        let connections = scenes[0]["connections"].as_array().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0]["id"], stairs);
+        assert_eq!(connections[0]["target_scene_id"], crypt);
+        assert_eq!(connections[0]["target_thumbnail"], "/assets/insta360/crypt.jpg");
+        // Export-time defaults are filled in, private fields left out, as in tourData.js
+        assert_eq!(connections[0]["icon_index"], config::Config::default().export.default_icon_index);
+        assert!(scenes[1]["connections"].as_array().unwrap().is_empty());
+        assert!(!tour.to_string().contains("reshoot"));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_connections_beyond_the_cap_are_refused() {
+        let mut config = config::Config::default();
+        config.server.max_connections = 1;
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        let connections = state.connections.clone();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, build_router(state, &config), None));
+        let url = format!("ws://{}/connect", addr);
+
+        let (_first, _) = tokio_tungstenite::connect_async(&url).await.expect("first connection is accepted");
+        match tokio_tungstenite::connect_async(&url).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+                let body = String::from_utf8(response.body().clone().unwrap_or_default()).unwrap();
+                assert!(body.contains("Too many connections"), "unexpected body {:?}", body);
+            }
+            other => panic!("second connection should be refused, got {:?}", other.map(|(_, response)| response.status())),
+        }
+        // The refused client never took a slot
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_scene_anchors_gltf_has_a_node_per_scene() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let tour_id = db.create_tour("owner", "Loft", "").await.unwrap();
+        let lobby = db.save_scene(tour_id, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(tour_id, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        let attic = db.save_scene(tour_id, "Attic", "/assets/insta360/attic.jpg", None, None, None).await.unwrap();
+        db.save_connection(tour_id, lobby, Some(hall), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let response = app.oneshot(axum::http::Request::builder()
+            .uri(format!("/api/tours/{}/scenes.gltf", tour_id))
+            .header("x-username", "owner")
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "model/gltf+json");
+
+        let gltf: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(gltf["asset"]["version"], "2.0");
+        assert_eq!(gltf["scenes"][gltf["scene"].as_u64().unwrap() as usize]["nodes"], serde_json::json!([0, 1, 2]));
+        let nodes = gltf["nodes"].as_array().unwrap();
+        let mut scene_ids: Vec<i64> = nodes.iter().map(|n| n["extras"]["scene_id"].as_i64().unwrap()).collect();
+        scene_ids.sort();
+        assert_eq!(scene_ids, vec![lobby, hall, attic]);
+        for node in nodes {
+            let translation = node["translation"].as_array().unwrap();
+            assert_eq!(translation.len(), 3);
+            let radius = translation[0].as_f64().unwrap().hypot(translation[2].as_f64().unwrap());
+            assert!((radius - gltf::CIRCLE_RADIUS).abs() < 1e-6, "node off the circle: {}", node);
+        }
+        let lobby_node = nodes.iter().find(|n| n["extras"]["scene_id"] == lobby).unwrap();
+        assert_eq!(lobby_node["name"], "Lobby");
+        assert_eq!(lobby_node["extras"]["connection_targets"], serde_json::json!([hall]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_detects_flat_scene_images() {
+        let upload = |app: axum::Router, kind: &'static str, width: u32, height: u32| async move {
+            let mut png = std::io::Cursor::new(Vec::new());
+            let pixel = image::Rgb([uuid::Uuid::new_v4().as_bytes()[0], 20, 200]);
+            image::RgbImage::from_pixel(width, height, pixel).write_to(&mut png, image::ImageFormat::Png).unwrap();
+            let boundary = "vte-test-boundary";
+            let mut body = format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"type\"\r\n\r\n{k}\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"shot.png\"\r\nContent-Type: image/png\r\n\r\n",
+                b = boundary, k = kind
+            ).into_bytes();
+            body.extend_from_slice(png.get_ref());
+            body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+            let response = app.oneshot(axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload-asset")
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .body(axum::body::Body::from(body))
+                .unwrap()).await.unwrap();
+            let status = response.status();
+            let body = body_string(response).await;
+            let reply: serde_json::Value = serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body));
+            for key in ["file_path", "thumbnail_path"] {
+                if let Some(path) = reply[key].as_str() {
+                    let _ = std::fs::remove_file(path.trim_start_matches('/'));
+                }
+            }
+            (status, reply)
+        };
+
+        let app = build_router(test_state().await, &config::Config::default());
+        for kind in ["scene", "closeups"] {
+            let (status, reply) = upload(app.clone(), kind, 400, 200).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(reply["projection"], "equirectangular");
+            assert!(reply["warning"].is_null());
+        }
+        // A flat photo is fine as a closeup but gets flagged as a scene
+        let (status, reply) = upload(app.clone(), "closeups", 400, 300).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(reply["projection"], "flat");
+        assert!(reply["warning"].is_null());
+        let (status, reply) = upload(app.clone(), "scene", 400, 300).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(reply["projection"], "flat");
+        assert!(reply["warning"].as_str().unwrap().contains("equirectangular"));
+
+        // ...or refused outright when configured to
+        let mut config = config::Config::default();
+        config.uploads.reject_flat_scenes = true;
+        let state = AppState { config: Arc::new(config.clone()), ..test_state().await };
+        let app = build_router(state, &config);
+        assert_eq!(upload(app.clone(), "scene", 400, 300).await.0, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(upload(app.clone(), "scene", 400, 200).await.0, StatusCode::OK);
+        assert_eq!(upload(app.clone(), "closeups", 400, 300).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_closeups_are_downscaled_to_the_pixel_budget() {
+        let mut config = config::Config::default();
+        config.uploads.max_closeup_megapixels = 0.01;
+        let app = build_router(AppState { config: Arc::new(config.clone()), ..test_state().await }, &config);
+        let upload = |kind: &'static str| {
+            let app = app.clone();
+            async move {
+                let mut png = std::io::Cursor::new(Vec::new());
+                let pixel = image::Rgb([uuid::Uuid::new_v4().as_bytes()[0], 90, 30]);
+                image::RgbImage::from_pixel(400, 200, pixel).write_to(&mut png, image::ImageFormat::Png).unwrap();
+                let boundary = "vte-test-boundary";
+                let mut body = format!(
+                    "--{b}\r\nContent-Disposition: form-data; name=\"type\"\r\n\r\n{k}\r\n--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"wide.png\"\r\nContent-Type: image/png\r\n\r\n",
+                    b = boundary, k = kind
+                ).into_bytes();
+                body.extend_from_slice(png.get_ref());
+                body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+                let response = app.oneshot(axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/upload-asset")
+                    .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                    .body(axum::body::Body::from(body))
+                    .unwrap()).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let reply: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+                let stored = reply["file_path"].as_str().unwrap().trim_start_matches('/').to_string();
+                let dimensions = image::image_dimensions(&stored).unwrap();
+                for path in [Some(stored), reply["thumbnail_path"].as_str().map(|p| p.trim_start_matches('/').to_string())].into_iter().flatten() {
+                    let _ = std::fs::remove_file(path);
+                }
+                (reply, dimensions)
+            }
+        };
+
+        // 80 000 pixels against a 10 000 pixel budget: stored smaller, still 2:1
+        let (reply, (width, height)) = upload("closeups").await;
+        assert_eq!((reply["width"].as_u64(), reply["height"].as_u64()), (Some(width as u64), Some(height as u64)));
+        assert!(width * height <= 10_000, "{}x{} is over the budget", width, height);
+        assert_eq!((width, height), (141, 70));
+
+        // Scenes keep their full resolution
+        let (reply, dimensions) = upload("scene").await;
+        assert_eq!(dimensions, (400, 200));
+        assert!(reply.get("width").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_backup_json_contains_every_tour_with_scenes() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("other", "password").await.unwrap();
+        let token = db.login_user("owner").await.unwrap();
+        let loft = db.create_tour("owner", "Loft", "").await.unwrap();
+        let villa = db.create_tour("owner", "Villa", "").await.unwrap();
+        db.create_tour("other", "Elsewhere", "").await.unwrap();
+        let lobby = db.save_scene(loft, "Lobby", "/assets/insta360/lobby.jpg", None, None, None).await.unwrap();
+        let hall = db.save_scene(loft, "Hall", "/assets/insta360/hall.jpg", None, None, None).await.unwrap();
+        db.save_connection(loft, lobby, Some(hall), 10.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+        db.save_scene(villa, "Pool", "/assets/insta360/pool.jpg", None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let backup = |username: &str, token: &str| app.clone().oneshot(axum::http::Request::builder()
+            .uri("/api/backup.json")
+            .header("x-username", username)
+            .header("x-session-token", token)
+            .body(axum::body::Body::empty())
+            .unwrap());
+        assert_eq!(backup("owner", "wrong").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        let response = backup("owner", &token).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let streamed: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(streamed, db.export_all_json("owner").await.unwrap());
+
+        let tours = streamed.as_array().unwrap();
+        let names: Vec<&str> = tours.iter().map(|t| t["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Loft", "Villa"]);
+        let scene_names = |tour: &serde_json::Value| -> Vec<String> {
+            tour["scenes"].as_array().unwrap().iter().map(|s| s["name"].as_str().unwrap().to_string()).collect()
+        };
+        let mut loft_scenes = scene_names(&tours[0]);
+        loft_scenes.sort();
+        assert_eq!(loft_scenes, vec!["Hall", "Lobby"]);
+        assert_eq!(scene_names(&tours[1]), vec!["Pool"]);
+        let lobby_json = tours[0]["scenes"].as_array().unwrap().iter().find(|s| s["id"] == lobby).unwrap();
+        assert_eq!(lobby_json["connections"][0]["target_scene_id"], hall);
+    }
+
+    #[tokio::test]
+    async fn test_restore_recreates_backed_up_tours() {
+        let state = test_state().await;
+        let db = state.database.clone();
+        db.register_user("owner", "password").await.unwrap();
+        db.register_user("fresh", "password").await.unwrap();
+        let owner_token = db.login_user("owner").await.unwrap();
+        let fresh_token = db.login_user("fresh").await.unwrap();
+
+        let stem = uuid::Uuid::new_v4();
+        let files: Vec<String> = ["insta360/lobby", "insta360/hall", "closeups/plaque"].iter()
+            .map(|name| format!("/assets/{}_{}.jpg", name, stem))
+            .collect();
+        for file in &files {
+            std::fs::create_dir_all(std::path::Path::new(file.trim_start_matches('/')).parent().unwrap()).unwrap();
+            std::fs::write(file.trim_start_matches('/'), b"jpeg").unwrap();
+        }
+        let gone = format!("/assets/insta360/gone_{}.jpg", stem);
+
+        let loft = db.create_tour("owner", "Loft", "").await.unwrap();
+        let lobby = db.save_scene(loft, "Lobby", &files[0], Some(15.0), Some(-5.0), Some(90.0)).await.unwrap();
+        let hall = db.save_scene(loft, "Hall", &files[1], None, None, None).await.unwrap();
+        db.save_connection(loft, lobby, Some(hall), 10.0, 2.0, editor::ConnectionType::Transition, Some("To hall"), None, None).await.unwrap();
+        db.save_connection(loft, hall, Some(lobby), 190.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+        let plaque = db.save_closeup(loft, "Plaque", &files[2], None).await.unwrap();
+        db.save_connection(loft, lobby, Some(plaque), 40.0, 1.0, editor::ConnectionType::Closeup, Some("Plaque"), Some(&files[2]), None).await.unwrap();
+        db.set_initial_scene(loft, hall).await.unwrap();
+        // A second tour where one panorama has gone missing from disk
+        let shed = db.create_tour("owner", "Shed", "").await.unwrap();
+        let porch = db.save_scene(shed, "Porch", &files[0], None, None, None).await.unwrap();
+        let attic = db.save_scene(shed, "Attic", &gone, None, None, None).await.unwrap();
+        db.save_connection(shed, porch, Some(attic), 0.0, 0.0, editor::ConnectionType::Transition, None, None, None).await.unwrap();
+
+        let app = build_router(state, &config::Config::default());
+        let response = app.clone().oneshot(axum::http::Request::builder()
+            .uri("/api/backup.json")
+            .header("x-username", "owner")
+            .header("x-session-token", owner_token)
+            .body(axum::body::Body::empty())
+            .unwrap()).await.unwrap();
+        let backup = body_string(response).await;
+        let restore = |body: String| app.clone().oneshot(axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/restore")
+            .header("x-username", "fresh")
+            .header("x-session-token", fresh_token.clone())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap());
+        assert_eq!(restore("{\"not\": \"a backup\"}".to_string()).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        // Someone else's files are left out until they're the caller's own
+        let response = restore(backup.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let reply: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(reply["report"]["scene_count"], 0);
+        for file in &files {
+            db.record_upload("fresh", file, "hash").await.unwrap();
+        }
+        let response = restore(backup).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let reply: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        for file in &files {
+            let _ = std::fs::remove_file(file.trim_start_matches('/'));
+        }
+        let warnings = reply["report"]["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].as_str().unwrap().contains(&gone));
+
+        // Same graph, keyed by names and files instead of ids
+        let shape = |tour: serde_json::Value| {
+            let scenes = tour["scenes"].as_array().unwrap();
+            let name_of = |id: &serde_json::Value| scenes.iter().find(|s| s["id"] == *id).map(|s| s["name"].clone());
+            let mut shaped: Vec<serde_json::Value> = scenes.iter().map(|scene| serde_json::json!({
+                "name": scene["name"],
+                "file_path": scene["file_path"],
+                "view": [scene["initial_view_x"], scene["initial_view_y"], scene["north_dir"]],
+                "connections": scene["connections"].as_array().unwrap().iter().map(|c| serde_json::json!({
+                    "type": c["connection_type"],
+                    "name": c["name"],
+                    "position": c["position"],
+                    "target": name_of(&c["target_scene_id"]).unwrap_or(c["file_path"].clone()),
+                })).collect::<Vec<_>>(),
+            })).collect();
+            shaped.sort_by_key(|s| s["name"].as_str().unwrap().to_string());
+            serde_json::json!({ "name": tour["name"], "initial": name_of(&tour["initial_scene_id"]), "scenes": shaped })
+        };
+        let restored_ids: Vec<i64> = reply["report"]["tour_ids"].as_array().unwrap().iter().map(|id| id.as_i64().unwrap()).collect();
+        assert_eq!(restored_ids.len(), 2);
+        let original = db.get_tour_with_scenes("owner", loft).await.unwrap().unwrap();
+        let restored = db.get_tour_with_scenes("fresh", restored_ids[0]).await.unwrap().unwrap();
+        assert_eq!(shape(restored), shape(original));
+
+        let restored_shed = db.get_tour_with_scenes("fresh", restored_ids[1]).await.unwrap().unwrap();
+        let scenes = restored_shed["scenes"].as_array().unwrap();
+        assert_eq!(scenes.len(), 1);
+        assert_eq!(scenes[0]["name"], "Porch");
+        assert!(scenes[0]["connections"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rapid_tour_creation_is_throttled() {
+        let state = test_state().await;
+        state.database.register_user("creator", "password").await.unwrap();
+        let token = state.database.login_user("creator").await.unwrap();
+        let app = build_router(state.clone(), &config::Config::default());
+        let create = |name: &str| axum::http::Request::builder()
+            .method("POST")
+            .uri("/api/tours")
+            .header("x-username", "creator")
+            .header("x-session-token", token.clone())
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(serde_json::json!({ "name": name }).to_string()))
+            .unwrap();
+
+        let response = app.clone().oneshot(create("First")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let created: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let response = app.clone().oneshot(create("Second")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[axum::http::header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((1..=2).contains(&retry_after));
+        assert!(body_string(response).await.contains("too quickly"));
+
+        let tours = state.database.get_tours("creator", database::TourOrder::default(), database::SortDirection::default()).await.unwrap();
+        assert_eq!(tours.len(), 1);
+
+        // Deleting needs the owner's session too
+        let delete = |token: Option<&str>| {
+            let mut request = axum::http::Request::builder().method("DELETE").uri(format!("/api/tours/{}", created["tour_id"]));
+            if let Some(token) = token {
+                request = request.header("x-username", "creator").header("x-session-token", token);
+            }
+            app.clone().oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+        assert_eq!(delete(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(delete(Some(&token)).await.unwrap().status(), StatusCode::OK);
+    }
+
+    // Sends a raw HTTP/1.1 request over TLS and returns the response head
+    async fn tls_request(addr: std::net::SocketAddr, cert_der: rustls::pki_types::CertificateDer<'static>, request: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert_der).unwrap();
+        let client_config = rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(server_name, tcp).await.expect("TLS handshake");
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_https_serves_requests_and_websocket_upgrades() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = std::path::PathBuf::from("target/test_tls").join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let tls = config::TlsConfig {
+            cert_path: dir.join("cert.pem").to_string_lossy().to_string(),
+            key_path: dir.join("key.pem").to_string_lossy().to_string(),
+        };
+        std::fs::write(&tls.cert_path, cert.cert.pem()).unwrap();
+        std::fs::write(&tls.key_path, cert.key_pair.serialize_pem()).unwrap();
+
+        let rustls_config = load_tls_config(&tls).await.expect("load self-signed cert");
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = build_router(test_state().await, &config::Config::default());
+        tokio::spawn(serve(listener, app, Some(rustls_config)));
+
+        let cert_der = cert.cert.der().clone();
+        let response = tls_request(addr, cert_der.clone(), "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+
+        let upgrade = tls_request(addr, cert_der, "GET /connect HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n").await;
+        assert!(upgrade.starts_with("HTTP/1.1 101"), "unexpected upgrade response: {}", upgrade);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,115 @@
+//! Outbound WebSocket queue
+//!
+//! Every connection gets a bounded queue between the handlers and the task that
+//! writes to the socket, so a slow client can't make the server buffer editor
+//! traffic without limit.
+//!
+//! When the queue is full:
+//! * `send_lossy` drops the message (presence and other best-effort updates).
+//! * `send` marks the client as lagging; the connection is then closed, since a
+//!   client that missed a confirmation can no longer trust its local state.
+
+use axum::extract::ws::Message;
+use tokio::sync::{mpsc, watch};
+use std::sync::Arc;
+
+/// Why a message was not queued
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SendError {
+    /// Queue full; a best-effort message was discarded
+    Dropped,
+    /// The client lagged or went away; nothing more will be delivered
+    Disconnected,
+}
+
+/// Sending half of a connection's outbound queue
+#[derive(Clone, Debug)]
+pub struct OutboundSender {
+    tx: mpsc::Sender<Message>,
+    lagging: Arc<watch::Sender<bool>>,
+}
+
+/// Creates an outbound queue holding at most `capacity` messages.
+pub fn channel(capacity: usize) -> (OutboundSender, mpsc::Receiver<Message>) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let (lagging, _) = watch::channel(false);
+    (OutboundSender { tx, lagging: Arc::new(lagging) }, rx)
+}
+
+impl OutboundSender {
+    /// Queues a message the client must receive; a full queue disconnects the client.
+    pub fn send(&self, msg: Message) -> Result<(), SendError> {
+        if self.is_disconnected() {
+            return Err(SendError::Disconnected);
+        }
+        match self.tx.try_send(msg) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                eprintln!("Outbound queue full ({} messages); disconnecting lagging client", self.tx.max_capacity());
+                self.lagging.send_replace(true);
+                Err(SendError::Disconnected)
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(SendError::Disconnected),
+        }
+    }
+
+    /// Queues a best-effort message; it is dropped (and logged) when the queue is full.
+    pub fn send_lossy(&self, msg: Message) -> Result<(), SendError> {
+        if self.is_disconnected() {
+            return Err(SendError::Disconnected);
+        }
+        match self.tx.try_send(msg) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                eprintln!("Outbound queue full; dropped a best-effort message");
+                Err(SendError::Dropped)
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(SendError::Disconnected),
+        }
+    }
+
+    /// True if both senders feed the same connection's queue.
+    pub fn same_connection(&self, other: &OutboundSender) -> bool {
+        self.tx.same_channel(&other.tx)
+    }
+
+    /// True once the client has been marked as lagging or the socket writer has stopped.
+    pub fn is_disconnected(&self) -> bool {
+        *self.lagging.borrow() || self.tx.is_closed()
+    }
+
+    /// Resolves when the client is marked as lagging.
+    pub async fn lagged(&self) {
+        let mut rx = self.lagging.subscribe();
+        let _ = rx.wait_for(|lagging| *lagging).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stalled_receiver_stays_bounded_then_disconnects() {
+        let (tx, mut rx) = channel(8);
+
+        // Nobody is reading: best-effort traffic is capped at the queue size
+        let dropped = (0..100)
+            .filter(|i| tx.send_lossy(Message::Text(format!("presence {}", i))) == Err(SendError::Dropped))
+            .count();
+        assert_eq!(dropped, 92);
+        assert!(!tx.is_disconnected());
+
+        // A confirmation that can't be queued drops the client
+        assert_eq!(tx.send(Message::Text("confirm".to_string())), Err(SendError::Disconnected));
+        assert!(tx.is_disconnected());
+        tokio::time::timeout(std::time::Duration::from_secs(1), tx.lagged()).await.expect("lagged resolves");
+        assert_eq!(tx.send_lossy(Message::Text("late".to_string())), Err(SendError::Disconnected));
+
+        let mut queued = 0;
+        while rx.try_recv().is_ok() {
+            queued += 1;
+        }
+        assert_eq!(queued, 8);
+    }
+}
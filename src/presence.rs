@@ -0,0 +1,241 @@
+//! Presence module
+//!
+//! Tracks who currently has each tour open in the editor so co-editors can show
+//! each other's avatars and cursors. Entries live only in memory: a connection joins
+//! when it loads a tour and leaves on logout/disconnect. A user with the tour open
+//! in several tabs stays present until the last of them leaves.
+//!
+//! Messages sent to clients:
+//! * `presence_list` - everyone else on the tour, sent to a user when they join.
+//! * `presence_update` - a co-editor joined or moved their cursor.
+//! * `presence_left` - a co-editor closed the tour.
+//!
+//! All presence traffic is best-effort and goes through `send_lossy`.
+
+use crate::outbound::OutboundSender;
+use axum::extract::ws::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Panorama position a co-editor is pointing at, in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HotspotPosition {
+    pub lon: f32,
+    pub lat: f32,
+}
+
+/// One user editing a tour
+#[derive(Debug, Clone)]
+struct PresenceEntry {
+    /// Unix timestamp (seconds) of the user's last join or cursor update
+    last_seen: u64,
+    cursor: Option<HotspotPosition>,
+    /// The user's connections with the tour open
+    connections: Vec<OutboundSender>,
+}
+
+/// Editors per tour: tour id -> username -> entry
+#[derive(Debug, Default)]
+pub struct PresenceRegistry {
+    tours: HashMap<i64, HashMap<String, PresenceEntry>>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl PresenceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everyone on a tour except `username`, as JSON for a `presence_list`.
+    pub fn list(&self, tour_id: i64, username: &str) -> Vec<serde_json::Value> {
+        let mut users: Vec<serde_json::Value> = self.tours
+            .get(&tour_id)
+            .map(|editors| editors.iter()
+                .filter(|(name, _)| name.as_str() != username)
+                .map(|(name, entry)| serde_json::json!({
+                    "username": name,
+                    "last_seen": entry.last_seen,
+                    "cursor": entry.cursor
+                }))
+                .collect())
+            .unwrap_or_default();
+        users.sort_by(|a, b| a["username"].as_str().cmp(&b["username"].as_str()));
+        users
+    }
+
+    /// Registers one of `username`'s connections on a tour, sends it the `presence_list` and
+    /// announces the user to the others if they weren't there yet.
+    pub fn join(&mut self, tour_id: i64, username: &str, tx: OutboundSender) {
+        let list = serde_json::json!({
+            "type": "presence_list",
+            "tour_id": tour_id,
+            "users": self.list(tour_id, username)
+        });
+        let _ = tx.send_lossy(Message::Text(list.to_string()));
+
+        let entry = self.tours.entry(tour_id).or_default().entry(username.to_string()).or_insert_with(|| PresenceEntry {
+            last_seen: now_secs(),
+            cursor: None,
+            connections: Vec::new(),
+        });
+        entry.last_seen = now_secs();
+        let arrived = entry.connections.is_empty();
+        if !entry.connections.iter().any(|open| open.same_connection(&tx)) {
+            entry.connections.push(tx);
+        }
+        if !arrived {
+            return;
+        }
+        self.broadcast(tour_id, username, serde_json::json!({
+            "type": "presence_update",
+            "tour_id": tour_id,
+            "username": username,
+            "cursor": null
+        }));
+    }
+
+    /// Records a cursor move and relays it to the other editors.
+    ///
+    /// Returns `false` if the user hasn't joined the tour.
+    pub fn update(&mut self, tour_id: i64, username: &str, cursor: Option<HotspotPosition>) -> bool {
+        match self.tours.get_mut(&tour_id).and_then(|editors| editors.get_mut(username)) {
+            Some(entry) => {
+                entry.last_seen = now_secs();
+                entry.cursor = cursor;
+            }
+            None => return false,
+        }
+        self.broadcast(tour_id, username, serde_json::json!({
+            "type": "presence_update",
+            "tour_id": tour_id,
+            "username": username,
+            "cursor": cursor
+        }));
+        true
+    }
+
+    /// Takes one connection off a tour. Once none of the user's connections has it open,
+    /// the remaining editors are told they left.
+    pub fn leave(&mut self, tour_id: i64, username: &str, tx: &OutboundSender) {
+        let Some(editors) = self.tours.get_mut(&tour_id) else { return };
+        let Some(entry) = editors.get_mut(username) else { return };
+        entry.connections.retain(|open| !open.same_connection(tx));
+        if !entry.connections.is_empty() {
+            return;
+        }
+        editors.remove(username);
+        self.broadcast(tour_id, username, serde_json::json!({
+            "type": "presence_left",
+            "tour_id": tour_id,
+            "username": username
+        }));
+        self.tours.retain(|_, editors| !editors.is_empty());
+    }
+
+    /// Removes a user (all of their connections) from every tour, telling the remaining editors they left.
+    pub fn remove_user(&mut self, username: &str) {
+        let left: Vec<i64> = self.tours
+            .iter_mut()
+            .filter_map(|(tour_id, editors)| editors.remove(username).map(|_| *tour_id))
+            .collect();
+        for tour_id in left {
+            self.broadcast(tour_id, username, serde_json::json!({
+                "type": "presence_left",
+                "tour_id": tour_id,
+                "username": username
+            }));
+        }
+        self.tours.retain(|_, editors| !editors.is_empty());
+    }
+
+    fn broadcast(&self, tour_id: i64, from: &str, message: serde_json::Value) {
+        if let Some(editors) = self.tours.get(&tour_id) {
+            let text = message.to_string();
+            for (name, entry) in editors {
+                if name != from {
+                    for tx in &entry.connections {
+                        let _ = tx.send_lossy(Message::Text(text.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn received(rx: &mut tokio::sync::mpsc::Receiver<Message>) -> Vec<serde_json::Value> {
+        let mut messages = Vec::new();
+        while let Ok(Message::Text(text)) = rx.try_recv() {
+            messages.push(serde_json::from_str(&text).unwrap());
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_second_editor_appears_in_first_presence_list() {
+        let mut registry = PresenceRegistry::new();
+        let (alice_tx, mut alice_rx) = crate::outbound::channel(16);
+        let (bob_tx, mut bob_rx) = crate::outbound::channel(16);
+
+        registry.join(7, "alice", alice_tx);
+        let joined = received(&mut alice_rx);
+        assert_eq!(joined[0]["type"], "presence_list");
+        assert_eq!(joined[0]["users"].as_array().unwrap().len(), 0);
+
+        registry.join(7, "bob", bob_tx);
+        let bob_joined = received(&mut bob_rx);
+        assert_eq!(bob_joined[0]["users"][0]["username"], "alice");
+        assert_eq!(received(&mut alice_rx)[0]["username"], "bob");
+
+        // Bob is in Alice's presence list, with his cursor once he moves
+        assert!(registry.update(7, "bob", Some(HotspotPosition { lon: 90.0, lat: -10.0 })));
+        let alice_view = registry.list(7, "alice");
+        assert_eq!(alice_view.len(), 1);
+        assert_eq!(alice_view[0]["username"], "bob");
+        assert_eq!(alice_view[0]["cursor"]["lon"], 90.0);
+        let moved = received(&mut alice_rx);
+        assert_eq!(moved[0]["type"], "presence_update");
+        assert!(received(&mut bob_rx).is_empty(), "updates are not echoed to the sender");
+
+        registry.remove_user("bob");
+        assert!(registry.list(7, "alice").is_empty());
+        assert_eq!(received(&mut alice_rx)[0]["type"], "presence_left");
+        assert!(!registry.update(7, "bob", None));
+    }
+
+    #[tokio::test]
+    async fn test_second_tab_keeps_the_user_present() {
+        let mut registry = PresenceRegistry::new();
+        let (alice_tx, mut alice_rx) = crate::outbound::channel(16);
+        let (first_tab, mut first_rx) = crate::outbound::channel(16);
+        let (second_tab, mut second_rx) = crate::outbound::channel(16);
+        registry.join(7, "alice", alice_tx);
+        received(&mut alice_rx);
+        registry.join(7, "bob", first_tab.clone());
+        registry.join(7, "bob", second_tab.clone());
+        assert_eq!(received(&mut alice_rx).len(), 1, "one arrival for both tabs");
+        received(&mut first_rx);
+        received(&mut second_rx);
+
+        // Both tabs hear about others; closing one leaves Bob on the tour
+        registry.update(7, "alice", None);
+        assert_eq!(received(&mut first_rx).len(), 1);
+        assert_eq!(received(&mut second_rx).len(), 1);
+        registry.leave(7, "bob", &first_tab);
+        assert!(received(&mut alice_rx).is_empty());
+        assert_eq!(registry.list(7, "alice").len(), 1);
+
+        registry.leave(7, "bob", &second_tab);
+        assert_eq!(received(&mut alice_rx)[0]["type"], "presence_left");
+        assert!(registry.list(7, "alice").is_empty());
+    }
+}
@@ -30,9 +30,33 @@ CREATE TABLE IF NOT EXISTS tours (
     floorplan_id INTEGER DEFAULT 1,
     sort_mode TEXT NOT NULL DEFAULT 'created_at', -- alphabetical | created_at | modified_at
     sort_direction TEXT NOT NULL DEFAULT 'asc',     -- asc | desc
+    views INTEGER NOT NULL DEFAULT 0, -- share link visits
+    brand_logo_path TEXT, -- /assets/... image shown in the exported viewer
+    brand_primary_color TEXT, -- #RRGGBB
+    brand_welcome_text TEXT, -- splash shown when the exported tour opens
+    share_base_url TEXT, -- custom domain share links are built on (overrides sharing.base_url)
+    is_template BOOLEAN NOT NULL DEFAULT 0, -- other users may start new tours from a copy of it
+    requires_auth BOOLEAN NOT NULL DEFAULT 0, -- share links only work for signed-in users
+    version INTEGER NOT NULL DEFAULT 0, -- bumped on every announced change, for cheap "has it changed?" polling
+    slug TEXT, -- unique URL name derived from tour_name, e.g. my-tour-2 (index created by migrate)
     FOREIGN KEY (owner) REFERENCES users(name)
 );
 
+-- The foreign key above only exists on tours tables created with it, and SQLite can't add one
+-- to an existing table; these triggers make the same check everywhere, and `migrate` adds them
+-- to older database files. Tours orphaned before then are left alone.
+CREATE TRIGGER IF NOT EXISTS tours_owner_exists_insert BEFORE INSERT ON tours
+WHEN NOT EXISTS (SELECT 1 FROM users WHERE name = NEW.owner)
+BEGIN
+    SELECT RAISE(ABORT, 'tour owner does not exist');
+END;
+
+CREATE TRIGGER IF NOT EXISTS tours_owner_exists_update BEFORE UPDATE OF owner ON tours
+WHEN NOT EXISTS (SELECT 1 FROM users WHERE name = NEW.owner)
+BEGIN
+    SELECT RAISE(ABORT, 'tour owner does not exist');
+END;
+
 CREATE TABLE IF NOT EXISTS assets (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
@@ -47,6 +71,24 @@ CREATE TABLE IF NOT EXISTS assets (
     initial_view_y FLOAT NOT NULL DEFAULT 0,
     north_dir FLOAT DEFAULT 0,
     pov FLOAT DEFAULT 75,
+    group_id INTEGER, -- scene_groups.id; NULL = default (ungrouped)
+    media_type TEXT NOT NULL DEFAULT 'image', -- 'image' | 'video' (scenes only)
+    thumbnail_path TEXT, -- downscaled /assets/closeups/thumbs/ copy (closeups only)
+    hidden BOOLEAN NOT NULL DEFAULT 0, -- draft scene left out of exports (scenes only)
+    captured_at TEXT, -- 'YYYY-MM-DD HH:MM:SS' the panorama was shot, from EXIF or set by hand (scenes only)
+    notes TEXT, -- author's private notes, never exported or shared (scenes only)
+    min_fov REAL, -- narrowest field of view viewers may zoom to, in degrees; NULL = viewer default (scenes only)
+    max_fov REAL, -- widest field of view viewers may zoom to, in degrees; NULL = viewer default (scenes only)
+    created_by TEXT, -- user who added the scene or closeup in the editor; never exported or shared
+    default_icon_index INTEGER, -- icon for hotspots without their own; NULL = export.default_icon_index (scenes only)
+    FOREIGN KEY (tour_id) REFERENCES tours(id)
+);
+
+CREATE TABLE IF NOT EXISTS scene_groups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    tour_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
     FOREIGN KEY (tour_id) REFERENCES tours(id)
 );
 
@@ -61,11 +103,73 @@ CREATE TABLE IF NOT EXISTS connections (
     name TEXT,
     world_lon FLOAT NOT NULL,
     world_lat FLOAT NOT NULL,
-    is_transition BOOLEAN NOT NULL DEFAULT 0,
+    is_transition BOOLEAN NOT NULL DEFAULT 0, -- legacy; superseded by connection_type
     file_path TEXT,
     icon_type INTEGER,
+    transition_style TEXT, -- fade | slide | none (NULL = viewer default, fade)
+    icon_color TEXT, -- #RRGGBB (NULL = viewer theme)
+    icon_scale FLOAT, -- 0.5 - 3.0 (NULL = viewer theme)
+    url_target TEXT, -- _self | _blank for URL hotspots (NULL = _blank)
+    connection_type TEXT, -- transition | closeup | info (NULL for floorplan markers)
+    z_index INTEGER NOT NULL DEFAULT 0, -- stacking order within the scene (higher is drawn on top)
+    audio_path TEXT, -- /assets/audio/... sound played from the hotspot (NULL = silent)
+    created_by TEXT, -- user who added the hotspot in the editor; never exported or shared
     FOREIGN KEY (tour_id) REFERENCES tours(id),
     FOREIGN KEY (start_id) REFERENCES assets(id),
     FOREIGN KEY (end_id) REFERENCES assets(id),
     FOREIGN KEY (floorplan_id) REFERENCES assets(id)
-);
\ No newline at end of file
+);
+
+-- Hotspots don't carry a modified_at of their own, so writing one stamps its tour
+-- (Database::tour_modified and the conditional GETs built on it)
+CREATE TRIGGER IF NOT EXISTS connections_touch_tour_insert AFTER INSERT ON connections
+BEGIN
+    UPDATE tours SET modified_at = CURRENT_TIMESTAMP WHERE id = NEW.tour_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS connections_touch_tour_update AFTER UPDATE ON connections
+BEGIN
+    UPDATE tours SET modified_at = CURRENT_TIMESTAMP WHERE id IN (OLD.tour_id, NEW.tour_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS connections_touch_tour_delete AFTER DELETE ON connections
+BEGIN
+    UPDATE tours SET modified_at = CURRENT_TIMESTAMP WHERE id = OLD.tour_id;
+END;
+
+CREATE TABLE IF NOT EXISTS tour_path (
+    tour_id INTEGER NOT NULL,
+    position INTEGER NOT NULL, -- 0-based step in the recommended route
+    scene_id INTEGER NOT NULL,
+    PRIMARY KEY (tour_id, position),
+    FOREIGN KEY (tour_id) REFERENCES tours(id),
+    FOREIGN KEY (scene_id) REFERENCES assets(id)
+);
+
+CREATE TABLE IF NOT EXISTS share_tokens (
+    token TEXT PRIMARY KEY,
+    tour_id INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP, -- NULL = never expires
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    FOREIGN KEY (tour_id) REFERENCES tours(id)
+);
+
+CREATE TABLE IF NOT EXISTS uploads (
+    file_path TEXT PRIMARY KEY, -- public path, e.g. /assets/insta360/uploaded_..jpg
+    username TEXT NOT NULL,
+    content_hash TEXT NOT NULL, -- hex SHA-256 of the file bytes
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (username) REFERENCES users(name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_uploads_user_hash ON uploads(username, content_hash);
+
+CREATE TABLE IF NOT EXISTS tour_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tour_id INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    data TEXT NOT NULL, -- JSON copy of the tour's assets, connections, path and groups
+    FOREIGN KEY (tour_id) REFERENCES tours(id)
+);
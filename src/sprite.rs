@@ -0,0 +1,87 @@
+//! Sprite sheet module
+//!
+//! Packs a user's tour thumbnails into one PNG so the tours grid needs a single
+//! image request. Cells are laid out left to right, `COLUMNS` per row; a tour
+//! without a usable thumbnail gets a plain placeholder cell so every tour still
+//! has a frame.
+//!
+//! Sheets are cached as `<owner key>_<fingerprint>.png`. The fingerprint covers
+//! each tour's id, `modified_at` and thumbnail path, so any tour change produces
+//! a new sheet and the owner's previous one is removed.
+
+use image::{imageops, ImageFormat, Rgb, RgbImage};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Size of one thumbnail cell (2:1 like the panoramas)
+pub const CELL_WIDTH: u32 = 256;
+pub const CELL_HEIGHT: u32 = 128;
+/// Cells per row
+pub const COLUMNS: u32 = 4;
+
+const PLACEHOLDER: Rgb<u8> = Rgb([200, 200, 200]);
+
+/// Where one tour's thumbnail sits in the sheet, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Frame {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// Sheet dimensions and the frame of each of `count` cells, in order
+pub fn layout(count: usize) -> (u32, u32, Vec<Frame>) {
+    let count = count as u32;
+    let columns = count.clamp(1, COLUMNS);
+    let rows = count.div_ceil(COLUMNS).max(1);
+    let frames = (0..count)
+        .map(|i| Frame { x: (i % COLUMNS) * CELL_WIDTH, y: (i / COLUMNS) * CELL_HEIGHT, w: CELL_WIDTH, h: CELL_HEIGHT })
+        .collect();
+    (columns * CELL_WIDTH, rows * CELL_HEIGHT, frames)
+}
+
+/// Renders the sheet for the given thumbnail files (one cell each, `None` or unreadable = placeholder).
+pub fn render_png(sources: &[Option<PathBuf>]) -> Result<Vec<u8>, image::ImageError> {
+    let (width, height, frames) = layout(sources.len());
+    let mut sheet = RgbImage::from_pixel(width, height, PLACEHOLDER);
+    for (source, frame) in sources.iter().zip(frames) {
+        let Some(thumb) = source.as_ref().and_then(|path| image::open(path).ok()) else { continue };
+        let cell = thumb.resize_to_fill(frame.w, frame.h, imageops::FilterType::Triangle).to_rgb8();
+        imageops::replace(&mut sheet, &cell, frame.x as i64, frame.y as i64);
+    }
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    sheet.write_to(&mut bytes, ImageFormat::Png)?;
+    Ok(bytes.into_inner())
+}
+
+/// Returns the cached sheet for `owner_key`/`fingerprint` under `cache_dir`, rendering it if needed.
+///
+/// Decoding and resizing are CPU-bound, so callers on the async runtime should run
+/// this through `spawn_blocking`.
+pub fn cached_sheet(
+    sources: &[Option<PathBuf>],
+    cache_dir: &Path,
+    owner_key: &str,
+    fingerprint: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let sheet = cache_dir.join(format!("{}_{}.png", owner_key, fingerprint));
+    if sheet.exists() {
+        return Ok(sheet);
+    }
+
+    let bytes = render_png(sources)?;
+    std::fs::create_dir_all(cache_dir)?;
+    std::fs::write(&sheet, bytes)?;
+
+    // Drop this owner's outdated sheets
+    let prefix = format!("{}_", owner_key);
+    for entry in std::fs::read_dir(cache_dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(&prefix) && entry.path() != sheet {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    Ok(sheet)
+}
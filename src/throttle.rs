@@ -0,0 +1,136 @@
+//! Throttle module
+//!
+//! Per-user limits on how fast tours can be created, so a script can't flood the
+//! database through `CreateTour` or the HTTP routes. Two limits apply: a minimum
+//! interval between creations and a cap on creations within the last hour. Both
+//! are tracked in memory only; a restart starts everyone afresh.
+
+use crate::config::EditorConfig;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Why a creation was refused, and when the user may try again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Throttled {
+    /// Less than the minimum interval since the previous creation
+    TooSoon { retry_after: Duration },
+    /// The hourly cap is used up
+    HourlyCap { retry_after: Duration },
+}
+
+impl Throttled {
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Throttled::TooSoon { retry_after } | Throttled::HourlyCap { retry_after } => *retry_after,
+        }
+    }
+
+    /// Message for the client, with the wait rounded up to whole seconds
+    pub fn message(&self) -> String {
+        let secs = self.retry_after().as_secs_f64().ceil().max(1.0) as u64;
+        match self {
+            Throttled::TooSoon { .. } => format!("Creating tours too quickly; try again in {} s", secs),
+            Throttled::HourlyCap { .. } => format!("Hourly tour limit reached; try again in {} s", secs),
+        }
+    }
+}
+
+/// Recent tour creations per user
+#[derive(Debug)]
+pub struct CreationThrottle {
+    /// Zero disables the interval check
+    min_interval: Duration,
+    /// Zero disables the hourly cap
+    max_per_hour: usize,
+    /// Creation times within the last hour, oldest first
+    recent: HashMap<String, VecDeque<Instant>>,
+}
+
+impl CreationThrottle {
+    pub fn new(min_interval: Duration, max_per_hour: usize) -> Self {
+        Self { min_interval, max_per_hour, recent: HashMap::new() }
+    }
+
+    pub fn from_config(config: &EditorConfig) -> Self {
+        Self::new(Duration::from_millis(config.tour_creation_interval_ms), config.max_tours_per_hour)
+    }
+
+    /// Records a creation by `username` at `now`, or refuses it without recording anything.
+    pub fn try_acquire(&mut self, username: &str, now: Instant) -> Result<(), Throttled> {
+        let times = self.recent.entry(username.to_string()).or_default();
+        while times.front().is_some_and(|t| now.duration_since(*t) >= HOUR) {
+            times.pop_front();
+        }
+
+        if let Some(last) = times.back() {
+            let since = now.duration_since(*last);
+            if since < self.min_interval {
+                return Err(Throttled::TooSoon { retry_after: self.min_interval - since });
+            }
+        }
+        if self.max_per_hour > 0 && times.len() >= self.max_per_hour {
+            let oldest = times[times.len() - self.max_per_hour];
+            return Err(Throttled::HourlyCap { retry_after: HOUR - now.duration_since(oldest) });
+        }
+
+        times.push_back(now);
+        // Keep just enough history for both checks
+        while times.len() > self.max_per_hour.max(1) {
+            times.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Takes back the creation recorded at `at` (by `try_acquire`) when it didn't go through,
+    /// so failed attempts don't use up the user's allowance.
+    pub fn refund(&mut self, username: &str, at: Instant) {
+        if let Some(times) = self.recent.get_mut(username) {
+            if let Some(index) = times.iter().rposition(|t| *t == at) {
+                times.remove(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_and_hourly_cap() {
+        let mut throttle = CreationThrottle::new(Duration::from_secs(2), 3);
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(throttle.try_acquire("alice", at(0)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(1)), Err(Throttled::TooSoon { retry_after: Duration::from_secs(1) }));
+        // Other users aren't affected
+        assert_eq!(throttle.try_acquire("bob", at(1)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(2)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(10)), Ok(()));
+
+        // Fourth creation within the hour waits for the first to age out
+        assert_eq!(throttle.try_acquire("alice", at(20)), Err(Throttled::HourlyCap { retry_after: Duration::from_secs(3580) }));
+        assert_eq!(throttle.try_acquire("alice", at(3600)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(3601)).unwrap_err().message(), "Creating tours too quickly; try again in 1 s");
+        assert_eq!(throttle.try_acquire("alice", at(3605)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(3607)).unwrap_err().message(), "Hourly tour limit reached; try again in 3 s");
+    }
+
+    #[test]
+    fn test_refunded_creations_dont_count() {
+        let mut throttle = CreationThrottle::new(Duration::from_secs(2), 2);
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(throttle.try_acquire("alice", at(0)), Ok(()));
+        throttle.refund("alice", at(0));
+        assert_eq!(throttle.try_acquire("alice", at(1)), Ok(()));
+        throttle.refund("alice", at(1));
+        assert_eq!(throttle.try_acquire("alice", at(2)), Ok(()));
+        assert_eq!(throttle.try_acquire("alice", at(4)), Ok(()));
+        assert!(matches!(throttle.try_acquire("alice", at(6)), Err(Throttled::HourlyCap { .. })));
+    }
+}
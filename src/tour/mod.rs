@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tour {
@@ -10,21 +12,24 @@ pub struct Tour {
     pub sort_mode: String,
     pub sort_direction: String,
     has_floorplan: bool,
-    floorplan_id: Option<i32>
+    floorplan_id: Option<i32>,
+    pub views: i64
 }
 
 impl Tour {
-    pub fn new(id: i32, name: String, created_at: String, modified_at: String, initial_scene_id: i32, sort_mode: String, sort_direction: String, has_floorplan: bool, floorplan_id: Option<i32>) -> Self {
+    /// Builds a tour from a `tours` row (the tour's name is selected as `tour_name`)
+    pub fn from_row(row: &SqliteRow) -> Self {
         Tour {
-            id,
-            name,
-            created_at,
-            modified_at,
-            initial_scene_id,
-            sort_mode,
-            sort_direction,
-            has_floorplan,
-            floorplan_id
+            id: row.get("id"),
+            name: row.get("tour_name"),
+            created_at: row.get("created_at"),
+            modified_at: row.get("modified_at"),
+            initial_scene_id: row.get("initial_scene_id"),
+            sort_mode: row.get("sort_mode"),
+            sort_direction: row.get("sort_direction"),
+            has_floorplan: row.get("has_floorplan"),
+            floorplan_id: row.get("floorplan_id"),
+            views: row.get("views"),
         }
     }
 
@@ -1,13 +1,14 @@
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
-use axum::extract::ws::{Message, WebSocket};
+use tokio::sync::Mutex;
+use axum::extract::ws::WebSocket;
+use crate::outbound::OutboundSender;
 
 
 // Define User struct
 #[derive(Clone)]
 pub struct User {
     pub name: String,
-    pub tx: mpsc::UnboundedSender<Message>,
+    pub tx: OutboundSender,
     pub rx: Arc<Mutex<futures::stream::SplitStream<WebSocket>>>,
     pub session_token: Option<String>,
 }
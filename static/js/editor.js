@@ -1059,6 +1059,10 @@ class VirtualTourEditor {
                 await this.removeSceneFromList(data.scene_id);
                 this.showSuccess('Scene has been deleted successfully');
                 break;
+            case 'initial_scene_changed':
+                this.tourData.initial_scene_id = data.scene_id;
+                this.updateSceneGallery();
+                break;
             case 'scene_updated':
                 this.handleSceneUpdate(data.scene);
                 break;
@@ -1260,6 +1264,9 @@ class VirtualTourEditor {
                 res = (a.created_at||'').localeCompare(b.created_at||'');
             } else if (mode === 'modified_at') {
                 res = (a.modified_at||'').localeCompare(b.modified_at||'');
+            } else if (mode === 'captured_at') {
+                // Scenes without a capture date fall back to when they were added
+                res = (a.captured_at||a.created_at||'').localeCompare(b.captured_at||b.created_at||'');
             }
             return direction === 'asc' ? res : -res;
         });
@@ -1434,37 +1441,13 @@ class VirtualTourEditor {
         
         console.log('Current scenes before deletion:', this.tourData.scenes.map(s => ({ id: s.id, type: typeof s.id })));
         
-        // Check if the deleted scene was the initial scene
-        const wasInitialScene = this.tourData.initial_scene_id && this.tourData.initial_scene_id == sceneId;
-        console.log('Was deleted scene the initial scene?', wasInitialScene);
-        
         // Remove the scene from the local data - ensure proper type comparison
         this.tourData.scenes = this.tourData.scenes.filter(scene => scene.id != sceneId); // Use != for type coercion
         
         console.log('Scenes after deletion:', this.tourData.scenes.map(s => ({ id: s.id, type: typeof s.id })));
         
-        // If the deleted scene was the initial scene and there are remaining scenes, set first scene as initial
-        if (wasInitialScene && this.tourData.scenes.length > 0) {
-            const newInitialScene = this.tourData.scenes[0];
-            console.log('Setting new initial scene:', newInitialScene.name, 'with ID:', newInitialScene.id);
-            
-            // Update local data
-            this.tourData.initial_scene_id = newInitialScene.id;
-            
-            // Send to server
-            if (window.app && window.app.socket) {
-                window.app.socket.send(JSON.stringify({
-                    action: "EditTour",
-                    data: {
-                        tour_id: this.currentTourId,
-                        editor_action: {
-                            action: "SetInitialScene",
-                            data: { scene_id: parseInt(newInitialScene.id) }
-                        }
-                    }
-                }));
-            }
-        }
+        // If the deleted scene was the initial scene the server picks the replacement
+        // and follows up with an initial_scene_changed message
         
         // Always update the scene gallery first
         this.updateSceneGallery();
@@ -2343,10 +2326,12 @@ class VirtualTourEditor {
                 this.updateUploadProgress(progressContainer, i + 1, this.uploadedFiles.length, file.name);
 
                 try {
-                    const fileRes = await this.uploadSingleFile(file);
+                    const isVideo = file.type && file.type.startsWith('video/');
+                    const fileRes = await this.uploadSingleFile(file, isVideo ? 'video' : 'insta360');
                     if (fileRes && fileRes.file_path) {
                         const sceneName = this.generateDefaultSceneName(file);
-                        this.sendAddSceneMessage(sceneName, fileRes.file_path);
+                        this.sendAddSceneMessage(sceneName, fileRes.file_path, fileRes.detected_north, fileRes.captured_at);
+                        if (fileRes.warning) this.showWarning(fileRes.warning, file.name);
                         successCount++;
                     } else {
                         failureCount++;
@@ -2392,6 +2377,15 @@ class VirtualTourEditor {
         }
     }
     
+    /**
+     * Session headers for HTTP API calls (lets the server deduplicate this user's uploads)
+     */
+    authHeaders() {
+        const session = SessionManager.getSession();
+        if (!session.username || !session.token) return {};
+        return { 'X-Username': session.username, 'X-Session-Token': session.token };
+    }
+
     /**
      * Upload a single file to server
      */
@@ -2403,6 +2397,7 @@ class VirtualTourEditor {
             
             const response = await fetch('/upload-asset', {
                 method: 'POST',
+                headers: this.authHeaders(),
                 body: formData
             });
             
@@ -2483,7 +2478,7 @@ class VirtualTourEditor {
     /**
      * Send add scene message to server
      */
-    sendAddSceneMessage(sceneName, filePath) {
+    sendAddSceneMessage(sceneName, filePath, northDirection = null, capturedAt = null) {
         if (window.app?.socket) {
             window.app.socket.send(JSON.stringify({
                 action: "EditTour",
@@ -2493,7 +2488,9 @@ class VirtualTourEditor {
                         action: "AddScene",
                         data: {
                             name: sceneName,
-                            file_path: filePath
+                            file_path: filePath,
+                            north_direction: northDirection ?? null,
+                            captured_at: capturedAt ?? null
                         }
                     }
                 }
@@ -3372,7 +3369,7 @@ class VirtualTourEditor {
         formData.append('file', file);
     formData.append('type', 'floorplan');
         try {
-            const resp = await fetch('/upload-asset', { method: 'POST', body: formData });
+            const resp = await fetch('/upload-asset', { method: 'POST', headers: this.authHeaders(), body: formData });
             const json = await resp.json();
             if (json.file_path) this.sendAddFloorplanMessage(json.file_path);
         } catch (e) { console.error('Floorplan upload failed', e); }
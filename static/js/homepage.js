@@ -121,6 +121,11 @@ class HomepageManager {
       if (response.tours) {
         this.displayTours(response.tours);
       }
+
+      // Another tab or session changed one of our tours
+      if (response.type === 'tour_updated') {
+        this.refreshTours();
+      }
       
       if (response.error) {
         this.showError(response.error);